@@ -1,5 +1,6 @@
 use structopt::StructOpt;
-use my_redis::{Result, DEFAULT_PORT, server};
+use my_redis::{Result, server};
+use my_redis::config::Config;
 use tokio::net::{TcpListener};
 use tokio::signal::ctrl_c;
 
@@ -7,17 +8,58 @@ use tokio::signal::ctrl_c;
 async fn main() -> Result<()> {
     // 开启日志
     tracing_subscriber::fmt::try_init()?;
-    // 自定义传入的端口
+    // 先读配置文件，命令行参数覆盖配置文件里面的值
     let cli = Cli::from_args();
-    let port = cli.port.as_deref().unwrap_or(DEFAULT_PORT);
-    let listener = TcpListener::bind(&format!("127.0.0.1:{}", port)).await?;
-    server::run(listener, ctrl_c()).await
+    let mut config = match &cli.config_file {
+        Some(path) => Config::from_file(path)?,
+        None => Config::default(),
+    };
+    if let Some(port) = &cli.port {
+        config.port = port.parse().map_err(|_| format!("invalid port '{}'", port))?;
+    }
+    if let Some(flags) = cli.notify_keyspace_events {
+        config.notify_keyspace_events = flags;
+    }
+    if let Some(path) = cli.functions_file {
+        config.functions_file = if path.is_empty() { None } else { Some(path) };
+    }
+    if let Some(spec) = &cli.client_output_buffer_limit {
+        config.client_output_buffer_limit.update(spec)?;
+    }
+    config.timeout = cli.timeout.unwrap_or(config.timeout);
+    config.tcp_keepalive = cli.tcp_keepalive.unwrap_or(config.tcp_keepalive);
+    config.maxclients = cli.maxclients.unwrap_or(config.maxclients);
+    // bind 可以写多个地址，只监听第一个；前面的 - 表示地址不可用的时候忽略
+    let bind = config.bind.split_whitespace().next().unwrap_or("127.0.0.1").trim_start_matches('-');
+    let listener = TcpListener::bind(&format!("{}:{}", bind, config.port)).await?;
+    server::run_with_config(listener, config, ctrl_c()).await
 }
 
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "my-redis-server", version = env ! ("CARGO_PKG_VERSION"), author = env ! ("CARGO_PKG_AUTHORS"), about = "A Redis server")]
 struct Cli {
+    // redis.conf 格式的配置文件，CONFIG REWRITE 会写回这个文件
+    #[structopt(name = "config-file")]
+    config_file: Option<String>,
     #[structopt(name = "port", long = "--port")]
     port: Option<String>,
+    // 开启的keyspace通知，比如 KEA
+    #[structopt(name = "notify-keyspace-events", long = "--notify-keyspace-events")]
+    notify_keyspace_events: Option<String>,
+    // 函数库保存的文件，重启的时候从这里加载，空字符串表示不保存
+    #[structopt(name = "functions-file", long = "--functions-file")]
+    functions_file: Option<String>,
+    // 输出缓冲区限制，比如 "pubsub 32mb 8mb 60"
+    #[structopt(name = "client-output-buffer-limit", long = "--client-output-buffer-limit")]
+    client_output_buffer_limit: Option<String>,
+    // 客户端空闲多少秒之后断开，0表示不断开
+    #[structopt(name = "timeout", long = "--timeout")]
+    timeout: Option<u64>,
+    // TCP keepalive的间隔秒数，0表示不开启
+    #[structopt(name = "tcp-keepalive", long = "--tcp-keepalive")]
+    tcp_keepalive: Option<u64>,
+    // 最多同时连接的客户端数
+    #[structopt(name = "maxclients", long = "--maxclients")]
+    maxclients: Option<u64>,
 }
\ No newline at end of file
@@ -0,0 +1,193 @@
+use bytes::BytesMut;
+
+// 字符串最大512MB，所以位偏移最多 2^32 - 1
+pub const MAX_BIT_OFFSET: u64 = 512 * 1024 * 1024 * 8 - 1;
+
+// 和redis一样，每个字节的最高位是第0位
+pub fn get_bit(buf: &[u8], offset: u64) -> u8 {
+    let byte = (offset >> 3) as usize;
+    match buf.get(byte) {
+        Some(b) => (b >> (7 - (offset & 7))) & 1,
+        None => 0,
+    }
+}
+
+// 返回原来的值，长度不够的时候自动用0补齐
+pub fn set_bit(buf: &mut BytesMut, offset: u64, bit: u8) -> u8 {
+    let byte = (offset >> 3) as usize;
+    if buf.len() <= byte {
+        buf.resize(byte + 1, 0);
+    }
+    let mask = 1u8 << (7 - (offset & 7));
+    let old = (buf[byte] & mask != 0) as u8;
+    if bit == 1 {
+        buf[byte] |= mask;
+    } else {
+        buf[byte] &= !mask;
+    }
+    old
+}
+
+// 按8字节一组统计，比逐字节快很多
+pub fn count_bytes(buf: &[u8]) -> u64 {
+    let mut chunks = buf.chunks_exact(8);
+    let mut count: u64 = 0;
+    for chunk in &mut chunks {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        count += u64::from_ne_bytes(word).count_ones() as u64;
+    }
+    for b in chunks.remainder() {
+        count += b.count_ones() as u64;
+    }
+    count
+}
+
+// 统计 [start, end] 位区间里面1的个数，调用方保证区间合法
+pub fn count_bits(buf: &[u8], start: u64, end: u64) -> u64 {
+    let first = (start >> 3) as usize;
+    let last = (end >> 3) as usize;
+    // 去掉头尾两个字节不在区间里面的位
+    let head_mask = 0xffu8 >> (start & 7);
+    let tail_mask = 0xffu8 << (7 - (end & 7));
+    if first == last {
+        return (buf[first] & head_mask & tail_mask).count_ones() as u64;
+    }
+    (buf[first] & head_mask).count_ones() as u64
+        + count_bytes(&buf[first + 1..last])
+        + (buf[last] & tail_mask).count_ones() as u64
+}
+
+// 在 [start, end] 位区间里面找第一个等于bit的位置
+pub fn find_bit(buf: &[u8], bit: u8, start: u64, end: u64) -> Option<u64> {
+    // 整个字节都不可能命中的时候直接跳过
+    let skip = if bit == 1 { 0x00 } else { 0xff };
+    let mut pos = start;
+    while pos <= end {
+        if pos & 7 == 0 && pos + 7 <= end {
+            let byte = buf[(pos >> 3) as usize];
+            if byte == skip {
+                pos += 8;
+                continue;
+            }
+        }
+        if get_bit(buf, pos) == bit {
+            return Some(pos);
+        }
+        pos += 1;
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+// 长度不够的源按0补齐，结果长度是最长的那个
+pub fn bit_op(op: BitOp, srcs: &[&[u8]]) -> Vec<u8> {
+    let len = srcs.iter().map(|src| src.len()).max().unwrap_or(0);
+    if op == BitOp::Not {
+        return srcs[0].iter().map(|b| !b).collect();
+    }
+
+    let mut result = srcs[0].to_vec();
+    result.resize(len, 0);
+    for src in &srcs[1..] {
+        match op {
+            BitOp::And => {
+                for (dst, b) in result.iter_mut().zip(src.iter()) {
+                    *dst &= b;
+                }
+                // 短的源后面都当作0
+                for dst in result.iter_mut().skip(src.len()) {
+                    *dst = 0;
+                }
+            }
+            BitOp::Or => {
+                for (dst, b) in result.iter_mut().zip(src.iter()) {
+                    *dst |= b;
+                }
+            }
+            BitOp::Xor => {
+                for (dst, b) in result.iter_mut().zip(src.iter()) {
+                    *dst ^= b;
+                }
+            }
+            BitOp::Not => unreachable!(),
+        }
+    }
+    result
+}
+
+// 读取从offset开始的bits位，当作无符号数
+pub fn get_bits(buf: &[u8], offset: u64, bits: u32) -> u64 {
+    let mut value = 0u64;
+    for i in 0..bits as u64 {
+        value = (value << 1) | get_bit(buf, offset + i) as u64;
+    }
+    value
+}
+
+// 写入value的低bits位，长度不够会自动扩展
+pub fn set_bits(buf: &mut BytesMut, offset: u64, bits: u32, value: u64) {
+    let byte = ((offset + bits as u64 - 1) >> 3) as usize;
+    if buf.len() <= byte {
+        buf.resize(byte + 1, 0);
+    }
+    for i in 0..bits as u64 {
+        let bit = ((value >> (bits as u64 - 1 - i)) & 1) as u8;
+        set_bit(buf, offset + i, bit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get() {
+        let mut buf = BytesMut::new();
+        assert_eq!(set_bit(&mut buf, 10, 1), 0);
+        assert_eq!(&buf[..], &[0x00, 0x20]);
+        assert_eq!(set_bit(&mut buf, 10, 1), 1);
+        assert_eq!(get_bit(&buf, 10), 1);
+        assert_eq!(get_bit(&buf, 1000), 0);
+        set_bit(&mut buf, 10, 0);
+        assert_eq!(&buf[..], &[0x00, 0x00]);
+    }
+
+    #[test]
+    fn count_and_find() {
+        let buf = [0xff, 0xf0, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0x80];
+        assert_eq!(count_bytes(&buf), 8 + 4 + 1 + 40 + 1);
+        assert_eq!(count_bits(&buf, 4, 11), 8);
+        assert_eq!(count_bits(&buf, 9, 10), 2);
+        assert_eq!(find_bit(&buf, 0, 0, 79), Some(12));
+        assert_eq!(find_bit(&buf, 1, 12, 79), Some(31));
+        assert_eq!(find_bit(&buf, 0, 32, 72), None);
+    }
+
+    #[test]
+    fn ops_pad_with_zero() {
+        let a: &[u8] = &[0xf0, 0xff];
+        let b: &[u8] = &[0x3c];
+        assert_eq!(bit_op(BitOp::And, &[a, b]), vec![0x30, 0x00]);
+        assert_eq!(bit_op(BitOp::Or, &[a, b]), vec![0xfc, 0xff]);
+        assert_eq!(bit_op(BitOp::Xor, &[a, b]), vec![0xcc, 0xff]);
+        assert_eq!(bit_op(BitOp::Not, &[b]), vec![0xc3]);
+    }
+
+    #[test]
+    fn multi_bit_fields() {
+        let mut buf = BytesMut::new();
+        set_bits(&mut buf, 5, 12, 0xabc);
+        assert_eq!(buf.len(), 3);
+        assert_eq!(get_bits(&buf, 5, 12), 0xabc);
+        assert_eq!(get_bits(&buf, 5, 4), 0xa);
+        assert_eq!(get_bits(&buf, 100, 8), 0);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+use tokio::time::{self, Duration, Instant};
+
+// 客户端ID从1开始递增，进程内不重复
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+// 没有实现 HELLO，协议版本固定是 RESP2
+const RESP: u8 = 2;
+
+// 一个连接自己的状态，RESET 会恢复成刚连上时候的样子
+#[derive(Debug)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub addr: SocketAddr,
+    pub local_addr: SocketAddr,
+    pub name: Option<String>,
+    pub db: usize,
+    // 订阅数量和事务里面排队的命令数，没在事务里面是-1
+    pub sub: usize,
+    pub psub: usize,
+    pub ssub: usize,
+    pub multi: i64,
+    // 输入缓冲区已用和空闲的字节数，还没推送的消息条数和字节数
+    pub qbuf: usize,
+    pub qbuf_free: usize,
+    pub oll: usize,
+    pub omem: usize,
+    // 最后执行的命令，子命令用 | 连起来，比如 client|info
+    pub last_cmd: String,
+    created: Instant,
+    last_interaction: Instant,
+}
+
+impl ClientInfo {
+    pub fn new(addr: SocketAddr, local_addr: SocketAddr) -> ClientInfo {
+        let now = Instant::now();
+        ClientInfo {
+            id: NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            addr,
+            local_addr,
+            name: None,
+            db: 0,
+            sub: 0,
+            psub: 0,
+            ssub: 0,
+            multi: -1,
+            qbuf: 0,
+            qbuf_free: 0,
+            oll: 0,
+            omem: 0,
+            last_cmd: "NULL".to_string(),
+            created: now,
+            last_interaction: now,
+        }
+    }
+
+    // 收到一个命令的时候调用
+    pub fn touch(&mut self, cmd: String) {
+        self.last_cmd = cmd;
+        self.last_interaction = Instant::now();
+    }
+
+    // 连接建立到现在的秒数
+    pub fn age(&self) -> u64 {
+        self.created.elapsed().as_secs()
+    }
+
+    // 最后一次执行命令到现在的秒数
+    pub fn idle(&self) -> u64 {
+        self.last_interaction.elapsed().as_secs()
+    }
+
+    // CLIENT INFO 和 CLIENT LIST 里面的一行
+    pub fn describe(&self) -> String {
+        let mut flags = String::new();
+        if self.sub + self.psub + self.ssub > 0 {
+            flags.push('P');
+        }
+        if self.multi >= 0 {
+            flags.push('x');
+        }
+        if flags.is_empty() {
+            flags.push('N');
+        }
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} flags={} db={} sub={} psub={} ssub={} multi={} qbuf={} qbuf-free={} oll={} omem={} cmd={} user=default resp={}",
+            self.id,
+            self.addr,
+            self.local_addr,
+            self.name.as_deref().unwrap_or(""),
+            self.age(),
+            self.idle(),
+            flags,
+            self.db,
+            self.sub,
+            self.psub,
+            self.ssub,
+            self.multi,
+            self.qbuf,
+            self.qbuf_free,
+            self.oll,
+            self.omem,
+            self.last_cmd,
+            RESP,
+        )
+    }
+
+    pub fn reset(&mut self) {
+        self.db = 0;
+    }
+
+    pub fn is_pubsub(&self) -> bool {
+        self.sub + self.psub + self.ssub > 0
+    }
+}
+
+// 注册表里面的一个连接，CLIENT KILL 通过它通知Handler退出
+#[derive(Debug)]
+pub(crate) struct ClientHandle {
+    info: Mutex<ClientInfo>,
+    killed: AtomicBool,
+    kill_notify: Notify,
+}
+
+impl ClientHandle {
+    pub(crate) fn info(&self) -> MutexGuard<'_, ClientInfo> {
+        self.info.lock().unwrap()
+    }
+
+    pub(crate) fn kill(&self) {
+        self.killed.store(true, Ordering::SeqCst);
+        // notify_one 会保留许可，Handler还没开始等待也不会丢
+        self.kill_notify.notify_one();
+    }
+
+    pub(crate) fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::SeqCst)
+    }
+
+    // 被 CLIENT KILL 之后返回
+    pub(crate) async fn killed(&self) {
+        while !self.is_killed() {
+            self.kill_notify.notified().await;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseMode {
+    // 只暂停会修改数据的命令
+    Write,
+    All,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    mode: PauseMode,
+    until: Instant,
+}
+
+// 所有连接的注册表，Db里面共用一份
+#[derive(Debug, Default)]
+pub(crate) struct Clients {
+    clients: Mutex<BTreeMap<u64, Arc<ClientHandle>>>,
+    pause: Mutex<Option<Pause>>,
+}
+
+impl Clients {
+    pub(crate) fn register(&self, info: ClientInfo) -> Arc<ClientHandle> {
+        let id = info.id;
+        let handle = Arc::new(ClientHandle { info: Mutex::new(info), killed: AtomicBool::new(false), kill_notify: Notify::new() });
+        self.clients.lock().unwrap().insert(id, handle.clone());
+        handle
+    }
+
+    pub(crate) fn unregister(&self, id: u64) {
+        self.clients.lock().unwrap().remove(&id);
+    }
+
+    // 按ID排好序
+    pub(crate) fn list(&self) -> Vec<Arc<ClientHandle>> {
+        self.clients.lock().unwrap().values().cloned().collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    // 已经在暂停的时候和redis一样取更晚的结束时间和更严格的模式
+    pub(crate) fn pause(&self, mode: PauseMode, timeout: Duration) {
+        let mut pause = self.pause.lock().unwrap();
+        let mut next = Pause { mode, until: Instant::now() + timeout };
+        if let Some(current) = *pause {
+            if current.until > Instant::now() {
+                next.until = next.until.max(current.until);
+                if current.mode == PauseMode::All {
+                    next.mode = PauseMode::All;
+                }
+            }
+        }
+        *pause = Some(next);
+    }
+
+    pub(crate) fn unpause(&self) {
+        *self.pause.lock().unwrap() = None;
+    }
+
+    // write为true表示要执行的命令可能修改数据
+    pub(crate) fn is_paused(&self, write: bool) -> bool {
+        let mut pause = self.pause.lock().unwrap();
+        match *pause {
+            Some(Pause { until, .. }) if until <= Instant::now() => {
+                *pause = None;
+                false
+            }
+            Some(Pause { mode, .. }) => mode == PauseMode::All || write,
+            None => false,
+        }
+    }
+
+    // 暂停期间一直等待，和等待脚本结束一样轮询
+    pub(crate) async fn wait_unpaused(&self, write: bool) {
+        while self.is_paused(write) {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+// 名字不能有空格和特殊字符，CLIENT LIST 是按空格分隔的
+pub fn valid_name(name: &str) -> bool {
+    name.bytes().all(|c| (b'!'..=b'~').contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_keeps_later_deadline_and_stricter_mode() {
+        let clients = Clients::default();
+        clients.pause(PauseMode::Write, Duration::from_secs(100));
+        clients.pause(PauseMode::All, Duration::from_millis(1));
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(clients.is_paused(true));
+        // 更短的ALL已经过期，剩下的是更长的那一次，模式是更严格的ALL
+        assert!(clients.is_paused(false));
+        clients.unpause();
+        assert!(!clients.is_paused(true));
+    }
+
+    #[test]
+    fn pause_write_only_blocks_writes() {
+        let clients = Clients::default();
+        clients.pause(PauseMode::Write, Duration::from_secs(100));
+        assert!(clients.is_paused(true));
+        assert!(!clients.is_paused(false));
+    }
+
+    #[test]
+    fn expired_pause_is_replaced() {
+        let clients = Clients::default();
+        clients.pause(PauseMode::All, Duration::from_millis(1));
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        clients.pause(PauseMode::Write, Duration::from_secs(100));
+        assert!(!clients.is_paused(false));
+    }
+
+    #[test]
+    fn client_names() {
+        assert!(valid_name("conn-1"));
+        assert!(valid_name(""));
+        assert!(!valid_name("a b"));
+        assert!(!valid_name("a\nb"));
+    }
+}
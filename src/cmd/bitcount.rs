@@ -0,0 +1,107 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::bitmap;
+use crate::util::parse_int;
+use bytes::Bytes;
+
+// BITCOUNT 和 BITPOS 的区间，下标可以是负数，bit为true的时候单位是位
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BitRange {
+    pub(crate) start: i64,
+    pub(crate) end: Option<i64>,
+    pub(crate) bit: bool,
+}
+
+impl BitRange {
+    // args 是 [start [end [BYTE|BIT]]]
+    pub(crate) fn parse(args: &[Bytes]) -> crate::Result<Option<BitRange>> {
+        if args.is_empty() {
+            return Ok(None);
+        }
+        if args.len() > 3 {
+            return Err("ERR syntax error".into());
+        }
+        let parse = |src: &Bytes| parse_int(src).ok_or("ERR value is not an integer or out of range");
+        let start = parse(&args[0])?;
+        let end = args.get(1).map(parse).transpose()?;
+        let bit = match args.get(2) {
+            Some(unit) if unit.eq_ignore_ascii_case(b"BIT") => true,
+            Some(unit) if unit.eq_ignore_ascii_case(b"BYTE") => false,
+            Some(_) => return Err("ERR syntax error".into()),
+            None => false,
+        };
+        Ok(Some(BitRange { start, end, bit }))
+    }
+
+    // 转成位的闭区间，区间为空的时候返回None
+    pub(crate) fn resolve(&self, len: usize) -> Option<(u64, u64)> {
+        let total = if self.bit { len as i64 * 8 } else { len as i64 };
+        let mut start = self.start;
+        let mut end = self.end.unwrap_or(total - 1);
+        if start < 0 && end < 0 && start > end {
+            return None;
+        }
+        if start < 0 {
+            start += total;
+        }
+        if end < 0 {
+            end += total;
+        }
+        start = start.max(0);
+        end = end.max(0).min(total - 1);
+        if total == 0 || start > end {
+            return None;
+        }
+        if self.bit {
+            Some((start as u64, end as u64))
+        } else {
+            Some((start as u64 * 8, end as u64 * 8 + 7))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BitCount {
+    key: String,
+    range: Option<BitRange>,
+}
+
+impl BitCount {
+    pub fn new(key: impl ToString) -> BitCount {
+        BitCount {
+            key: key.to_string(),
+            range: None,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitCount> {
+        let mut bitcount = BitCount::new(parse.next_string()?);
+        let args = parse.rest_bytes()?;
+        // 只给了start是不行的
+        if args.len() == 1 {
+            return Err("ERR syntax error".into());
+        }
+        bitcount.range = BitRange::parse(&args)?;
+        Ok(bitcount)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let value = match db.get_string(&self.key)? {
+            Some(value) => value,
+            None => return Ok(Frame::Integer(0)),
+        };
+        let count = match self.range {
+            None => bitmap::count_bytes(value),
+            Some(range) => match range.resolve(value.len()) {
+                Some((start, end)) => bitmap::count_bits(value, start, end),
+                None => 0,
+            },
+        };
+        Ok(Frame::Integer(count as i64))
+    }
+}
@@ -0,0 +1,270 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STRING;
+use crate::bitmap::{self, MAX_BIT_OFFSET};
+use crate::cmd::setbit::INVALID_OFFSET;
+use crate::util::parse_int;
+use bytes::{Bytes, BytesMut};
+
+const INVALID_TYPE: &str = "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    Wrap,
+    Sat,
+    // 溢出的时候不修改，返回nil
+    Fail,
+}
+
+// i1 ~ i64 或者 u1 ~ u63
+#[derive(Debug, Clone, Copy)]
+pub struct FieldType {
+    signed: bool,
+    bits: u32,
+}
+
+#[derive(Debug)]
+pub enum FieldOp {
+    Get { ty: FieldType, offset: u64 },
+    Set { ty: FieldType, offset: u64, value: i64, overflow: Overflow },
+    IncrBy { ty: FieldType, offset: u64, incr: i64, overflow: Overflow },
+}
+
+#[derive(Debug)]
+pub struct BitField {
+    key: String,
+    ops: Vec<FieldOp>,
+}
+
+impl BitField {
+    pub fn new(key: impl ToString, ops: Vec<FieldOp>) -> BitField {
+        BitField {
+            key: key.to_string(),
+            ops,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    // 只有GET的时候不会修改key
+    pub(crate) fn is_write(&self) -> bool {
+        self.ops.iter().any(|op| !matches!(op, FieldOp::Get { .. }))
+    }
+
+    // readonly为true的时候是 BITFIELD_RO，只能用GET
+    pub(crate) fn parse_frames(parse: &mut Parse, readonly: bool) -> crate::Result<BitField> {
+        let key = parse.next_string()?;
+        let args = parse.rest_bytes()?;
+
+        let mut ops = vec![];
+        let mut overflow = Overflow::Wrap;
+        let mut idx = 0;
+        while idx < args.len() {
+            let subcommand = String::from_utf8_lossy(&args[idx]).to_uppercase();
+            let remaining = args.len() - idx - 1;
+            match &subcommand[..] {
+                "GET" if remaining >= 2 => {
+                    let ty = parse_type(&args[idx + 1])?;
+                    let offset = parse_offset(&args[idx + 2], ty)?;
+                    ops.push(FieldOp::Get { ty, offset });
+                    idx += 3;
+                    continue;
+                }
+                "OVERFLOW" if remaining >= 1 && !readonly => {
+                    overflow = match &String::from_utf8_lossy(&args[idx + 1]).to_uppercase()[..] {
+                        "WRAP" => Overflow::Wrap,
+                        "SAT" => Overflow::Sat,
+                        "FAIL" => Overflow::Fail,
+                        _ => return Err("ERR Invalid OVERFLOW type specified".into()),
+                    };
+                    idx += 2;
+                    continue;
+                }
+                "SET" | "INCRBY" if remaining >= 3 && !readonly => {
+                    let ty = parse_type(&args[idx + 1])?;
+                    let offset = parse_offset(&args[idx + 2], ty)?;
+                    let value = parse_int(&args[idx + 3]).ok_or("ERR value is not an integer or out of range")?;
+                    ops.push(if subcommand == "SET" {
+                        FieldOp::Set { ty, offset, value, overflow }
+                    } else {
+                        FieldOp::IncrBy { ty, offset, incr: value, overflow }
+                    });
+                    idx += 4;
+                    continue;
+                }
+                _ => {}
+            }
+            if readonly && subcommand != "GET" {
+                return Err("ERR BITFIELD_RO only supports the GET subcommand".into());
+            }
+            return Err("ERR syntax error".into());
+        }
+        Ok(BitField::new(key, ops))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let readonly = self.ops.iter().all(|op| matches!(op, FieldOp::Get { .. }));
+        if readonly {
+            // 只读的时候不创建key
+            let value = db.get_string(&self.key)?.cloned().unwrap_or_default();
+            let result = self.ops.iter().map(|op| match op {
+                FieldOp::Get { ty, offset } => Frame::Integer(ty.get(&value, *offset)),
+                _ => unreachable!(),
+            });
+            return Ok(Frame::Array(result.collect()));
+        }
+
+        let value = db.get_or_create_string(&self.key)?;
+        let mut buf = BytesMut::from(std::mem::take(value));
+        let result = self.ops.iter().map(|op| apply(&mut buf, op)).collect();
+        *value = buf.freeze();
+        db.signal_modified_key(&self.key);
+        db.notify_keyspace_event(NOTIFY_STRING, "setbit", &self.key);
+        Ok(Frame::Array(result))
+    }
+}
+
+fn apply(buf: &mut BytesMut, op: &FieldOp) -> Frame {
+    match *op {
+        FieldOp::Get { ty, offset } => Frame::Integer(ty.get(buf, offset)),
+        FieldOp::Set { ty, offset, value, overflow } => {
+            let old = ty.get(buf, offset);
+            // 无符号类型的值按u64解释，-1就是一个非常大的数
+            let value = if ty.signed { value as i128 } else { value as u64 as i128 };
+            match ty.fit(value, overflow) {
+                Some(value) => {
+                    bitmap::set_bits(buf, offset, ty.bits, value as u64);
+                    Frame::Integer(old)
+                }
+                None => Frame::Null,
+            }
+        }
+        FieldOp::IncrBy { ty, offset, incr, overflow } => {
+            let old = ty.get(buf, offset);
+            match ty.fit(old as i128 + incr as i128, overflow) {
+                Some(value) => {
+                    bitmap::set_bits(buf, offset, ty.bits, value as u64);
+                    Frame::Integer(value)
+                }
+                None => Frame::Null,
+            }
+        }
+    }
+}
+
+impl FieldType {
+    fn min(&self) -> i128 {
+        if self.signed { -(1i128 << (self.bits - 1)) } else { 0 }
+    }
+
+    fn max(&self) -> i128 {
+        if self.signed { (1i128 << (self.bits - 1)) - 1 } else { (1i128 << self.bits) - 1 }
+    }
+
+    fn get(&self, buf: &[u8], offset: u64) -> i64 {
+        let raw = bitmap::get_bits(buf, offset, self.bits);
+        // 有符号数需要做符号扩展
+        if self.signed && self.bits < 64 && raw >> (self.bits - 1) == 1 {
+            (raw as i128 - (1i128 << self.bits)) as i64
+        } else {
+            raw as i64
+        }
+    }
+
+    // 按溢出策略处理，FAIL并且溢出的时候返回None
+    fn fit(&self, value: i128, overflow: Overflow) -> Option<i64> {
+        if value >= self.min() && value <= self.max() {
+            return Some(value as i64);
+        }
+        match overflow {
+            Overflow::Wrap => {
+                let modulus = 1i128 << self.bits;
+                let mut wrapped = value.rem_euclid(modulus);
+                if wrapped > self.max() {
+                    wrapped -= modulus;
+                }
+                Some(wrapped as i64)
+            }
+            Overflow::Sat => Some(value.clamp(self.min(), self.max()) as i64),
+            Overflow::Fail => None,
+        }
+    }
+}
+
+fn parse_type(src: &Bytes) -> crate::Result<FieldType> {
+    let signed = match src.first() {
+        Some(b'i') | Some(b'I') => true,
+        Some(b'u') | Some(b'U') => false,
+        _ => return Err(INVALID_TYPE.into()),
+    };
+    let bits = std::str::from_utf8(&src[1..]).ok().and_then(|s| s.parse::<u32>().ok()).ok_or(INVALID_TYPE)?;
+    let max_bits = if signed { 64 } else { 63 };
+    if bits < 1 || bits > max_bits {
+        return Err(INVALID_TYPE.into());
+    }
+    Ok(FieldType { signed, bits })
+}
+
+// # 开头代表按类型宽度的倍数计算偏移
+fn parse_offset(src: &Bytes, ty: FieldType) -> crate::Result<u64> {
+    let (src, multiply) = match src.strip_prefix(b"#") {
+        Some(rest) => (rest, true),
+        None => (&src[..], false),
+    };
+    let offset = std::str::from_utf8(src).ok().and_then(|s| s.parse::<u64>().ok()).ok_or(INVALID_OFFSET)?;
+    let offset = if multiply { offset.checked_mul(ty.bits as u64).ok_or(INVALID_OFFSET)? } else { offset };
+    match offset.checked_add(ty.bits as u64 - 1) {
+        Some(last) if last <= MAX_BIT_OFFSET => Ok(offset),
+        _ => Err(INVALID_OFFSET.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ty(src: &str) -> FieldType {
+        parse_type(&Bytes::from(src.to_string())).unwrap()
+    }
+
+    #[test]
+    fn parse_type_limits() {
+        assert!(parse_type(&Bytes::from_static(b"i64")).is_ok());
+        assert!(parse_type(&Bytes::from_static(b"u63")).is_ok());
+        assert!(parse_type(&Bytes::from_static(b"u64")).is_err());
+        assert!(parse_type(&Bytes::from_static(b"i0")).is_err());
+        assert!(parse_type(&Bytes::from_static(b"x8")).is_err());
+    }
+
+    #[test]
+    fn parse_offset_range() {
+        let u8 = ty("u8");
+        assert_eq!(parse_offset(&Bytes::from_static(b"8"), u8).unwrap(), 8);
+        assert_eq!(parse_offset(&Bytes::from_static(b"#2"), u8).unwrap(), 16);
+        assert_eq!(parse_offset(&Bytes::from((MAX_BIT_OFFSET - 7).to_string()), u8).unwrap(), MAX_BIT_OFFSET - 7);
+        assert!(parse_offset(&Bytes::from((MAX_BIT_OFFSET - 6).to_string()), u8).is_err());
+        // 接近u64最大值的时候不能溢出
+        assert!(parse_offset(&Bytes::from(u64::MAX.to_string()), u8).is_err());
+        assert!(parse_offset(&Bytes::from(format!("#{}", u64::MAX / 8)), u8).is_err());
+        assert!(parse_offset(&Bytes::from_static(b"-1"), u8).is_err());
+    }
+
+    #[test]
+    fn fit_overflow() {
+        let u8 = ty("u8");
+        assert_eq!(u8.fit(256, Overflow::Wrap), Some(0));
+        assert_eq!(u8.fit(-1, Overflow::Wrap), Some(255));
+        assert_eq!(u8.fit(300, Overflow::Sat), Some(255));
+        assert_eq!(u8.fit(-5, Overflow::Sat), Some(0));
+        assert_eq!(u8.fit(256, Overflow::Fail), None);
+        let i8 = ty("i8");
+        assert_eq!(i8.fit(128, Overflow::Wrap), Some(-128));
+        assert_eq!(i8.fit(-129, Overflow::Wrap), Some(127));
+        assert_eq!(i8.fit(1000, Overflow::Sat), Some(127));
+        let i64 = ty("i64");
+        assert_eq!(i64.fit(i64::MAX as i128 + 1, Overflow::Wrap), Some(i64::MIN));
+    }
+}
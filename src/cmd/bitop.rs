@@ -0,0 +1,65 @@
+use crate::parse::Parse;
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::notify::{NOTIFY_STRING, NOTIFY_GENERIC};
+use crate::bitmap::{self, BitOp as Op};
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct BitOp {
+    op: Op,
+    dest: String,
+    keys: Vec<String>,
+}
+
+impl BitOp {
+    pub fn new(op: Op, dest: impl ToString, keys: Vec<String>) -> BitOp {
+        BitOp {
+            op,
+            dest: dest.to_string(),
+            keys,
+        }
+    }
+
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitOp> {
+        let op = match &parse.next_string()?.to_uppercase()[..] {
+            "AND" => Op::And,
+            "OR" => Op::Or,
+            "XOR" => Op::Xor,
+            "NOT" => Op::Not,
+            _ => return Err("ERR syntax error".into()),
+        };
+        let dest = parse.next_string()?;
+        let mut keys = vec![parse.next_string()?];
+        keys.extend(parse.rest_bytes()?.iter().map(|key| String::from_utf8_lossy(key).to_string()));
+        if op == Op::Not && keys.len() != 1 {
+            return Err("ERR BITOP NOT must be called with a single source key.".into());
+        }
+        Ok(BitOp::new(op, dest, keys))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        // Bytes的clone只是增加引用计数
+        let mut srcs = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            srcs.push(db.get_string(key)?.cloned().unwrap_or_default());
+        }
+        let srcs: Vec<&[u8]> = srcs.iter().map(|src| &src[..]).collect();
+        let result = bitmap::bit_op(self.op, &srcs);
+
+        let len = result.len();
+        if len == 0 {
+            if db.remove(&self.dest).is_some() {
+                db.notify_keyspace_event(NOTIFY_GENERIC, "del", &self.dest);
+            }
+        } else {
+            db.set(self.dest.clone(), Value::String(Bytes::from(result)), None);
+            db.notify_keyspace_event(NOTIFY_STRING, "set", &self.dest);
+        }
+        Ok(Frame::Integer(len as i64))
+    }
+}
@@ -0,0 +1,59 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::bitmap;
+use crate::cmd::bitcount::BitRange;
+
+#[derive(Debug)]
+pub struct BitPos {
+    key: String,
+    bit: u8,
+    range: Option<BitRange>,
+}
+
+impl BitPos {
+    pub fn new(key: impl ToString, bit: u8) -> BitPos {
+        BitPos {
+            key: key.to_string(),
+            bit,
+            range: None,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitPos> {
+        let key = parse.next_string()?;
+        let bit = match &parse.next_bytes()?[..] {
+            b"0" => 0,
+            b"1" => 1,
+            _ => return Err("ERR The bit argument must be 1 or 0.".into()),
+        };
+        let mut bitpos = BitPos::new(key, bit);
+        bitpos.range = BitRange::parse(&parse.rest_bytes()?)?;
+        Ok(bitpos)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let value = match db.get_string(&self.key)? {
+            Some(value) => value,
+            // 不存在的key当作全是0
+            None => return Ok(Frame::Integer(if self.bit == 1 { -1 } else { 0 })),
+        };
+        let range = self.range.unwrap_or(BitRange { start: 0, end: None, bit: false });
+        let (start, end) = match range.resolve(value.len()) {
+            Some(range) => range,
+            None => return Ok(Frame::Integer(-1)),
+        };
+
+        let pos = match bitmap::find_bit(value, self.bit, start, end) {
+            Some(pos) => pos as i64,
+            // 没有指定end的时候，认为右边是无限的0
+            None if self.bit == 0 && range.end.is_none() => end as i64 + 1,
+            None => -1,
+        };
+        Ok(Frame::Integer(pos))
+    }
+}
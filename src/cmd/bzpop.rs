@@ -0,0 +1,109 @@
+use crate::parse::Parse;
+use crate::db::{Db, State};
+use crate::frame::Frame;
+use crate::notify::NOTIFY_ZSET;
+use crate::connection::Connection;
+use crate::shutdown::Shutdown;
+use crate::cmd::serve_blocking;
+use crate::util::{parse_double, format_double};
+use bytes::Bytes;
+use tokio::time::Duration;
+
+// BZPOPMIN 和 BZPOPMAX 共用
+#[derive(Debug)]
+pub struct BZPop {
+    keys: Vec<String>,
+    // None代表一直等待
+    timeout: Option<Duration>,
+    max: bool,
+}
+
+impl BZPop {
+    // 命令表里面的名字
+    pub(crate) fn name(&self) -> &'static str {
+        if self.max { "bzpopmax" } else { "bzpopmin" }
+    }
+
+    pub fn new(keys: Vec<String>, timeout: Option<Duration>, max: bool) -> BZPop {
+        BZPop {
+            keys,
+            timeout,
+            max,
+        }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse, max: bool) -> crate::Result<BZPop> {
+        // 最后一个参数是超时时间，前面的都是key，命令表保证了至少有一个key
+        let mut args = parse.rest_bytes()?;
+        let timeout = args.pop().unwrap();
+
+        let keys = args.iter().map(|key| String::from_utf8_lossy(key).to_string()).collect();
+        Ok(BZPop::new(keys, parse_timeout(&timeout)?, max))
+    }
+
+    // 按顺序找到第一个非空的有序集合弹出一个成员
+    fn pop(&self, db: &mut State) -> crate::Result<Option<Frame>> {
+        for key in &self.keys {
+            let zset = match db.get_zset_mut(key)? {
+                Some(zset) => zset,
+                None => continue,
+            };
+            let (member, score) = zset.pop(1, self.max).pop().unwrap();
+            db.signal_modified_key(key);
+            db.notify_keyspace_event(NOTIFY_ZSET, if self.max { "zpopmax" } else { "zpopmin" }, key);
+            db.remove_if_empty(key);
+            return Ok(Some(Frame::Array(vec![
+                Frame::Bulk(Bytes::from(key.clone())),
+                Frame::Bulk(member),
+                Frame::Bulk(Bytes::from(format_double(score))),
+            ])));
+        }
+        Ok(None)
+    }
+
+    // 不阻塞的执行，事务里面就是这样
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        Ok(self.pop(db)?.unwrap_or(Frame::NullArray))
+    }
+
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let response = serve_blocking(db, &self.keys, self.timeout, shutdown, |state| self.pop(state)).await;
+        if let Some(response) = response {
+            dst.write_frame(&response).await?;
+        }
+        Ok(())
+    }
+}
+
+// 超时时间单位是秒，可以是小数，0代表一直等待
+pub(crate) fn parse_timeout(src: &[u8]) -> crate::Result<Option<Duration>> {
+    let timeout = parse_double(src)
+        .filter(|timeout| timeout.is_finite())
+        .ok_or("ERR timeout is not a float or out of range")?;
+    if timeout < 0.0 {
+        return Err("ERR timeout is negative".into());
+    }
+    if timeout == 0.0 {
+        Ok(None)
+    } else {
+        Ok(Some(Duration::from_secs_f64(timeout)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeouts() {
+        assert_eq!(parse_timeout(b"0").unwrap(), None);
+        assert_eq!(parse_timeout(b"0.5").unwrap(), Some(Duration::from_millis(500)));
+        assert_eq!(parse_timeout(b"-1").unwrap_err().to_string(), "ERR timeout is negative");
+        assert!(parse_timeout(b"inf").is_err());
+        assert!(parse_timeout(b"abc").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::parse::Parse;
+use crate::client::{valid_name, ClientHandle, ClientInfo, Clients, PauseMode};
+use crate::frame::Frame;
+use crate::util::parse_int;
+use bytes::Bytes;
+use std::time::Duration;
+
+// 只有默认用户
+const DEFAULT_USER: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClientType {
+    Normal,
+    PubSub,
+    // 没有主从复制，不会匹配任何连接
+    Master,
+    Replica,
+}
+
+impl ClientType {
+    fn parse(name: &[u8]) -> crate::Result<ClientType> {
+        match &String::from_utf8_lossy(name).to_lowercase()[..] {
+            "normal" => Ok(ClientType::Normal),
+            "pubsub" => Ok(ClientType::PubSub),
+            "master" => Ok(ClientType::Master),
+            "replica" | "slave" => Ok(ClientType::Replica),
+            _ => Err(format!("ERR Unknown client type '{}'", String::from_utf8_lossy(name)).into()),
+        }
+    }
+
+    fn matches(&self, info: &ClientInfo) -> bool {
+        match self {
+            ClientType::Normal => !info.is_pubsub(),
+            ClientType::PubSub => info.is_pubsub(),
+            ClientType::Master | ClientType::Replica => false,
+        }
+    }
+}
+
+// CLIENT KILL 的过滤条件，都满足的连接才会被关掉
+#[derive(Debug, Default)]
+pub struct KillFilter {
+    id: Option<u64>,
+    addr: Option<String>,
+    laddr: Option<String>,
+    user: Option<String>,
+    client_type: Option<ClientType>,
+    skip_me: bool,
+}
+
+impl KillFilter {
+    fn matches(&self, info: &ClientInfo, me: u64) -> bool {
+        self.id.is_none_or(|id| info.id == id)
+            && self.addr.as_ref().is_none_or(|addr| info.addr.to_string() == *addr)
+            && self.laddr.as_ref().is_none_or(|laddr| info.local_addr.to_string() == *laddr)
+            && self.user.as_ref().is_none_or(|user| user == DEFAULT_USER)
+            && self.client_type.is_none_or(|client_type| client_type.matches(info))
+            && !(self.skip_me && info.id == me)
+    }
+}
+
+// CLIENT 的子命令，都要用到连接自己的状态，由Handler执行
+#[derive(Debug)]
+pub enum Client {
+    SetName { name: String },
+    GetName,
+    Id,
+    Info,
+    List { client_type: Option<ClientType>, ids: Vec<u64> },
+    // 旧的 CLIENT KILL addr 格式找不到的时候回复错误，新的格式回复关掉的个数
+    Kill { filter: KillFilter, legacy: bool },
+    Pause { timeout: Duration, mode: PauseMode },
+    Unpause,
+}
+
+impl Client {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Client::SetName { .. } => "client|setname",
+            Client::GetName => "client|getname",
+            Client::Id => "client|id",
+            Client::Info => "client|info",
+            Client::List { .. } => "client|list",
+            Client::Kill { .. } => "client|kill",
+            Client::Pause { .. } => "client|pause",
+            Client::Unpause => "client|unpause",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Client> {
+        let subcommand = parse.next_string()?.to_uppercase();
+        let args = parse.rest_bytes()?;
+        let client = match (&subcommand[..], &args[..]) {
+            ("SETNAME", [name]) => {
+                let name = String::from_utf8_lossy(name).to_string();
+                if !valid_name(&name) {
+                    return Err("ERR Client names cannot contain spaces, newlines or special characters.".into());
+                }
+                Client::SetName { name }
+            }
+            ("GETNAME", []) => Client::GetName,
+            ("ID", []) => Client::Id,
+            ("INFO", []) => Client::Info,
+            ("LIST", args) => parse_list(args)?,
+            ("KILL", [addr]) => {
+                let filter = KillFilter { addr: Some(String::from_utf8_lossy(addr).to_string()), ..KillFilter::default() };
+                Client::Kill { filter, legacy: true }
+            }
+            ("KILL", args) => Client::Kill { filter: parse_kill_filter(args)?, legacy: false },
+            ("PAUSE", [timeout]) => Client::Pause { timeout: parse_timeout(timeout)?, mode: PauseMode::All },
+            ("PAUSE", [timeout, mode]) => {
+                let mode = match &String::from_utf8_lossy(mode).to_uppercase()[..] {
+                    "WRITE" => PauseMode::Write,
+                    "ALL" => PauseMode::All,
+                    _ => return Err("ERR CLIENT PAUSE mode must be WRITE or ALL".into()),
+                };
+                Client::Pause { timeout: parse_timeout(timeout)?, mode }
+            }
+            ("PAUSE", _) => return Err("ERR syntax error".into()),
+            ("UNPAUSE", []) => Client::Unpause,
+            // 其他子命令的参数个数已经按命令表检查过
+            _ => return Err(format!("ERR unknown subcommand '{}'. Try CLIENT HELP.", subcommand).into()),
+        };
+        Ok(client)
+    }
+
+    pub(crate) fn execute(self, me: &ClientHandle, clients: &Clients) -> crate::Result<Frame> {
+        let ok = Frame::Simple("OK".to_string());
+        Ok(match self {
+            // 空的名字表示清掉名字
+            Client::SetName { name } => {
+                me.info().name = if name.is_empty() { None } else { Some(name) };
+                ok
+            }
+            Client::GetName => match &me.info().name {
+                Some(name) => Frame::Bulk(Bytes::from(name.clone())),
+                None => Frame::Null,
+            },
+            Client::Id => Frame::Integer(me.info().id as i64),
+            Client::Info => Frame::Bulk(Bytes::from(format!("{}\n", me.info().describe()))),
+            Client::List { client_type, ids } => {
+                let mut lines = String::new();
+                for client in clients.list() {
+                    let info = client.info();
+                    if client_type.is_some_and(|client_type| !client_type.matches(&info)) {
+                        continue;
+                    }
+                    if !ids.is_empty() && !ids.contains(&info.id) {
+                        continue;
+                    }
+                    lines.push_str(&info.describe());
+                    lines.push('\n');
+                }
+                Frame::Bulk(Bytes::from(lines))
+            }
+            Client::Kill { filter, legacy } => {
+                let me = me.info().id;
+                let mut killed = 0;
+                for client in clients.list() {
+                    if filter.matches(&client.info(), me) {
+                        client.kill();
+                        killed += 1;
+                    }
+                }
+                match legacy {
+                    true if killed == 0 => return Err("ERR No such client".into()),
+                    true => ok,
+                    false => Frame::Integer(killed),
+                }
+            }
+            Client::Pause { timeout, mode } => {
+                clients.pause(mode, timeout);
+                ok
+            }
+            Client::Unpause => {
+                clients.unpause();
+                ok
+            }
+        })
+    }
+}
+
+fn parse_list(args: &[Bytes]) -> crate::Result<Client> {
+    match args {
+        [] => Ok(Client::List { client_type: None, ids: vec![] }),
+        [option, name] if option.eq_ignore_ascii_case(b"type") => {
+            Ok(Client::List { client_type: Some(ClientType::parse(name)?), ids: vec![] })
+        }
+        [option, ids @ ..] if option.eq_ignore_ascii_case(b"id") && !ids.is_empty() => {
+            let ids = ids
+                .iter()
+                .map(|id| parse_int(id).filter(|id| *id > 0).map(|id| id as u64))
+                .collect::<Option<Vec<_>>>()
+                .ok_or("ERR Invalid client ID")?;
+            Ok(Client::List { client_type: None, ids })
+        }
+        _ => Err("ERR syntax error".into()),
+    }
+}
+
+fn parse_kill_filter(args: &[Bytes]) -> crate::Result<KillFilter> {
+    if !args.len().is_multiple_of(2) {
+        return Err("ERR syntax error".into());
+    }
+    // 新的格式默认不关掉自己
+    let mut filter = KillFilter { skip_me: true, ..KillFilter::default() };
+    for pair in args.chunks(2) {
+        let value = String::from_utf8_lossy(&pair[1]).to_string();
+        match &String::from_utf8_lossy(&pair[0]).to_uppercase()[..] {
+            "ID" => {
+                let id = parse_int(&pair[1]).filter(|id| *id > 0).ok_or("ERR client-id should be greater than 0")?;
+                filter.id = Some(id as u64);
+            }
+            "ADDR" => filter.addr = Some(value),
+            "LADDR" => filter.laddr = Some(value),
+            "USER" => {
+                if value != DEFAULT_USER {
+                    return Err(format!("ERR No such user '{}'", value).into());
+                }
+                filter.user = Some(value);
+            }
+            "TYPE" => filter.client_type = Some(ClientType::parse(&pair[1])?),
+            "SKIPME" => {
+                filter.skip_me = match &value.to_lowercase()[..] {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err("ERR syntax error".into()),
+                }
+            }
+            _ => return Err("ERR syntax error".into()),
+        }
+    }
+    Ok(filter)
+}
+
+// 毫秒数
+fn parse_timeout(timeout: &[u8]) -> crate::Result<Duration> {
+    let timeout = parse_int(timeout).filter(|ms| *ms >= 0).ok_or("ERR timeout is not an integer or out of range")?;
+    Ok(Duration::from_millis(timeout as u64))
+}
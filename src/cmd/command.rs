@@ -0,0 +1,217 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::module::CommandHandler;
+use super::table::{self, CommandSpec, KeySpec, CMD_MODULE};
+use bytes::Bytes;
+use std::sync::Arc;
+
+// COMMAND 以及它的子命令，信息都来自命令表和注册的命令
+#[derive(Debug)]
+pub enum CommandInfo {
+    All,
+    Count,
+    List,
+    Info { names: Vec<String> },
+    Docs { names: Vec<String> },
+    GetKeys { args: Vec<Bytes> },
+}
+
+// 内置命令和注册的命令统一成一样的输出
+enum Entry {
+    Builtin(&'static CommandSpec),
+    Module(Arc<dyn CommandHandler>),
+}
+
+impl Entry {
+    fn name(&self) -> String {
+        match self {
+            Entry::Builtin(spec) => spec.name.to_string(),
+            Entry::Module(handler) => handler.name().to_lowercase(),
+        }
+    }
+
+    fn arity(&self) -> i32 {
+        match self {
+            Entry::Builtin(spec) => spec.arity,
+            Entry::Module(handler) => handler.arity(),
+        }
+    }
+
+    fn flags(&self) -> u32 {
+        match self {
+            Entry::Builtin(spec) => spec.flags,
+            Entry::Module(handler) => handler.flags() | CMD_MODULE,
+        }
+    }
+
+    fn key_spec(&self) -> KeySpec {
+        match self {
+            Entry::Builtin(spec) => spec.keys,
+            Entry::Module(handler) => handler.key_spec(),
+        }
+    }
+
+    fn key_positions(&self, args: &[Bytes]) -> Option<Vec<usize>> {
+        match self {
+            Entry::Builtin(spec) => spec.key_positions(args),
+            Entry::Module(handler) => Some(handler.key_spec().positions(args)),
+        }
+    }
+
+    fn info(&self) -> Frame {
+        let keys = self.key_spec();
+        let (categories, subcommands) = match self {
+            Entry::Builtin(spec) => (
+                spec.categories.iter().map(|category| simple(category)).collect(),
+                spec.subcommands.iter().map(|sub| Entry::Builtin(sub).info()).collect(),
+            ),
+            Entry::Module(_) => (Vec::new(), Vec::new()),
+        };
+        Frame::Array(vec![
+            bulk(&self.name()),
+            Frame::Integer(self.arity() as i64),
+            Frame::Array(table::flag_names(self.flags()).into_iter().map(simple).collect()),
+            Frame::Integer(keys.first as i64),
+            Frame::Integer(keys.last as i64),
+            Frame::Integer(keys.step as i64),
+            Frame::Array(categories),
+            Frame::Array(vec![]),
+            Frame::Array(key_specs(keys)),
+            Frame::Array(subcommands),
+        ])
+    }
+
+    fn docs(&self) -> Frame {
+        let fields = match self {
+            Entry::Builtin(spec) => vec![
+                bulk("summary"),
+                bulk(spec.summary),
+                bulk("since"),
+                bulk(spec.since),
+                bulk("group"),
+                bulk(spec.group),
+            ],
+            Entry::Module(_) => vec![bulk("group"), bulk("module")],
+        };
+        Frame::Array(fields)
+    }
+}
+
+fn bulk(s: &str) -> Frame {
+    Frame::Bulk(Bytes::copy_from_slice(s.as_bytes()))
+}
+
+fn simple(s: &str) -> Frame {
+    Frame::Simple(s.to_string())
+}
+
+// 把旧的 first/last/step 转成redis7的key spec格式
+fn key_specs(keys: KeySpec) -> Vec<Frame> {
+    if keys.first <= 0 {
+        return Vec::new();
+    }
+    let lastkey = if keys.last < 0 { keys.last } else { keys.last - keys.first };
+    vec![Frame::Array(vec![
+        bulk("flags"),
+        Frame::Array(vec![]),
+        bulk("begin_search"),
+        Frame::Array(vec![
+            bulk("type"),
+            bulk("index"),
+            bulk("spec"),
+            Frame::Array(vec![bulk("index"), Frame::Integer(keys.first as i64)]),
+        ]),
+        bulk("find_keys"),
+        Frame::Array(vec![
+            bulk("type"),
+            bulk("range"),
+            bulk("spec"),
+            Frame::Array(vec![
+                bulk("lastkey"),
+                Frame::Integer(lastkey as i64),
+                bulk("keystep"),
+                Frame::Integer(keys.step as i64),
+                bulk("limit"),
+                Frame::Integer(0),
+            ]),
+        ]),
+    ])]
+}
+
+impl CommandInfo {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            CommandInfo::All => "command",
+            CommandInfo::Count => "command|count",
+            CommandInfo::List => "command|list",
+            CommandInfo::Info { .. } => "command|info",
+            CommandInfo::Docs { .. } => "command|docs",
+            CommandInfo::GetKeys { .. } => "command|getkeys",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<CommandInfo> {
+        let args = parse.rest_bytes()?;
+        let (subcommand, rest) = match args.split_first() {
+            Some(split) => split,
+            None => return Ok(CommandInfo::All),
+        };
+        let subcommand = String::from_utf8_lossy(subcommand).to_uppercase();
+        let names = || rest.iter().map(|name| String::from_utf8_lossy(name).to_lowercase()).collect();
+        match &subcommand[..] {
+            "COUNT" => Ok(CommandInfo::Count),
+            // 不支持 FILTERBY
+            "LIST" if rest.is_empty() => Ok(CommandInfo::List),
+            "LIST" => Err("ERR syntax error".into()),
+            "INFO" => Ok(CommandInfo::Info { names: names() }),
+            "DOCS" => Ok(CommandInfo::Docs { names: names() }),
+            "GETKEYS" => Ok(CommandInfo::GetKeys { args: rest.to_vec() }),
+            _ => Err(format!("ERR unknown subcommand '{}'. Try COMMAND HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let entries = || {
+            let builtin = table::commands().iter().map(Entry::Builtin);
+            builtin.chain(db.registry().commands().into_iter().map(Entry::Module)).collect::<Vec<_>>()
+        };
+        let find = |name: &str| match table::lookup(name) {
+            Some(spec) => Some(Entry::Builtin(spec)),
+            None => db.registry().command(name).map(Entry::Module),
+        };
+        Ok(match self {
+            CommandInfo::All => Frame::Array(entries().iter().map(Entry::info).collect()),
+            CommandInfo::Count => Frame::Integer(entries().len() as i64),
+            CommandInfo::List => Frame::Array(entries().iter().map(|entry| bulk(&entry.name())).collect()),
+            // 不存在的命令回复Null
+            CommandInfo::Info { names } if names.is_empty() => Frame::Array(entries().iter().map(Entry::info).collect()),
+            CommandInfo::Info { names } => {
+                Frame::Array(names.iter().map(|name| find(name).map_or(Frame::Null, |entry| entry.info())).collect())
+            }
+            // 不存在的命令直接跳过
+            CommandInfo::Docs { names } => {
+                let found = if names.is_empty() { entries() } else { names.iter().filter_map(|name| find(name)).collect() };
+                let mut items = Vec::with_capacity(found.len() * 2);
+                for entry in found {
+                    items.push(bulk(&entry.name()));
+                    items.push(entry.docs());
+                }
+                Frame::Array(items)
+            }
+            CommandInfo::GetKeys { args } => {
+                let name = String::from_utf8_lossy(&args[0]).to_string();
+                let entry = find(&name).ok_or("ERR Invalid command specified")?;
+                if table::check_arity(&name, entry.arity(), args.len()).is_err() {
+                    return Err("ERR Invalid number of arguments specified for command".into());
+                }
+                let positions = entry.key_positions(&args).ok_or("ERR Invalid arguments specified for command")?;
+                if positions.is_empty() {
+                    return Err("ERR The command has no key arguments".into());
+                }
+                Frame::Array(positions.into_iter().map(|idx| Frame::Bulk(args[idx].clone())).collect())
+            }
+        })
+    }
+}
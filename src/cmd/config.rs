@@ -0,0 +1,96 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::config::Config;
+use crate::notify;
+use crate::cmd::arity_error;
+use bytes::Bytes;
+use std::collections::HashSet;
+
+// CONFIG 的子命令
+#[derive(Debug)]
+pub enum ConfigCommand {
+    Get { patterns: Vec<String> },
+    // 可以一次设置多个，有一个失败的时候都不生效
+    Set { pairs: Vec<(String, String)> },
+    // 把当前配置写回启动时读取的配置文件
+    Rewrite,
+    ResetStat,
+}
+
+impl ConfigCommand {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ConfigCommand::Get { .. } => "config|get",
+            ConfigCommand::Set { .. } => "config|set",
+            ConfigCommand::Rewrite => "config|rewrite",
+            ConfigCommand::ResetStat => "config|resetstat",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ConfigCommand> {
+        let subcommand = parse.next_string()?.to_uppercase();
+        let args: Vec<String> = parse.rest_bytes()?.iter().map(|arg| String::from_utf8_lossy(arg).to_string()).collect();
+        match &subcommand[..] {
+            "GET" => Ok(ConfigCommand::Get { patterns: args }),
+            // 参数要成对出现，命令表的arity只能保证至少一对
+            "SET" if !args.len().is_multiple_of(2) => Err(arity_error("config|set")),
+            "SET" => {
+                let pairs = args.chunks(2).map(|pair| (pair[0].to_lowercase(), pair[1].clone())).collect();
+                Ok(ConfigCommand::Set { pairs })
+            }
+            "REWRITE" => Ok(ConfigCommand::Rewrite),
+            "RESETSTAT" => Ok(ConfigCommand::ResetStat),
+            _ => Err(format!("ERR unknown subcommand '{}'. Try CONFIG HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let config = db.config();
+        match self {
+            ConfigCommand::Get { patterns } => {
+                let config = config.read().unwrap();
+                // 多个pattern匹配到同一个参数的时候只返回一次
+                let mut seen = HashSet::new();
+                let names: Vec<&str> = patterns.iter().flat_map(|pattern| Config::names(pattern)).filter(|name| seen.insert(*name)).collect();
+                let mut items = Vec::with_capacity(names.len() * 2);
+                for name in names {
+                    let value = config.get(name).unwrap_or_default();
+                    items.push(Frame::Bulk(Bytes::from(name)));
+                    items.push(Frame::Bulk(Bytes::from(value)));
+                }
+                Ok(Frame::Array(items))
+            }
+            ConfigCommand::Set { pairs } => {
+                let mut config = config.write().unwrap();
+                let mut updated = config.clone();
+                let mut seen = HashSet::new();
+                for (name, value) in &pairs {
+                    if !seen.insert(name) {
+                        return Err(format!("ERR CONFIG SET failed (possibly related to argument '{}') - duplicate parameter", name).into());
+                    }
+                    match updated.set(name, value) {
+                        Some(Ok(())) => {}
+                        Some(Err(reason)) => {
+                            return Err(format!("ERR CONFIG SET failed (possibly related to argument '{}') - {}", name, reason).into());
+                        }
+                        None => return Err(format!("ERR Unknown option or number of arguments for CONFIG SET - '{}'", name).into()),
+                    }
+                }
+                // 通知类型在State里面另外保存了一份解析好的
+                let flags = notify::parse_flags(&updated.notify_keyspace_events).unwrap_or(0);
+                *config = updated;
+                drop(config);
+                db.set_notify_flags(flags);
+                Ok(Frame::Simple("OK".to_string()))
+            }
+            // 写文件不能持有State的锁，只能由 Command::apply 在锁外面执行
+            ConfigCommand::Rewrite => Err("ERR CONFIG REWRITE isn't allowed in this context".into()),
+            ConfigCommand::ResetStat => {
+                db.stats().reset();
+                Ok(Frame::Simple("OK".to_string()))
+            }
+        }
+    }
+}
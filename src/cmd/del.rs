@@ -0,0 +1,37 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_GENERIC;
+
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<String>,
+}
+
+impl Del {
+    pub fn new(keys: Vec<String>) -> Del {
+        Del { keys }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Del> {
+        // 至少要有一个key
+        let mut keys = vec![parse.next_string()?];
+        keys.extend(parse.rest_bytes()?.iter().map(|key| String::from_utf8_lossy(key).to_string()));
+        Ok(Del::new(keys))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let mut removed = 0;
+        for key in &self.keys {
+            // 已经过期的key不算
+            if db.get(key).is_none() {
+                continue;
+            }
+            db.remove(key);
+            db.signal_modified_key(key);
+            db.notify_keyspace_event(NOTIFY_GENERIC, "del", key);
+            removed += 1;
+        }
+        Ok(Frame::Integer(removed))
+    }
+}
@@ -0,0 +1,22 @@
+use crate::parse::Parse;
+use crate::frame::Frame;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct Echo {
+    msg: Bytes,
+}
+
+impl Echo {
+    pub fn new(msg: Bytes) -> Echo {
+        Echo { msg }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Echo> {
+        Ok(Echo::new(parse.next_bytes()?))
+    }
+
+    pub(crate) fn execute(self) -> crate::Result<Frame> {
+        Ok(Frame::Bulk(self.msg))
+    }
+}
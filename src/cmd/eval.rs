@@ -0,0 +1,61 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::scripting;
+use crate::util::parse_int;
+use bytes::Bytes;
+
+// EVAL 和 EVALSHA，区别只在于脚本是直接给出还是用sha1引用缓存
+#[derive(Debug)]
+pub struct Eval {
+    script: Script,
+    keys: Vec<Bytes>,
+    args: Vec<Bytes>,
+}
+
+#[derive(Debug)]
+enum Script {
+    Body(Bytes),
+    Sha(String),
+}
+
+impl Eval {
+    // 命令表里面的名字
+    pub(crate) fn name(&self) -> &'static str {
+        match self.script {
+            Script::Body(_) => "eval",
+            Script::Sha(_) => "evalsha",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Eval> {
+        let body = parse.next_bytes()?;
+        Eval::parse_keys(parse, Script::Body(body))
+    }
+
+    pub(crate) fn parse_sha_frames(parse: &mut Parse) -> crate::Result<Eval> {
+        let sha = parse.next_string()?.to_lowercase();
+        Eval::parse_keys(parse, Script::Sha(sha))
+    }
+
+    fn parse_keys(parse: &mut Parse, script: Script) -> crate::Result<Eval> {
+        let numkeys = parse_int(&parse.next_bytes()?).ok_or("ERR value is not an integer or out of range")?;
+        let mut args = parse.rest_bytes()?;
+        if numkeys < 0 {
+            return Err("ERR Number of keys can't be negative".into());
+        }
+        if numkeys as usize > args.len() {
+            return Err("ERR Number of keys can't be greater than number of args".into());
+        }
+        let keys: Vec<Bytes> = args.drain(..numkeys as usize).collect();
+        Ok(Eval { script, keys, args })
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let sha = match self.script {
+            Script::Body(body) => db.scripting().load(&body)?,
+            Script::Sha(sha) => sha,
+        };
+        scripting::eval(db, &sha, self.keys, self.args)
+    }
+}
@@ -0,0 +1,44 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::functions;
+use crate::util::parse_int;
+use bytes::Bytes;
+
+// FCALL 和 FCALL_RO，参数的格式和 EVAL 一样
+#[derive(Debug)]
+pub struct FCall {
+    function: String,
+    keys: Vec<Bytes>,
+    args: Vec<Bytes>,
+    read_only: bool,
+}
+
+impl FCall {
+    // 命令表里面的名字
+    pub(crate) fn name(&self) -> &'static str {
+        if self.read_only { "fcall_ro" } else { "fcall" }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse, read_only: bool) -> crate::Result<FCall> {
+        let function = parse.next_string()?;
+        let numkeys = parse_int(&parse.next_bytes()?).ok_or("ERR value is not an integer or out of range")?;
+        let mut args = parse.rest_bytes()?;
+        if numkeys < 0 {
+            return Err("ERR Number of keys can't be negative".into());
+        }
+        if numkeys as usize > args.len() {
+            return Err("ERR Number of keys can't be greater than number of args".into());
+        }
+        let keys: Vec<Bytes> = args.drain(..numkeys as usize).collect();
+        Ok(FCall { function, keys, args, read_only })
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        functions::fcall(db, &self.function, self.keys, self.args, self.read_only)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use crate::functions::RestorePolicy;
+use bytes::Bytes;
+
+// FUNCTION 的各个子命令，KILL 和 SCRIPT KILL 一样不需要拿锁
+#[derive(Debug)]
+pub enum Function {
+    Load { code: Bytes, replace: bool },
+    Delete { library: String },
+    Flush,
+    List { pattern: Option<Bytes>, with_code: bool },
+    Dump,
+    Restore { payload: Bytes, policy: RestorePolicy },
+    Kill,
+}
+
+impl Function {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Function::Load { .. } => "function|load",
+            Function::Delete { .. } => "function|delete",
+            Function::Flush => "function|flush",
+            Function::List { .. } => "function|list",
+            Function::Dump => "function|dump",
+            Function::Restore { .. } => "function|restore",
+            Function::Kill => "function|kill",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Function> {
+        let subcommand = parse.next_string()?;
+        match &subcommand.to_uppercase()[..] {
+            "LOAD" => {
+                let mut args = parse.rest_bytes()?;
+                // 最后一个参数是代码，命令表保证了至少有一个参数
+                let code = args.pop().unwrap();
+                let replace = match &args[..] {
+                    [] => false,
+                    [arg] if arg.eq_ignore_ascii_case(b"replace") => true,
+                    [arg] => return Err(format!("ERR Unknown option given: {}", String::from_utf8_lossy(arg)).into()),
+                    _ => return Err("ERR syntax error".into()),
+                };
+                Ok(Function::Load { code, replace })
+            }
+            "DELETE" => Ok(Function::Delete { library: parse.next_string()? }),
+            "FLUSH" => {
+                // ASYNC 和 SYNC 效果一样，都是立即清空
+                match parse.next_string() {
+                    Ok(mode) if mode.eq_ignore_ascii_case("async") || mode.eq_ignore_ascii_case("sync") => {}
+                    Err(EndOfStream) => {}
+                    Ok(_) => return Err("ERR FUNCTION FLUSH only supports SYNC|ASYNC option".into()),
+                    Err(err) => return Err(err.into()),
+                }
+                Ok(Function::Flush)
+            }
+            "LIST" => {
+                let mut pattern = None;
+                let mut with_code = false;
+                let args = parse.rest_bytes()?;
+                let mut args = args.into_iter();
+                while let Some(arg) = args.next() {
+                    match &String::from_utf8_lossy(&arg).to_uppercase()[..] {
+                        "WITHCODE" if !with_code => with_code = true,
+                        "LIBRARYNAME" if pattern.is_none() => {
+                            pattern = Some(args.next().ok_or("ERR library name argument was not given")?);
+                        }
+                        _ => return Err(format!("ERR Unknown argument {}", String::from_utf8_lossy(&arg)).into()),
+                    }
+                }
+                Ok(Function::List { pattern, with_code })
+            }
+            "DUMP" => Ok(Function::Dump),
+            "RESTORE" => {
+                let payload = parse.next_bytes()?;
+                let policy = match parse.next_string() {
+                    Ok(policy) => match &policy.to_uppercase()[..] {
+                        "APPEND" => RestorePolicy::Append,
+                        "REPLACE" => RestorePolicy::Replace,
+                        "FLUSH" => RestorePolicy::Flush,
+                        _ => return Err("ERR Wrong restore policy given, value should be either FLUSH, APPEND or REPLACE.".into()),
+                    },
+                    Err(EndOfStream) => RestorePolicy::Append,
+                    Err(err) => return Err(err.into()),
+                };
+                Ok(Function::Restore { payload, policy })
+            }
+            "KILL" => Ok(Function::Kill),
+            _ => Err(format!("ERR unknown subcommand '{}'. Try FUNCTION HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let functions = db.functions();
+        let response = match self {
+            Function::Load { code, replace } => Frame::Bulk(Bytes::from(functions.load(code, replace)?)),
+            Function::Delete { library } => {
+                functions.delete(&library)?;
+                Frame::Simple("OK".to_string())
+            }
+            Function::Flush => {
+                functions.flush();
+                Frame::Simple("OK".to_string())
+            }
+            Function::List { pattern, with_code } => return Ok(functions.list(pattern.as_deref(), with_code)),
+            Function::Dump => return Ok(Frame::Bulk(functions.dump())),
+            Function::Restore { payload, policy } => {
+                functions.restore(&payload, policy)?;
+                Frame::Simple("OK".to_string())
+            }
+            // 能拿到锁说明没有脚本在执行
+            Function::Kill => return Err("NOTBUSY No scripts in execution right now.".into()),
+        };
+        // 函数库被修改了，保存下来
+        functions.persist();
+        Ok(response)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::cmd::ZAdd;
+use crate::geohash::{self, GEO_STEP_MAX};
+use crate::zset::AddFlags;
+
+#[derive(Debug)]
+pub struct GeoAdd {
+    zadd: ZAdd,
+}
+
+impl GeoAdd {
+    pub fn key(&self) -> &str {
+        self.zadd.key()
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoAdd> {
+        let key = parse.next_string()?;
+        let args = parse.rest_bytes()?;
+
+        let mut flags = AddFlags::default();
+        let mut ch = false;
+        let mut idx = 0;
+        while idx < args.len() {
+            match &String::from_utf8_lossy(&args[idx]).to_uppercase()[..] {
+                "NX" => flags.nx = true,
+                "XX" => flags.xx = true,
+                "CH" => ch = true,
+                _ => break,
+            }
+            idx += 1;
+        }
+        let rest = &args[idx..];
+        if rest.is_empty() || rest.len() % 3 != 0 {
+            return Err("ERR syntax error. Try GEOADD key [NX|XX] [CH] longitude latitude member".into());
+        }
+        if flags.nx && flags.xx {
+            return Err("ERR XX and NX options at the same time are not compatible".into());
+        }
+
+        let mut members = Vec::with_capacity(rest.len() / 3);
+        for chunk in rest.chunks(3) {
+            let (longitude, latitude) = geohash::parse_long_lat(&chunk[0], &chunk[1])?;
+            let hash = geohash::encode_wgs84(longitude, latitude, GEO_STEP_MAX).unwrap();
+            members.push((geohash::align_52bits(hash) as f64, chunk[2].clone()));
+        }
+        Ok(GeoAdd {
+            zadd: ZAdd::new(key, members).with_flags(flags, ch),
+        })
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        self.zadd.execute(db)
+    }
+}
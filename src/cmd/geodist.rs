@@ -0,0 +1,57 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use crate::geohash;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct GeoDist {
+    key: String,
+    member1: Bytes,
+    member2: Bytes,
+    // 换算成米的系数
+    unit: f64,
+}
+
+impl GeoDist {
+    pub fn new(key: impl ToString, member1: Bytes, member2: Bytes) -> GeoDist {
+        GeoDist {
+            key: key.to_string(),
+            member1,
+            member2,
+            unit: 1.0,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoDist> {
+        let key = parse.next_string()?;
+        let member1 = parse.next_bytes()?;
+        let member2 = parse.next_bytes()?;
+        let mut geodist = GeoDist::new(key, member1, member2);
+        match parse.next_bytes() {
+            Ok(unit) => geodist.unit = geohash::parse_unit(&unit)?,
+            Err(EndOfStream) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(geodist)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let zset = match db.get_zset(&self.key)? {
+            Some(zset) => zset,
+            None => return Ok(Frame::Null),
+        };
+        match (zset.score(&self.member1), zset.score(&self.member2)) {
+            (Some(score1), Some(score2)) => {
+                let (lon1, lat1) = geohash::decode_score(score1);
+                let (lon2, lat2) = geohash::decode_score(score2);
+                Ok(geohash::distance_frame(geohash::distance(lon1, lat1, lon2, lat2), self.unit))
+            }
+            _ => Ok(Frame::Null),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::geohash;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct GeoHash {
+    key: String,
+    members: Vec<Bytes>,
+}
+
+impl GeoHash {
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> GeoHash {
+        GeoHash {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoHash> {
+        let key = parse.next_string()?;
+        let members = parse.rest_bytes()?;
+        Ok(GeoHash::new(key, members))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let zset = db.get_zset(&self.key)?;
+        let hashes = self
+            .members
+            .iter()
+            .map(|member| match zset.and_then(|zset| zset.score(member)) {
+                Some(score) => Frame::Bulk(Bytes::from(geohash::to_base32(score))),
+                None => Frame::Null,
+            })
+            .collect();
+        Ok(Frame::Array(hashes))
+    }
+}
@@ -0,0 +1,43 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::geohash;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct GeoPos {
+    key: String,
+    members: Vec<Bytes>,
+}
+
+impl GeoPos {
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> GeoPos {
+        GeoPos {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GeoPos> {
+        let key = parse.next_string()?;
+        let members = parse.rest_bytes()?;
+        Ok(GeoPos::new(key, members))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let zset = db.get_zset(&self.key)?;
+        let positions = self
+            .members
+            .iter()
+            .map(|member| match zset.and_then(|zset| zset.score(member)) {
+                Some(score) => geohash::coord_frame(geohash::decode_score(score)),
+                None => Frame::NullArray,
+            })
+            .collect();
+        Ok(Frame::Array(positions))
+    }
+}
@@ -0,0 +1,278 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_GENERIC;
+use crate::cmd::zsetstore::store_members;
+use crate::geohash::{self, GeoShape, GeoHashBits};
+use crate::skiplist::ScoreRange;
+use crate::util::{parse_double, parse_int};
+use crate::zset::ZSet;
+use bytes::Bytes;
+
+#[derive(Debug, Clone)]
+pub enum GeoOrigin {
+    Member(Bytes),
+    // (经度, 纬度)
+    LonLat(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoSort {
+    None,
+    Asc,
+    Desc,
+}
+
+// 搜索命中的点，dist单位是米
+#[derive(Debug)]
+struct GeoPoint {
+    member: Bytes,
+    dist: f64,
+    score: f64,
+    coord: (f64, f64),
+}
+
+// GEOSEARCH 和 GEOSEARCHSTORE 共用，store不为None的时候把结果写到目标key
+#[derive(Debug)]
+pub struct GeoSearch {
+    key: String,
+    store: Option<String>,
+    store_dist: bool,
+    origin: GeoOrigin,
+    // 已经换算成米
+    shape: GeoShape,
+    // 单位换算成米的系数
+    unit: f64,
+    sort: GeoSort,
+    count: Option<usize>,
+    any: bool,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+}
+
+impl GeoSearch {
+    // 命令表里面的名字
+    pub(crate) fn name(&self) -> &'static str {
+        if self.store.is_some() { "geosearchstore" } else { "geosearch" }
+    }
+
+    pub fn new(key: impl ToString, origin: GeoOrigin, shape: GeoShape) -> GeoSearch {
+        GeoSearch {
+            key: key.to_string(),
+            store: None,
+            store_dist: false,
+            origin,
+            shape,
+            unit: 1.0,
+            sort: GeoSort::None,
+            count: None,
+            any: false,
+            with_coord: false,
+            with_dist: false,
+            with_hash: false,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn store(&self) -> Option<&str> {
+        self.store.as_deref()
+    }
+
+    // store为true的时候是 GEOSEARCHSTORE，第一个参数是目标key
+    pub(crate) fn parse_frames(parse: &mut Parse, store: bool) -> crate::Result<GeoSearch> {
+        let dest = if store { Some(parse.next_string()?) } else { None };
+        let key = parse.next_string()?;
+        let args = parse.rest_bytes()?;
+
+        let mut origin = None;
+        let mut shape = None;
+        let mut search = GeoSearch::new(key, GeoOrigin::LonLat(0.0, 0.0), GeoShape::Radius(0.0));
+        let mut idx = 0;
+        while idx < args.len() {
+            let remaining = args.len() - idx - 1;
+            match &String::from_utf8_lossy(&args[idx]).to_uppercase()[..] {
+                "WITHDIST" => search.with_dist = true,
+                "WITHHASH" => search.with_hash = true,
+                "WITHCOORD" => search.with_coord = true,
+                "ANY" => search.any = true,
+                "ASC" => search.sort = GeoSort::Asc,
+                "DESC" => search.sort = GeoSort::Desc,
+                "STOREDIST" if store => search.store_dist = true,
+                "COUNT" if remaining >= 1 => {
+                    let count = parse_int(&args[idx + 1]).filter(|count| *count > 0).ok_or("ERR COUNT must be > 0")?;
+                    search.count = Some(count as usize);
+                    idx += 1;
+                }
+                "FROMMEMBER" if remaining >= 1 => {
+                    if origin.is_some() {
+                        return Err("ERR syntax error".into());
+                    }
+                    origin = Some(GeoOrigin::Member(args[idx + 1].clone()));
+                    idx += 1;
+                }
+                "FROMLONLAT" if remaining >= 2 => {
+                    if origin.is_some() {
+                        return Err("ERR syntax error".into());
+                    }
+                    let (longitude, latitude) = geohash::parse_long_lat(&args[idx + 1], &args[idx + 2])?;
+                    origin = Some(GeoOrigin::LonLat(longitude, latitude));
+                    idx += 2;
+                }
+                "BYRADIUS" if remaining >= 2 => {
+                    if shape.is_some() {
+                        return Err("ERR syntax error".into());
+                    }
+                    let radius = parse_double(&args[idx + 1]).ok_or("ERR need numeric radius")?;
+                    if radius < 0.0 {
+                        return Err("ERR radius cannot be negative".into());
+                    }
+                    search.unit = geohash::parse_unit(&args[idx + 2])?;
+                    shape = Some(GeoShape::Radius(radius * search.unit));
+                    idx += 2;
+                }
+                "BYBOX" if remaining >= 3 => {
+                    if shape.is_some() {
+                        return Err("ERR syntax error".into());
+                    }
+                    let width = parse_double(&args[idx + 1]).ok_or("ERR need numeric width")?;
+                    let height = parse_double(&args[idx + 2]).ok_or("ERR need numeric height")?;
+                    if width < 0.0 || height < 0.0 {
+                        return Err("ERR height or width cannot be negative".into());
+                    }
+                    search.unit = geohash::parse_unit(&args[idx + 3])?;
+                    shape = Some(GeoShape::Box { width: width * search.unit, height: height * search.unit });
+                    idx += 3;
+                }
+                _ => return Err("ERR syntax error".into()),
+            }
+            idx += 1;
+        }
+
+        search.origin = origin.ok_or("ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH")?;
+        search.shape = shape.ok_or("ERR exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH")?;
+        if search.any && search.count.is_none() {
+            return Err("ERR the ANY argument requires COUNT argument".into());
+        }
+        if store && (search.with_dist || search.with_hash || search.with_coord) {
+            return Err("ERR GEOSEARCHSTORE is not compatible with WITHDIST, WITHHASH and WITHCOORD options".into());
+        }
+        search.store = dest;
+        Ok(search)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let zset = match db.get_zset(&self.key)? {
+            Some(zset) => zset,
+            None => {
+                return Ok(match self.store {
+                    Some(dest) => {
+                        if db.remove(&dest).is_some() {
+                            db.notify_keyspace_event(NOTIFY_GENERIC, "del", &dest);
+                        }
+                        Frame::Integer(0)
+                    }
+                    None => Frame::Array(vec![]),
+                });
+            }
+        };
+
+        let center = match &self.origin {
+            GeoOrigin::LonLat(longitude, latitude) => (*longitude, *latitude),
+            GeoOrigin::Member(member) => {
+                let score = zset.score(member).ok_or("ERR could not decode requested zset member")?;
+                geohash::decode_score(score)
+            }
+        };
+
+        // 只有ANY的时候才能提前结束
+        let limit = if self.any { self.count } else { None };
+        let mut points = search_points(zset, self.shape, center, limit);
+
+        // 没有ANY的时候，COUNT需要返回最近的N个，所以一定要排序
+        let sort = if !self.any && self.count.is_some() && self.sort == GeoSort::None { GeoSort::Asc } else { self.sort };
+        match sort {
+            GeoSort::Asc => points.sort_by(|a, b| a.dist.partial_cmp(&b.dist).unwrap()),
+            GeoSort::Desc => points.sort_by(|a, b| b.dist.partial_cmp(&a.dist).unwrap()),
+            GeoSort::None => {}
+        }
+        if let Some(count) = self.count {
+            points.truncate(count);
+        }
+
+        if let Some(dest) = &self.store {
+            let members = points
+                .into_iter()
+                .map(|point| {
+                    let score = if self.store_dist { point.dist / self.unit } else { point.score };
+                    (point.member, score)
+                })
+                .collect();
+            return Ok(Frame::Integer(store_members(db, dest.clone(), members, "geosearchstore") as i64));
+        }
+
+        let with_any = self.with_dist || self.with_hash || self.with_coord;
+        let result = points
+            .into_iter()
+            .map(|point| {
+                if !with_any {
+                    return Frame::Bulk(point.member);
+                }
+                let mut item = vec![Frame::Bulk(point.member)];
+                if self.with_dist {
+                    item.push(geohash::distance_frame(point.dist, self.unit));
+                }
+                if self.with_hash {
+                    item.push(Frame::Integer(point.score as i64));
+                }
+                if self.with_coord {
+                    item.push(geohash::coord_frame(point.coord));
+                }
+                Frame::Array(item)
+            })
+            .collect();
+        Ok(Frame::Array(result))
+    }
+}
+
+// 依次搜索中心格子和周围8个格子，limit不为None的时候找到足够的点就返回
+fn search_points(zset: &ZSet, shape: GeoShape, center: (f64, f64), limit: Option<usize>) -> Vec<GeoPoint> {
+    let areas = geohash::areas_by_shape(center.0, center.1, shape);
+    let cells = std::iter::once(Some(areas.hash)).chain(areas.neighbors.iter().copied());
+
+    let mut points = vec![];
+    let mut last: Option<GeoHashBits> = None;
+    for (i, cell) in cells.enumerate() {
+        let cell = match cell {
+            Some(cell) => cell,
+            None => continue,
+        };
+        // 半径很大的时候相邻的格子可能是同一个，和redis一样只和上一个处理过的邻居比较
+        if last == Some(cell) {
+            continue;
+        }
+        if limit.is_some_and(|limit| points.len() >= limit) {
+            break;
+        }
+
+        let min = geohash::align_52bits(cell);
+        let max = geohash::align_52bits(GeoHashBits { bits: cell.bits + 1, step: cell.step });
+        let range = ScoreRange { min: min as f64, max: max as f64, minex: false, maxex: true };
+        for (member, score) in zset.range_by_score(&range, false, 0, None) {
+            let coord = geohash::decode_score(score);
+            if let Some(dist) = geohash::distance_if_in_shape(shape, center, coord) {
+                points.push(GeoPoint { member, dist, score, coord });
+                if limit.is_some_and(|limit| points.len() >= limit) {
+                    break;
+                }
+            }
+        }
+        if i > 0 {
+            last = Some(cell);
+        }
+    }
+    points
+}
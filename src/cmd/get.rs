@@ -1,6 +1,6 @@
 use crate::parse::Parse;
-use crate::db::Db;
-use crate::connection::Connection;
+use crate::db::{State, Value, WRONGTYPE};
+use crate::frame::Frame;
 
 #[derive(Debug)]
 pub struct Get {
@@ -23,5 +23,11 @@ impl Get {
         Ok(Get::new(key))
     }
 
-    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {}
-}
\ No newline at end of file
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        match db.lookup_read(&self.key) {
+            Some(Value::String(value)) => Ok(Frame::Bulk(value.clone())),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(Frame::Null),
+        }
+    }
+}
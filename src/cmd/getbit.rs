@@ -0,0 +1,38 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::bitmap;
+use crate::cmd::setbit::parse_bit_offset;
+
+#[derive(Debug)]
+pub struct GetBit {
+    key: String,
+    offset: u64,
+}
+
+impl GetBit {
+    pub fn new(key: impl ToString, offset: u64) -> GetBit {
+        GetBit {
+            key: key.to_string(),
+            offset,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetBit> {
+        let key = parse.next_string()?;
+        let offset = parse_bit_offset(&parse.next_bytes()?)?;
+        Ok(GetBit::new(key, offset))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let bit = match db.get_string(&self.key)? {
+            Some(value) => bitmap::get_bit(value, self.offset),
+            None => 0,
+        };
+        Ok(Frame::Integer(bit as i64))
+    }
+}
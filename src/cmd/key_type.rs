@@ -0,0 +1,23 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+
+#[derive(Debug)]
+pub struct Type {
+    key: String,
+}
+
+impl Type {
+    pub fn new(key: impl ToString) -> Type {
+        Type { key: key.to_string() }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Type> {
+        Ok(Type::new(parse.next_string()?))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let name = db.get(&self.key).map(|value| value.type_name().to_string());
+        Ok(Frame::Simple(name.unwrap_or_else(|| "none".to_string())))
+    }
+}
@@ -0,0 +1,50 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+
+// key本身和过期时间等结构体的开销
+const KEY_OVERHEAD: usize = 56;
+
+// MEMORY 目前只支持 USAGE
+#[derive(Debug)]
+pub enum Memory {
+    Usage { key: String },
+}
+
+impl Memory {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Memory::Usage { .. } => "memory|usage",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Memory> {
+        let subcommand = parse.next_string()?;
+        match &subcommand.to_uppercase()[..] {
+            "USAGE" => {
+                let key = parse.next_string()?;
+                // 估算的时候总是统计全部元素，SAMPLES 只检查格式
+                let args = parse.rest_bytes()?;
+                match &args[..] {
+                    [] => {}
+                    [option, count] if option.eq_ignore_ascii_case(b"samples") => {
+                        crate::util::parse_int(count).filter(|n| *n >= 0).ok_or("ERR value is out of range, must be positive")?;
+                    }
+                    _ => return Err("ERR syntax error".into()),
+                }
+                Ok(Memory::Usage { key })
+            }
+            _ => Err(format!("ERR unknown subcommand '{}'. Try MEMORY HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        match self {
+            Memory::Usage { key } => Ok(match db.get(&key) {
+                Some(value) => Frame::Integer((KEY_OVERHEAD + key.len() + value.mem_usage()) as i64),
+                None => Frame::Null,
+            }),
+        }
+    }
+}
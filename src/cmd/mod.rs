@@ -1,9 +1,11 @@
 use crate::frame::Frame;
-use crate::db::Db;
+use crate::db::{Db, State};
 use crate::connection::Connection;
 use crate::shutdown::Shutdown;
-use crate::parse::{Parse, ParseError};
-use tracing::{debug};
+use crate::parse::Parse;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::time::{self, Duration, Instant};
 
 mod get;
 
@@ -13,6 +15,262 @@ mod set;
 
 pub use set::Set;
 
+mod del;
+
+pub use del::Del;
+
+mod zadd;
+
+pub use zadd::ZAdd;
+
+mod zrange;
+
+pub use zrange::ZRange;
+
+mod zrank;
+
+pub use zrank::ZRank;
+
+mod zscore;
+
+pub use zscore::ZScore;
+
+mod zincrby;
+
+pub use zincrby::ZIncrBy;
+
+mod zrem;
+
+pub use zrem::ZRem;
+
+mod zcount;
+
+pub use zcount::ZCount;
+
+mod zpop;
+
+pub use zpop::ZPop;
+
+mod zsetstore;
+
+pub use zsetstore::{ZSetStore, SetOp, Aggregate};
+
+mod zrangestore;
+
+pub use zrangestore::ZRangeStore;
+
+mod bzpop;
+
+pub use bzpop::BZPop;
+
+mod xadd;
+
+pub use xadd::XAdd;
+
+mod xrange;
+
+pub use xrange::XRange;
+
+mod xlen;
+
+pub use xlen::XLen;
+
+mod xtrim;
+
+pub use xtrim::XTrim;
+
+mod xdel;
+
+pub use xdel::XDel;
+
+mod xread;
+
+pub use xread::{XRead, ReadId};
+
+mod xgroup;
+
+pub use xgroup::XGroup;
+
+mod xreadgroup;
+
+pub use xreadgroup::{XReadGroup, GroupReadId};
+
+mod xack;
+
+pub use xack::XAck;
+
+mod xpending;
+
+pub use xpending::XPending;
+
+mod xclaim;
+
+pub use xclaim::XClaim;
+
+mod xautoclaim;
+
+pub use xautoclaim::XAutoClaim;
+
+mod xinfo;
+
+pub use xinfo::XInfo;
+
+mod setbit;
+
+pub use setbit::SetBit;
+
+mod getbit;
+
+pub use getbit::GetBit;
+
+mod bitcount;
+
+pub use bitcount::BitCount;
+
+mod bitpos;
+
+pub use bitpos::BitPos;
+
+mod bitop;
+
+pub use bitop::BitOp;
+
+mod bitfield;
+
+pub use bitfield::{BitField, FieldOp, FieldType, Overflow};
+
+mod pfadd;
+
+pub use pfadd::PfAdd;
+
+mod pfcount;
+
+pub use pfcount::PfCount;
+
+mod pfmerge;
+
+pub use pfmerge::PfMerge;
+
+mod geoadd;
+
+pub use geoadd::GeoAdd;
+
+mod geodist;
+
+pub use geodist::GeoDist;
+
+mod geopos;
+
+pub use geopos::GeoPos;
+
+mod geohash;
+
+pub use geohash::GeoHash;
+
+mod geosearch;
+
+pub use geosearch::{GeoSearch, GeoOrigin, GeoSort};
+
+mod subscribe;
+
+pub use subscribe::{Subscribe, Unsubscribe};
+
+mod psubscribe;
+
+pub use psubscribe::{PSubscribe, PUnsubscribe};
+
+mod ssubscribe;
+
+pub use ssubscribe::{SSubscribe, SUnsubscribe};
+
+mod spublish;
+
+pub use spublish::SPublish;
+
+mod pubsub;
+
+pub use pubsub::PubSubInfo;
+
+mod publish;
+
+pub use publish::Publish;
+
+mod ping;
+
+pub use ping::Ping;
+
+mod quit;
+
+pub use quit::Quit;
+
+mod echo;
+
+pub use echo::Echo;
+
+mod select;
+
+pub use select::{Select, DATABASES};
+
+mod reset;
+
+pub use reset::Reset;
+
+mod client;
+
+pub use client::Client;
+
+mod multi;
+
+pub use multi::{Multi, Exec, Discard, Transaction};
+
+mod watch;
+
+pub use watch::{Watch, Unwatch, WatchedKeys};
+
+mod eval;
+
+pub use eval::Eval;
+
+mod script;
+
+pub use script::Script;
+
+mod function;
+
+pub use function::Function;
+
+mod fcall;
+
+pub use fcall::FCall;
+
+mod key_type;
+
+pub use key_type::Type;
+
+mod memory;
+
+pub use memory::Memory;
+
+mod config;
+
+pub use config::ConfigCommand;
+
+mod command;
+
+pub use command::CommandInfo;
+
+mod table;
+
+pub use table::{
+    KeySpec, CMD_ADMIN, CMD_ALLOW_BUSY, CMD_BLOCKING, CMD_DENYOOM, CMD_FAST, CMD_LOADING, CMD_MODULE, CMD_MOVABLE_KEYS,
+    CMD_MAY_REPLICATE, CMD_NOSCRIPT, CMD_NO_MULTI, CMD_PUBSUB, CMD_READONLY, CMD_STALE, CMD_WRITE,
+};
+pub(crate) use table::{arity_error, check_arity, lookup as lookup_command};
+
+mod module;
+
+pub use module::ModuleCommand;
+
 mod unknown;
 
 pub use unknown::Unknown;
@@ -21,11 +279,90 @@ pub use unknown::Unknown;
 pub enum Command {
     Get(Get),
     Set(Set),
+    Del(Del),
+    ZAdd(ZAdd),
+    ZRange(ZRange),
+    ZRank(ZRank),
+    ZScore(ZScore),
+    ZIncrBy(ZIncrBy),
+    ZRem(ZRem),
+    ZCount(ZCount),
+    ZPop(ZPop),
+    ZSetStore(ZSetStore),
+    ZRangeStore(ZRangeStore),
+    BZPop(BZPop),
+    XAdd(XAdd),
+    XRange(XRange),
+    XLen(XLen),
+    XTrim(XTrim),
+    XDel(XDel),
+    XRead(XRead),
+    XGroup(XGroup),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    XInfo(XInfo),
+    SetBit(SetBit),
+    GetBit(GetBit),
+    BitCount(BitCount),
+    BitPos(BitPos),
+    BitOp(BitOp),
+    BitField(BitField),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    GeoAdd(GeoAdd),
+    GeoDist(GeoDist),
+    GeoPos(GeoPos),
+    GeoHash(GeoHash),
+    GeoSearch(GeoSearch),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    Publish(Publish),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
+    SPublish(SPublish),
+    PubSub(PubSubInfo),
+    Ping(Ping),
+    Quit(Quit),
+    Echo(Echo),
+    Select(Select),
+    Reset(Reset),
+    Client(Client),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    Eval(Eval),
+    Script(Script),
+    Function(Function),
+    FCall(FCall),
+    Type(Type),
+    Memory(Memory),
+    CommandInfo(CommandInfo),
+    Config(ConfigCommand),
+    Module(ModuleCommand),
     UnKnown(Unknown),
 }
 
 impl Command {
     pub fn from_frame(frame: Frame) -> crate::Result<Command> {
+        let argc = match &frame {
+            Frame::Array(items) => items.len(),
+            _ => 0,
+        };
+        // 参数个数按命令表统一检查，有子命令的时候检查子命令的，不认识的子命令由命令自己回复错误
+        let spec = command_fullname(&frame)
+            .and_then(|name| table::lookup(&name))
+            .or_else(|| command_name(&frame).and_then(|name| table::lookup(&name)));
+        if let Some(spec) = spec {
+            check_arity(spec.name, spec.arity, argc)?;
+        }
         let mut parse = Parse::new(frame)?;
         let command_name = parse.next_string()?;
         let command = match &command_name.to_lowercase()[..] {
@@ -35,25 +372,613 @@ impl Command {
             "set" => {
                 Command::Set(Set::parse_frames(&mut parse)?)
             }
+            "del" => {
+                Command::Del(Del::parse_frames(&mut parse)?)
+            }
+            "zadd" => {
+                Command::ZAdd(ZAdd::parse_frames(&mut parse)?)
+            }
+            "zrange" => {
+                Command::ZRange(ZRange::parse_frames(&mut parse)?)
+            }
+            "zrank" => {
+                Command::ZRank(ZRank::parse_frames(&mut parse)?)
+            }
+            "zscore" => {
+                Command::ZScore(ZScore::parse_frames(&mut parse)?)
+            }
+            "zincrby" => {
+                Command::ZIncrBy(ZIncrBy::parse_frames(&mut parse)?)
+            }
+            "zrem" => {
+                Command::ZRem(ZRem::parse_frames(&mut parse)?)
+            }
+            "zcount" => {
+                Command::ZCount(ZCount::parse_frames(&mut parse)?)
+            }
+            "zpopmin" => {
+                Command::ZPop(ZPop::parse_frames(&mut parse, false)?)
+            }
+            "zpopmax" => {
+                Command::ZPop(ZPop::parse_frames(&mut parse, true)?)
+            }
+            "zunionstore" => {
+                Command::ZSetStore(ZSetStore::parse_frames(&mut parse, SetOp::Union, "zunionstore")?)
+            }
+            "zinterstore" => {
+                Command::ZSetStore(ZSetStore::parse_frames(&mut parse, SetOp::Inter, "zinterstore")?)
+            }
+            "zdiffstore" => {
+                Command::ZSetStore(ZSetStore::parse_frames(&mut parse, SetOp::Diff, "zdiffstore")?)
+            }
+            "zrangestore" => {
+                Command::ZRangeStore(ZRangeStore::parse_frames(&mut parse)?)
+            }
+            "bzpopmin" => {
+                Command::BZPop(BZPop::parse_frames(&mut parse, false)?)
+            }
+            "bzpopmax" => {
+                Command::BZPop(BZPop::parse_frames(&mut parse, true)?)
+            }
+            "xadd" => {
+                Command::XAdd(XAdd::parse_frames(&mut parse)?)
+            }
+            "xrange" => {
+                Command::XRange(XRange::parse_frames(&mut parse, false)?)
+            }
+            "xrevrange" => {
+                Command::XRange(XRange::parse_frames(&mut parse, true)?)
+            }
+            "xlen" => {
+                Command::XLen(XLen::parse_frames(&mut parse)?)
+            }
+            "xtrim" => {
+                Command::XTrim(XTrim::parse_frames(&mut parse)?)
+            }
+            "xdel" => {
+                Command::XDel(XDel::parse_frames(&mut parse)?)
+            }
+            "xread" => {
+                Command::XRead(XRead::parse_frames(&mut parse)?)
+            }
+            "xgroup" => {
+                Command::XGroup(XGroup::parse_frames(&mut parse)?)
+            }
+            "xreadgroup" => {
+                Command::XReadGroup(XReadGroup::parse_frames(&mut parse)?)
+            }
+            "xack" => {
+                Command::XAck(XAck::parse_frames(&mut parse)?)
+            }
+            "xpending" => {
+                Command::XPending(XPending::parse_frames(&mut parse)?)
+            }
+            "xclaim" => {
+                Command::XClaim(XClaim::parse_frames(&mut parse)?)
+            }
+            "xautoclaim" => {
+                Command::XAutoClaim(XAutoClaim::parse_frames(&mut parse)?)
+            }
+            "xinfo" => {
+                Command::XInfo(XInfo::parse_frames(&mut parse)?)
+            }
+            "setbit" => {
+                Command::SetBit(SetBit::parse_frames(&mut parse)?)
+            }
+            "getbit" => {
+                Command::GetBit(GetBit::parse_frames(&mut parse)?)
+            }
+            "bitcount" => {
+                Command::BitCount(BitCount::parse_frames(&mut parse)?)
+            }
+            "bitpos" => {
+                Command::BitPos(BitPos::parse_frames(&mut parse)?)
+            }
+            "bitop" => {
+                Command::BitOp(BitOp::parse_frames(&mut parse)?)
+            }
+            "bitfield" => {
+                Command::BitField(BitField::parse_frames(&mut parse, false)?)
+            }
+            "bitfield_ro" => {
+                Command::BitField(BitField::parse_frames(&mut parse, true)?)
+            }
+            "pfadd" => {
+                Command::PfAdd(PfAdd::parse_frames(&mut parse)?)
+            }
+            "pfcount" => {
+                Command::PfCount(PfCount::parse_frames(&mut parse)?)
+            }
+            "pfmerge" => {
+                Command::PfMerge(PfMerge::parse_frames(&mut parse)?)
+            }
+            "geoadd" => {
+                Command::GeoAdd(GeoAdd::parse_frames(&mut parse)?)
+            }
+            "geodist" => {
+                Command::GeoDist(GeoDist::parse_frames(&mut parse)?)
+            }
+            "geopos" => {
+                Command::GeoPos(GeoPos::parse_frames(&mut parse)?)
+            }
+            "geohash" => {
+                Command::GeoHash(GeoHash::parse_frames(&mut parse)?)
+            }
+            "geosearch" => {
+                Command::GeoSearch(GeoSearch::parse_frames(&mut parse, false)?)
+            }
+            "geosearchstore" => {
+                Command::GeoSearch(GeoSearch::parse_frames(&mut parse, true)?)
+            }
+            "subscribe" => {
+                Command::Subscribe(Subscribe::parse_frames(&mut parse)?)
+            }
+            "unsubscribe" => {
+                Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?)
+            }
+            "publish" => {
+                Command::Publish(Publish::parse_frames(&mut parse)?)
+            }
+            "psubscribe" => {
+                Command::PSubscribe(PSubscribe::parse_frames(&mut parse)?)
+            }
+            "punsubscribe" => {
+                Command::PUnsubscribe(PUnsubscribe::parse_frames(&mut parse)?)
+            }
+            "ssubscribe" => {
+                Command::SSubscribe(SSubscribe::parse_frames(&mut parse)?)
+            }
+            "sunsubscribe" => {
+                Command::SUnsubscribe(SUnsubscribe::parse_frames(&mut parse)?)
+            }
+            "spublish" => {
+                Command::SPublish(SPublish::parse_frames(&mut parse)?)
+            }
+            "pubsub" => {
+                Command::PubSub(PubSubInfo::parse_frames(&mut parse)?)
+            }
+            "ping" => {
+                Command::Ping(Ping::parse_frames(&mut parse)?)
+            }
+            "quit" => {
+                Command::Quit(Quit::parse_frames(&mut parse)?)
+            }
+            "echo" => {
+                Command::Echo(Echo::parse_frames(&mut parse)?)
+            }
+            "select" => {
+                Command::Select(Select::parse_frames(&mut parse)?)
+            }
+            "reset" => {
+                Command::Reset(Reset::parse_frames(&mut parse)?)
+            }
+            "client" => {
+                Command::Client(Client::parse_frames(&mut parse)?)
+            }
+            "multi" => {
+                Command::Multi(Multi::parse_frames(&mut parse)?)
+            }
+            "exec" => {
+                Command::Exec(Exec::parse_frames(&mut parse)?)
+            }
+            "discard" => {
+                Command::Discard(Discard::parse_frames(&mut parse)?)
+            }
+            "watch" => {
+                Command::Watch(Watch::parse_frames(&mut parse)?)
+            }
+            "unwatch" => {
+                Command::Unwatch(Unwatch::parse_frames(&mut parse)?)
+            }
+            "eval" => {
+                Command::Eval(Eval::parse_frames(&mut parse)?)
+            }
+            "evalsha" => {
+                Command::Eval(Eval::parse_sha_frames(&mut parse)?)
+            }
+            "script" => {
+                Command::Script(Script::parse_frames(&mut parse)?)
+            }
+            "function" => {
+                Command::Function(Function::parse_frames(&mut parse)?)
+            }
+            "fcall" => {
+                Command::FCall(FCall::parse_frames(&mut parse, false)?)
+            }
+            "fcall_ro" => {
+                Command::FCall(FCall::parse_frames(&mut parse, true)?)
+            }
+            "type" => {
+                Command::Type(Type::parse_frames(&mut parse)?)
+            }
+            "memory" => {
+                Command::Memory(Memory::parse_frames(&mut parse)?)
+            }
+            "command" => {
+                Command::CommandInfo(CommandInfo::parse_frames(&mut parse)?)
+            }
+            "config" => {
+                Command::Config(ConfigCommand::parse_frames(&mut parse)?)
+            }
             _ => {
-                Command::UnKnown(Unknown::new(command_name))
+                // 未知命令不再检查剩余的参数
+                return Ok(Command::UnKnown(Unknown::new(command_name)));
             }
         };
 
-        // 检查是否还有剩余的
-        parse.finish()?;
+        // 还有剩余的参数说明比命令能接受的多
+        if parse.finish().is_err() {
+            return Err(arity_error(spec.map_or(&command_name[..], |spec| spec.name)));
+        }
         Ok(command)
     }
 
-    pub async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+    // 在锁住的State上执行命令，错误会作为回复返回给客户端
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
         match self {
-            Command::Get(cmd) => {}
-            Command::Set(cmd) => {}
-            Command::UnKnown(cmd) => {}
+            Command::Get(cmd) => cmd.execute(db),
+            Command::Set(cmd) => cmd.execute(db),
+            Command::Del(cmd) => cmd.execute(db),
+            Command::ZAdd(cmd) => cmd.execute(db),
+            Command::ZRange(cmd) => cmd.execute(db),
+            Command::ZRank(cmd) => cmd.execute(db),
+            Command::ZScore(cmd) => cmd.execute(db),
+            Command::ZIncrBy(cmd) => cmd.execute(db),
+            Command::ZRem(cmd) => cmd.execute(db),
+            Command::ZCount(cmd) => cmd.execute(db),
+            Command::ZPop(cmd) => cmd.execute(db),
+            Command::ZSetStore(cmd) => cmd.execute(db),
+            Command::ZRangeStore(cmd) => cmd.execute(db),
+            Command::BZPop(cmd) => cmd.execute(db),
+            Command::XAdd(cmd) => cmd.execute(db),
+            Command::XRange(cmd) => cmd.execute(db),
+            Command::XLen(cmd) => cmd.execute(db),
+            Command::XTrim(cmd) => cmd.execute(db),
+            Command::XDel(cmd) => cmd.execute(db),
+            Command::XRead(cmd) => cmd.execute(db),
+            Command::XGroup(cmd) => cmd.execute(db),
+            Command::XReadGroup(cmd) => cmd.execute(db),
+            Command::XAck(cmd) => cmd.execute(db),
+            Command::XPending(cmd) => cmd.execute(db),
+            Command::XClaim(cmd) => cmd.execute(db),
+            Command::XAutoClaim(cmd) => cmd.execute(db),
+            Command::XInfo(cmd) => cmd.execute(db),
+            Command::SetBit(cmd) => cmd.execute(db),
+            Command::GetBit(cmd) => cmd.execute(db),
+            Command::BitCount(cmd) => cmd.execute(db),
+            Command::BitPos(cmd) => cmd.execute(db),
+            Command::BitOp(cmd) => cmd.execute(db),
+            Command::BitField(cmd) => cmd.execute(db),
+            Command::PfAdd(cmd) => cmd.execute(db),
+            Command::PfCount(cmd) => cmd.execute(db),
+            Command::PfMerge(cmd) => cmd.execute(db),
+            Command::GeoAdd(cmd) => cmd.execute(db),
+            Command::GeoDist(cmd) => cmd.execute(db),
+            Command::GeoPos(cmd) => cmd.execute(db),
+            Command::GeoHash(cmd) => cmd.execute(db),
+            Command::GeoSearch(cmd) => cmd.execute(db),
+            Command::Publish(cmd) => cmd.execute(db),
+            Command::SPublish(cmd) => cmd.execute(db),
+            Command::PubSub(cmd) => cmd.execute(db),
+            Command::Ping(cmd) => cmd.execute(),
+            Command::Quit(_) => Ok(Frame::Simple("OK".to_string())),
+            Command::Echo(cmd) => cmd.execute(),
+            // 只有一个数据库，事务和脚本里面的 SELECT 不需要切换
+            Command::Select(_) => Ok(Frame::Simple("OK".to_string())),
+            // 连接的状态由Handler处理
+            Command::Reset(_) => Err("ERR RESET isn't allowed in this context".into()),
+            Command::Client(_) => Err("ERR CLIENT isn't allowed in this context".into()),
+            // 订阅需要连接的状态，由Handler处理
+            Command::Subscribe(_) | Command::Unsubscribe(_) => Err("ERR SUBSCRIBE isn't allowed in this context".into()),
+            Command::PSubscribe(_) | Command::PUnsubscribe(_) => Err("ERR PSUBSCRIBE isn't allowed in this context".into()),
+            Command::SSubscribe(_) | Command::SUnsubscribe(_) => Err("ERR SSUBSCRIBE isn't allowed in this context".into()),
+            // 事务的状态在连接上，由Handler处理
+            Command::Multi(_) | Command::Exec(_) | Command::Discard(_) => Err("ERR Command not allowed inside a transaction".into()),
+            Command::Watch(_) => Err("ERR WATCH inside MULTI is not allowed".into()),
+            // 事务里面的UNWATCH没有效果，EXEC 之后所有的key都会取消WATCH
+            Command::Unwatch(_) => Ok(Frame::Simple("OK".to_string())),
+            Command::Eval(cmd) => cmd.execute(db),
+            Command::Script(cmd) => cmd.execute(db),
+            Command::Function(cmd) => cmd.execute(db),
+            Command::FCall(cmd) => cmd.execute(db),
+            Command::Type(cmd) => cmd.execute(db),
+            Command::Memory(cmd) => cmd.execute(db),
+            Command::CommandInfo(cmd) => cmd.execute(db),
+            Command::Config(cmd) => cmd.execute(db),
+            Command::Module(cmd) => cmd.execute(db),
+            Command::UnKnown(cmd) => cmd.execute(),
         }
+    }
+
+    // 命令表里面的名字，子命令带上父命令，比如 config|get
+    fn table_name(&self) -> Option<&'static str> {
+        Some(match self {
+            Command::Get(_) => "get",
+            Command::Set(_) => "set",
+            Command::Del(_) => "del",
+            Command::ZAdd(_) => "zadd",
+            Command::ZRange(_) => "zrange",
+            Command::ZRank(_) => "zrank",
+            Command::ZScore(_) => "zscore",
+            Command::ZIncrBy(_) => "zincrby",
+            Command::ZRem(_) => "zrem",
+            Command::ZCount(_) => "zcount",
+            Command::ZPop(cmd) => cmd.name(),
+            Command::ZSetStore(cmd) => cmd.name(),
+            Command::ZRangeStore(_) => "zrangestore",
+            Command::BZPop(cmd) => cmd.name(),
+            Command::XAdd(_) => "xadd",
+            Command::XRange(cmd) => cmd.name(),
+            Command::XLen(_) => "xlen",
+            Command::XTrim(_) => "xtrim",
+            Command::XDel(_) => "xdel",
+            Command::XRead(_) => "xread",
+            Command::XGroup(cmd) => cmd.name(),
+            Command::XReadGroup(_) => "xreadgroup",
+            Command::XAck(_) => "xack",
+            Command::XPending(_) => "xpending",
+            Command::XClaim(_) => "xclaim",
+            Command::XAutoClaim(_) => "xautoclaim",
+            Command::XInfo(cmd) => cmd.name(),
+            Command::SetBit(_) => "setbit",
+            Command::GetBit(_) => "getbit",
+            Command::BitCount(_) => "bitcount",
+            Command::BitPos(_) => "bitpos",
+            Command::BitOp(_) => "bitop",
+            // 只有GET的 BITFIELD 和 BITFIELD_RO 一样不修改数据
+            Command::BitField(cmd) if !cmd.is_write() => "bitfield_ro",
+            Command::BitField(_) => "bitfield",
+            Command::PfAdd(_) => "pfadd",
+            Command::PfCount(_) => "pfcount",
+            Command::PfMerge(_) => "pfmerge",
+            Command::GeoAdd(_) => "geoadd",
+            Command::GeoDist(_) => "geodist",
+            Command::GeoPos(_) => "geopos",
+            Command::GeoHash(_) => "geohash",
+            Command::GeoSearch(cmd) => cmd.name(),
+            Command::Subscribe(_) => "subscribe",
+            Command::Unsubscribe(_) => "unsubscribe",
+            Command::Publish(_) => "publish",
+            Command::PSubscribe(_) => "psubscribe",
+            Command::PUnsubscribe(_) => "punsubscribe",
+            Command::SSubscribe(_) => "ssubscribe",
+            Command::SUnsubscribe(_) => "sunsubscribe",
+            Command::SPublish(_) => "spublish",
+            Command::PubSub(cmd) => cmd.name(),
+            Command::Ping(_) => "ping",
+            Command::Quit(_) => "quit",
+            Command::Echo(_) => "echo",
+            Command::Select(_) => "select",
+            Command::Reset(_) => "reset",
+            Command::Client(cmd) => cmd.name(),
+            Command::Multi(_) => "multi",
+            Command::Exec(_) => "exec",
+            Command::Discard(_) => "discard",
+            Command::Watch(_) => "watch",
+            Command::Unwatch(_) => "unwatch",
+            Command::Eval(cmd) => cmd.name(),
+            Command::Script(cmd) => cmd.name(),
+            Command::Function(cmd) => cmd.name(),
+            Command::FCall(cmd) => cmd.name(),
+            Command::Type(_) => "type",
+            Command::Memory(cmd) => cmd.name(),
+            Command::CommandInfo(cmd) => cmd.name(),
+            Command::Config(cmd) => cmd.name(),
+            Command::Module(_) | Command::UnKnown(_) => return None,
+        })
+    }
 
+    // 命令表里面的flag，注册的命令用注册时候的flag
+    pub(crate) fn flags(&self) -> u32 {
+        match self {
+            Command::Module(cmd) => cmd.flags(),
+            cmd => cmd.table_name().and_then(table::lookup).map_or(0, |spec| spec.flags),
+        }
+    }
+
+    // MULTI 之后除了 CMD_NO_MULTI 的命令都要排队
+    pub(crate) fn is_queueable(&self) -> bool {
+        self.flags() & CMD_NO_MULTI == 0
+    }
+
+    // 脚本里面不能执行依赖连接状态的命令，也不能嵌套执行脚本
+    pub(crate) fn is_script_allowed(&self) -> bool {
+        self.flags() & CMD_NOSCRIPT == 0
+    }
+
+    // 会修改数据的命令，只读的脚本里面不能执行
+    pub(crate) fn is_write(&self) -> bool {
+        self.flags() & CMD_WRITE != 0
+    }
+
+    // CLIENT PAUSE WRITE 期间要等待的命令，脚本和发布消息也算
+    pub(crate) fn may_write(&self) -> bool {
+        self.flags() & (CMD_WRITE | CMD_MAY_REPLICATE) != 0
+    }
+
+    // 会执行Lua代码的命令
+    pub(crate) fn runs_scripts(&self) -> bool {
+        matches!(self, Command::Eval(_) | Command::FCall(_) | Command::Function(Function::Load { .. } | Function::Restore { .. }))
+    }
+
+    // 有脚本在执行的时候，只有这些命令不用等待
+    pub(crate) fn is_allowed_when_busy(&self) -> bool {
+        matches!(self, Command::Script(Script::Kill) | Command::Function(Function::Kill))
+    }
+
+    pub async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let cmd = match self {
+            // 脚本执行期间锁一直被占用，SCRIPT KILL 不能拿锁
+            Command::Script(Script::Kill) | Command::Function(Function::Kill) => {
+                let response = db.script_status().kill().unwrap_or_else(error_frame);
+                return dst.write_frame(&response).await.map_err(Into::into);
+            }
+            // 写配置文件不需要State，放到阻塞线程里面做IO
+            Command::Config(ConfigCommand::Rewrite) => {
+                let config = db.config().clone();
+                let response = tokio::task::spawn_blocking(move || config.rewrite()).await?;
+                let response = response.map(|_| Frame::Simple("OK".to_string())).unwrap_or_else(error_frame);
+                return dst.write_frame(&response).await.map_err(Into::into);
+            }
+            // 阻塞命令需要在锁外面等待
+            Command::BZPop(cmd) => return cmd.apply(db, dst, shutdown).await,
+            Command::XRead(cmd) => return cmd.apply(db, dst, shutdown).await,
+            Command::XReadGroup(cmd) => return cmd.apply(db, dst, shutdown).await,
+            // 脚本可能执行很久，放到阻塞线程里面，其他连接才能收到BUSY
+            cmd if cmd.runs_scripts() => {
+                let response = db.execute_blocking(move |state| cmd.execute(state).unwrap_or_else(error_frame)).await?;
+                return dst.write_frame(&response).await.map_err(Into::into);
+            }
+            cmd => cmd,
+        };
+
+        // 锁只在执行期间持有，不能跨越await
+        let response = match db.lock_unless_busy().await {
+            Ok(mut state) => cmd.execute(&mut state).unwrap_or_else(error_frame),
+            Err(err) => error_frame(err),
+        };
+        dst.write_frame(&response).await?;
 
         Ok(())
     }
 }
 
+// 不解析参数，只取出命令名，用来在订阅模式下做检查
+pub(crate) fn command_name(frame: &Frame) -> Option<String> {
+    match frame {
+        Frame::Array(items) => match items.first() {
+            Some(Frame::Bulk(name)) => Some(String::from_utf8_lossy(name).to_lowercase()),
+            Some(Frame::Simple(name)) => Some(name.to_lowercase()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// 带子命令的完整命令名，比如 client|info，命令表里面有子命令的才带上
+pub(crate) fn command_fullname(frame: &Frame) -> Option<String> {
+    let name = command_name(frame)?;
+    if table::lookup(&name).is_none_or(|spec| spec.subcommands.is_empty()) {
+        return Some(name);
+    }
+    let subcommand = match frame {
+        Frame::Array(items) => match items.get(1) {
+            Some(Frame::Bulk(sub)) => String::from_utf8_lossy(sub).to_lowercase(),
+            Some(Frame::Simple(sub)) => sub.to_lowercase(),
+            _ => return Some(name),
+        },
+        _ => return Some(name),
+    };
+    Some(format!("{}|{}", name, subcommand))
+}
+
+// 把错误转成回复，没有错误码前缀的统一加上ERR
+pub(crate) fn error_frame(err: crate::Error) -> Frame {
+    let msg = err.to_string();
+    let prefix = msg.split(' ').next().unwrap_or("");
+    if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_uppercase()) {
+        Frame::Error(msg)
+    } else {
+        Frame::Error(format!("ERR {}", msg))
+    }
+}
+
+// 阻塞等待keys被写入，serve返回Some的时候结束，超时返回空数组，服务关闭的时候返回None
+pub(crate) async fn serve_blocking<F>(db: &Db, keys: &[String], timeout: Option<Duration>, shutdown: &mut Shutdown, mut serve: F) -> Option<Frame>
+    where F: FnMut(&mut State) -> crate::Result<Option<Frame>> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let notify = Arc::new(Notify::new());
+    // 不管怎么结束都要取消等待，包括连接被 CLIENT KILL 的时候整个future被丢掉
+    let _guard = BlockGuard { db, keys, notify: &notify };
+
+    loop {
+        {
+            let mut state = match db.lock_unless_busy().await {
+                Ok(state) => state,
+                Err(err) => return Some(error_frame(err)),
+            };
+            state.unblock_keys(keys, &notify);
+            match serve(&mut state) {
+                Ok(Some(frame)) => return Some(frame),
+                Ok(None) => state.block_on_keys(keys, &notify),
+                Err(err) => return Some(error_frame(err)),
+            }
+        }
+
+        let timeout = async {
+            match deadline {
+                Some(deadline) => time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = notify.notified() => {}
+            _ = timeout => return Some(Frame::NullArray),
+            _ = shutdown.recv() => return None,
+        }
+    }
+}
+
+struct BlockGuard<'a> {
+    db: &'a Db,
+    keys: &'a [String],
+    notify: &'a Arc<Notify>,
+}
+
+impl Drop for BlockGuard<'_> {
+    fn drop(&mut self) {
+        let keys = self.keys.to_vec();
+        let notify = self.notify.clone();
+        self.db.lock_or_defer(move |state| state.unblock_keys(&keys, &notify));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn cmd(line: &str) -> Command {
+        let args = line.split(' ').map(|arg| Frame::Bulk(Bytes::from(arg.to_string()))).collect();
+        Command::from_frame(Frame::Array(args)).unwrap()
+    }
+
+    #[test]
+    fn every_command_is_in_table() {
+        for line in [
+            "zpopmax z", "bzpopmin a 0", "xrevrange s + -", "evalsha 0000000000000000000000000000000000000000 0",
+            "zdiffstore d 1 a", "fcall_ro f 0", "geosearchstore d s FROMLONLAT 0 0 BYRADIUS 1 m", "client id",
+            "command", "command count", "config resetstat", "function dump", "memory usage k", "pubsub numpat",
+            "script flush", "xgroup destroy s g", "xinfo groups s",
+        ] {
+            let name = cmd(line).table_name().unwrap();
+            assert!(table::lookup(name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn predicates_follow_table_flags() {
+        assert!(cmd("set k v").is_write());
+        assert!(!cmd("get k").is_write());
+        assert!(cmd("xgroup destroy s g").is_write());
+        assert!(cmd("geosearchstore d s FROMLONLAT 0 0 BYRADIUS 1 m").is_write());
+        assert!(!cmd("geosearch s FROMLONLAT 0 0 BYRADIUS 1 m").is_write());
+        assert!(cmd("bitfield k SET u8 0 1").is_write());
+        assert!(!cmd("bitfield k GET u8 0").is_write());
+
+        assert!(cmd("publish c m").may_write());
+        assert!(cmd("fcall f 0").may_write());
+        assert!(!cmd("fcall_ro f 0").may_write());
+        assert!(cmd("function flush").may_write());
+        assert!(!cmd("function list").may_write());
+
+        assert!(!cmd("config get x").is_script_allowed());
+        assert!(!cmd("subscribe c").is_script_allowed());
+        assert!(cmd("ping").is_script_allowed());
+
+        assert!(!cmd("multi").is_queueable());
+        assert!(!cmd("watch k").is_queueable());
+        assert!(cmd("unwatch").is_queueable());
+        assert!(cmd("nosuchcommand").is_queueable());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::cmd::check_arity;
+use crate::module::{CommandHandler, Context, Reply};
+use std::fmt;
+use std::sync::Arc;
+
+// 通过 Db::register_command 注册的命令
+pub struct ModuleCommand {
+    handler: Arc<dyn CommandHandler>,
+    // 已经去掉了命令名
+    parse: Parse,
+}
+
+impl fmt::Debug for ModuleCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModuleCommand").field("name", &self.handler.name()).finish()
+    }
+}
+
+impl ModuleCommand {
+    // 参数个数在这里统一检查
+    pub(crate) fn new(handler: Arc<dyn CommandHandler>, frame: Frame) -> crate::Result<ModuleCommand> {
+        let argc = match &frame {
+            Frame::Array(items) => items.len(),
+            _ => 0,
+        };
+        check_arity(&handler.name().to_lowercase(), handler.arity(), argc)?;
+        let mut parse = Parse::new(frame)?;
+        parse.next()?;
+        Ok(ModuleCommand { handler, parse })
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.handler.name()
+    }
+
+    pub(crate) fn flags(&self) -> u32 {
+        self.handler.flags()
+    }
+
+    pub(crate) fn execute(mut self, db: &mut State) -> crate::Result<Frame> {
+        let mut reply = Reply::default();
+        self.handler.execute(&mut self.parse, &mut Context::new(db), &mut reply)?;
+        Ok(reply.into_frame())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::parse::Parse;
+use crate::db::{Db, State};
+use crate::frame::Frame;
+use crate::cmd::{Command, WatchedKeys, error_frame};
+
+#[derive(Debug, Default)]
+pub struct Multi {}
+
+#[derive(Debug, Default)]
+pub struct Exec {}
+
+#[derive(Debug, Default)]
+pub struct Discard {}
+
+impl Multi {
+    pub fn new() -> Multi {
+        Multi {}
+    }
+
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Multi> {
+        Ok(Multi::new())
+    }
+}
+
+impl Exec {
+    pub fn new() -> Exec {
+        Exec {}
+    }
+
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Exec> {
+        Ok(Exec::new())
+    }
+}
+
+impl Discard {
+    pub fn new() -> Discard {
+        Discard {}
+    }
+
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Discard> {
+        Ok(Discard::new())
+    }
+}
+
+// MULTI 之后排队的命令，由Handler持有，EXEC 的时候一次执行完
+#[derive(Debug, Default)]
+pub struct Transaction {
+    commands: Vec<Command>,
+    // 排队的时候出现过语法错误，EXEC 会直接放弃
+    aborted: bool,
+}
+
+impl Transaction {
+    pub fn new() -> Transaction {
+        Transaction::default()
+    }
+
+    pub(crate) fn queue(&mut self, cmd: Command) {
+        self.commands.push(cmd);
+    }
+
+    // 排队的命令数，CLIENT INFO 里面显示
+    pub(crate) fn queued(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub(crate) fn may_write(&self) -> bool {
+        self.commands.iter().any(Command::may_write)
+    }
+
+    pub(crate) fn abort(&mut self) {
+        self.aborted = true;
+    }
+
+    // 事务里面有脚本的时候整个事务放到阻塞线程里面执行
+    pub(crate) async fn exec(self, db: &Db, watched: &mut WatchedKeys) -> crate::Result<Frame> {
+        if self.commands.iter().any(Command::runs_scripts) {
+            let mut taken = std::mem::take(watched);
+            let (response, taken) = db
+                .execute_blocking(move |state| {
+                    let response = self.execute(state, &mut taken);
+                    (response, taken)
+                })
+                .await?;
+            *watched = taken;
+            return Ok(response);
+        }
+        Ok(match db.lock_unless_busy().await {
+            Ok(mut state) => self.execute(&mut state, watched),
+            Err(err) => error_frame(err),
+        })
+    }
+
+    // 整个事务只加一次锁，其他连接的命令不会穿插进来，执行完之后取消所有WATCH
+    fn execute(self, state: &mut State, watched: &mut WatchedKeys) -> Frame {
+        if self.aborted {
+            watched.unwatch_all(state);
+            return Frame::Error("EXECABORT Transaction discarded because of previous errors.".to_string());
+        }
+        // WATCH 的key被修改过，事务不执行
+        if watched.is_dirty(state) {
+            watched.unwatch_all(state);
+            return Frame::NullArray;
+        }
+        watched.unwatch_all(state);
+        let replies = self
+            .commands
+            .into_iter()
+            .map(|cmd| cmd.execute(state).unwrap_or_else(error_frame))
+            .collect();
+        Frame::Array(replies)
+    }
+}
@@ -0,0 +1,60 @@
+use crate::parse::Parse;
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STRING;
+use crate::hyperloglog::HyperLogLog;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: String,
+    elements: Vec<Bytes>,
+}
+
+impl PfAdd {
+    pub fn new(key: impl ToString, elements: Vec<Bytes>) -> PfAdd {
+        PfAdd {
+            key: key.to_string(),
+            elements,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PfAdd> {
+        let key = parse.next_string()?;
+        let elements = parse.rest_bytes()?;
+        Ok(PfAdd::new(key, elements))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let (mut hll, mut updated) = match db.get(&self.key).map(Value::as_string).transpose()? {
+            Some(value) => (HyperLogLog::from_bytes(value)?, false),
+            // 新建的key就算没有元素也算更新
+            None => (HyperLogLog::new(), true),
+        };
+        let mut changed = false;
+        for element in &self.elements {
+            changed |= hll.add(element);
+        }
+        if changed {
+            hll.invalidate_cache();
+            updated = true;
+        }
+
+        if updated {
+            // 保留原来的过期时间
+            match db.get_mut(&self.key) {
+                Some(value) => {
+                    *value = Value::String(Bytes::from(hll.encode()));
+                    db.signal_modified_key(&self.key);
+                }
+                None => db.set(self.key.clone(), Value::String(Bytes::from(hll.encode())), None),
+            }
+            db.notify_keyspace_event(NOTIFY_STRING, "pfadd", &self.key);
+        }
+        Ok(Frame::Integer(updated as i64))
+    }
+}
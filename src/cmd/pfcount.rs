@@ -0,0 +1,58 @@
+use crate::parse::Parse;
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::hyperloglog::HyperLogLog;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<String>,
+}
+
+impl PfCount {
+    pub fn new(keys: Vec<String>) -> PfCount {
+        PfCount { keys }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PfCount> {
+        let mut keys = vec![parse.next_string()?];
+        keys.extend(parse.rest_bytes()?.iter().map(|key| String::from_utf8_lossy(key).to_string()));
+        Ok(PfCount::new(keys))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        // 多个key的时候合并到一个临时的HLL里面计算，不使用缓存
+        if self.keys.len() > 1 {
+            let mut union = HyperLogLog::new();
+            for key in &self.keys {
+                if let Some(value) = db.get_string(key)? {
+                    union.merge(&HyperLogLog::from_bytes(value)?);
+                }
+            }
+            return Ok(Frame::Integer(union.count() as i64));
+        }
+
+        let key = &self.keys[0];
+        let mut hll = match db.get_string(key)? {
+            Some(value) => HyperLogLog::from_bytes(value)?,
+            None => return Ok(Frame::Integer(0)),
+        };
+        if let Some(count) = hll.cached_count() {
+            return Ok(Frame::Integer(count as i64));
+        }
+
+        // 缓存失效了，重新计算之后写回去
+        let count = hll.count();
+        hll.set_cached_count(count);
+        // 和redis一样，写回缓存也算修改
+        if let Some(value) = db.get_mut(key) {
+            *value = Value::String(Bytes::from(hll.encode()));
+            db.signal_modified_key(key);
+        }
+        Ok(Frame::Integer(count as i64))
+    }
+}
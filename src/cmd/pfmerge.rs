@@ -0,0 +1,60 @@
+use crate::parse::Parse;
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STRING;
+use crate::hyperloglog::HyperLogLog;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct PfMerge {
+    dest: String,
+    sources: Vec<String>,
+}
+
+impl PfMerge {
+    pub fn new(dest: impl ToString, sources: Vec<String>) -> PfMerge {
+        PfMerge {
+            dest: dest.to_string(),
+            sources,
+        }
+    }
+
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PfMerge> {
+        let dest = parse.next_string()?;
+        let sources = parse.rest_bytes()?.iter().map(|key| String::from_utf8_lossy(key).to_string()).collect();
+        Ok(PfMerge::new(dest, sources))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        // 目标key自己也参与合并
+        let mut merged = HyperLogLog::new();
+        let mut dense = false;
+        for key in std::iter::once(&self.dest).chain(self.sources.iter()) {
+            if let Some(value) = db.get_string(key)? {
+                let hll = HyperLogLog::from_bytes(value)?;
+                dense |= hll.is_dense();
+                merged.merge(&hll);
+            }
+        }
+
+        // 有一个输入是dense的时候结果也是dense
+        if dense {
+            merged.set_dense();
+        }
+        merged.invalidate_cache();
+        let value = Value::String(Bytes::from(merged.encode()));
+        match db.get_mut(&self.dest) {
+            Some(dest) => {
+                *dest = value;
+                db.signal_modified_key(&self.dest);
+            }
+            None => db.set(self.dest.clone(), value, None),
+        }
+        db.notify_keyspace_event(NOTIFY_STRING, "pfadd", &self.dest);
+        Ok(Frame::Simple("OK".to_string()))
+    }
+}
@@ -0,0 +1,37 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::frame::Frame;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct Ping {
+    msg: Option<Bytes>,
+}
+
+impl Ping {
+    pub fn new(msg: Option<Bytes>) -> Ping {
+        Ping { msg }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Ping> {
+        match parse.next_bytes() {
+            Ok(msg) => Ok(Ping::new(Some(msg))),
+            Err(EndOfStream) => Ok(Ping::new(None)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub(crate) fn execute(self) -> crate::Result<Frame> {
+        Ok(match self.msg {
+            Some(msg) => Frame::Bulk(msg),
+            None => Frame::Simple("PONG".to_string()),
+        })
+    }
+
+    // 订阅模式下回复的格式不一样
+    pub(crate) fn subscribed_reply(self) -> Frame {
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from_static(b"pong")),
+            Frame::Bulk(self.msg.unwrap_or_default()),
+        ])
+    }
+}
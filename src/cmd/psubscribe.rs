@@ -0,0 +1,70 @@
+use crate::parse::Parse;
+use crate::db::Db;
+use crate::frame::Frame;
+use crate::connection::Connection;
+use crate::pubsub::Subscriber;
+use crate::cmd::subscribe::subscription_frame;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<Bytes>,
+}
+
+// patterns为空的时候退订所有模式
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<Bytes>,
+}
+
+impl PSubscribe {
+    pub fn new(patterns: Vec<Bytes>) -> PSubscribe {
+        PSubscribe { patterns }
+    }
+
+    pub fn patterns(&self) -> &[Bytes] {
+        &self.patterns
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PSubscribe> {
+        let mut patterns = vec![parse.next_bytes()?];
+        patterns.extend(parse.rest_bytes()?);
+        Ok(PSubscribe::new(patterns))
+    }
+
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, subscriber: &mut Subscriber) -> crate::Result<()> {
+        for pattern in self.patterns {
+            subscriber.psubscribe(db, pattern.clone()).await;
+            dst.write_frame(&subscription_frame("psubscribe", Frame::Bulk(pattern), subscriber.count())).await?;
+        }
+        Ok(())
+    }
+}
+
+impl PUnsubscribe {
+    pub fn new(patterns: Vec<Bytes>) -> PUnsubscribe {
+        PUnsubscribe { patterns }
+    }
+
+    pub fn patterns(&self) -> &[Bytes] {
+        &self.patterns
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PUnsubscribe> {
+        Ok(PUnsubscribe::new(parse.rest_bytes()?))
+    }
+
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, subscriber: &mut Subscriber) -> crate::Result<()> {
+        let patterns = if self.patterns.is_empty() { subscriber.patterns() } else { self.patterns };
+        // 本来就没有订阅任何模式
+        if patterns.is_empty() {
+            return dst.write_frame(&subscription_frame("punsubscribe", Frame::Null, subscriber.count())).await.map_err(Into::into);
+        }
+
+        for pattern in patterns {
+            subscriber.punsubscribe(db, &pattern).await;
+            dst.write_frame(&subscription_frame("punsubscribe", Frame::Bulk(pattern), subscriber.count())).await?;
+        }
+        Ok(())
+    }
+}
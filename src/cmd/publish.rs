@@ -0,0 +1,28 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: Bytes,
+    message: Bytes,
+}
+
+impl Publish {
+    pub fn new(channel: Bytes, message: Bytes) -> Publish {
+        Publish { channel, message }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Publish> {
+        let channel = parse.next_bytes()?;
+        let message = parse.next_bytes()?;
+        Ok(Publish::new(channel, message))
+    }
+
+    // 返回收到消息的客户端数量
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let receivers = db.pub_sub().publish(&self.channel, &self.message);
+        Ok(Frame::Integer(receivers as i64))
+    }
+}
@@ -0,0 +1,73 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use bytes::Bytes;
+
+// PUBSUB 的各个子命令，只查看订阅的状态
+#[derive(Debug)]
+pub enum PubSubInfo {
+    Channels { pattern: Option<Bytes> },
+    NumSub { channels: Vec<Bytes> },
+    NumPat,
+    ShardChannels { pattern: Option<Bytes> },
+    ShardNumSub { channels: Vec<Bytes> },
+}
+
+impl PubSubInfo {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            PubSubInfo::Channels { .. } => "pubsub|channels",
+            PubSubInfo::NumSub { .. } => "pubsub|numsub",
+            PubSubInfo::NumPat => "pubsub|numpat",
+            PubSubInfo::ShardChannels { .. } => "pubsub|shardchannels",
+            PubSubInfo::ShardNumSub { .. } => "pubsub|shardnumsub",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<PubSubInfo> {
+        let subcommand = parse.next_string()?;
+        match &subcommand.to_uppercase()[..] {
+            "CHANNELS" => Ok(PubSubInfo::Channels { pattern: parse_pattern(parse)? }),
+            "NUMSUB" => Ok(PubSubInfo::NumSub { channels: parse.rest_bytes()? }),
+            "NUMPAT" => Ok(PubSubInfo::NumPat),
+            "SHARDCHANNELS" => Ok(PubSubInfo::ShardChannels { pattern: parse_pattern(parse)? }),
+            "SHARDNUMSUB" => Ok(PubSubInfo::ShardNumSub { channels: parse.rest_bytes()? }),
+            _ => Err(format!("ERR unknown subcommand '{}'. Try PUBSUB HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let pub_sub = db.pub_sub();
+        Ok(match self {
+            PubSubInfo::Channels { pattern } => {
+                Frame::Array(pub_sub.channels(pattern.as_deref()).into_iter().map(Frame::Bulk).collect())
+            }
+            PubSubInfo::NumSub { channels } => numsub_frame(channels, |channel| pub_sub.numsub(channel)),
+            PubSubInfo::NumPat => Frame::Integer(pub_sub.numpat() as i64),
+            PubSubInfo::ShardChannels { pattern } => {
+                Frame::Array(pub_sub.shard_channels(pattern.as_deref()).into_iter().map(Frame::Bulk).collect())
+            }
+            PubSubInfo::ShardNumSub { channels } => numsub_frame(channels, |channel| pub_sub.shard_numsub(channel)),
+        })
+    }
+}
+
+fn parse_pattern(parse: &mut Parse) -> crate::Result<Option<Bytes>> {
+    match parse.next_bytes() {
+        Ok(pattern) => Ok(Some(pattern)),
+        Err(EndOfStream) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// 频道和订阅数交替排列
+fn numsub_frame(channels: Vec<Bytes>, numsub: impl Fn(&[u8]) -> usize) -> Frame {
+    let mut frames = Vec::with_capacity(channels.len() * 2);
+    for channel in channels {
+        let count = numsub(&channel);
+        frames.push(Frame::Bulk(channel));
+        frames.push(Frame::Integer(count as i64));
+    }
+    Frame::Array(frames)
+}
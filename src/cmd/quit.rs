@@ -0,0 +1,17 @@
+use crate::parse::Parse;
+
+// 回复OK之后由Handler关闭连接
+#[derive(Debug, Default)]
+pub struct Quit {}
+
+impl Quit {
+    pub fn new() -> Quit {
+        Quit {}
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Quit> {
+        // 和redis一样忽略多余的参数
+        parse.rest_bytes()?;
+        Ok(Quit::new())
+    }
+}
@@ -0,0 +1,15 @@
+use crate::parse::Parse;
+
+// 清掉连接的所有状态：事务、WATCH、订阅和选择的数据库，由Handler执行
+#[derive(Debug, Default)]
+pub struct Reset {}
+
+impl Reset {
+    pub fn new() -> Reset {
+        Reset {}
+    }
+
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Reset> {
+        Ok(Reset::new())
+    }
+}
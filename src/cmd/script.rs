@@ -0,0 +1,64 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use bytes::Bytes;
+
+// SCRIPT 的各个子命令，KILL 不需要拿锁，在 Command::apply 里面处理
+#[derive(Debug)]
+pub enum Script {
+    Load { body: Bytes },
+    Exists { shas: Vec<String> },
+    Flush,
+    Kill,
+}
+
+impl Script {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Script::Load { .. } => "script|load",
+            Script::Exists { .. } => "script|exists",
+            Script::Flush => "script|flush",
+            Script::Kill => "script|kill",
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Script> {
+        let subcommand = parse.next_string()?;
+        match &subcommand.to_uppercase()[..] {
+            "LOAD" => Ok(Script::Load { body: parse.next_bytes()? }),
+            "EXISTS" => {
+                let shas = parse.rest_bytes()?;
+                Ok(Script::Exists { shas: shas.iter().map(|sha| String::from_utf8_lossy(sha).into_owned()).collect() })
+            }
+            "FLUSH" => {
+                // ASYNC 和 SYNC 效果一样，都是立即清空
+                match parse.next_string() {
+                    Ok(mode) if mode.eq_ignore_ascii_case("async") || mode.eq_ignore_ascii_case("sync") => {}
+                    Err(EndOfStream) => {}
+                    Ok(_) => return Err("ERR SCRIPT FLUSH only support SYNC|ASYNC option".into()),
+                    Err(err) => return Err(err.into()),
+                }
+                Ok(Script::Flush)
+            }
+            "KILL" => Ok(Script::Kill),
+            _ => Err(format!("ERR unknown subcommand '{}'. Try SCRIPT HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let scripting = db.scripting();
+        Ok(match self {
+            Script::Load { body } => Frame::Bulk(Bytes::from(scripting.load(&body)?)),
+            Script::Exists { shas } => {
+                Frame::Array(shas.iter().map(|sha| Frame::Integer(scripting.exists(sha) as i64)).collect())
+            }
+            Script::Flush => {
+                scripting.flush();
+                Frame::Simple("OK".to_string())
+            }
+            // 能拿到锁说明没有脚本在执行
+            Script::Kill => return Err("NOTBUSY No scripts in execution right now.".into()),
+        })
+    }
+}
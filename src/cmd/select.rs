@@ -0,0 +1,29 @@
+use crate::parse::Parse;
+use crate::util::parse_int;
+
+// 只有一个数据库，只能选择0号
+pub const DATABASES: usize = 1;
+
+// 修改连接自己的状态，由Handler执行
+#[derive(Debug)]
+pub struct Select {
+    index: usize,
+}
+
+impl Select {
+    pub fn new(index: usize) -> Select {
+        Select { index }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Select> {
+        let index = parse_int(&parse.next_bytes()?).ok_or("ERR value is not an integer or out of range")?;
+        if index < 0 || index as usize >= DATABASES {
+            return Err("ERR DB index is out of range".into());
+        }
+        Ok(Select::new(index as usize))
+    }
+}
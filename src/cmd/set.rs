@@ -1,6 +1,9 @@
 use bytes::Bytes;
 use std::time::{Duration};
 use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::notify::{NOTIFY_STRING, NOTIFY_GENERIC};
 
 #[derive(Debug)]
 pub struct Set {
@@ -56,4 +59,13 @@ impl Set {
             expire,
         })
     }
-}
\ No newline at end of file
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        db.set(self.key.clone(), Value::String(self.value), self.expire);
+        db.notify_keyspace_event(NOTIFY_STRING, "set", &self.key);
+        if self.expire.is_some() {
+            db.notify_keyspace_event(NOTIFY_GENERIC, "expire", &self.key);
+        }
+        Ok(Frame::Simple("OK".to_string()))
+    }
+}
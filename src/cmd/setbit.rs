@@ -0,0 +1,59 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STRING;
+use crate::bitmap::{self, MAX_BIT_OFFSET};
+use bytes::{Bytes, BytesMut};
+
+pub(crate) const INVALID_OFFSET: &str = "ERR bit offset is not an integer or out of range";
+
+#[derive(Debug)]
+pub struct SetBit {
+    key: String,
+    offset: u64,
+    bit: u8,
+}
+
+impl SetBit {
+    pub fn new(key: impl ToString, offset: u64, bit: u8) -> SetBit {
+        SetBit {
+            key: key.to_string(),
+            offset,
+            bit,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetBit> {
+        let key = parse.next_string()?;
+        let offset = parse_bit_offset(&parse.next_bytes()?)?;
+        let bit = match &parse.next_bytes()?[..] {
+            b"0" => 0,
+            b"1" => 1,
+            _ => return Err("ERR bit is not an integer or out of range".into()),
+        };
+        Ok(SetBit::new(key, offset, bit))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let value = db.get_or_create_string(&self.key)?;
+        // 只有自己持有的时候不会拷贝
+        let mut buf = BytesMut::from(std::mem::take(value));
+        let old = bitmap::set_bit(&mut buf, self.offset, self.bit);
+        *value = buf.freeze();
+        db.signal_modified_key(&self.key);
+        db.notify_keyspace_event(NOTIFY_STRING, "setbit", &self.key);
+        Ok(Frame::Integer(old as i64))
+    }
+}
+
+pub(crate) fn parse_bit_offset(src: &Bytes) -> crate::Result<u64> {
+    std::str::from_utf8(src)
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|offset| *offset <= MAX_BIT_OFFSET)
+        .ok_or_else(|| INVALID_OFFSET.into())
+}
@@ -0,0 +1,28 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct SPublish {
+    channel: Bytes,
+    message: Bytes,
+}
+
+impl SPublish {
+    pub fn new(channel: Bytes, message: Bytes) -> SPublish {
+        SPublish { channel, message }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SPublish> {
+        let channel = parse.next_bytes()?;
+        let message = parse.next_bytes()?;
+        Ok(SPublish::new(channel, message))
+    }
+
+    // 只发给订阅了这个分片频道的客户端
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let receivers = db.pub_sub().spublish(&self.channel, &self.message);
+        Ok(Frame::Integer(receivers as i64))
+    }
+}
@@ -0,0 +1,71 @@
+use crate::parse::Parse;
+use crate::db::Db;
+use crate::frame::Frame;
+use crate::connection::Connection;
+use crate::pubsub::Subscriber;
+use crate::cmd::subscribe::subscription_frame;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct SSubscribe {
+    channels: Vec<Bytes>,
+}
+
+// channels为空的时候退订所有分片频道
+#[derive(Debug)]
+pub struct SUnsubscribe {
+    channels: Vec<Bytes>,
+}
+
+impl SSubscribe {
+    pub fn new(channels: Vec<Bytes>) -> SSubscribe {
+        SSubscribe { channels }
+    }
+
+    pub fn channels(&self) -> &[Bytes] {
+        &self.channels
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SSubscribe> {
+        let mut channels = vec![parse.next_bytes()?];
+        channels.extend(parse.rest_bytes()?);
+        Ok(SSubscribe::new(channels))
+    }
+
+    // 回复里的数量只统计分片频道的订阅
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, subscriber: &mut Subscriber) -> crate::Result<()> {
+        for channel in self.channels {
+            subscriber.ssubscribe(db, channel.clone()).await;
+            dst.write_frame(&subscription_frame("ssubscribe", Frame::Bulk(channel), subscriber.shard_count())).await?;
+        }
+        Ok(())
+    }
+}
+
+impl SUnsubscribe {
+    pub fn new(channels: Vec<Bytes>) -> SUnsubscribe {
+        SUnsubscribe { channels }
+    }
+
+    pub fn channels(&self) -> &[Bytes] {
+        &self.channels
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SUnsubscribe> {
+        Ok(SUnsubscribe::new(parse.rest_bytes()?))
+    }
+
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, subscriber: &mut Subscriber) -> crate::Result<()> {
+        let channels = if self.channels.is_empty() { subscriber.shard_channels() } else { self.channels };
+        // 本来就没有订阅任何分片频道
+        if channels.is_empty() {
+            return dst.write_frame(&subscription_frame("sunsubscribe", Frame::Null, subscriber.shard_count())).await.map_err(Into::into);
+        }
+
+        for channel in channels {
+            subscriber.sunsubscribe(db, &channel).await;
+            dst.write_frame(&subscription_frame("sunsubscribe", Frame::Bulk(channel), subscriber.shard_count())).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::parse::Parse;
+use crate::db::Db;
+use crate::frame::Frame;
+use crate::connection::Connection;
+use crate::pubsub::Subscriber;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct Subscribe {
+    channels: Vec<Bytes>,
+}
+
+// channels为空的时候退订所有频道
+#[derive(Debug)]
+pub struct Unsubscribe {
+    channels: Vec<Bytes>,
+}
+
+impl Subscribe {
+    pub fn new(channels: Vec<Bytes>) -> Subscribe {
+        Subscribe { channels }
+    }
+
+    pub fn channels(&self) -> &[Bytes] {
+        &self.channels
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Subscribe> {
+        let mut channels = vec![parse.next_bytes()?];
+        channels.extend(parse.rest_bytes()?);
+        Ok(Subscribe::new(channels))
+    }
+
+    // 每个频道回复一次，带上当前的订阅总数
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, subscriber: &mut Subscriber) -> crate::Result<()> {
+        for channel in self.channels {
+            subscriber.subscribe(db, channel.clone()).await;
+            dst.write_frame(&subscription_frame("subscribe", Frame::Bulk(channel), subscriber.count())).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Unsubscribe {
+    pub fn new(channels: Vec<Bytes>) -> Unsubscribe {
+        Unsubscribe { channels }
+    }
+
+    pub fn channels(&self) -> &[Bytes] {
+        &self.channels
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Unsubscribe> {
+        Ok(Unsubscribe::new(parse.rest_bytes()?))
+    }
+
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, subscriber: &mut Subscriber) -> crate::Result<()> {
+        let channels = if self.channels.is_empty() { subscriber.channels() } else { self.channels };
+        // 本来就没有订阅任何频道
+        if channels.is_empty() {
+            return dst.write_frame(&subscription_frame("unsubscribe", Frame::Null, subscriber.count())).await.map_err(Into::into);
+        }
+
+        for channel in channels {
+            subscriber.unsubscribe(db, &channel).await;
+            dst.write_frame(&subscription_frame("unsubscribe", Frame::Bulk(channel), subscriber.count())).await?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn subscription_frame(kind: &'static str, channel: Frame, count: usize) -> Frame {
+    Frame::Array(vec![
+        Frame::Bulk(Bytes::from_static(kind.as_bytes())),
+        channel,
+        Frame::Integer(count as i64),
+    ])
+}
@@ -0,0 +1,424 @@
+use crate::util::parse_int;
+use bytes::Bytes;
+
+// 命令的flag，和redis的命令flag一致
+pub const CMD_WRITE: u32 = 1 << 0;
+pub const CMD_READONLY: u32 = 1 << 1;
+pub const CMD_DENYOOM: u32 = 1 << 2;
+pub const CMD_ADMIN: u32 = 1 << 3;
+pub const CMD_FAST: u32 = 1 << 4;
+// 脚本里面不能调用
+pub const CMD_NOSCRIPT: u32 = 1 << 5;
+pub const CMD_BLOCKING: u32 = 1 << 6;
+pub const CMD_PUBSUB: u32 = 1 << 7;
+pub const CMD_LOADING: u32 = 1 << 8;
+pub const CMD_STALE: u32 = 1 << 9;
+// 不能放到 MULTI 里面，MULTI 之后不排队，事务相关的命令由连接直接处理，其他的回复错误
+pub const CMD_NO_MULTI: u32 = 1 << 10;
+// key的位置要解析参数才知道，比如 EVAL 的 numkeys
+pub const CMD_MOVABLE_KEYS: u32 = 1 << 11;
+// 有脚本在执行的时候也可以执行
+pub const CMD_ALLOW_BUSY: u32 = 1 << 12;
+pub const CMD_MODULE: u32 = 1 << 13;
+// 不是写命令但是可能产生写操作，比如脚本和发布消息，CLIENT PAUSE WRITE 期间也要等待
+pub const CMD_MAY_REPLICATE: u32 = 1 << 14;
+
+const FLAG_NAMES: [(u32, &str); 15] = [
+    (CMD_WRITE, "write"),
+    (CMD_READONLY, "readonly"),
+    (CMD_DENYOOM, "denyoom"),
+    (CMD_MODULE, "module"),
+    (CMD_ADMIN, "admin"),
+    (CMD_PUBSUB, "pubsub"),
+    (CMD_NOSCRIPT, "noscript"),
+    (CMD_BLOCKING, "blocking"),
+    (CMD_LOADING, "loading"),
+    (CMD_STALE, "stale"),
+    (CMD_FAST, "fast"),
+    (CMD_MAY_REPLICATE, "may_replicate"),
+    (CMD_NO_MULTI, "no_multi"),
+    (CMD_MOVABLE_KEYS, "movablekeys"),
+    (CMD_ALLOW_BUSY, "allow_busy"),
+];
+
+// key在参数里面的位置，和redis旧的 first/last/step 格式一样，下标从命令名开始算，
+// last为负数表示从后往前数，first为0表示没有key
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KeySpec {
+    pub first: i32,
+    pub last: i32,
+    pub step: i32,
+}
+
+impl KeySpec {
+    pub const fn new(first: i32, last: i32, step: i32) -> KeySpec {
+        KeySpec { first, last, step }
+    }
+
+    // 取出参数里面key的下标，args包含命令名
+    pub fn positions(&self, args: &[Bytes]) -> Vec<usize> {
+        if self.first <= 0 || self.step <= 0 {
+            return Vec::new();
+        }
+        let last = if self.last < 0 { args.len() as i32 + self.last } else { self.last };
+        (self.first..=last.min(args.len() as i32 - 1)).step_by(self.step as usize).map(|idx| idx as usize).collect()
+    }
+
+    // 取出参数里面的key，args包含命令名
+    pub fn keys<'a>(&self, args: &'a [Bytes]) -> Vec<&'a Bytes> {
+        self.positions(args).into_iter().map(|idx| &args[idx]).collect()
+    }
+}
+
+// 找出key的下标，参数不对的时候返回None
+type FindKeys = fn(&[Bytes]) -> Option<Vec<usize>>;
+
+// 命令表里面的一项，COMMAND 系列命令和参数个数检查都用这里的信息
+#[derive(Debug)]
+pub(crate) struct CommandSpec {
+    // 子命令的名字和redis一样带上父命令，比如 config|get
+    pub(crate) name: &'static str,
+    // 参数个数，包括命令名，负数表示至少这么多个
+    pub(crate) arity: i32,
+    pub(crate) flags: u32,
+    pub(crate) categories: &'static [&'static str],
+    pub(crate) keys: KeySpec,
+    // 有 CMD_MOVABLE_KEYS 的命令用这个找出key的下标
+    pub(crate) movable_keys: Option<FindKeys>,
+    pub(crate) group: &'static str,
+    pub(crate) since: &'static str,
+    pub(crate) summary: &'static str,
+    // CONFIG、CLIENT 这样的容器命令的子命令
+    pub(crate) subcommands: &'static [CommandSpec],
+}
+
+impl CommandSpec {
+    #[allow(clippy::too_many_arguments)]
+    const fn new(
+        name: &'static str,
+        arity: i32,
+        flags: u32,
+        categories: &'static [&'static str],
+        keys: KeySpec,
+        group: &'static str,
+        since: &'static str,
+        summary: &'static str,
+    ) -> CommandSpec {
+        CommandSpec { name, arity, flags, categories, keys, movable_keys: None, group, since, summary, subcommands: &[] }
+    }
+
+    const fn subcommands(mut self, subcommands: &'static [CommandSpec]) -> CommandSpec {
+        self.subcommands = subcommands;
+        self
+    }
+
+    pub(crate) fn subcommand(&self, name: &str) -> Option<&'static CommandSpec> {
+        self.subcommands.iter().find(|spec| spec.name[self.name.len() + 1..].eq_ignore_ascii_case(name))
+    }
+
+    const fn movable(mut self, find: FindKeys) -> CommandSpec {
+        self.flags |= CMD_MOVABLE_KEYS;
+        self.movable_keys = Some(find);
+        self
+    }
+
+    // 找出key的下标，args包含命令名
+    pub(crate) fn key_positions(&self, args: &[Bytes]) -> Option<Vec<usize>> {
+        match self.movable_keys {
+            Some(find) => find(args),
+            None => Some(self.keys.positions(args)),
+        }
+    }
+}
+
+pub(crate) fn flag_names(flags: u32) -> Vec<&'static str> {
+    FLAG_NAMES.iter().filter(|(flag, _)| flags & flag != 0).map(|(_, name)| *name).collect()
+}
+
+pub(crate) fn check_arity(name: &str, arity: i32, argc: usize) -> crate::Result<()> {
+    let argc = argc as i32;
+    if (arity > 0 && argc != arity) || argc < -arity {
+        return Err(arity_error(name));
+    }
+    Ok(())
+}
+
+// 参数个数不对的错误，命令表的arity表达不了的情况也用这个，比如成对出现的参数
+pub(crate) fn arity_error(name: &str) -> crate::Error {
+    format!("ERR wrong number of arguments for '{}' command", name).into()
+}
+
+// 命令不多，直接遍历；子命令用 config|get 这样的名字查找
+pub(crate) fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    match name.split_once('|') {
+        Some((container, subcommand)) => lookup(container)?.subcommand(subcommand),
+        None => COMMAND_TABLE.iter().find(|spec| spec.name.eq_ignore_ascii_case(name)),
+    }
+}
+
+pub(crate) fn commands() -> &'static [CommandSpec] {
+    COMMAND_TABLE
+}
+
+// idx位置是key的个数，后面紧跟着这么多个key
+fn numkeys_at(args: &[Bytes], idx: usize) -> Option<Vec<usize>> {
+    let numkeys = parse_int(args.get(idx)?)?;
+    if numkeys < 0 || idx + numkeys as usize >= args.len() {
+        return None;
+    }
+    Some((idx + 1..=idx + numkeys as usize).collect())
+}
+
+// EVAL script numkeys key...
+fn eval_keys(args: &[Bytes]) -> Option<Vec<usize>> {
+    numkeys_at(args, 2)
+}
+
+// ZUNIONSTORE destination numkeys key...
+fn store_keys(args: &[Bytes]) -> Option<Vec<usize>> {
+    let mut keys = vec![1];
+    keys.extend(numkeys_at(args, 2)?);
+    Some(keys)
+}
+
+// XREAD ... STREAMS key... id...
+fn streams_keys(args: &[Bytes]) -> Option<Vec<usize>> {
+    let streams = args.iter().position(|arg| arg.eq_ignore_ascii_case(b"streams"))?;
+    let rest = args.len() - streams - 1;
+    if rest == 0 || !rest.is_multiple_of(2) {
+        return None;
+    }
+    Some((streams + 1..=streams + rest / 2).collect())
+}
+
+const NO_KEYS: KeySpec = KeySpec::new(0, 0, 0);
+const FIRST_KEY: KeySpec = KeySpec::new(1, 1, 1);
+const ALL_KEYS: KeySpec = KeySpec::new(1, -1, 1);
+
+static COMMAND_TABLE: &[CommandSpec] = &[
+    // string
+    CommandSpec::new("get", 2, CMD_READONLY | CMD_FAST, &["@read", "@string", "@fast"], FIRST_KEY, "string", "1.0.0", "Returns the string value of a key."),
+    CommandSpec::new("set", -3, CMD_WRITE | CMD_DENYOOM, &["@write", "@string", "@slow"], FIRST_KEY, "string", "1.0.0", "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist."),
+    // sorted set
+    CommandSpec::new("zadd", -4, CMD_WRITE | CMD_DENYOOM | CMD_FAST, &["@write", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "1.2.0", "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist."),
+    CommandSpec::new("zrange", -4, CMD_READONLY, &["@read", "@sortedset", "@slow"], FIRST_KEY, "sorted-set", "1.2.0", "Returns members in a sorted set within a range of indexes."),
+    CommandSpec::new("zrank", -3, CMD_READONLY | CMD_FAST, &["@read", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "2.0.0", "Returns the index of a member in a sorted set ordered by ascending scores."),
+    CommandSpec::new("zscore", 3, CMD_READONLY | CMD_FAST, &["@read", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "1.2.0", "Returns the score of a member in a sorted set."),
+    CommandSpec::new("zincrby", 4, CMD_WRITE | CMD_DENYOOM | CMD_FAST, &["@write", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "1.2.0", "Increments the score of a member in a sorted set."),
+    CommandSpec::new("zrem", -3, CMD_WRITE | CMD_FAST, &["@write", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "1.2.0", "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed."),
+    CommandSpec::new("zcount", 4, CMD_READONLY | CMD_FAST, &["@read", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "2.0.0", "Returns the count of members in a sorted set that have scores within a range."),
+    CommandSpec::new("zpopmin", -2, CMD_WRITE | CMD_FAST, &["@write", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "5.0.0", "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped."),
+    CommandSpec::new("zpopmax", -2, CMD_WRITE | CMD_FAST, &["@write", "@sortedset", "@fast"], FIRST_KEY, "sorted-set", "5.0.0", "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped."),
+    CommandSpec::new("zunionstore", -4, CMD_WRITE | CMD_DENYOOM, &["@write", "@sortedset", "@slow"], FIRST_KEY, "sorted-set", "2.0.0", "Stores the union of multiple sorted sets in a key.").movable(store_keys),
+    CommandSpec::new("zinterstore", -4, CMD_WRITE | CMD_DENYOOM, &["@write", "@sortedset", "@slow"], FIRST_KEY, "sorted-set", "2.0.0", "Stores the intersect of multiple sorted sets in a key.").movable(store_keys),
+    CommandSpec::new("zdiffstore", -4, CMD_WRITE | CMD_DENYOOM, &["@write", "@sortedset", "@slow"], FIRST_KEY, "sorted-set", "6.2.0", "Stores the difference of multiple sorted sets in a key.").movable(store_keys),
+    CommandSpec::new("zrangestore", -5, CMD_WRITE | CMD_DENYOOM, &["@write", "@sortedset", "@slow"], KeySpec::new(1, 2, 1), "sorted-set", "6.2.0", "Stores a range of members from sorted set in a key."),
+    CommandSpec::new("bzpopmin", -3, CMD_WRITE | CMD_FAST | CMD_BLOCKING, &["@write", "@sortedset", "@fast", "@blocking"], KeySpec::new(1, -2, 1), "sorted-set", "5.0.0", "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped."),
+    CommandSpec::new("bzpopmax", -3, CMD_WRITE | CMD_FAST | CMD_BLOCKING, &["@write", "@sortedset", "@fast", "@blocking"], KeySpec::new(1, -2, 1), "sorted-set", "5.0.0", "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member available otherwise.  Deletes the sorted set if the last element was popped."),
+    // stream
+    CommandSpec::new("xadd", -5, CMD_WRITE | CMD_DENYOOM | CMD_FAST, &["@write", "@stream", "@fast"], FIRST_KEY, "stream", "5.0.0", "Appends a new message to a stream. Creates the key if it doesn't exist."),
+    CommandSpec::new("xrange", -4, CMD_READONLY, &["@read", "@stream", "@slow"], FIRST_KEY, "stream", "5.0.0", "Returns the messages from a stream within a range of IDs."),
+    CommandSpec::new("xrevrange", -4, CMD_READONLY, &["@read", "@stream", "@slow"], FIRST_KEY, "stream", "5.0.0", "Returns the messages from a stream within a range of IDs in reverse order."),
+    CommandSpec::new("xlen", 2, CMD_READONLY | CMD_FAST, &["@read", "@stream", "@fast"], FIRST_KEY, "stream", "5.0.0", "Return the number of messages in a stream."),
+    CommandSpec::new("xtrim", -4, CMD_WRITE, &["@write", "@stream", "@slow"], FIRST_KEY, "stream", "5.0.0", "Deletes messages from the beginning of a stream."),
+    CommandSpec::new("xdel", -3, CMD_WRITE | CMD_FAST, &["@write", "@stream", "@fast"], FIRST_KEY, "stream", "5.0.0", "Returns the number of messages after removing them from a stream."),
+    CommandSpec::new("xread", -4, CMD_READONLY | CMD_BLOCKING, &["@read", "@stream", "@slow", "@blocking"], NO_KEYS, "stream", "5.0.0", "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise.").movable(streams_keys),
+    CommandSpec::new("xgroup", -2, 0, &["@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "A container for consumer groups commands.").subcommands(XGROUP_SUBCOMMANDS),
+    CommandSpec::new("xreadgroup", -7, CMD_WRITE | CMD_BLOCKING, &["@write", "@stream", "@slow", "@blocking"], NO_KEYS, "stream", "5.0.0", "Returns new or historical messages from a stream for a consumer in a group. Blocks until a message is available otherwise.").movable(streams_keys),
+    CommandSpec::new("xack", -4, CMD_WRITE | CMD_FAST, &["@write", "@stream", "@fast"], FIRST_KEY, "stream", "5.0.0", "Returns the number of messages that were successfully acknowledged by the consumer group member of a stream."),
+    CommandSpec::new("xpending", -3, CMD_READONLY, &["@read", "@stream", "@slow"], FIRST_KEY, "stream", "5.0.0", "Returns the information and entries from a stream consumer group's pending entries list."),
+    CommandSpec::new("xclaim", -6, CMD_WRITE | CMD_FAST, &["@write", "@stream", "@fast"], FIRST_KEY, "stream", "5.0.0", "Changes, or acquires, ownership of a message in a consumer group, as if the message was delivered a consumer group member."),
+    CommandSpec::new("xautoclaim", -6, CMD_WRITE | CMD_FAST, &["@write", "@stream", "@fast"], FIRST_KEY, "stream", "6.2.0", "Changes, or acquires, ownership of messages in a consumer group, as if the messages were delivered to as consumer group member."),
+    CommandSpec::new("xinfo", -2, 0, &["@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "A container for stream introspection commands.").subcommands(XINFO_SUBCOMMANDS),
+    // bitmap
+    CommandSpec::new("setbit", 4, CMD_WRITE | CMD_DENYOOM, &["@write", "@bitmap", "@slow"], FIRST_KEY, "bitmap", "2.2.0", "Sets or clears the bit at offset of the string value. Creates the key if it doesn't exist."),
+    CommandSpec::new("getbit", 3, CMD_READONLY | CMD_FAST, &["@read", "@bitmap", "@fast"], FIRST_KEY, "bitmap", "2.2.0", "Returns a bit value by offset."),
+    CommandSpec::new("bitcount", -2, CMD_READONLY, &["@read", "@bitmap", "@slow"], FIRST_KEY, "bitmap", "2.6.0", "Counts the number of set bits (population counting) in a string."),
+    CommandSpec::new("bitpos", -3, CMD_READONLY, &["@read", "@bitmap", "@slow"], FIRST_KEY, "bitmap", "2.8.7", "Finds the first set (1) or clear (0) bit in a string."),
+    CommandSpec::new("bitop", -4, CMD_WRITE | CMD_DENYOOM, &["@write", "@bitmap", "@slow"], KeySpec::new(2, -1, 1), "bitmap", "2.6.0", "Performs bitwise operations on multiple strings, and stores the result."),
+    CommandSpec::new("bitfield", -2, CMD_WRITE | CMD_DENYOOM, &["@write", "@bitmap", "@slow"], FIRST_KEY, "bitmap", "3.2.0", "Performs arbitrary bitfield integer operations on strings."),
+    CommandSpec::new("bitfield_ro", -2, CMD_READONLY | CMD_FAST, &["@read", "@bitmap", "@fast"], FIRST_KEY, "bitmap", "6.0.0", "Performs arbitrary read-only bitfield integer operations on strings."),
+    // hyperloglog
+    CommandSpec::new("pfadd", -2, CMD_WRITE | CMD_DENYOOM | CMD_FAST, &["@write", "@hyperloglog", "@fast"], FIRST_KEY, "hyperloglog", "2.8.9", "Adds elements to a HyperLogLog key. Creates the key if it doesn't exist."),
+    CommandSpec::new("pfcount", -2, CMD_READONLY, &["@read", "@hyperloglog", "@slow"], ALL_KEYS, "hyperloglog", "2.8.9", "Returns the approximated cardinality of the set(s) observed by the HyperLogLog key(s)."),
+    CommandSpec::new("pfmerge", -2, CMD_WRITE | CMD_DENYOOM, &["@write", "@hyperloglog", "@slow"], ALL_KEYS, "hyperloglog", "2.8.9", "Merges one or more HyperLogLog values into a single key."),
+    // geo
+    CommandSpec::new("geoadd", -5, CMD_WRITE | CMD_DENYOOM, &["@write", "@geo", "@slow"], FIRST_KEY, "geo", "3.2.0", "Adds one or more members to a geospatial index. The key is created if it doesn't exist."),
+    CommandSpec::new("geodist", -4, CMD_READONLY, &["@read", "@geo", "@slow"], FIRST_KEY, "geo", "3.2.0", "Returns the distance between two members of a geospatial index."),
+    CommandSpec::new("geopos", -2, CMD_READONLY, &["@read", "@geo", "@slow"], FIRST_KEY, "geo", "3.2.0", "Returns the longitude and latitude of members from a geospatial index."),
+    CommandSpec::new("geohash", -2, CMD_READONLY, &["@read", "@geo", "@slow"], FIRST_KEY, "geo", "3.2.0", "Returns members from a geospatial index as geohash strings."),
+    CommandSpec::new("geosearch", -7, CMD_READONLY, &["@read", "@geo", "@slow"], FIRST_KEY, "geo", "6.2.0", "Queries a geospatial index for members inside an area of a box or a circle."),
+    CommandSpec::new("geosearchstore", -8, CMD_WRITE | CMD_DENYOOM, &["@write", "@geo", "@slow"], KeySpec::new(1, 2, 1), "geo", "6.2.0", "Queries a geospatial index for members inside an area of a box or a circle, optionally stores the result."),
+    // pubsub
+    CommandSpec::new("subscribe", -2, CMD_PUBSUB | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "2.0.0", "Listens for messages published to channels."),
+    CommandSpec::new("unsubscribe", -1, CMD_PUBSUB | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "2.0.0", "Stops listening to messages posted to channels."),
+    CommandSpec::new("publish", 3, CMD_PUBSUB | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_MAY_REPLICATE, &["@pubsub", "@fast"], NO_KEYS, "pubsub", "2.0.0", "Posts a message to a channel."),
+    CommandSpec::new("psubscribe", -2, CMD_PUBSUB | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "2.0.0", "Listens for messages published to channels that match one or more patterns."),
+    CommandSpec::new("punsubscribe", -1, CMD_PUBSUB | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "2.0.0", "Stops listening to messages published to channels that match one or more patterns."),
+    CommandSpec::new("ssubscribe", -2, CMD_PUBSUB | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], ALL_KEYS, "pubsub", "7.0.0", "Listens for messages published to shard channels."),
+    CommandSpec::new("sunsubscribe", -1, CMD_PUBSUB | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], ALL_KEYS, "pubsub", "7.0.0", "Stops listening to messages posted to shard channels."),
+    CommandSpec::new("spublish", 3, CMD_PUBSUB | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_MAY_REPLICATE, &["@pubsub", "@fast"], FIRST_KEY, "pubsub", "7.0.0", "Post a message to a shard channel"),
+    CommandSpec::new("pubsub", -2, 0, &["@slow"], NO_KEYS, "pubsub", "2.8.0", "A container for Pub/Sub commands.").subcommands(PUBSUB_SUBCOMMANDS),
+    // connection
+    CommandSpec::new("ping", -1, CMD_FAST, &["@fast", "@connection"], NO_KEYS, "connection", "1.0.0", "Returns the server's liveliness response."),
+    CommandSpec::new("quit", -1, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_NO_MULTI | CMD_ALLOW_BUSY, &["@fast", "@connection"], NO_KEYS, "connection", "1.0.0", "Closes the connection."),
+    CommandSpec::new("echo", 2, CMD_FAST, &["@fast", "@connection"], NO_KEYS, "connection", "1.0.0", "Returns the given string."),
+    CommandSpec::new("select", 2, CMD_LOADING | CMD_STALE | CMD_FAST, &["@fast", "@connection"], NO_KEYS, "connection", "1.0.0", "Changes the selected database."),
+    CommandSpec::new("reset", 1, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_NO_MULTI | CMD_ALLOW_BUSY, &["@fast", "@connection"], NO_KEYS, "connection", "6.2.0", "Resets the connection."),
+    CommandSpec::new("client", -2, 0, &["@slow"], NO_KEYS, "connection", "2.4.0", "A container for client connection commands.").subcommands(CLIENT_SUBCOMMANDS),
+    // transactions
+    CommandSpec::new("multi", 1, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_NO_MULTI | CMD_ALLOW_BUSY, &["@fast", "@transaction"], NO_KEYS, "transactions", "1.2.0", "Starts a transaction."),
+    CommandSpec::new("exec", 1, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_NO_MULTI, &["@slow", "@transaction"], NO_KEYS, "transactions", "1.2.0", "Executes all commands in a transaction."),
+    CommandSpec::new("discard", 1, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_NO_MULTI | CMD_ALLOW_BUSY, &["@fast", "@transaction"], NO_KEYS, "transactions", "2.0.0", "Discards a transaction."),
+    CommandSpec::new("watch", -2, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_NO_MULTI | CMD_ALLOW_BUSY, &["@fast", "@transaction"], ALL_KEYS, "transactions", "2.2.0", "Monitors changes to keys to determine the execution of a transaction."),
+    CommandSpec::new("unwatch", 1, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_FAST | CMD_ALLOW_BUSY, &["@fast", "@transaction"], NO_KEYS, "transactions", "2.2.0", "Forgets about watched keys of a transaction."),
+    // scripting
+    CommandSpec::new("eval", -3, CMD_NOSCRIPT | CMD_MAY_REPLICATE | CMD_STALE, &["@slow", "@scripting"], NO_KEYS, "scripting", "2.6.0", "Executes a server-side Lua script.").movable(eval_keys),
+    CommandSpec::new("evalsha", -3, CMD_NOSCRIPT | CMD_MAY_REPLICATE | CMD_STALE, &["@slow", "@scripting"], NO_KEYS, "scripting", "2.6.0", "Executes a server-side Lua script by SHA1 digest.").movable(eval_keys),
+    CommandSpec::new("script", -2, 0, &["@slow"], NO_KEYS, "scripting", "2.6.0", "A container for Lua scripts management commands.").subcommands(SCRIPT_SUBCOMMANDS),
+    CommandSpec::new("function", -2, 0, &["@slow"], NO_KEYS, "scripting", "7.0.0", "A container for function commands.").subcommands(FUNCTION_SUBCOMMANDS),
+    CommandSpec::new("fcall", -3, CMD_NOSCRIPT | CMD_MAY_REPLICATE | CMD_STALE, &["@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Invokes a function.").movable(eval_keys),
+    CommandSpec::new("fcall_ro", -3, CMD_NOSCRIPT | CMD_STALE | CMD_READONLY, &["@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Invokes a read-only function.").movable(eval_keys),
+    // generic
+    CommandSpec::new("del", -2, CMD_WRITE, &["@keyspace", "@write", "@slow"], ALL_KEYS, "generic", "1.0.0", "Deletes one or more keys."),
+    CommandSpec::new("type", 2, CMD_READONLY | CMD_FAST, &["@keyspace", "@read", "@fast"], FIRST_KEY, "generic", "1.0.0", "Determines the type of value stored at a key."),
+    CommandSpec::new("memory", -2, 0, &["@slow"], KeySpec::new(2, 2, 1), "server", "4.0.0", "A container for memory diagnostics commands.").subcommands(MEMORY_SUBCOMMANDS),
+    CommandSpec::new("config", -2, 0, &["@slow"], NO_KEYS, "server", "2.0.0", "A container for server configuration commands.").subcommands(CONFIG_SUBCOMMANDS),
+    CommandSpec::new("command", -1, CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "server", "2.8.13", "Returns detailed information about all commands.").subcommands(COMMAND_SUBCOMMANDS),
+];
+
+static XGROUP_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("xgroup|create", -5, CMD_WRITE | CMD_DENYOOM, &["@write", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "Creates a consumer group."),
+    CommandSpec::new("xgroup|setid", -5, CMD_WRITE, &["@write", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "Sets the last-delivered ID of a consumer group."),
+    CommandSpec::new("xgroup|destroy", 4, CMD_WRITE, &["@write", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "Destroys a consumer group."),
+    CommandSpec::new("xgroup|createconsumer", 5, CMD_WRITE | CMD_DENYOOM, &["@write", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "6.2.0", "Creates a consumer in a consumer group."),
+    CommandSpec::new("xgroup|delconsumer", 5, CMD_WRITE, &["@write", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "Deletes a consumer from a consumer group."),
+];
+
+static XINFO_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("xinfo|stream", -3, CMD_READONLY, &["@read", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "Returns information about a stream."),
+    CommandSpec::new("xinfo|groups", 3, CMD_READONLY, &["@read", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "Returns a list of the consumer groups of a stream."),
+    CommandSpec::new("xinfo|consumers", 4, CMD_READONLY, &["@read", "@stream", "@slow"], KeySpec::new(2, 2, 1), "stream", "5.0.0", "Returns a list of the consumers in a consumer group."),
+];
+
+static PUBSUB_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("pubsub|channels", -2, CMD_PUBSUB | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "2.8.0", "Returns the active channels."),
+    CommandSpec::new("pubsub|numsub", -2, CMD_PUBSUB | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "2.8.0", "Returns a count of subscribers to channels."),
+    CommandSpec::new("pubsub|numpat", 2, CMD_PUBSUB | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "2.8.0", "Returns a count of unique pattern subscriptions."),
+    CommandSpec::new("pubsub|shardchannels", -2, CMD_PUBSUB | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "7.0.0", "Returns the active shard channels."),
+    CommandSpec::new("pubsub|shardnumsub", -2, CMD_PUBSUB | CMD_LOADING | CMD_STALE, &["@pubsub", "@slow"], NO_KEYS, "pubsub", "7.0.0", "Returns the count of subscribers of shard channels."),
+];
+
+static CLIENT_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("client|setname", 3, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "connection", "2.6.9", "Sets the connection name."),
+    CommandSpec::new("client|getname", 2, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "connection", "2.6.9", "Returns the name of the connection."),
+    CommandSpec::new("client|id", 2, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "connection", "5.0.0", "Returns the unique client ID of the connection."),
+    CommandSpec::new("client|info", 2, CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "connection", "6.2.0", "Returns information about the connection."),
+    CommandSpec::new("client|list", -2, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@admin", "@slow", "@dangerous", "@connection"], NO_KEYS, "connection", "2.4.0", "Lists open connections."),
+    CommandSpec::new("client|kill", -3, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@admin", "@slow", "@dangerous", "@connection"], NO_KEYS, "connection", "2.4.0", "Terminates open connections."),
+    CommandSpec::new("client|pause", -3, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@admin", "@slow", "@dangerous", "@connection"], NO_KEYS, "connection", "3.0.0", "Suspends commands processing."),
+    CommandSpec::new("client|unpause", 2, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@admin", "@slow", "@dangerous", "@connection"], NO_KEYS, "connection", "6.2.0", "Resumes processing commands from paused clients."),
+];
+
+static SCRIPT_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("script|load", 3, CMD_NOSCRIPT | CMD_STALE, &["@slow", "@scripting"], NO_KEYS, "scripting", "2.6.0", "Loads a server-side Lua script to the script cache."),
+    CommandSpec::new("script|exists", -3, CMD_NOSCRIPT, &["@slow", "@scripting"], NO_KEYS, "scripting", "2.6.0", "Determines whether server-side Lua scripts exist in the script cache."),
+    CommandSpec::new("script|flush", -2, CMD_NOSCRIPT, &["@slow", "@scripting"], NO_KEYS, "scripting", "2.6.0", "Removes all server-side Lua scripts from the script cache."),
+    CommandSpec::new("script|kill", 2, CMD_NOSCRIPT | CMD_ALLOW_BUSY, &["@slow", "@scripting"], NO_KEYS, "scripting", "2.6.0", "Terminates a server-side Lua script during execution."),
+];
+
+static FUNCTION_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("function|load", -3, CMD_WRITE | CMD_DENYOOM | CMD_NOSCRIPT, &["@write", "@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Creates a library."),
+    CommandSpec::new("function|delete", 3, CMD_WRITE | CMD_NOSCRIPT, &["@write", "@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Deletes a library and its functions."),
+    CommandSpec::new("function|flush", -2, CMD_WRITE | CMD_NOSCRIPT, &["@write", "@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Deletes all libraries and functions."),
+    CommandSpec::new("function|list", -2, CMD_NOSCRIPT, &["@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Returns information about all libraries."),
+    CommandSpec::new("function|dump", 2, CMD_NOSCRIPT, &["@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Dumps all libraries into a serialized binary payload."),
+    CommandSpec::new("function|restore", -3, CMD_WRITE | CMD_DENYOOM | CMD_NOSCRIPT, &["@write", "@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Restores all libraries from a payload."),
+    CommandSpec::new("function|kill", 2, CMD_NOSCRIPT | CMD_ALLOW_BUSY, &["@slow", "@scripting"], NO_KEYS, "scripting", "7.0.0", "Terminates a function during execution."),
+];
+
+static MEMORY_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("memory|usage", -3, CMD_READONLY, &["@read", "@slow"], KeySpec::new(2, 2, 1), "server", "4.0.0", "Estimates the memory usage of a key."),
+];
+
+static CONFIG_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("config|get", -3, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@admin", "@slow", "@dangerous"], NO_KEYS, "server", "2.0.0", "Returns the effective values of configuration parameters."),
+    CommandSpec::new("config|set", -4, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@admin", "@slow", "@dangerous"], NO_KEYS, "server", "2.0.0", "Sets configuration parameters in-flight."),
+    CommandSpec::new("config|rewrite", 2, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE | CMD_NO_MULTI, &["@admin", "@slow", "@dangerous"], NO_KEYS, "server", "2.8.0", "Persists the effective configuration to file."),
+    CommandSpec::new("config|resetstat", 2, CMD_ADMIN | CMD_NOSCRIPT | CMD_LOADING | CMD_STALE, &["@admin", "@slow", "@dangerous"], NO_KEYS, "server", "2.0.0", "Resets the server's statistics."),
+];
+
+static COMMAND_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("command|count", 2, CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "server", "2.8.13", "Returns a count of commands."),
+    CommandSpec::new("command|list", -2, CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "server", "7.0.0", "Returns a list of command names."),
+    CommandSpec::new("command|info", -2, CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "server", "2.8.13", "Returns information about one, multiple or all commands."),
+    CommandSpec::new("command|docs", -2, CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "server", "7.0.0", "Returns documentary information about one, multiple or all commands."),
+    CommandSpec::new("command|getkeys", -3, CMD_LOADING | CMD_STALE, &["@slow", "@connection"], NO_KEYS, "server", "2.8.13", "Extracts the key names from an arbitrary command."),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<Bytes> {
+        line.split(' ').map(|arg| Bytes::from(arg.to_string())).collect()
+    }
+
+    #[test]
+    fn table_is_consistent() {
+        let mut names: Vec<_> = commands().iter().map(|spec| spec.name).collect();
+        assert!(names.iter().all(|name| *name == name.to_lowercase()));
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), commands().len());
+        let subcommands = commands().iter().flat_map(|spec| spec.subcommands);
+        for spec in commands().iter().chain(subcommands) {
+            assert_ne!(spec.arity, 0, "{}", spec.name);
+            assert!(spec.flags & CMD_WRITE == 0 || spec.flags & CMD_READONLY == 0, "{}", spec.name);
+            assert_eq!(spec.flags & CMD_MOVABLE_KEYS != 0, spec.movable_keys.is_some(), "{}", spec.name);
+            for sub in spec.subcommands {
+                assert!(sub.name.starts_with(&format!("{}|", spec.name)), "{}", sub.name);
+                // 子命令至少有父命令和子命令两个参数
+                assert!(sub.arity >= 2 || sub.arity <= -2, "{}", sub.name);
+            }
+        }
+        assert!(lookup("GET").is_some());
+        assert!(lookup("nosuchcommand").is_none());
+        assert_eq!(lookup("CONFIG|Get").map(|spec| spec.arity), Some(-3));
+        assert!(lookup("config|nosuch").is_none());
+        assert!(lookup("get|x").is_none());
+    }
+
+    #[test]
+    fn arity() {
+        assert!(check_arity("get", 2, 2).is_ok());
+        assert!(check_arity("get", 2, 3).is_err());
+        assert!(check_arity("set", -3, 5).is_ok());
+        assert_eq!(
+            check_arity("set", -3, 2).unwrap_err().to_string(),
+            "ERR wrong number of arguments for 'set' command"
+        );
+    }
+
+    #[test]
+    fn key_positions() {
+        assert_eq!(FIRST_KEY.positions(&args("get k")), vec![1]);
+        assert_eq!(ALL_KEYS.positions(&args("pfcount a b c")), vec![1, 2, 3]);
+        assert_eq!(KeySpec::new(1, -2, 1).positions(&args("bzpopmin a b 0")), vec![1, 2]);
+        assert_eq!(KeySpec::new(1, -1, 2).keys(&args("mset a 1 b 2")), vec![&Bytes::from("a"), &Bytes::from("b")]);
+        assert!(NO_KEYS.positions(&args("ping")).is_empty());
+
+        let spec = |name| lookup(name).unwrap();
+        assert_eq!(spec("eval").key_positions(&args("eval s 2 a b c")), Some(vec![3, 4]));
+        assert_eq!(spec("eval").key_positions(&args("eval s 3 a b")), None);
+        assert_eq!(spec("zunionstore").key_positions(&args("zunionstore d 2 a b")), Some(vec![1, 3, 4]));
+        assert_eq!(spec("xread").key_positions(&args("xread COUNT 1 STREAMS a b 0 0")), Some(vec![4, 5]));
+        assert_eq!(spec("xread").key_positions(&args("xread STREAMS a b 0")), None);
+    }
+
+    #[test]
+    fn flag_names_in_redis_order() {
+        assert_eq!(flag_names(CMD_FAST | CMD_WRITE | CMD_DENYOOM), vec!["write", "denyoom", "fast"]);
+        assert!(flag_names(0).is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::frame::Frame;
+
 #[derive(Debug)]
 pub struct Unknown {
     command_name: String,
@@ -13,4 +15,8 @@ impl Unknown {
     pub fn get_name(&self) -> &str {
         &self.command_name
     }
-}
\ No newline at end of file
+
+    pub(crate) fn execute(self) -> crate::Result<Frame> {
+        Ok(Frame::Error(format!("ERR unknown command '{}'", self.command_name)))
+    }
+}
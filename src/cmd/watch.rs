@@ -0,0 +1,85 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct Watch {
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Unwatch {}
+
+impl Watch {
+    pub fn new(keys: Vec<String>) -> Watch {
+        Watch { keys }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Watch> {
+        let mut keys = vec![parse.next_string()?];
+        keys.extend(parse.rest_bytes()?.iter().map(|key| String::from_utf8_lossy(key).to_string()));
+        Ok(Watch::new(keys))
+    }
+
+    pub(crate) fn execute(self, db: &mut State, watched: &mut WatchedKeys) -> Frame {
+        for key in self.keys {
+            watched.watch(db, key);
+        }
+        Frame::Simple("OK".to_string())
+    }
+}
+
+impl Unwatch {
+    pub fn new() -> Unwatch {
+        Unwatch {}
+    }
+
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Unwatch> {
+        Ok(Unwatch::new())
+    }
+}
+
+// 连接WATCH的key，由Handler持有，任意一个key被修改之后dirty会被置为true
+#[derive(Debug, Default)]
+pub struct WatchedKeys {
+    keys: Vec<String>,
+    dirty: Arc<AtomicBool>,
+}
+
+impl WatchedKeys {
+    pub fn new() -> WatchedKeys {
+        WatchedKeys::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub(crate) fn watch(&mut self, db: &mut State, key: String) {
+        if self.keys.contains(&key) {
+            return;
+        }
+        db.watch_key(&key, &self.dirty);
+        self.keys.push(key);
+    }
+
+    // 还没有被清理的过期key也要算作修改
+    pub(crate) fn is_dirty(&self, db: &mut State) -> bool {
+        for key in &self.keys {
+            db.contains_key(key);
+        }
+        self.dirty.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn unwatch_all(&mut self, db: &mut State) {
+        db.unwatch_keys(&self.keys, &self.dirty);
+        self.keys.clear();
+        self.dirty.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::stream::StreamId;
+use crate::cmd::xadd::INVALID_ID;
+
+#[derive(Debug)]
+pub struct XAck {
+    key: String,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+impl XAck {
+    pub fn new(key: impl ToString, group: impl ToString, ids: Vec<StreamId>) -> XAck {
+        XAck {
+            key: key.to_string(),
+            group: group.to_string(),
+            ids,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAck> {
+        let key = parse.next_string()?;
+        let group = parse.next_string()?;
+        let mut args = vec![parse.next_bytes()?];
+        args.extend(parse.rest_bytes()?);
+
+        let mut ids = Vec::with_capacity(args.len());
+        for arg in args {
+            ids.push(StreamId::parse(&arg, 0).ok_or(INVALID_ID)?);
+        }
+        Ok(XAck::new(key, group, ids))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let cg = match db.get_stream_mut(&self.key)?.and_then(|stream| stream.group_mut(&self.group)) {
+            Some(cg) => cg,
+            None => return Ok(Frame::Integer(0)),
+        };
+        let acked = self.ids.iter().filter(|id| cg.ack(**id)).count();
+        Ok(Frame::Integer(acked as i64))
+    }
+}
@@ -0,0 +1,151 @@
+use crate::parse::Parse;
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STREAM;
+use crate::stream::{StreamId, IdSpec, TrimOptions, TrimStrategy};
+use crate::util::parse_int;
+use crate::cmd::arity_error;
+use bytes::Bytes;
+
+pub(crate) const INVALID_ID: &str = "ERR Invalid stream ID specified as stream command argument";
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+    // 不存在的时候不创建stream
+    nomkstream: bool,
+    trim: Option<TrimOptions>,
+    id: IdSpec,
+    fields: Vec<(Bytes, Bytes)>,
+}
+
+impl XAdd {
+    pub fn new(key: impl ToString, id: IdSpec, fields: Vec<(Bytes, Bytes)>) -> XAdd {
+        XAdd {
+            key: key.to_string(),
+            nomkstream: false,
+            trim: None,
+            id,
+            fields,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAdd> {
+        let key = parse.next_string()?;
+        let args = parse.rest_bytes()?;
+
+        let mut nomkstream = false;
+        let mut trim = None;
+        let mut idx = 0;
+        while idx < args.len() {
+            match &String::from_utf8_lossy(&args[idx]).to_uppercase()[..] {
+                "NOMKSTREAM" => {
+                    nomkstream = true;
+                    idx += 1;
+                }
+                "MAXLEN" | "MINID" => trim = Some(parse_trim_options(&args, &mut idx)?),
+                _ => break,
+            }
+        }
+
+        // ID后面至少要有一对 field value
+        let rest = args.len().saturating_sub(idx + 1);
+        if rest == 0 || rest % 2 != 0 {
+            return Err(arity_error("xadd"));
+        }
+
+        let id = parse_id_spec(&args[idx])?;
+        let fields = args[idx + 1..].chunks(2).map(|kv| (kv[0].clone(), kv[1].clone())).collect();
+
+        let mut xadd = XAdd::new(key, id, fields);
+        xadd.nomkstream = nomkstream;
+        xadd.trim = trim;
+        Ok(xadd)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        if self.nomkstream && db.get(&self.key).map(Value::as_stream).transpose()?.is_none() {
+            return Ok(Frame::Null);
+        }
+
+        let stream = db.get_or_create_stream(&self.key)?;
+        let id = stream.next_id(self.id)?;
+        stream.add(id, self.fields);
+        let trimmed = match &self.trim {
+            Some(trim) => stream.trim(trim),
+            None => 0,
+        };
+
+        // 唤醒阻塞在这个stream上的XREAD
+        db.signal_key_ready(&self.key);
+        db.signal_modified_key(&self.key);
+        db.notify_keyspace_event(NOTIFY_STREAM, "xadd", &self.key);
+        if trimmed > 0 {
+            db.notify_keyspace_event(NOTIFY_STREAM, "xtrim", &self.key);
+        }
+        Ok(id.to_frame())
+    }
+}
+
+fn parse_id_spec(src: &Bytes) -> crate::Result<IdSpec> {
+    if &src[..] == b"*" {
+        return Ok(IdSpec::Auto);
+    }
+    if let Some(ms) = src.strip_suffix(b"-*") {
+        let ms = std::str::from_utf8(ms).ok().and_then(|ms| ms.parse().ok()).ok_or(INVALID_ID)?;
+        return Ok(IdSpec::AutoSeq(ms));
+    }
+    let id = StreamId::parse(src, 0).ok_or(INVALID_ID)?;
+    if id == StreamId::MIN {
+        return Err("ERR The ID specified in XADD must be greater than 0-0".into());
+    }
+    Ok(IdSpec::Explicit(id))
+}
+
+// 解析 MAXLEN|MINID [=|~] threshold [LIMIT count]，idx指向MAXLEN或者MINID，XTRIM也会用到
+pub(crate) fn parse_trim_options(args: &[Bytes], idx: &mut usize) -> crate::Result<TrimOptions> {
+    let maxlen = args[*idx].eq_ignore_ascii_case(b"MAXLEN");
+    *idx += 1;
+
+    let mut approx = false;
+    match args.get(*idx).map(|arg| &arg[..]) {
+        Some(b"~") => {
+            approx = true;
+            *idx += 1;
+        }
+        Some(b"=") => *idx += 1,
+        _ => {}
+    }
+
+    let threshold = args.get(*idx).ok_or("ERR syntax error")?;
+    *idx += 1;
+    let strategy = if maxlen {
+        let maxlen = parse_int(threshold).ok_or("ERR value is not an integer or out of range")?;
+        if maxlen < 0 {
+            return Err("ERR The MAXLEN argument must be >= 0.".into());
+        }
+        TrimStrategy::MaxLen(maxlen as usize)
+    } else {
+        TrimStrategy::MinId(StreamId::parse(threshold, 0).ok_or(INVALID_ID)?)
+    };
+
+    let mut limit = None;
+    if args.get(*idx).is_some_and(|arg| arg.eq_ignore_ascii_case(b"LIMIT")) {
+        let count = args.get(*idx + 1).ok_or("ERR syntax error")?;
+        let count = parse_int(count).ok_or("ERR value is not an integer or out of range")?;
+        if count < 0 {
+            return Err("ERR The LIMIT argument must be >= 0.".into());
+        }
+        if !approx {
+            return Err("ERR syntax error, LIMIT cannot be used without the special ~ option".into());
+        }
+        limit = Some(count as usize);
+        *idx += 2;
+    }
+
+    Ok(TrimOptions { strategy, approx, limit })
+}
@@ -0,0 +1,106 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::stream::{StreamId, ClaimOptions, ClaimResult, entry_frame};
+use crate::cmd::xrange::parse_interval_id;
+use crate::util::{parse_int, now_ms};
+
+// 每次最多检查 count * ATTEMPTS_FACTOR 条pending消息
+const ATTEMPTS_FACTOR: usize = 10;
+
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    justid: bool,
+}
+
+impl XAutoClaim {
+    pub fn new(key: impl ToString, group: impl ToString, consumer: impl ToString, min_idle: u64, start: StreamId) -> XAutoClaim {
+        XAutoClaim {
+            key: key.to_string(),
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            min_idle,
+            start,
+            count: 100,
+            justid: false,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XAutoClaim> {
+        let key = parse.next_string()?;
+        let group = parse.next_string()?;
+        let consumer = parse.next_string()?;
+        let min_idle = parse_int(&parse.next_bytes()?).ok_or("ERR Invalid min-idle-time argument for XAUTOCLAIM")?;
+        let start = match parse_interval_id(&parse.next_bytes()?, 0)? {
+            (id, false) => id,
+            (id, true) => id.incr().ok_or("ERR invalid start ID for the interval")?,
+        };
+
+        let mut xautoclaim = XAutoClaim::new(key, group, consumer, min_idle.max(0) as u64, start);
+        let args = parse.rest_bytes()?;
+        let mut idx = 0;
+        while idx < args.len() {
+            if args[idx].eq_ignore_ascii_case(b"COUNT") && idx + 1 < args.len() {
+                let count = parse_int(&args[idx + 1]).ok_or("ERR value is not an integer or out of range")?;
+                if count < 1 || count as usize > usize::MAX / ATTEMPTS_FACTOR {
+                    return Err("ERR COUNT must be > 0".into());
+                }
+                xautoclaim.count = count as usize;
+                idx += 2;
+            } else if args[idx].eq_ignore_ascii_case(b"JUSTID") {
+                xautoclaim.justid = true;
+                idx += 1;
+            } else {
+                return Err("ERR syntax error".into());
+            }
+        }
+        Ok(xautoclaim)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let no_group = || format!("NOGROUP No such key '{}' or consumer group '{}'", self.key, self.group);
+        let stream = db.get_stream_mut(&self.key)?.ok_or_else(no_group)?;
+        let cg = stream.group(&self.group).ok_or_else(no_group)?;
+
+        let options = ClaimOptions {
+            min_idle: self.min_idle,
+            delivery_time: now_ms(),
+            justid: self.justid,
+            ..ClaimOptions::default()
+        };
+        // 多取一个，用来当作下一次的游标
+        let attempts = self.count * ATTEMPTS_FACTOR;
+        let ids: Vec<StreamId> = cg.pending.range(self.start..).take(attempts + 1).map(|(id, _)| *id).collect();
+
+        let mut claimed = vec![];
+        let mut deleted = vec![];
+        let mut cursor = StreamId::MIN;
+        for (scanned, id) in ids.into_iter().enumerate() {
+            if scanned >= attempts || claimed.len() >= self.count {
+                cursor = id;
+                break;
+            }
+            match stream.claim(&self.group, &self.consumer, id, &options) {
+                ClaimResult::Claimed if self.justid => claimed.push(id.to_frame()),
+                ClaimResult::Claimed => {
+                    let fields = stream.get(id).unwrap().clone();
+                    claimed.push(entry_frame((id, fields)));
+                }
+                ClaimResult::Deleted => deleted.push(id.to_frame()),
+                ClaimResult::Skipped => {}
+            }
+        }
+
+        Ok(Frame::Array(vec![cursor.to_frame(), Frame::Array(claimed), Frame::Array(deleted)]))
+    }
+}
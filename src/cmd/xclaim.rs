@@ -0,0 +1,121 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::stream::{StreamId, ClaimOptions, ClaimResult, entry_frame};
+use crate::cmd::xadd::INVALID_ID;
+use crate::util::{parse_int, now_ms};
+
+#[derive(Debug)]
+pub struct XClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    ids: Vec<StreamId>,
+    options: ClaimOptions,
+    // 如果比组的last_id大，就更新组的last_id
+    last_id: Option<StreamId>,
+}
+
+impl XClaim {
+    pub fn new(key: impl ToString, group: impl ToString, consumer: impl ToString, ids: Vec<StreamId>, options: ClaimOptions) -> XClaim {
+        XClaim {
+            key: key.to_string(),
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            ids,
+            options,
+            last_id: None,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XClaim> {
+        let key = parse.next_string()?;
+        let group = parse.next_string()?;
+        let consumer = parse.next_string()?;
+        let min_idle = parse_int(&parse.next_bytes()?).ok_or("ERR Invalid min-idle-time argument for XCLAIM")?;
+        let mut args = vec![parse.next_bytes()?];
+        args.extend(parse.rest_bytes()?);
+
+        // 先读ID，遇到第一个不是ID的参数就开始解析选项
+        let mut idx = 0;
+        let mut ids = vec![];
+        while let Some(id) = args.get(idx).and_then(|arg| StreamId::parse(arg, 0)) {
+            ids.push(id);
+            idx += 1;
+        }
+        if ids.is_empty() {
+            return Err(INVALID_ID.into());
+        }
+
+        let now = now_ms();
+        let mut options = ClaimOptions {
+            min_idle: min_idle.max(0) as u64,
+            delivery_time: now,
+            ..ClaimOptions::default()
+        };
+        let mut last_id = None;
+        while idx < args.len() {
+            let opt = String::from_utf8_lossy(&args[idx]).to_string();
+            let has_value = idx + 1 < args.len();
+            match &opt.to_uppercase()[..] {
+                "FORCE" => options.force = true,
+                "JUSTID" => options.justid = true,
+                "IDLE" if has_value => {
+                    idx += 1;
+                    let idle = parse_int(&args[idx]).ok_or("ERR Invalid IDLE option argument for XCLAIM")?;
+                    options.delivery_time = now.saturating_sub(idle.max(0) as u64);
+                }
+                "TIME" if has_value => {
+                    idx += 1;
+                    let time = parse_int(&args[idx]).ok_or("ERR Invalid TIME option argument for XCLAIM")?;
+                    // 不允许设置成将来的时间
+                    options.delivery_time = (time.max(0) as u64).min(now);
+                }
+                "RETRYCOUNT" if has_value => {
+                    idx += 1;
+                    let count = parse_int(&args[idx]).ok_or("ERR Invalid RETRYCOUNT option argument for XCLAIM")?;
+                    options.retry_count = Some(count.max(0) as u64);
+                }
+                "LASTID" if has_value => {
+                    idx += 1;
+                    last_id = Some(StreamId::parse(&args[idx], 0).ok_or(INVALID_ID)?);
+                }
+                _ => return Err(format!("ERR Unrecognized XCLAIM option '{}'", opt).into()),
+            }
+            idx += 1;
+        }
+
+        let mut xclaim = XClaim::new(key, group, consumer, ids, options);
+        xclaim.last_id = last_id;
+        Ok(xclaim)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let no_group = || format!("NOGROUP No such key '{}' or consumer group '{}'", self.key, self.group);
+        let stream = db.get_stream_mut(&self.key)?.ok_or_else(no_group)?;
+        let cg = stream.group_mut(&self.group).ok_or_else(no_group)?;
+        if let Some(last_id) = self.last_id {
+            if last_id > cg.last_id {
+                cg.last_id = last_id;
+            }
+        }
+
+        let mut result = vec![];
+        for id in self.ids {
+            if stream.claim(&self.group, &self.consumer, id, &self.options) != ClaimResult::Claimed {
+                continue;
+            }
+            if self.options.justid {
+                result.push(id.to_frame());
+            } else {
+                let fields = stream.get(id).unwrap().clone();
+                result.push(entry_frame((id, fields)));
+            }
+        }
+        Ok(Frame::Array(result))
+    }
+}
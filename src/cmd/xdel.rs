@@ -0,0 +1,50 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STREAM;
+use crate::stream::StreamId;
+use crate::cmd::xadd::INVALID_ID;
+
+#[derive(Debug)]
+pub struct XDel {
+    key: String,
+    ids: Vec<StreamId>,
+}
+
+impl XDel {
+    pub fn new(key: impl ToString, ids: Vec<StreamId>) -> XDel {
+        XDel {
+            key: key.to_string(),
+            ids,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XDel> {
+        let key = parse.next_string()?;
+        let mut args = vec![parse.next_bytes()?];
+        args.extend(parse.rest_bytes()?);
+
+        let mut ids = Vec::with_capacity(args.len());
+        for arg in args {
+            ids.push(StreamId::parse(&arg, 0).ok_or(INVALID_ID)?);
+        }
+        Ok(XDel::new(key, ids))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let stream = match db.get_stream_mut(&self.key)? {
+            Some(stream) => stream,
+            None => return Ok(Frame::Integer(0)),
+        };
+        let deleted = self.ids.iter().filter(|id| stream.delete(**id)).count();
+        if deleted > 0 {
+            db.signal_modified_key(&self.key);
+            db.notify_keyspace_event(NOTIFY_STREAM, "xdel", &self.key);
+        }
+        Ok(Frame::Integer(deleted as i64))
+    }
+}
@@ -0,0 +1,196 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STREAM;
+use crate::stream::StreamId;
+use crate::cmd::xadd::INVALID_ID;
+use crate::util::{parse_int, now_ms};
+use bytes::Bytes;
+
+const NO_KEY: &str = "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.";
+
+#[derive(Debug)]
+pub enum XGroup {
+    Create {
+        key: String,
+        group: String,
+        // None代表 $
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    },
+    Destroy {
+        key: String,
+        group: String,
+    },
+    CreateConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+    DelConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+}
+
+impl XGroup {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            XGroup::Create { .. } => "xgroup|create",
+            XGroup::SetId { .. } => "xgroup|setid",
+            XGroup::Destroy { .. } => "xgroup|destroy",
+            XGroup::CreateConsumer { .. } => "xgroup|createconsumer",
+            XGroup::DelConsumer { .. } => "xgroup|delconsumer",
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            XGroup::Create { key, .. }
+            | XGroup::SetId { key, .. }
+            | XGroup::Destroy { key, .. }
+            | XGroup::CreateConsumer { key, .. }
+            | XGroup::DelConsumer { key, .. } => key,
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XGroup> {
+        let subcommand = parse.next_string()?;
+        match &subcommand.to_uppercase()[..] {
+            "CREATE" => {
+                let key = parse.next_string()?;
+                let group = parse.next_string()?;
+                let id = parse_group_id(&parse.next_bytes()?)?;
+                let mut mkstream = false;
+                let mut entries_read = None;
+                let args = parse.rest_bytes()?;
+                let mut idx = 0;
+                while idx < args.len() {
+                    if args[idx].eq_ignore_ascii_case(b"MKSTREAM") {
+                        mkstream = true;
+                    } else if args[idx].eq_ignore_ascii_case(b"ENTRIESREAD") && idx + 1 < args.len() {
+                        entries_read = parse_entries_read(&args[idx + 1])?;
+                        idx += 1;
+                    } else {
+                        return Err("ERR syntax error".into());
+                    }
+                    idx += 1;
+                }
+                Ok(XGroup::Create { key, group, id, mkstream, entries_read })
+            }
+            "SETID" => {
+                let key = parse.next_string()?;
+                let group = parse.next_string()?;
+                let id = parse_group_id(&parse.next_bytes()?)?;
+                let args = parse.rest_bytes()?;
+                let entries_read = match &args[..] {
+                    [] => None,
+                    [option, n] if option.eq_ignore_ascii_case(b"ENTRIESREAD") => parse_entries_read(n)?,
+                    _ => return Err("ERR syntax error".into()),
+                };
+                Ok(XGroup::SetId { key, group, id, entries_read })
+            }
+            "DESTROY" => {
+                let key = parse.next_string()?;
+                let group = parse.next_string()?;
+                Ok(XGroup::Destroy { key, group })
+            }
+            "CREATECONSUMER" => {
+                let key = parse.next_string()?;
+                let group = parse.next_string()?;
+                let consumer = parse.next_string()?;
+                Ok(XGroup::CreateConsumer { key, group, consumer })
+            }
+            "DELCONSUMER" => {
+                let key = parse.next_string()?;
+                let group = parse.next_string()?;
+                let consumer = parse.next_string()?;
+                Ok(XGroup::DelConsumer { key, group, consumer })
+            }
+            _ => Err(format!("ERR unknown subcommand '{}'. Try XGROUP HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        if let XGroup::Create { key, group, id, mkstream, entries_read } = self {
+            if mkstream {
+                db.get_or_create_stream(&key)?;
+            }
+            let stream = db.get_stream_mut(&key)?.ok_or(NO_KEY)?;
+            let id = id.unwrap_or_else(|| stream.last_id());
+            if !stream.create_group(&group, id, entries_read) {
+                return Err("BUSYGROUP Consumer Group name already exists".into());
+            }
+            db.signal_modified_key(&key);
+            db.notify_keyspace_event(NOTIFY_STREAM, "xgroup-create", &key);
+            return Ok(Frame::Simple("OK".to_string()));
+        }
+
+        let key = self.key().to_string();
+        let event = match &self {
+            XGroup::SetId { .. } => "xgroup-setid",
+            XGroup::Destroy { .. } => "xgroup-destroy",
+            XGroup::CreateConsumer { .. } => "xgroup-createconsumer",
+            XGroup::DelConsumer { .. } => "xgroup-delconsumer",
+            XGroup::Create { .. } => unreachable!(),
+        };
+        let stream = db.get_stream_mut(&key)?.ok_or(NO_KEY)?;
+        let response = match self {
+            XGroup::SetId { key, group, id, entries_read } => {
+                let id = id.unwrap_or_else(|| stream.last_id());
+                let cg = stream.group_mut(&group).ok_or_else(|| no_group(&key, &group))?;
+                cg.last_id = id;
+                cg.entries_read = entries_read;
+                Ok(Frame::Simple("OK".to_string()))
+            }
+            XGroup::Destroy { group, .. } => {
+                Ok(Frame::Integer(stream.destroy_group(&group) as i64))
+            }
+            XGroup::CreateConsumer { key, group, consumer } => {
+                let cg = stream.group_mut(&group).ok_or_else(|| no_group(&key, &group))?;
+                Ok(Frame::Integer(cg.create_consumer(&consumer, now_ms()) as i64))
+            }
+            XGroup::DelConsumer { key, group, consumer } => {
+                let cg = stream.group_mut(&group).ok_or_else(|| no_group(&key, &group))?;
+                Ok(Frame::Integer(cg.delete_consumer(&consumer).unwrap_or(0) as i64))
+            }
+            XGroup::Create { .. } => unreachable!(),
+        };
+        if response.is_ok() {
+            db.signal_modified_key(&key);
+            db.notify_keyspace_event(NOTIFY_STREAM, event, &key);
+        }
+        response
+    }
+}
+
+pub(crate) fn no_group(key: &str, group: &str) -> String {
+    format!("NOGROUP No such consumer group '{}' for key name '{}'", group, key)
+}
+
+// $ 代表stream当前最后一个ID
+fn parse_group_id(src: &Bytes) -> crate::Result<Option<StreamId>> {
+    if &src[..] == b"$" {
+        return Ok(None);
+    }
+    Ok(Some(StreamId::parse(src, 0).ok_or(INVALID_ID)?))
+}
+
+// -1 代表不知道读过多少条
+fn parse_entries_read(src: &Bytes) -> crate::Result<Option<u64>> {
+    match parse_int(src) {
+        Some(-1) => Ok(None),
+        Some(n) if n >= 0 => Ok(Some(n as u64)),
+        Some(_) => Err("ERR value for ENTRIESREAD must be positive or -1".into()),
+        None => Err("ERR value is not an integer or out of range".into()),
+    }
+}
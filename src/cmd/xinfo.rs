@@ -0,0 +1,234 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use crate::stream::{Stream, ConsumerGroup, STREAM_NODE_MAX_ENTRIES, entry_frame, entries_frame};
+use crate::cmd::xgroup::no_group;
+use crate::util::{parse_int, now_ms};
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub enum XInfo {
+    // full为Some的时候是 FULL 格式，里面是COUNT，0代表全部
+    Stream { key: String, full: Option<usize> },
+    Groups { key: String },
+    Consumers { key: String, group: String },
+}
+
+impl XInfo {
+    // 命令表里面的名字，带上父命令
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            XInfo::Stream { .. } => "xinfo|stream",
+            XInfo::Groups { .. } => "xinfo|groups",
+            XInfo::Consumers { .. } => "xinfo|consumers",
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            XInfo::Stream { key, .. } | XInfo::Groups { key } | XInfo::Consumers { key, .. } => key,
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XInfo> {
+        let subcommand = parse.next_string()?;
+        match &subcommand.to_uppercase()[..] {
+            "STREAM" => {
+                let key = parse.next_string()?;
+                let full = match parse.next_string() {
+                    Ok(s) if s.to_uppercase() == "FULL" => Some(parse_full_count(parse)?),
+                    Ok(_) => return Err("ERR syntax error".into()),
+                    Err(EndOfStream) => None,
+                    Err(err) => return Err(err.into()),
+                };
+                Ok(XInfo::Stream { key, full })
+            }
+            "GROUPS" => Ok(XInfo::Groups { key: parse.next_string()? }),
+            "CONSUMERS" => Ok(XInfo::Consumers { key: parse.next_string()?, group: parse.next_string()? }),
+            _ => Err(format!("ERR unknown subcommand '{}'. Try XINFO HELP.", subcommand).into()),
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let stream = db.get_stream(self.key())?.ok_or("ERR no such key")?;
+        match &self {
+            XInfo::Stream { full: None, .. } => Ok(stream_info(stream)),
+            XInfo::Stream { full: Some(count), .. } => Ok(stream_info_full(stream, *count)),
+            XInfo::Groups { .. } => {
+                let groups = stream.groups().iter().map(|(name, cg)| group_info(stream, name, cg)).collect();
+                Ok(Frame::Array(groups))
+            }
+            XInfo::Consumers { key, group } => {
+                let now = now_ms();
+                let cg = stream.group(group).ok_or_else(|| no_group(key, group))?;
+                let consumers = cg
+                    .consumers
+                    .iter()
+                    .map(|(name, consumer)| {
+                        let inactive = consumer.active_time.map(|t| now.saturating_sub(t) as i64).unwrap_or(-1);
+                        Frame::Array(vec![
+                            bulk("name"),
+                            bulk(name),
+                            bulk("pending"),
+                            Frame::Integer(consumer.pending.len() as i64),
+                            bulk("idle"),
+                            Frame::Integer(now.saturating_sub(consumer.seen_time) as i64),
+                            bulk("inactive"),
+                            Frame::Integer(inactive),
+                        ])
+                    })
+                    .collect();
+                Ok(Frame::Array(consumers))
+            }
+        }
+    }
+}
+
+fn parse_full_count(parse: &mut Parse) -> crate::Result<usize> {
+    match parse.next_string() {
+        Ok(s) if s.to_uppercase() == "COUNT" => {
+            let count = parse_int(&parse.next_bytes()?).ok_or("ERR value is not an integer or out of range")?;
+            Ok(count.max(0) as usize)
+        }
+        Ok(_) => Err("ERR syntax error".into()),
+        Err(EndOfStream) => Ok(10),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn bulk(s: &str) -> Frame {
+    Frame::Bulk(Bytes::from(s.to_string()))
+}
+
+fn optional_int(v: Option<u64>) -> Frame {
+    v.map(|v| Frame::Integer(v as i64)).unwrap_or(Frame::Null)
+}
+
+// 没有真正的radix tree，按每个节点最多放多少entry估算
+fn radix_tree_keys(stream: &Stream) -> i64 {
+    stream.len().div_ceil(STREAM_NODE_MAX_ENTRIES) as i64
+}
+
+// 公共的头部字段，FULL格式也会用到
+fn stream_header(stream: &Stream) -> Vec<Frame> {
+    let keys = radix_tree_keys(stream);
+    vec![
+        bulk("length"),
+        Frame::Integer(stream.len() as i64),
+        bulk("radix-tree-keys"),
+        Frame::Integer(keys),
+        bulk("radix-tree-nodes"),
+        Frame::Integer(keys * 2 + 1),
+        bulk("last-generated-id"),
+        stream.last_id().to_frame(),
+        bulk("max-deleted-entry-id"),
+        stream.max_deleted_id().to_frame(),
+        bulk("entries-added"),
+        Frame::Integer(stream.entries_added() as i64),
+        bulk("recorded-first-entry-id"),
+        stream.first_id().to_frame(),
+    ]
+}
+
+fn stream_info(stream: &Stream) -> Frame {
+    let mut info = stream_header(stream);
+    info.push(bulk("groups"));
+    info.push(Frame::Integer(stream.groups().len() as i64));
+    info.push(bulk("first-entry"));
+    info.push(stream.first_entry().map(entry_frame).unwrap_or(Frame::Null));
+    info.push(bulk("last-entry"));
+    info.push(stream.last_entry().map(entry_frame).unwrap_or(Frame::Null));
+    Frame::Array(info)
+}
+
+fn group_info(stream: &Stream, name: &str, cg: &ConsumerGroup) -> Frame {
+    Frame::Array(vec![
+        bulk("name"),
+        bulk(name),
+        bulk("consumers"),
+        Frame::Integer(cg.consumers.len() as i64),
+        bulk("pending"),
+        Frame::Integer(cg.pending.len() as i64),
+        bulk("last-delivered-id"),
+        cg.last_id.to_frame(),
+        bulk("entries-read"),
+        optional_int(cg.entries_read),
+        bulk("lag"),
+        optional_int(stream.group_lag(cg)),
+    ])
+}
+
+// count为0代表不限制数量
+fn stream_info_full(stream: &Stream, count: usize) -> Frame {
+    let limit = if count == 0 { None } else { Some(count) };
+    let mut info = stream_header(stream);
+    info.push(bulk("entries"));
+    info.push(entries_frame(stream.range(stream.first_id(), stream.last_id(), limit, false)));
+
+    let mut groups = vec![];
+    for (name, cg) in stream.groups() {
+        let take = limit.unwrap_or(usize::MAX);
+        let pending = cg
+            .pending
+            .iter()
+            .take(take)
+            .map(|(id, entry)| Frame::Array(vec![
+                id.to_frame(),
+                bulk(&entry.consumer),
+                Frame::Integer(entry.delivery_time as i64),
+                Frame::Integer(entry.delivery_count as i64),
+            ]))
+            .collect();
+        let consumers = cg
+            .consumers
+            .iter()
+            .map(|(consumer_name, consumer)| {
+                let pending = consumer
+                    .pending
+                    .iter()
+                    .take(take)
+                    .map(|id| {
+                        let entry = &cg.pending[id];
+                        Frame::Array(vec![
+                            id.to_frame(),
+                            Frame::Integer(entry.delivery_time as i64),
+                            Frame::Integer(entry.delivery_count as i64),
+                        ])
+                    })
+                    .collect();
+                let active_time = consumer.active_time.map(|t| t as i64).unwrap_or(-1);
+                Frame::Array(vec![
+                    bulk("name"),
+                    bulk(consumer_name),
+                    bulk("seen-time"),
+                    Frame::Integer(consumer.seen_time as i64),
+                    bulk("active-time"),
+                    Frame::Integer(active_time),
+                    bulk("pel-count"),
+                    Frame::Integer(consumer.pending.len() as i64),
+                    bulk("pending"),
+                    Frame::Array(pending),
+                ])
+            })
+            .collect();
+        groups.push(Frame::Array(vec![
+            bulk("name"),
+            bulk(name),
+            bulk("last-delivered-id"),
+            cg.last_id.to_frame(),
+            bulk("entries-read"),
+            optional_int(cg.entries_read),
+            bulk("lag"),
+            optional_int(stream.group_lag(cg)),
+            bulk("pel-count"),
+            Frame::Integer(cg.pending.len() as i64),
+            bulk("pending"),
+            Frame::Array(pending),
+            bulk("consumers"),
+            Frame::Array(consumers),
+        ]));
+    }
+    info.push(bulk("groups"));
+    info.push(Frame::Array(groups));
+    Frame::Array(info)
+}
@@ -0,0 +1,30 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+
+#[derive(Debug)]
+pub struct XLen {
+    key: String,
+}
+
+impl XLen {
+    pub fn new(key: impl ToString) -> XLen {
+        XLen {
+            key: key.to_string(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XLen> {
+        let key = parse.next_string()?;
+        Ok(XLen::new(key))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let len = db.get_stream(&self.key)?.map_or(0, |stream| stream.len());
+        Ok(Frame::Integer(len as i64))
+    }
+}
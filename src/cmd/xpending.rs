@@ -0,0 +1,143 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::stream::StreamId;
+use crate::cmd::xrange::parse_interval_id;
+use crate::util::{parse_int, now_ms};
+use bytes::Bytes;
+use std::collections::BTreeMap;
+
+// 带范围参数的扩展格式
+#[derive(Debug)]
+pub struct PendingRange {
+    min_idle: Option<u64>,
+    start: StreamId,
+    end: StreamId,
+    count: usize,
+    consumer: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct XPending {
+    key: String,
+    group: String,
+    // None的时候只返回汇总信息
+    range: Option<PendingRange>,
+}
+
+impl XPending {
+    pub fn new(key: impl ToString, group: impl ToString) -> XPending {
+        XPending {
+            key: key.to_string(),
+            group: group.to_string(),
+            range: None,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XPending> {
+        let mut xpending = XPending::new(parse.next_string()?, parse.next_string()?);
+        let args = parse.rest_bytes()?;
+        if args.is_empty() {
+            return Ok(xpending);
+        }
+
+        let mut idx = 0;
+        let mut min_idle = None;
+        if args[0].eq_ignore_ascii_case(b"IDLE") && args.len() > 1 {
+            let idle = parse_int(&args[1]).ok_or("ERR value is not an integer or out of range")?;
+            min_idle = Some(idle.max(0) as u64);
+            idx = 2;
+        }
+        let rest = &args[idx..];
+        if rest.len() != 3 && rest.len() != 4 {
+            return Err("ERR syntax error".into());
+        }
+
+        let start = match parse_interval_id(&rest[0], 0)? {
+            (id, false) => id,
+            (id, true) => id.incr().ok_or("ERR invalid start ID for the interval")?,
+        };
+        let end = match parse_interval_id(&rest[1], u64::MAX)? {
+            (id, false) => id,
+            (id, true) => id.decr().ok_or("ERR invalid end ID for the interval")?,
+        };
+        let count = parse_int(&rest[2]).ok_or("ERR value is not an integer or out of range")?;
+        let consumer = rest.get(3).map(|c| String::from_utf8_lossy(c).to_string());
+
+        xpending.range = Some(PendingRange {
+            min_idle,
+            start,
+            end,
+            count: count.max(0) as usize,
+            consumer,
+        });
+        Ok(xpending)
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let no_group = || format!("NOGROUP No such key '{}' or consumer group '{}'", self.key, self.group);
+        let stream = db.get_stream(&self.key)?.ok_or_else(no_group)?;
+        let cg = stream.group(&self.group).ok_or_else(no_group)?;
+
+        let range = match self.range {
+            Some(range) => range,
+            None => {
+                if cg.pending.is_empty() {
+                    return Ok(Frame::Array(vec![Frame::Integer(0), Frame::Null, Frame::Null, Frame::NullArray]));
+                }
+                let mut per_consumer: BTreeMap<&str, u64> = BTreeMap::new();
+                for entry in cg.pending.values() {
+                    *per_consumer.entry(&entry.consumer).or_default() += 1;
+                }
+                let consumers = per_consumer
+                    .into_iter()
+                    .map(|(name, count)| Frame::Array(vec![
+                        Frame::Bulk(Bytes::from(name.to_string())),
+                        Frame::Bulk(Bytes::from(count.to_string())),
+                    ]))
+                    .collect();
+                return Ok(Frame::Array(vec![
+                    Frame::Integer(cg.pending.len() as i64),
+                    cg.pending.keys().next().unwrap().to_frame(),
+                    cg.pending.keys().next_back().unwrap().to_frame(),
+                    Frame::Array(consumers),
+                ]));
+            }
+        };
+
+        if range.start > range.end || range.count == 0 {
+            return Ok(Frame::Array(vec![]));
+        }
+        let now = now_ms();
+        let ids: Vec<StreamId> = match &range.consumer {
+            Some(name) => match cg.consumers.get(name) {
+                Some(consumer) => consumer.pending.range(range.start..=range.end).copied().collect(),
+                None => return Ok(Frame::Array(vec![])),
+            },
+            None => cg.pending.range(range.start..=range.end).map(|(id, _)| *id).collect(),
+        };
+
+        let mut result = vec![];
+        for id in ids {
+            if result.len() >= range.count {
+                break;
+            }
+            let entry = &cg.pending[&id];
+            let idle = now.saturating_sub(entry.delivery_time);
+            if range.min_idle.is_some_and(|min| idle < min) {
+                continue;
+            }
+            result.push(Frame::Array(vec![
+                id.to_frame(),
+                Frame::Bulk(Bytes::from(entry.consumer.clone())),
+                Frame::Integer(idle as i64),
+                Frame::Integer(entry.delivery_count as i64),
+            ]));
+        }
+        Ok(Frame::Array(result))
+    }
+}
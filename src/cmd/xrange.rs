@@ -0,0 +1,93 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use crate::stream::{StreamId, entries_frame};
+use crate::cmd::xadd::INVALID_ID;
+use crate::util::parse_int;
+use bytes::Bytes;
+
+// XRANGE 和 XREVRANGE 共用，start和end已经处理成闭区间
+#[derive(Debug)]
+pub struct XRange {
+    key: String,
+    start: StreamId,
+    end: StreamId,
+    count: Option<usize>,
+    rev: bool,
+}
+
+impl XRange {
+    // 命令表里面的名字
+    pub(crate) fn name(&self) -> &'static str {
+        if self.rev { "xrevrange" } else { "xrange" }
+    }
+
+    pub fn new(key: impl ToString, start: StreamId, end: StreamId, count: Option<usize>, rev: bool) -> XRange {
+        XRange {
+            key: key.to_string(),
+            start,
+            end,
+            count,
+            rev,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse, rev: bool) -> crate::Result<XRange> {
+        let key = parse.next_string()?;
+        let first = parse.next_bytes()?;
+        let second = parse.next_bytes()?;
+        // XREVRANGE 的参数顺序是 end start
+        let (start, end) = if rev { (second, first) } else { (first, second) };
+
+        let start = match parse_interval_id(&start, 0)? {
+            (id, false) => id,
+            (id, true) => id.incr().ok_or("ERR invalid start ID for the interval")?,
+        };
+        let end = match parse_interval_id(&end, u64::MAX)? {
+            (id, false) => id,
+            (id, true) => id.decr().ok_or("ERR invalid end ID for the interval")?,
+        };
+
+        let mut count = None;
+        match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "COUNT" => {
+                let n = parse_int(&parse.next_bytes()?).ok_or("ERR value is not an integer or out of range")?;
+                count = Some(n.max(0) as usize);
+            }
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(EndOfStream) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(XRange::new(key, start, end, count, rev))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let stream = match db.get_stream(&self.key)? {
+            Some(stream) => stream,
+            None => return Ok(Frame::Array(vec![])),
+        };
+        if self.count == Some(0) {
+            return Ok(Frame::NullArray);
+        }
+        Ok(entries_frame(stream.range(self.start, self.end, self.count, self.rev)))
+    }
+}
+
+// 区间的端点，- 和 + 代表最小和最大，( 开头代表开区间
+pub(crate) fn parse_interval_id(src: &Bytes, missing_seq: u64) -> crate::Result<(StreamId, bool)> {
+    let (src, exclusive) = match src.strip_prefix(b"(") {
+        Some(rest) if !rest.is_empty() => (rest, true),
+        _ => (&src[..], false),
+    };
+    let id = match src {
+        b"-" => StreamId::MIN,
+        b"+" => StreamId::MAX,
+        _ => StreamId::parse(src, missing_seq).ok_or(INVALID_ID)?,
+    };
+    Ok((id, exclusive))
+}
@@ -0,0 +1,165 @@
+use crate::parse::Parse;
+use crate::db::{Db, State};
+use crate::frame::Frame;
+use crate::connection::Connection;
+use crate::shutdown::Shutdown;
+use crate::stream::{StreamId, entries_frame};
+use crate::cmd::{serve_blocking, error_frame};
+use crate::cmd::xadd::INVALID_ID;
+use crate::util::parse_int;
+use bytes::Bytes;
+use tokio::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ReadId {
+    // $ 代表只读取之后新加的entry
+    Last,
+    Id(StreamId),
+}
+
+#[derive(Debug)]
+pub struct XRead {
+    keys: Vec<String>,
+    ids: Vec<ReadId>,
+    count: Option<usize>,
+    // Some(0)代表一直等待
+    block: Option<Duration>,
+}
+
+impl XRead {
+    pub fn new(keys: Vec<String>, ids: Vec<ReadId>) -> XRead {
+        XRead {
+            keys,
+            ids,
+            count: None,
+            block: None,
+        }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XRead> {
+        let args = parse.rest_bytes()?;
+
+        let mut count = None;
+        let mut block = None;
+        let mut idx = 0;
+        loop {
+            let arg = args.get(idx).ok_or("ERR syntax error")?;
+            match &String::from_utf8_lossy(arg).to_uppercase()[..] {
+                "COUNT" => {
+                    let n = args.get(idx + 1).ok_or("ERR syntax error")?;
+                    let n = parse_int(n).ok_or("ERR value is not an integer or out of range")?;
+                    // 0和负数都代表不限制
+                    count = if n > 0 { Some(n as usize) } else { None };
+                    idx += 2;
+                }
+                "BLOCK" => {
+                    let ms = args.get(idx + 1).ok_or("ERR syntax error")?;
+                    let ms = parse_int(ms).ok_or("ERR timeout is not an integer or out of range")?;
+                    if ms < 0 {
+                        return Err("ERR timeout is negative".into());
+                    }
+                    block = Some(Duration::from_millis(ms as u64));
+                    idx += 2;
+                }
+                "STREAMS" => {
+                    idx += 1;
+                    break;
+                }
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        let streams = &args[idx..];
+        if streams.is_empty() || streams.len() % 2 != 0 {
+            return Err("ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.".into());
+        }
+        let half = streams.len() / 2;
+        let keys = streams[..half].iter().map(|key| String::from_utf8_lossy(key).to_string()).collect();
+        let mut ids = Vec::with_capacity(half);
+        for id in &streams[half..] {
+            ids.push(parse_read_id(id)?);
+        }
+
+        let mut xread = XRead::new(keys, ids);
+        xread.count = count;
+        xread.block = block;
+        Ok(xread)
+    }
+
+    // 把 $ 换成当前最后一个ID，阻塞等待期间都用这个ID比较
+    fn resolve_ids(&mut self, db: &mut State) -> crate::Result<()> {
+        for (key, id) in self.keys.iter().zip(self.ids.iter_mut()) {
+            let last = db.get_stream(key)?.map_or(StreamId::MIN, |stream| stream.last_id());
+            if let ReadId::Last = id {
+                *id = ReadId::Id(last);
+            }
+        }
+        Ok(())
+    }
+
+    // 读取每个stream中比给定ID大的entry，都没有的时候返回None
+    fn read(&self, db: &mut State) -> crate::Result<Option<Frame>> {
+        let mut result = vec![];
+        for (key, id) in self.keys.iter().zip(self.ids.iter()) {
+            let stream = match db.get_stream(key)? {
+                Some(stream) => stream,
+                None => continue,
+            };
+            let start = match id {
+                ReadId::Last => continue,
+                ReadId::Id(id) => match id.incr() {
+                    Some(start) => start,
+                    None => continue,
+                },
+            };
+            let entries = stream.range(start, StreamId::MAX, self.count, false);
+            if !entries.is_empty() {
+                result.push(Frame::Array(vec![Frame::Bulk(Bytes::from(key.clone())), entries_frame(entries)]));
+            }
+        }
+        Ok(if result.is_empty() { None } else { Some(Frame::Array(result)) })
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        Ok(self.read(db)?.unwrap_or(Frame::NullArray))
+    }
+
+    pub(crate) async fn apply(mut self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let block = match self.block {
+            Some(block) => block,
+            None => {
+                let response = match db.lock_unless_busy().await {
+                    Ok(mut state) => self.execute(&mut state).unwrap_or_else(error_frame),
+                    Err(err) => error_frame(err),
+                };
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let resolved = self.resolve_ids(&mut *db.lock_async().await);
+        if let Err(err) = resolved {
+            dst.write_frame(&error_frame(err)).await?;
+            return Ok(());
+        }
+
+        let timeout = if block.as_millis() == 0 { None } else { Some(block) };
+        let keys = self.keys.clone();
+        let response = serve_blocking(db, &keys, timeout, shutdown, |state| self.read(state)).await;
+        if let Some(response) = response {
+            dst.write_frame(&response).await?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_read_id(src: &Bytes) -> crate::Result<ReadId> {
+    if &src[..] == b"$" {
+        return Ok(ReadId::Last);
+    }
+    Ok(ReadId::Id(StreamId::parse(src, 0).ok_or(INVALID_ID)?))
+}
@@ -0,0 +1,193 @@
+use crate::parse::Parse;
+use crate::db::{Db, State};
+use crate::frame::Frame;
+use crate::connection::Connection;
+use crate::shutdown::Shutdown;
+use crate::stream::{StreamId, entries_frame, entry_frame};
+use crate::cmd::{serve_blocking, error_frame};
+use crate::cmd::xadd::INVALID_ID;
+use crate::util::parse_int;
+use bytes::Bytes;
+use tokio::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub enum GroupReadId {
+    // > 代表读取从来没有投递给任何消费者的消息
+    Undelivered,
+    // 读取自己pending列表里面的历史消息
+    Pending(StreamId),
+}
+
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: String,
+    consumer: String,
+    keys: Vec<String>,
+    ids: Vec<GroupReadId>,
+    count: Option<usize>,
+    // Some(0)代表一直等待
+    block: Option<Duration>,
+    // 不放到pending列表里面，相当于读完立刻ACK
+    noack: bool,
+}
+
+impl XReadGroup {
+    pub fn new(group: impl ToString, consumer: impl ToString, keys: Vec<String>, ids: Vec<GroupReadId>) -> XReadGroup {
+        XReadGroup {
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            keys,
+            ids,
+            count: None,
+            block: None,
+            noack: false,
+        }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XReadGroup> {
+        let args = parse.rest_bytes()?;
+
+        let mut group = None;
+        let mut count = None;
+        let mut block = None;
+        let mut noack = false;
+        let mut idx = 0;
+        loop {
+            let arg = args.get(idx).ok_or("ERR syntax error")?;
+            match &String::from_utf8_lossy(arg).to_uppercase()[..] {
+                "GROUP" if idx + 2 < args.len() => {
+                    let name = String::from_utf8_lossy(&args[idx + 1]).to_string();
+                    let consumer = String::from_utf8_lossy(&args[idx + 2]).to_string();
+                    group = Some((name, consumer));
+                    idx += 3;
+                }
+                "COUNT" if idx + 1 < args.len() => {
+                    let n = parse_int(&args[idx + 1]).ok_or("ERR value is not an integer or out of range")?;
+                    count = if n > 0 { Some(n as usize) } else { None };
+                    idx += 2;
+                }
+                "BLOCK" if idx + 1 < args.len() => {
+                    let ms = parse_int(&args[idx + 1]).ok_or("ERR timeout is not an integer or out of range")?;
+                    if ms < 0 {
+                        return Err("ERR timeout is negative".into());
+                    }
+                    block = Some(Duration::from_millis(ms as u64));
+                    idx += 2;
+                }
+                "NOACK" => {
+                    noack = true;
+                    idx += 1;
+                }
+                "STREAMS" => {
+                    idx += 1;
+                    break;
+                }
+                _ => return Err("ERR syntax error".into()),
+            }
+        }
+
+        let (group, consumer) = group.ok_or("ERR Missing GROUP option for XREADGROUP")?;
+        let streams = &args[idx..];
+        if streams.is_empty() || streams.len() % 2 != 0 {
+            return Err("ERR Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must be specified.".into());
+        }
+        let half = streams.len() / 2;
+        let keys = streams[..half].iter().map(|key| String::from_utf8_lossy(key).to_string()).collect();
+        let mut ids = Vec::with_capacity(half);
+        for id in &streams[half..] {
+            ids.push(parse_group_read_id(id)?);
+        }
+
+        let mut xreadgroup = XReadGroup::new(group, consumer, keys, ids);
+        xreadgroup.count = count;
+        xreadgroup.block = block;
+        xreadgroup.noack = noack;
+        Ok(xreadgroup)
+    }
+
+    // 所有的stream和组都必须存在
+    fn check_groups(&self, db: &mut State) -> crate::Result<()> {
+        for key in &self.keys {
+            let exists = db.get_stream(key)?.is_some_and(|stream| stream.group(&self.group).is_some());
+            if !exists {
+                return Err(format!(
+                    "NOGROUP No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                    key, self.group
+                ).into());
+            }
+        }
+        Ok(())
+    }
+
+    // 没有可以返回的消息时返回None，读历史消息的时候总是有返回
+    fn read(&self, db: &mut State) -> crate::Result<Option<Frame>> {
+        self.check_groups(db)?;
+
+        let mut result = vec![];
+        for (key, id) in self.keys.iter().zip(self.ids.iter()) {
+            let stream = db.get_stream_mut(key)?.unwrap();
+            match id {
+                GroupReadId::Undelivered => {
+                    let entries = stream.read_group_new(&self.group, &self.consumer, self.count, self.noack);
+                    if !entries.is_empty() {
+                        result.push(Frame::Array(vec![Frame::Bulk(Bytes::from(key.clone())), entries_frame(entries)]));
+                    }
+                }
+                GroupReadId::Pending(id) => {
+                    let start = match id.incr() {
+                        Some(start) => start,
+                        None => StreamId::MAX,
+                    };
+                    let entries = stream
+                        .read_group_history(&self.group, &self.consumer, start, self.count)
+                        .into_iter()
+                        .map(|(id, fields)| match fields {
+                            Some(fields) => entry_frame((id, fields)),
+                            // 消息已经被删除了
+                            None => Frame::Array(vec![id.to_frame(), Frame::NullArray]),
+                        })
+                        .collect();
+                    result.push(Frame::Array(vec![Frame::Bulk(Bytes::from(key.clone())), Frame::Array(entries)]));
+                }
+            }
+        }
+        Ok(if result.is_empty() { None } else { Some(Frame::Array(result)) })
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        Ok(self.read(db)?.unwrap_or(Frame::NullArray))
+    }
+
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection, shutdown: &mut Shutdown) -> crate::Result<()> {
+        let block = match self.block {
+            Some(block) => block,
+            None => {
+                let response = match db.lock_unless_busy().await {
+                    Ok(mut state) => self.execute(&mut state).unwrap_or_else(error_frame),
+                    Err(err) => error_frame(err),
+                };
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+
+        let timeout = if block.as_millis() == 0 { None } else { Some(block) };
+        let response = serve_blocking(db, &self.keys, timeout, shutdown, |state| self.read(state)).await;
+        if let Some(response) = response {
+            dst.write_frame(&response).await?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_group_read_id(src: &Bytes) -> crate::Result<GroupReadId> {
+    match &src[..] {
+        b">" => Ok(GroupReadId::Undelivered),
+        b"$" => Err("ERR The $ ID is meaningless in the context of XREADGROUP: you want to read the history of this consumer by specifying a proper ID, or use the > ID to get new messages. The $ ID would just return an empty result set.".into()),
+        _ => Ok(GroupReadId::Pending(StreamId::parse(src, 0).ok_or(INVALID_ID)?)),
+    }
+}
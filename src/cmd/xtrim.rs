@@ -0,0 +1,54 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_STREAM;
+use crate::stream::TrimOptions;
+use crate::cmd::xadd::parse_trim_options;
+
+#[derive(Debug)]
+pub struct XTrim {
+    key: String,
+    options: TrimOptions,
+}
+
+impl XTrim {
+    pub fn new(key: impl ToString, options: TrimOptions) -> XTrim {
+        XTrim {
+            key: key.to_string(),
+            options,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<XTrim> {
+        let key = parse.next_string()?;
+        let args = parse.rest_bytes()?;
+
+        let strategy = args.first().ok_or("ERR syntax error")?;
+        if !strategy.eq_ignore_ascii_case(b"MAXLEN") && !strategy.eq_ignore_ascii_case(b"MINID") {
+            return Err("ERR syntax error".into());
+        }
+        let mut idx = 0;
+        let options = parse_trim_options(&args, &mut idx)?;
+        if idx != args.len() {
+            return Err("ERR syntax error".into());
+        }
+
+        Ok(XTrim::new(key, options))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let removed = match db.get_stream_mut(&self.key)? {
+            Some(stream) => stream.trim(&self.options),
+            None => 0,
+        };
+        if removed > 0 {
+            db.signal_modified_key(&self.key);
+            db.notify_keyspace_event(NOTIFY_STREAM, "xtrim", &self.key);
+        }
+        Ok(Frame::Integer(removed as i64))
+    }
+}
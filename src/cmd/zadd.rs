@@ -0,0 +1,128 @@
+use crate::parse::Parse;
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::notify::NOTIFY_ZSET;
+use crate::zset::{AddFlags, AddResult};
+use crate::util::{parse_double, format_double};
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    flags: AddFlags,
+    // 返回值统计被修改的成员，而不只是新增的
+    ch: bool,
+    members: Vec<(f64, Bytes)>,
+}
+
+impl ZAdd {
+    pub fn new(key: impl ToString, members: Vec<(f64, Bytes)>) -> ZAdd {
+        ZAdd {
+            key: key.to_string(),
+            flags: AddFlags::default(),
+            ch: false,
+            members,
+        }
+    }
+
+    // GEOADD 最终也是转成 ZADD 执行
+    pub(crate) fn with_flags(mut self, flags: AddFlags, ch: bool) -> ZAdd {
+        self.flags = flags;
+        self.ch = ch;
+        self
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZAdd> {
+        let key = parse.next_string()?;
+        let args = parse.rest_bytes()?;
+
+        let mut flags = AddFlags::default();
+        let mut ch = false;
+        let mut idx = 0;
+        while idx < args.len() {
+            match &String::from_utf8_lossy(&args[idx]).to_uppercase()[..] {
+                "NX" => flags.nx = true,
+                "XX" => flags.xx = true,
+                "GT" => flags.gt = true,
+                "LT" => flags.lt = true,
+                "CH" => ch = true,
+                "INCR" => flags.incr = true,
+                _ => break,
+            }
+            idx += 1;
+        }
+
+        let pairs = &args[idx..];
+        if pairs.is_empty() || pairs.len() % 2 != 0 {
+            return Err("ERR syntax error".into());
+        }
+        if flags.nx && flags.xx {
+            return Err("ERR XX and NX options at the same time are not compatible".into());
+        }
+        if ((flags.gt || flags.lt) && flags.nx) || (flags.gt && flags.lt) {
+            return Err("ERR GT, LT, and/or NX options at the same time are not compatible".into());
+        }
+        if flags.incr && pairs.len() > 2 {
+            return Err("ERR INCR option supports a single increment-element pair".into());
+        }
+
+        let mut members = Vec::with_capacity(pairs.len() / 2);
+        for pair in pairs.chunks(2) {
+            let score = parse_double(&pair[0]).ok_or("ERR value is not a valid float")?;
+            members.push((score, pair[1].clone()));
+        }
+
+        Ok(ZAdd { key, flags, ch, members })
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        // XX 不会创建新的key
+        if self.flags.xx && db.get(&self.key).map(Value::as_zset).transpose()?.is_none() {
+            return Ok(if self.flags.incr { Frame::Null } else { Frame::Integer(0) });
+        }
+
+        let zset = db.get_or_create_zset(&self.key)?;
+        let mut added = 0;
+        let mut updated = 0;
+        let mut score = None;
+        let mut nan = false;
+        for (incr, member) in self.members {
+            match zset.add(member, incr, self.flags) {
+                AddResult::Added(s) => {
+                    added += 1;
+                    score = Some(s);
+                }
+                AddResult::Updated(s) => {
+                    updated += 1;
+                    score = Some(s);
+                }
+                AddResult::Unchanged(s) => score = Some(s),
+                AddResult::Skipped => {}
+                AddResult::Nan => {
+                    nan = true;
+                    break;
+                }
+            }
+        }
+        if added + updated > 0 {
+            db.signal_modified_key(&self.key);
+            db.notify_keyspace_event(NOTIFY_ZSET, if self.flags.incr { "zincr" } else { "zadd" }, &self.key);
+        }
+        db.remove_if_empty(&self.key);
+
+        if nan {
+            return Err("ERR resulting score is not a number (NaN)".into());
+        }
+        if self.flags.incr {
+            return Ok(match score {
+                Some(score) => Frame::Bulk(Bytes::from(format_double(score))),
+                None => Frame::Null,
+            });
+        }
+        Ok(Frame::Integer(if self.ch { added + updated } else { added }))
+    }
+}
@@ -0,0 +1,39 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::zset::parse_score_range;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: String,
+    min: Bytes,
+    max: Bytes,
+}
+
+impl ZCount {
+    pub fn new(key: impl ToString, min: Bytes, max: Bytes) -> ZCount {
+        ZCount {
+            key: key.to_string(),
+            min,
+            max,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZCount> {
+        let key = parse.next_string()?;
+        let min = parse.next_bytes()?;
+        let max = parse.next_bytes()?;
+        Ok(ZCount::new(key, min, max))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let range = parse_score_range(&self.min, &self.max)?;
+        let count = db.get_zset(&self.key)?.map_or(0, |zset| zset.count_in_score_range(&range));
+        Ok(Frame::Integer(count as i64))
+    }
+}
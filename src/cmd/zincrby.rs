@@ -0,0 +1,54 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_ZSET;
+use crate::zset::{AddFlags, AddResult};
+use crate::util::{parse_double, format_double};
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: String,
+    increment: f64,
+    member: Bytes,
+}
+
+impl ZIncrBy {
+    pub fn new(key: impl ToString, increment: f64, member: Bytes) -> ZIncrBy {
+        ZIncrBy {
+            key: key.to_string(),
+            increment,
+            member,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZIncrBy> {
+        let key = parse.next_string()?;
+        let increment = parse_double(&parse.next_bytes()?).ok_or("ERR value is not a valid float")?;
+        let member = parse.next_bytes()?;
+        Ok(ZIncrBy::new(key, increment, member))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let flags = AddFlags { incr: true, ..AddFlags::default() };
+        let zset = db.get_or_create_zset(&self.key)?;
+        let result = zset.add(self.member, self.increment, flags);
+        if let AddResult::Added(_) | AddResult::Updated(_) = result {
+            db.signal_modified_key(&self.key);
+            db.notify_keyspace_event(NOTIFY_ZSET, "zincr", &self.key);
+        }
+        db.remove_if_empty(&self.key);
+
+        match result {
+            AddResult::Added(score) | AddResult::Updated(score) | AddResult::Unchanged(score) => {
+                Ok(Frame::Bulk(Bytes::from(format_double(score))))
+            }
+            AddResult::Nan => Err("ERR resulting score is not a number (NaN)".into()),
+            AddResult::Skipped => Ok(Frame::Null),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_ZSET;
+use crate::zset::members_frame;
+use crate::util::parse_int;
+
+// ZPOPMIN 和 ZPOPMAX 共用
+#[derive(Debug)]
+pub struct ZPop {
+    key: String,
+    count: Option<usize>,
+    max: bool,
+}
+
+impl ZPop {
+    // 命令表里面的名字
+    pub(crate) fn name(&self) -> &'static str {
+        if self.max { "zpopmax" } else { "zpopmin" }
+    }
+
+    pub fn new(key: impl ToString, count: Option<usize>, max: bool) -> ZPop {
+        ZPop {
+            key: key.to_string(),
+            count,
+            max,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse, max: bool) -> crate::Result<ZPop> {
+        let key = parse.next_string()?;
+
+        let count = match parse.next_bytes() {
+            Ok(count) => {
+                let count = parse_int(&count).ok_or("ERR value is not an integer or out of range")?;
+                if count < 0 {
+                    return Err("ERR value is out of range, must be positive".into());
+                }
+                Some(count as usize)
+            }
+            Err(EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ZPop::new(key, count, max))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let zset = match db.get_zset_mut(&self.key)? {
+            Some(zset) => zset,
+            None => return Ok(Frame::Array(vec![])),
+        };
+        let popped = zset.pop(self.count.unwrap_or(1), self.max);
+        if !popped.is_empty() {
+            db.signal_modified_key(&self.key);
+            db.notify_keyspace_event(NOTIFY_ZSET, if self.max { "zpopmax" } else { "zpopmin" }, &self.key);
+        }
+        db.remove_if_empty(&self.key);
+        Ok(members_frame(popped, true))
+    }
+}
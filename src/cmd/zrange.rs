@@ -0,0 +1,142 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::zset::{ZSet, members_frame, parse_score_range, parse_lex_range};
+use crate::util::parse_int;
+use bytes::Bytes;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeBy {
+    Rank,
+    Score,
+    Lex,
+}
+
+#[derive(Debug)]
+pub struct ZRange {
+    key: String,
+    start: Bytes,
+    stop: Bytes,
+    by: RangeBy,
+    rev: bool,
+    // (offset, count)，count为负数代表不限制
+    limit: Option<(i64, i64)>,
+    with_scores: bool,
+}
+
+impl ZRange {
+    pub fn new(key: impl ToString, start: Bytes, stop: Bytes) -> ZRange {
+        ZRange {
+            key: key.to_string(),
+            start,
+            stop,
+            by: RangeBy::Rank,
+            rev: false,
+            limit: None,
+            with_scores: false,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn with_scores(&self) -> bool {
+        self.with_scores
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_bytes()?;
+        let stop = parse.next_bytes()?;
+        let mut range = ZRange::new(key, start, stop);
+
+        let args = parse.rest_bytes()?;
+        let mut idx = 0;
+        while idx < args.len() {
+            match &String::from_utf8_lossy(&args[idx]).to_uppercase()[..] {
+                "BYSCORE" => range.by = RangeBy::Score,
+                "BYLEX" => range.by = RangeBy::Lex,
+                "REV" => range.rev = true,
+                "WITHSCORES" => range.with_scores = true,
+                "LIMIT" if idx + 2 < args.len() => {
+                    let offset = parse_int(&args[idx + 1]).ok_or("ERR value is not an integer or out of range")?;
+                    let count = parse_int(&args[idx + 2]).ok_or("ERR value is not an integer or out of range")?;
+                    range.limit = Some((offset, count));
+                    idx += 2;
+                }
+                _ => return Err("ERR syntax error".into()),
+            }
+            idx += 1;
+        }
+
+        if range.limit.is_some() && range.by == RangeBy::Rank {
+            return Err("ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX".into());
+        }
+        if range.with_scores && range.by == RangeBy::Lex {
+            return Err("ERR syntax error, WITHSCORES not supported in combination with BYLEX".into());
+        }
+
+        Ok(range)
+    }
+
+    // 按参数取出区间内的成员，ZRANGESTORE 也会用到
+    pub(crate) fn range(&self, zset: &ZSet) -> crate::Result<Vec<(Bytes, f64)>> {
+        // REV 配合 BYSCORE/BYLEX 的时候参数顺序是 max min
+        let (min, max) = if self.rev && self.by != RangeBy::Rank {
+            (&self.stop, &self.start)
+        } else {
+            (&self.start, &self.stop)
+        };
+
+        let (offset, count) = match self.limit {
+            // offset为负数的时候什么都不返回
+            Some((offset, _)) if offset < 0 => return Ok(vec![]),
+            Some((offset, count)) => (offset as usize, if count < 0 { None } else { Some(count as usize) }),
+            None => (0, None),
+        };
+
+        match self.by {
+            RangeBy::Rank => {
+                let len = zset.len() as i64;
+                let mut start = parse_int(min).ok_or("ERR value is not an integer or out of range")?;
+                let mut end = parse_int(max).ok_or("ERR value is not an integer or out of range")?;
+                if start < 0 {
+                    start += len;
+                }
+                if end < 0 {
+                    end += len;
+                }
+                if start < 0 {
+                    start = 0;
+                }
+                if start > end || start >= len {
+                    return Ok(vec![]);
+                }
+                if end >= len {
+                    end = len - 1;
+                }
+                Ok(zset.range_by_rank(start as usize, end as usize, self.rev))
+            }
+            RangeBy::Score => {
+                let range = parse_score_range(min, max)?;
+                Ok(zset.range_by_score(&range, self.rev, offset, count))
+            }
+            RangeBy::Lex => {
+                let range = parse_lex_range(min, max)?;
+                Ok(zset.range_by_lex(&range, self.rev, offset, count))
+            }
+        }
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let items = match db.get_zset(&self.key)? {
+            Some(zset) => self.range(zset)?,
+            None => {
+                // key不存在也要校验区间参数
+                self.range(&ZSet::new())?
+            }
+        };
+        Ok(members_frame(items, self.with_scores))
+    }
+}
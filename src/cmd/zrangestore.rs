@@ -0,0 +1,43 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::zset::ZSet;
+use crate::cmd::ZRange;
+use crate::cmd::zsetstore::store_members;
+
+#[derive(Debug)]
+pub struct ZRangeStore {
+    destination: String,
+    range: ZRange,
+}
+
+impl ZRangeStore {
+    pub fn new(destination: impl ToString, range: ZRange) -> ZRangeStore {
+        ZRangeStore {
+            destination: destination.to_string(),
+            range,
+        }
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRangeStore> {
+        let destination = parse.next_string()?;
+        let range = ZRange::parse_frames(parse)?;
+        // 存储的时候不支持 WITHSCORES
+        if range.with_scores() {
+            return Err("ERR syntax error".into());
+        }
+        Ok(ZRangeStore::new(destination, range))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let members = match db.get_zset(self.range.key())? {
+            Some(zset) => self.range.range(zset)?,
+            None => self.range.range(&ZSet::new())?,
+        };
+        Ok(Frame::Integer(store_members(db, self.destination, members, "zrangestore") as i64))
+    }
+}
@@ -0,0 +1,56 @@
+use crate::parse::{Parse, ParseError::EndOfStream};
+use crate::db::State;
+use crate::frame::Frame;
+use crate::util::format_double;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct ZRank {
+    key: String,
+    member: Bytes,
+    with_score: bool,
+}
+
+impl ZRank {
+    pub fn new(key: impl ToString, member: Bytes, with_score: bool) -> ZRank {
+        ZRank {
+            key: key.to_string(),
+            member,
+            with_score,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRank> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+
+        let mut with_score = false;
+        match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "WITHSCORE" => with_score = true,
+            Ok(_) => return Err("ERR syntax error".into()),
+            Err(EndOfStream) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(ZRank::new(key, member, with_score))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let zset = db.get_zset(&self.key)?;
+        let found = zset.and_then(|zset| Some((zset.rank(&self.member, false)?, zset.score(&self.member)?)));
+
+        Ok(match found {
+            Some((rank, score)) if self.with_score => Frame::Array(vec![
+                Frame::Integer(rank as i64),
+                Frame::Bulk(Bytes::from(format_double(score))),
+            ]),
+            Some((rank, _)) => Frame::Integer(rank as i64),
+            None if self.with_score => Frame::NullArray,
+            None => Frame::Null,
+        })
+    }
+}
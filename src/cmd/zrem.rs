@@ -0,0 +1,46 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::notify::NOTIFY_ZSET;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<Bytes>,
+}
+
+impl ZRem {
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> ZRem {
+        ZRem {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRem> {
+        let key = parse.next_string()?;
+        // 至少要有一个成员
+        let mut members = vec![parse.next_bytes()?];
+        members.extend(parse.rest_bytes()?);
+        Ok(ZRem::new(key, members))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let zset = match db.get_zset_mut(&self.key)? {
+            Some(zset) => zset,
+            None => return Ok(Frame::Integer(0)),
+        };
+        let removed = self.members.iter().filter(|member| zset.remove(member)).count();
+        if removed > 0 {
+            db.signal_modified_key(&self.key);
+            db.notify_keyspace_event(NOTIFY_ZSET, "zrem", &self.key);
+        }
+        db.remove_if_empty(&self.key);
+        Ok(Frame::Integer(removed as i64))
+    }
+}
@@ -0,0 +1,38 @@
+use crate::parse::Parse;
+use crate::db::State;
+use crate::frame::Frame;
+use crate::util::format_double;
+use bytes::Bytes;
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: String,
+    member: Bytes,
+}
+
+impl ZScore {
+    pub fn new(key: impl ToString, member: Bytes) -> ZScore {
+        ZScore {
+            key: key.to_string(),
+            member,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZScore> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+        Ok(ZScore::new(key, member))
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let score = db.get_zset(&self.key)?.and_then(|zset| zset.score(&self.member));
+        Ok(match score {
+            Some(score) => Frame::Bulk(Bytes::from(format_double(score))),
+            None => Frame::Null,
+        })
+    }
+}
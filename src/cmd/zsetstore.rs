@@ -0,0 +1,240 @@
+use crate::parse::Parse;
+use crate::db::{State, Value};
+use crate::frame::Frame;
+use crate::notify::{NOTIFY_ZSET, NOTIFY_GENERIC};
+use crate::zset::{ZSet, AddFlags};
+use crate::util::{parse_int, parse_double};
+use bytes::Bytes;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetOp {
+    Union,
+    Inter,
+    Diff,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+// ZUNIONSTORE ZINTERSTORE ZDIFFSTORE 共用
+#[derive(Debug)]
+pub struct ZSetStore {
+    destination: String,
+    keys: Vec<String>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    op: SetOp,
+}
+
+impl Aggregate {
+    fn apply(self, target: f64, value: f64) -> f64 {
+        match self {
+            Aggregate::Sum => {
+                // inf + -inf 的结果按0处理
+                let sum = target + value;
+                if sum.is_nan() { 0.0 } else { sum }
+            }
+            Aggregate::Min => target.min(value),
+            Aggregate::Max => target.max(value),
+        }
+    }
+}
+
+impl ZSetStore {
+    // 命令表里面的名字
+    pub(crate) fn name(&self) -> &'static str {
+        match self.op {
+            SetOp::Union => "zunionstore",
+            SetOp::Inter => "zinterstore",
+            SetOp::Diff => "zdiffstore",
+        }
+    }
+
+    pub fn new(destination: impl ToString, keys: Vec<String>, op: SetOp) -> ZSetStore {
+        let weights = vec![1.0; keys.len()];
+        ZSetStore {
+            destination: destination.to_string(),
+            keys,
+            weights,
+            aggregate: Aggregate::Sum,
+            op,
+        }
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse, op: SetOp, name: &str) -> crate::Result<ZSetStore> {
+        let destination = parse.next_string()?;
+        let numkeys = parse_int(&parse.next_bytes()?).ok_or("ERR value is not an integer or out of range")?;
+        if numkeys <= 0 {
+            return Err(format!("ERR at least 1 input key is needed for '{}' command", name).into());
+        }
+
+        let args = parse.rest_bytes()?;
+        let numkeys = numkeys as usize;
+        if numkeys > args.len() {
+            return Err("ERR syntax error".into());
+        }
+        let keys = args[..numkeys].iter().map(|key| String::from_utf8_lossy(key).to_string()).collect();
+        let mut store = ZSetStore::new(destination, keys, op);
+
+        let mut idx = numkeys;
+        while idx < args.len() {
+            let remaining = args.len() - idx - 1;
+            match &String::from_utf8_lossy(&args[idx]).to_uppercase()[..] {
+                "WEIGHTS" if op != SetOp::Diff && remaining >= numkeys => {
+                    for (i, weight) in args[idx + 1..idx + 1 + numkeys].iter().enumerate() {
+                        store.weights[i] = parse_double(weight).ok_or("ERR weight value is not a float")?;
+                    }
+                    idx += numkeys;
+                }
+                "AGGREGATE" if op != SetOp::Diff && remaining >= 1 => {
+                    store.aggregate = match &String::from_utf8_lossy(&args[idx + 1]).to_uppercase()[..] {
+                        "SUM" => Aggregate::Sum,
+                        "MIN" => Aggregate::Min,
+                        "MAX" => Aggregate::Max,
+                        _ => return Err("ERR syntax error".into()),
+                    };
+                    idx += 1;
+                }
+                _ => return Err("ERR syntax error".into()),
+            }
+            idx += 1;
+        }
+
+        Ok(store)
+    }
+
+    // 计算结果，成员按分数从小到大
+    fn compute(&self, db: &mut State) -> crate::Result<Vec<(Bytes, f64)>> {
+        // 先检查类型，不存在的key当作空集合
+        let mut sets = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            sets.push(db.get_zset(key)?.is_some());
+        }
+
+        let mut result: HashMap<Bytes, f64> = HashMap::new();
+        match self.op {
+            SetOp::Union => {
+                for (i, key) in self.keys.iter().enumerate() {
+                    let zset = match db.get_zset(key)? {
+                        Some(zset) => zset,
+                        None => continue,
+                    };
+                    for (member, score) in zset.iter() {
+                        let score = weighted(score, self.weights[i]);
+                        result
+                            .entry(member.clone())
+                            .and_modify(|target| *target = self.aggregate.apply(*target, score))
+                            .or_insert(score);
+                    }
+                }
+            }
+            SetOp::Inter => {
+                if sets.iter().any(|exists| !exists) {
+                    return Ok(vec![]);
+                }
+                // 从最小的集合开始遍历
+                let mut order: Vec<usize> = (0..self.keys.len()).collect();
+                order.sort_by_key(|i| db.get_zset(&self.keys[*i]).ok().flatten().map_or(0, ZSet::len));
+                let first = order[0];
+                let candidates: Vec<(Bytes, f64)> = db
+                    .get_zset(&self.keys[first])?
+                    .unwrap()
+                    .iter()
+                    .map(|(member, score)| (member.clone(), weighted(score, self.weights[first])))
+                    .collect();
+                'member: for (member, mut score) in candidates {
+                    for &i in &order[1..] {
+                        match db.get_zset(&self.keys[i])?.unwrap().score(&member) {
+                            Some(other) => score = self.aggregate.apply(score, weighted(other, self.weights[i])),
+                            None => continue 'member,
+                        }
+                    }
+                    result.insert(member, score);
+                }
+            }
+            SetOp::Diff => {
+                if !sets[0] {
+                    return Ok(vec![]);
+                }
+                let candidates: Vec<(Bytes, f64)> = db
+                    .get_zset(&self.keys[0])?
+                    .unwrap()
+                    .iter()
+                    .map(|(member, score)| (member.clone(), score))
+                    .collect();
+                'diff: for (member, score) in candidates {
+                    for key in &self.keys[1..] {
+                        if db.get_zset(key)?.is_some_and(|zset| zset.score(&member).is_some()) {
+                            continue 'diff;
+                        }
+                    }
+                    result.insert(member, score);
+                }
+            }
+        }
+
+        Ok(result.into_iter().collect())
+    }
+
+    pub(crate) fn execute(self, db: &mut State) -> crate::Result<Frame> {
+        let members = self.compute(db)?;
+        let event = match self.op {
+            SetOp::Union => "zunionstore",
+            SetOp::Inter => "zinterstore",
+            SetOp::Diff => "zdiffstore",
+        };
+        Ok(Frame::Integer(store_members(db, self.destination, members, event) as i64))
+    }
+}
+
+fn weighted(score: f64, weight: f64) -> f64 {
+    // inf * 0 的结果按0处理
+    let score = score * weight;
+    if score.is_nan() { 0.0 } else { score }
+}
+
+// 把结果写入目标key，结果为空的时候删除目标key，返回写入的成员数量，event是写入时的通知
+pub(crate) fn store_members(db: &mut State, destination: String, members: Vec<(Bytes, f64)>, event: &str) -> usize {
+    if members.is_empty() {
+        if db.remove(&destination).is_some() {
+            db.notify_keyspace_event(NOTIFY_GENERIC, "del", &destination);
+        }
+        return 0;
+    }
+    let mut zset = ZSet::new();
+    for (member, score) in members {
+        zset.add(member, score, AddFlags::default());
+    }
+    let len = zset.len();
+    db.set(destination.clone(), Value::ZSet(zset), None);
+    db.notify_keyspace_event(NOTIFY_ZSET, event, &destination);
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infinities_do_not_produce_nan() {
+        assert_eq!(Aggregate::Sum.apply(f64::INFINITY, f64::NEG_INFINITY), 0.0);
+        assert_eq!(Aggregate::Sum.apply(1.0, 2.0), 3.0);
+        assert_eq!(Aggregate::Min.apply(1.0, -2.0), -2.0);
+        assert_eq!(Aggregate::Max.apply(1.0, -2.0), 1.0);
+        assert_eq!(weighted(f64::INFINITY, 0.0), 0.0);
+        assert_eq!(weighted(2.0, -1.5), -3.0);
+    }
+}
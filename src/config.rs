@@ -0,0 +1,575 @@
+use crate::notify;
+use crate::util::{self, parse_memory, string_match};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+static REWRITE_LOCK: Mutex<()> = Mutex::new(());
+
+// 服务器的配置，启动的时候从配置文件和命令行参数生成，运行时可以通过 CONFIG SET 修改
+#[derive(Debug, Clone)]
+pub struct Config {
+    // 监听的地址和端口
+    pub bind: String,
+    pub port: u16,
+    // 开启的keyspace通知，格式和redis的notify-keyspace-events一样，空字符串代表关闭
+    pub notify_keyspace_events: String,
+    // FUNCTION LOAD 加载的函数库保存的文件，默认是当前目录下的 functions.dump，
+    // 配置成空字符串的时候是None，不保存，重启之后函数库会丢失
+    pub functions_file: Option<String>,
+    // 每类客户端的输出缓冲区限制，超过的连接会被关掉
+    pub client_output_buffer_limit: OutputBufferLimits,
+    // 客户端空闲多少秒之后断开，0表示不断开，订阅了频道的客户端不受影响
+    pub timeout: u64,
+    // 连接上开启TCP keepalive，单位秒，0表示不开启
+    pub tcp_keepalive: u64,
+    // 最多同时连接的客户端数，超过的连接会收到错误然后被关掉
+    pub maxclients: u64,
+    // 启动时读取的配置文件，CONFIG REWRITE 写回这里
+    pub config_file: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            bind: "127.0.0.1".to_string(),
+            port: 6379,
+            notify_keyspace_events: String::new(),
+            functions_file: Some("functions.dump".to_string()),
+            client_output_buffer_limit: OutputBufferLimits::default(),
+            timeout: 0,
+            tcp_keepalive: 300,
+            maxclients: 10000,
+            config_file: None,
+        }
+    }
+}
+
+// CONFIG GET/SET 能访问的参数，第二个值表示运行时能不能修改
+const PARAMETERS: &[(&str, bool)] = &[
+    ("bind", false),
+    ("port", false),
+    ("timeout", true),
+    ("tcp-keepalive", true),
+    ("maxclients", true),
+    ("notify-keyspace-events", true),
+    ("client-output-buffer-limit", true),
+    ("functions-file", false),
+];
+
+// redis.conf 里面有但是这里没有实现的配置，读配置文件的时候跳过并打印警告
+const IGNORED_DIRECTIVES: &[&str] = &[
+    "include", "loadmodule", "bind-source-addr", "protected-mode", "enable-protected-configs", "enable-debug-command",
+    "enable-module-command", "tcp-backlog", "unixsocket", "unixsocketperm", "socket-mark-id", "daemonize", "supervised",
+    "pidfile", "loglevel", "logfile", "syslog-enabled", "syslog-ident", "syslog-facility", "crash-log-enabled",
+    "crash-memcheck-enabled", "databases", "always-show-logo", "set-proc-title", "proc-title-template", "locale-collate",
+    "save", "stop-writes-on-bgsave-error", "rdbcompression", "rdbchecksum", "sanitize-dump-payload", "dbfilename",
+    "rdb-del-sync-files", "dir", "replicaof", "slaveof", "masterauth", "masteruser", "replica-serve-stale-data",
+    "replica-read-only", "repl-diskless-sync", "repl-diskless-sync-delay", "repl-diskless-sync-max-replicas",
+    "repl-diskless-load", "repl-ping-replica-period", "repl-timeout", "repl-disable-tcp-nodelay", "repl-backlog-size",
+    "repl-backlog-ttl", "replica-priority", "propagation-error-behavior", "replica-ignore-disk-write-errors",
+    "replica-announced", "min-replicas-to-write", "min-replicas-max-lag", "replica-announce-ip", "replica-announce-port",
+    "tracking-table-max-keys", "acllog-max-len", "aclfile", "requirepass", "user", "rename-command", "maxmemory",
+    "maxmemory-policy", "maxmemory-samples", "maxmemory-eviction-tenacity", "replica-ignore-maxmemory",
+    "active-expire-effort", "lazyfree-lazy-eviction", "lazyfree-lazy-expire", "lazyfree-lazy-server-del",
+    "replica-lazy-flush", "lazyfree-lazy-user-del", "lazyfree-lazy-user-flush", "io-threads", "io-threads-do-reads",
+    "oom-score-adj", "oom-score-adj-values", "disable-thp", "appendonly", "appendfilename", "appenddirname", "appendfsync",
+    "no-appendfsync-on-rewrite", "auto-aof-rewrite-percentage", "auto-aof-rewrite-min-size", "aof-load-truncated",
+    "aof-use-rdb-preamble", "aof-timestamp-enabled", "shutdown-timeout", "shutdown-on-sigint", "shutdown-on-sigterm",
+    "lua-time-limit", "busy-reply-threshold", "slowlog-log-slower-than", "slowlog-max-len", "latency-monitor-threshold",
+    "latency-tracking", "latency-tracking-info-percentiles", "hash-max-listpack-entries", "hash-max-listpack-value",
+    "hash-max-ziplist-entries", "hash-max-ziplist-value", "list-max-listpack-size", "list-max-ziplist-size",
+    "list-compress-depth", "set-max-intset-entries", "set-max-listpack-entries", "set-max-listpack-value",
+    "zset-max-listpack-entries", "zset-max-listpack-value", "zset-max-ziplist-entries", "zset-max-ziplist-value",
+    "hll-sparse-max-bytes", "stream-node-max-bytes", "stream-node-max-entries", "activerehashing",
+    "client-query-buffer-limit", "proto-max-bulk-len", "hz", "dynamic-hz", "aof-rewrite-incremental-fsync",
+    "rdb-save-incremental-fsync", "lfu-log-factor", "lfu-decay-time", "max-new-connections-per-cycle",
+    "max-new-tls-connections-per-cycle", "activedefrag", "jemalloc-bg-thread", "server_cpulist", "bio_cpulist",
+    "aof_rewrite_cpulist", "bgsave_cpulist", "ignore-warnings",
+];
+
+// 这些前缀开头的整组配置都没有实现
+const IGNORED_PREFIXES: &[&str] = &["tls-", "cluster-", "active-defrag-"];
+
+fn is_ignored_directive(name: &str) -> bool {
+    IGNORED_DIRECTIVES.contains(&name) || IGNORED_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+// CONFIG REWRITE 追加的配置前面加上这一行
+const REWRITE_SIGNATURE: &str = "# Generated by CONFIG REWRITE";
+
+impl Config {
+    // 读取redis.conf格式的配置文件，没有出现的参数使用默认值
+    pub fn from_file(path: &str) -> crate::Result<Config> {
+        let content = fs::read_to_string(path).map_err(|err| format!("can't open config file '{}': {}", path, err))?;
+        let mut config = Config::parse(&content)?;
+        config.config_file = Some(path.to_string());
+        Ok(config)
+    }
+
+    pub(crate) fn parse(content: &str) -> crate::Result<Config> {
+        let mut config = Config::default();
+        for (idx, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let res = match split_args(trimmed) {
+                Some(args) => config.apply_directive(&args[0].to_lowercase(), &args[1..]),
+                None => Err("Unbalanced quotes in configuration line".to_string()),
+            };
+            if let Err(reason) = res {
+                return Err(format!("config file error at line {}: >>> '{}' {}", idx + 1, trimmed, reason).into());
+            }
+        }
+        Ok(config)
+    }
+
+    // 配置文件里面的一行，client-output-buffer-limit 可以出现多次，每次设置一类客户端
+    fn apply_directive(&mut self, name: &str, args: &[String]) -> Result<(), String> {
+        match name {
+            // 可以监听多个地址，用空格分开
+            "bind" | "client-output-buffer-limit" if !args.is_empty() => {
+                return self.set_value(name, &args.join(" ")).unwrap_or(Ok(()));
+            }
+            name if is_ignored_directive(name) => {
+                warn!(directive = name, "ignoring unsupported config directive");
+                return Ok(());
+            }
+            _ => {}
+        }
+        match args {
+            [value] => self.set_value(name, value).unwrap_or_else(|| Err("Bad directive or wrong number of arguments".to_string())),
+            _ => Err("Bad directive or wrong number of arguments".to_string()),
+        }
+    }
+
+    // 匹配pattern的参数名，CONFIG GET 用
+    pub fn names(pattern: &str) -> Vec<&'static str> {
+        PARAMETERS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| string_match(pattern.as_bytes(), name.as_bytes(), true))
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = match &name.to_lowercase()[..] {
+            "bind" => self.bind.clone(),
+            "port" => self.port.to_string(),
+            "timeout" => self.timeout.to_string(),
+            "tcp-keepalive" => self.tcp_keepalive.to_string(),
+            "maxclients" => self.maxclients.to_string(),
+            "notify-keyspace-events" => self.notify_keyspace_events.clone(),
+            "client-output-buffer-limit" => self.client_output_buffer_limit.to_string(),
+            "functions-file" => self.functions_file.clone().unwrap_or_default(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    // 运行时修改，参数不存在的时候返回None，值不合法或者不能修改的时候返回错误原因
+    pub fn set(&mut self, name: &str, value: &str) -> Option<Result<(), String>> {
+        let name = name.to_lowercase();
+        let (_, mutable) = PARAMETERS.iter().find(|(param, _)| *param == name)?;
+        if !mutable {
+            return Some(Err("can't set immutable config".to_string()));
+        }
+        self.set_value(&name, value)
+    }
+
+    fn set_value(&mut self, name: &str, value: &str) -> Option<Result<(), String>> {
+        let res = match name {
+            "bind" => {
+                self.bind = value.to_string();
+                Ok(())
+            }
+            "port" => parse_number(value, 0, u16::MAX as u64).map(|n| self.port = n as u16),
+            "timeout" => parse_number(value, 0, i32::MAX as u64).map(|n| self.timeout = n),
+            "tcp-keepalive" => parse_number(value, 0, i32::MAX as u64).map(|n| self.tcp_keepalive = n),
+            "maxclients" => parse_number(value, 1, u32::MAX as u64).map(|n| self.maxclients = n),
+            "notify-keyspace-events" => match notify::parse_flags(value) {
+                Some(flags) => {
+                    self.notify_keyspace_events = notify::flags_to_string(flags);
+                    Ok(())
+                }
+                None => Err("Invalid event class character. Use 'Ag$lshzxeKEtmdn'.".to_string()),
+            },
+            // 只修改写到的类别，其他的保持原来的值
+            "client-output-buffer-limit" => self.client_output_buffer_limit.update(value),
+            "functions-file" => {
+                self.functions_file = if value.is_empty() { None } else { Some(value.to_string()) };
+                Ok(())
+            }
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    // 参数在配置文件里面的写法，每个元素是一行
+    fn rewrite_lines(&self, name: &str) -> Vec<String> {
+        match name {
+            "client-output-buffer-limit" => self.client_output_buffer_limit.rewrite_lines(),
+            "bind" => vec![format!("bind {}", self.bind.split_whitespace().map(quote).collect::<Vec<_>>().join(" "))],
+            "functions-file" if self.functions_file.is_none() => vec![],
+            _ => vec![format!("{} {}", name, quote(&self.get(name).unwrap_or_default()))],
+        }
+    }
+
+    // 把当前的配置写回配置文件，注释和不认识的行保持不变；已有的参数原地替换，
+    // 文件里面没有并且和默认值不一样的参数追加到最后
+    pub fn rewrite(&self) -> crate::Result<()> {
+        let path = self.config_file.as_ref().ok_or("ERR The server is running without a config file")?;
+        // 在锁外面写文件，多个连接同时 CONFIG REWRITE 的时候按顺序来
+        let _guard = REWRITE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let content = fs::read_to_string(path).unwrap_or_default();
+        let mut out = Vec::new();
+        let mut rewritten = HashSet::new();
+        let mut signed = false;
+        for line in content.lines() {
+            let trimmed = line.trim();
+            signed |= trimmed == REWRITE_SIGNATURE;
+            let name = match split_args(trimmed) {
+                Some(args) if !trimmed.starts_with('#') && !args.is_empty() => args[0].to_lowercase(),
+                _ => {
+                    out.push(line.to_string());
+                    continue;
+                }
+            };
+            if !PARAMETERS.iter().any(|(param, _)| *param == name) {
+                out.push(line.to_string());
+                continue;
+            }
+            // 同一个参数出现多次的时候只在第一次的位置写
+            if rewritten.insert(name.clone()) {
+                out.extend(self.rewrite_lines(&name));
+            }
+        }
+        let defaults = Config::default();
+        let mut appended = Vec::new();
+        for (name, _) in PARAMETERS {
+            if rewritten.contains(*name) {
+                continue;
+            }
+            let default_lines = defaults.rewrite_lines(name);
+            appended.extend(self.rewrite_lines(name).into_iter().filter(|line| !default_lines.contains(line)));
+        }
+        // 之前追加过的时候已经有这一行了
+        if !appended.is_empty() && !signed {
+            out.push(REWRITE_SIGNATURE.to_string());
+        }
+        out.extend(appended);
+        let mut data = out.join("\n");
+        data.push('\n');
+        util::write_atomic(Path::new(path), data.as_bytes()).map_err(|err| format!("ERR Rewriting config file: {}", err))?;
+        Ok(())
+    }
+}
+
+fn parse_number(value: &str, min: u64, max: u64) -> Result<u64, String> {
+    let n = value.parse::<u64>().map_err(|_| "argument couldn't be parsed into an integer".to_string())?;
+    if n < min || n > max {
+        return Err(format!("argument must be between {} and {} inclusive", min, max));
+    }
+    Ok(n)
+}
+
+fn split_args(line: &str) -> Option<Vec<String>> {
+    let args = util::split_args(line.as_bytes())?;
+    Some(args.into_iter().map(|arg| String::from_utf8_lossy(&arg).to_string()).collect())
+}
+
+// 值为空或者有空白、引号的时候加上双引号
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.chars().any(|c| c.is_whitespace() || c == '"' || c == '\'' || c == '\\') {
+        return value.to_string();
+    }
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// 写配置文件的时候用，能整除的时候用 gb/mb/kb 表示
+fn format_memory(bytes: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1024 * 1024 * 1024, "gb"), (1024 * 1024, "mb"), (1024, "kb")];
+    for (unit, suffix) in UNITS {
+        if bytes > 0 && bytes.is_multiple_of(unit) {
+            return format!("{}{}", bytes / unit, suffix);
+        }
+    }
+    bytes.to_string()
+}
+
+// 输出缓冲区的限制，0表示不限制；超过hard马上断开，超过soft持续soft_seconds秒才断开
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OutputBufferLimit {
+    pub hard: u64,
+    pub soft: u64,
+    pub soft_seconds: u64,
+}
+
+impl OutputBufferLimit {
+    pub const fn new(hard: u64, soft: u64, soft_seconds: u64) -> OutputBufferLimit {
+        OutputBufferLimit { hard, soft, soft_seconds }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClientClass {
+    Normal,
+    Replica,
+    PubSub,
+}
+
+impl ClientClass {
+    fn parse(name: &str) -> Option<ClientClass> {
+        match &name.to_lowercase()[..] {
+            "normal" => Some(ClientClass::Normal),
+            "replica" | "slave" => Some(ClientClass::Replica),
+            "pubsub" => Some(ClientClass::PubSub),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ClientClass::Normal => "normal",
+            ClientClass::Replica => "slave",
+            ClientClass::PubSub => "pubsub",
+        }
+    }
+}
+
+// 对应 client-output-buffer-limit，默认值和redis一样
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputBufferLimits {
+    pub normal: OutputBufferLimit,
+    pub replica: OutputBufferLimit,
+    pub pubsub: OutputBufferLimit,
+}
+
+impl Default for OutputBufferLimits {
+    fn default() -> OutputBufferLimits {
+        OutputBufferLimits {
+            normal: OutputBufferLimit::new(0, 0, 0),
+            replica: OutputBufferLimit::new(256 * 1024 * 1024, 64 * 1024 * 1024, 60),
+            pubsub: OutputBufferLimit::new(32 * 1024 * 1024, 8 * 1024 * 1024, 60),
+        }
+    }
+}
+
+impl OutputBufferLimits {
+    pub fn get(&self, class: ClientClass) -> OutputBufferLimit {
+        match class {
+            ClientClass::Normal => self.normal,
+            ClientClass::Replica => self.replica,
+            ClientClass::PubSub => self.pubsub,
+        }
+    }
+
+    fn get_mut(&mut self, class: ClientClass) -> &mut OutputBufferLimit {
+        match class {
+            ClientClass::Normal => &mut self.normal,
+            ClientClass::Replica => &mut self.replica,
+            ClientClass::PubSub => &mut self.pubsub,
+        }
+    }
+
+    // 格式是 <class> <hard> <soft> <soft seconds>，可以写多组，没写的类别保持原来的值
+    pub fn update(&mut self, spec: &str) -> Result<(), String> {
+        let args: Vec<&str> = spec.split_whitespace().collect();
+        if args.is_empty() || !args.len().is_multiple_of(4) {
+            return Err("Wrong number of arguments in buffer limit configuration.".to_string());
+        }
+        let mut limits = *self;
+        for group in args.chunks(4) {
+            let class = ClientClass::parse(group[0]).ok_or("Invalid client class specified in buffer limit configuration.")?;
+            let hard = parse_memory(group[1]);
+            let soft = parse_memory(group[2]);
+            let soft_seconds = group[3].parse::<u64>().ok();
+            match (hard, soft, soft_seconds) {
+                (Some(hard), Some(soft), Some(soft_seconds)) => {
+                    *limits.get_mut(class) = OutputBufferLimit::new(hard, soft, soft_seconds);
+                }
+                _ => return Err("Error in hard, soft or soft_seconds setting in buffer limit configuration.".to_string()),
+            }
+        }
+        *self = limits;
+        Ok(())
+    }
+
+    // 配置文件里面每类客户端写一行
+    fn rewrite_lines(&self) -> Vec<String> {
+        [ClientClass::Normal, ClientClass::Replica, ClientClass::PubSub]
+            .iter()
+            .map(|class| {
+                let limit = self.get(*class);
+                format!(
+                    "client-output-buffer-limit {} {} {} {}",
+                    class.name(),
+                    format_memory(limit.hard),
+                    format_memory(limit.soft),
+                    limit.soft_seconds
+                )
+            })
+            .collect()
+    }
+}
+
+// 和 CONFIG GET 的格式一样
+impl fmt::Display for OutputBufferLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let classes = [ClientClass::Normal, ClientClass::Replica, ClientClass::PubSub];
+        let groups: Vec<String> = classes
+            .iter()
+            .map(|class| {
+                let limit = self.get(*class);
+                format!("{} {} {} {}", class.name(), limit.hard, limit.soft, limit.soft_seconds)
+            })
+            .collect();
+        write!(f, "{}", groups.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        split_args(line).unwrap()
+    }
+
+    #[test]
+    fn split_args_handles_quotes() {
+        assert_eq!(args("set a b"), vec!["set", "a", "b"]);
+        assert_eq!(args("  set   \"a b\"  'c d' "), vec!["set", "a b", "c d"]);
+        assert_eq!(args("x \"a\\nb\\\"c\""), vec!["x", "a\nb\"c"]);
+        assert_eq!(args("x \"\""), vec!["x", ""]);
+        assert!(args("").is_empty());
+        assert!(split_args("x \"abc").is_none());
+        assert!(split_args("x 'abc").is_none());
+        // 引号后面必须是空白
+        assert!(split_args("x \"a\"b").is_none());
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        for value in ["plain", "", "a b", "say \"hi\"", "back\\slash", "line\nbreak"] {
+            let line = format!("key {}", quote(value));
+            assert_eq!(args(&line), vec!["key".to_string(), value.to_string()], "{}", line);
+        }
+        assert_eq!(quote("plain"), "plain");
+    }
+
+    #[test]
+    fn format_memory_units() {
+        assert_eq!(format_memory(0), "0");
+        assert_eq!(format_memory(1000), "1000");
+        assert_eq!(format_memory(2048), "2kb");
+        assert_eq!(format_memory(64 * 1024 * 1024), "64mb");
+        assert_eq!(format_memory(2 * 1024 * 1024 * 1024), "2gb");
+    }
+
+    #[test]
+    fn parse_stock_redis_conf_lines() {
+        let config = Config::parse(
+            "# comment\n\
+             bind 127.0.0.1 -::1\n\
+             protected-mode yes\n\
+             daemonize no\n\
+             save 3600 1 300 100\n\
+             tls-port 0\n\
+             PORT 7000\n\
+             timeout 10\n\
+             notify-keyspace-events \"Ex\"\n\
+             client-output-buffer-limit normal 1mb 512kb 5\n\
+             client-output-buffer-limit pubsub 16mb 4mb 30\n",
+        )
+        .unwrap();
+        assert_eq!(config.bind, "127.0.0.1 -::1");
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.timeout, 10);
+        assert_eq!(config.notify_keyspace_events, "xE");
+        assert_eq!(config.client_output_buffer_limit.normal, OutputBufferLimit::new(1024 * 1024, 512 * 1024, 5));
+        assert_eq!(config.client_output_buffer_limit.pubsub, OutputBufferLimit::new(16 * 1024 * 1024, 4 * 1024 * 1024, 30));
+        assert_eq!(config.client_output_buffer_limit.replica, OutputBufferLimits::default().replica);
+    }
+
+    #[test]
+    fn parse_errors_report_line() {
+        let err = Config::parse("port 7000\nno-such-directive 1\n").unwrap_err().to_string();
+        assert!(err.contains("line 2"), "{}", err);
+        assert!(Config::parse("timeout 1 2").is_err());
+        assert!(Config::parse("maxclients 0").is_err());
+        assert!(Config::parse("port \"7000").is_err());
+    }
+
+    #[test]
+    fn set_validates_and_respects_immutable() {
+        let mut config = Config::default();
+        assert_eq!(config.set("TIMEOUT", "5"), Some(Ok(())));
+        assert_eq!(config.timeout, 5);
+        assert!(matches!(config.set("port", "1"), Some(Err(_))));
+        assert!(matches!(config.set("timeout", "-1"), Some(Err(_))));
+        assert!(matches!(config.set("notify-keyspace-events", "Q"), Some(Err(_))));
+        assert_eq!(config.set("nope", "1"), None);
+    }
+
+    #[test]
+    fn set_output_limit_keeps_other_classes() {
+        let mut config = Config::default();
+        config.set("client-output-buffer-limit", "normal 1mb 1mb 10").unwrap().unwrap();
+        config.set("client-output-buffer-limit", "pubsub 2mb 2mb 0").unwrap().unwrap();
+        assert_eq!(config.get("client-output-buffer-limit").unwrap(), "normal 1048576 1048576 10 slave 268435456 67108864 60 pubsub 2097152 2097152 0");
+        // 有一组不合法的时候都不生效
+        assert!(config.set("client-output-buffer-limit", "normal 0 0 0 bogus 1 1 1").unwrap().is_err());
+        assert_eq!(config.client_output_buffer_limit.normal.hard, 1024 * 1024);
+    }
+
+    #[test]
+    fn names_match_patterns() {
+        assert_eq!(Config::names("*clients"), vec!["maxclients"]);
+        assert_eq!(Config::names("PORT"), vec!["port"]);
+        assert_eq!(Config::names("*").len(), PARAMETERS.len());
+    }
+
+    #[test]
+    fn rewrite_preserves_comments_and_appends_changes() {
+        let path = std::env::temp_dir().join(format!("my-redis-rewrite-{}.conf", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, "# header\nbind 127.0.0.1 -::1\ndaemonize no\ntimeout 0\ntimeout 1\n\n# tail\n").unwrap();
+        let mut config = Config::from_file(&path).unwrap();
+        config.set("timeout", "30").unwrap().unwrap();
+        config.set("maxclients", "50").unwrap().unwrap();
+        config.rewrite().unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "# header\nbind 127.0.0.1 -::1\ndaemonize no\ntimeout 30\n\n# tail\n# Generated by CONFIG REWRITE\nmaxclients 50\n"
+        );
+        // 再写一次内容不变
+        Config::from_file(&path).unwrap().rewrite().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rewrite_without_file_fails() {
+        assert!(Config::default().rewrite().is_err());
+    }
+}
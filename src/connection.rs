@@ -1,12 +1,30 @@
 use crate::frame::Frame;
-use tokio::io::{BufWriter, AsyncReadExt};
+use crate::config::OutputBufferLimit;
+use crate::util;
+use crate::pubsub::Backlog;
+use tokio::io::{BufWriter, AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
-use bytes::{BytesMut, Buf};
-use std::io::Cursor;
+use tokio::time::{self, Duration, Instant};
+use bytes::{Bytes, BytesMut, Buf};
+use std::io::{self, Cursor};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+// inline命令一行最长的长度
+const MAX_INLINE_SIZE: usize = 64 * 1024;
+
+// 写不出去的时候每隔这么久检查一次输出缓冲区有没有超过限制
+const OUTPUT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct Connection {
     stream: BufWriter<TcpStream>,
     buffer: BytesMut,
+    // 输出缓冲区的限制和连接上还在排队等着发送的字节数（比如订阅的消息）
+    output_limit: OutputBufferLimit,
+    queued: Arc<Backlog>,
+    // 开始超过soft限制的时间
+    soft_limit_since: Option<Instant>,
+    output_limit_reached: bool,
 }
 
 impl Connection {
@@ -14,9 +32,18 @@ impl Connection {
         Connection {
             stream: BufWriter::new(socket),
             buffer: BytesMut::with_capacity(4 * 1024),
+            output_limit: OutputBufferLimit::default(),
+            queued: Arc::default(),
+            soft_limit_since: None,
+            output_limit_reached: false,
         }
     }
 
+    // 输入缓冲区已用和空闲的字节数
+    pub fn input_buffer(&self) -> (usize, usize) {
+        (self.buffer.len(), self.buffer.capacity() - self.buffer.len())
+    }
+
     pub async fn read_frame(&mut self) -> crate::Result<Option<Frame>> {
         loop {
             if let Some(frame) = self.parse_frame()? {
@@ -38,9 +65,14 @@ impl Connection {
 
     pub fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
         use crate::frame::Error;
+        // 不是RESP格式开头的当成inline命令，比如telnet或者健康检查直接发 PING\r\n
+        match self.buffer.first() {
+            Some(first) if !b"*$+-:".contains(first) => return self.parse_inline(),
+            _ => {}
+        }
         // 新建一个游标
         let mut buff = Cursor::new(&self.buffer[..]);
-        return match Frame::check(&mut buff) {
+        match Frame::check(&mut buff) {
             Ok(_) => {
                 // 检测完毕后得到一个完整帧的长度
                 let len = buff.position() as usize;
@@ -62,6 +94,79 @@ impl Connection {
                 // 读取真正出现了错误
                 Err(err.into())
             }
+        }
+    }
+
+    // 一行按空白分隔成参数，支持和redis-cli一样的引号，空行直接跳过
+    fn parse_inline(&mut self) -> crate::Result<Option<Frame>> {
+        let end = match self.buffer.iter().position(|&c| c == b'\n') {
+            Some(end) => end,
+            None if self.buffer.len() > MAX_INLINE_SIZE => return Err("ERR Protocol error: too big inline request".into()),
+            None => return Ok(None),
         };
+        let line = self.buffer.split_to(end + 1);
+        let args: Vec<Frame> = util::split_args(&line[..])
+            .ok_or("ERR Protocol error: unbalanced quotes in request")?
+            .into_iter()
+            .map(|arg| Frame::Bulk(Bytes::from(arg)))
+            .collect();
+        if args.is_empty() {
+            return self.parse_frame();
+        }
+        Ok(Some(Frame::Array(args)))
+    }
+
+    // 按连接的类别设置输出缓冲区的限制，queued是连接上还没开始写的消息，写数据的时候会实时读取
+    pub(crate) fn set_output_limit(&mut self, limit: OutputBufferLimit, queued: Arc<Backlog>) {
+        self.output_limit = limit;
+        self.queued = queued;
+    }
+
+    // 因为输出缓冲区超过限制而写失败
+    pub fn output_limit_reached(&self) -> bool {
+        self.output_limit_reached
     }
-}
\ No newline at end of file
+
+    // 客户端读得太慢的时候，没写出去的数据会一直占着内存，超过限制就断开
+    fn check_output_limit(&mut self, pending: usize) -> io::Result<()> {
+        let pending = (pending + self.queued.bytes.load(Ordering::Relaxed)) as u64;
+        let limit = self.output_limit;
+        // 订阅消息的积压超过硬上限的时候不管配置都要断开
+        let mut reached = self.queued.overflowed.load(Ordering::Relaxed);
+        reached |= limit.hard > 0 && pending >= limit.hard;
+        if limit.soft > 0 && pending >= limit.soft {
+            let since = *self.soft_limit_since.get_or_insert_with(Instant::now);
+            reached |= since.elapsed() >= Duration::from_secs(limit.soft_seconds);
+        } else {
+            self.soft_limit_since = None;
+        }
+        if reached {
+            self.output_limit_reached = true;
+            return Err(io::Error::other("output buffer limit reached"));
+        }
+        Ok(())
+    }
+
+    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let mut buf = Vec::new();
+        frame.encode(&mut buf);
+        // 分段写，写不出去的时候定期检查限制，不会一直卡在这里
+        let mut written = 0;
+        while written < buf.len() {
+            self.check_output_limit(buf.len() - written + self.stream.buffer().len())?;
+            if let Ok(n) = time::timeout(OUTPUT_CHECK_INTERVAL, self.stream.write(&buf[written..])).await {
+                match n? {
+                    0 => return Err(io::ErrorKind::WriteZero.into()),
+                    n => written += n,
+                }
+            }
+        }
+        // BufWriter里面的内容需要刷到socket中
+        loop {
+            self.check_output_limit(self.stream.buffer().len())?;
+            if let Ok(res) = time::timeout(OUTPUT_CHECK_INTERVAL, self.stream.flush()).await {
+                return res;
+            }
+        }
+    }
+}
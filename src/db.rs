@@ -1,8 +1,746 @@
+use crate::zset::ZSet;
+use crate::stream::Stream;
+use crate::pubsub::PubSub;
+use crate::events::{self, KeyEvent, KeyEventSender, KeyEvents};
+use crate::scripting::{Scripting, ScriptStatus};
+use crate::functions::Functions;
+use crate::client::Clients;
+use crate::config::Config;
+use crate::stats::{Stats, StatsSnapshot};
+use crate::module::{CommandHandler, DataType, ModuleValue, Registry, SavedValue};
+use crate::cmd::Command;
+use crate::frame::Frame;
+use crate::notify::{NOTIFY_KEYSPACE, NOTIFY_KEYEVENT, NOTIFY_GENERIC, NOTIFY_EXPIRED, NOTIFY_KEY_MISS, NOTIFY_NEW};
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+use tokio::task;
+use tokio::time::{self, Duration, Instant};
+use tracing::debug;
+
+pub(crate) const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
 #[derive(Debug, Clone)]
-pub struct Db {}
+pub struct Db {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    // 每次释放State的锁都会通知，拿不到锁的异步任务在这里等
+    unlocked: Notify,
+    // 通知后台清理过期key的任务
+    background_task: Arc<Notify>,
+    // 脚本执行期间一直持有锁，其他连接通过这个判断要不要等待
+    script_status: Arc<ScriptStatus>,
+    // 外部注册的命令
+    registry: Registry,
+    // 所有连上来的客户端
+    clients: Clients,
+    // 运行时的配置，CONFIG SET 可以修改
+    config: Arc<RwLock<Config>>,
+    stats: Arc<Stats>,
+}
+
+// 所有的key都放在State里面，命令执行的时候整个State都是锁住的，所以每个命令都是原子的
+#[derive(Debug)]
+pub(crate) struct State {
+    entries: HashMap<String, Entry>,
+    // 按到期时间排序，id用来区分同一时间到期的key
+    expirations: BTreeMap<(Instant, u64), String>,
+    next_id: u64,
+    shutdown: bool,
+    background_task: Arc<Notify>,
+    // 阻塞命令正在等待的key，key被写入时唤醒
+    blocking_keys: HashMap<String, Vec<Arc<Notify>>>,
+    // 被WATCH的key，key被修改的时候把对应连接的标记置为true
+    watched_keys: HashMap<String, Vec<Arc<AtomicBool>>>,
+    // 发布订阅的频道，和key不在一个命名空间
+    pub_sub: PubSub,
+    // 开启的keyspace通知类型，见notify模块
+    notify_flags: u32,
+    // 进程内订阅key变化的接收者
+    key_event_senders: Vec<KeyEventSender>,
+    // Lua脚本的缓存和虚拟机
+    scripting: Scripting,
+    // FUNCTION LOAD 加载的函数库
+    functions: Functions,
+    // 和Shared里面的是同一份，脚本里面执行命令的时候用
+    registry: Registry,
+    // key被修改的次数，用来判断脚本有没有执行过写命令
+    dirty: u64,
+    // 和Shared里面的是同一份，CONFIG 命令在这里读写
+    config: Arc<RwLock<Config>>,
+    stats: Arc<Stats>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Value {
+    String(Bytes),
+    ZSet(ZSet),
+    Stream(Stream),
+    // 模块通过 Db::register_type 注册的类型
+    Module(ModuleValue),
+}
 
 impl Db {
     pub fn new() -> Db {
-        Db {}
+        let background_task = Arc::new(Notify::new());
+        let script_status = Arc::new(ScriptStatus::default());
+        let registry = Registry::default();
+        let config = Arc::new(RwLock::new(Config::default()));
+        let stats = Arc::new(Stats::default());
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                entries: HashMap::new(),
+                expirations: BTreeMap::new(),
+                next_id: 0,
+                shutdown: false,
+                background_task: background_task.clone(),
+                blocking_keys: HashMap::new(),
+                watched_keys: HashMap::new(),
+                pub_sub: PubSub::default(),
+                notify_flags: 0,
+                key_event_senders: Vec::new(),
+                scripting: Scripting::new(script_status.clone()),
+                functions: Functions::new(script_status.clone()),
+                registry: registry.clone(),
+                dirty: 0,
+                config: config.clone(),
+                stats: stats.clone(),
+            }),
+            unlocked: Notify::new(),
+            background_task,
+            script_status,
+            registry,
+            clients: Clients::default(),
+            config,
+            stats,
+        });
+
+        // 开启后台任务清理过期的key
+        tokio::spawn(purge_expired_tasks(shared.clone()));
+
+        Db { shared }
+    }
+
+    // 阻塞拿锁，只能在阻塞线程或者runtime外面用，异步任务里面用lock_async
+    pub(crate) fn lock(&self) -> StateGuard<'_> {
+        self.shared.lock()
+    }
+
+    fn try_lock(&self) -> Option<StateGuard<'_>> {
+        self.shared.try_lock()
+    }
+
+    // 脚本在阻塞线程里执行，整个过程都持有锁，异步任务直接lock会卡住runtime的线程，
+    // 所以拿不到锁的时候等锁释放的通知
+    pub(crate) async fn lock_async(&self) -> StateGuard<'_> {
+        loop {
+            // 先注册再尝试，中间释放的通知不会丢
+            let unlocked = self.shared.unlocked.notified();
+            if let Some(state) = self.try_lock() {
+                return state;
+            }
+            unlocked.await;
+        }
+    }
+
+    // 和lock_async一样，不过脚本执行太久的时候回复BUSY，不再等下去
+    pub(crate) async fn lock_unless_busy(&self) -> crate::Result<StateGuard<'_>> {
+        loop {
+            let unlocked = self.shared.unlocked.notified();
+            if let Some(state) = self.try_lock() {
+                return Ok(state);
+            }
+            self.script_status().check_busy()?;
+            match self.script_status().until_busy() {
+                // 脚本执行到阈值的时候醒过来回复BUSY
+                Some(timeout) => {
+                    let _ = time::timeout(timeout, unlocked).await;
+                }
+                None => unlocked.await,
+            }
+        }
+    }
+
+    // 等待其他连接正在执行的脚本结束，脚本执行时间超过阈值之后返回BUSY；
+    // 脚本结束之后才会释放锁，所以也是等锁释放的通知
+    pub(crate) async fn wait_script_idle(&self) -> crate::Result<()> {
+        loop {
+            let unlocked = self.shared.unlocked.notified();
+            let timeout = match self.script_status().until_busy() {
+                Some(timeout) => timeout,
+                None => return Ok(()),
+            };
+            self.script_status().check_busy()?;
+            let _ = time::timeout(timeout, unlocked).await;
+        }
+    }
+
+    // drop的时候不能await，拿不到锁就交给一个新任务去做
+    pub(crate) fn lock_or_defer<F>(&self, f: F)
+        where F: FnOnce(&mut State) + Send + 'static {
+        if let Some(mut state) = self.try_lock() {
+            return f(&mut state);
+        }
+        match Handle::try_current() {
+            Ok(handle) => {
+                let db = self.clone();
+                handle.spawn(async move { f(&mut *db.lock_async().await) });
+            }
+            Err(_) => f(&mut self.lock()),
+        }
+    }
+
+    // 会执行Lua代码的命令放到阻塞线程里面，脚本可能执行很久，不能占住runtime的线程
+    pub(crate) async fn execute_blocking<F, R>(&self, f: F) -> crate::Result<R>
+        where F: FnOnce(&mut State) -> R + Send + 'static, R: Send + 'static {
+        let db = self.clone();
+        Ok(task::spawn_blocking(move || f(&mut db.lock())).await?)
+    }
+
+    // 注册一个新的命令，和已有的命令重名会失败
+    pub fn register_command(&self, handler: impl CommandHandler) -> crate::Result<()> {
+        self.shared.registry.register(handler)
+    }
+
+    // 注册自定义的数据类型，之后模块命令可以通过Context读写这个类型的值
+    pub fn register_type<T: DataType>(&self, ty: T) -> crate::Result<()> {
+        self.shared.registry.register_type(ty)
+    }
+
+    // 持久化用，序列化key对应的自定义类型的值
+    pub fn save_module_value(&self, key: &str) -> crate::Result<Option<SavedValue>> {
+        match self.lock().get(key) {
+            Some(Value::Module(value)) => Ok(Some(value.save())),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    // 加载之前保存的值，会覆盖已经存在的key
+    pub fn load_module_value(&self, key: &str, saved: &SavedValue) -> crate::Result<()> {
+        let value = self.shared.registry.load_value(saved)?;
+        self.lock().set(key.to_string(), Value::Module(value), None);
+        Ok(())
+    }
+
+    // AOF重写用，生成能重建这个值的命令
+    pub fn rewrite_module_value(&self, key: &str) -> crate::Result<Option<Vec<Vec<Bytes>>>> {
+        match self.lock().get(key) {
+            Some(Value::Module(value)) => Ok(Some(value.rewrite(key))),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn parse_command(&self, frame: Frame) -> crate::Result<Command> {
+        self.shared.registry.parse_command(frame)
+    }
+
+    pub(crate) fn script_status(&self) -> &ScriptStatus {
+        &self.shared.script_status
+    }
+
+    pub(crate) fn clients(&self) -> &Clients {
+        &self.shared.clients
+    }
+
+    // 不需要锁住State，脚本执行期间也能读
+    pub fn config(&self) -> RwLockReadGuard<'_, Config> {
+        self.shared.config.read().unwrap()
+    }
+
+    pub(crate) fn config_mut(&self) -> RwLockWriteGuard<'_, Config> {
+        self.shared.config.write().unwrap()
+    }
+
+    pub fn stats(&self) -> StatsSnapshot {
+        self.shared.stats.snapshot()
+    }
+
+    pub(crate) fn stats_counters(&self) -> &Stats {
+        &self.shared.stats
+    }
+
+    // 在进程内订阅匹配pattern的key的变化，最多缓存capacity个事件，
+    // 消费太慢的时候后面的事件会被丢掉，recv的时候返回Lagged
+    pub fn subscribe_key_events(&self, pattern: &str, capacity: usize) -> KeyEvents {
+        let (sender, receiver) = events::channel(pattern, capacity);
+        self.lock().key_event_senders.push(sender);
+        receiver
+    }
+}
+
+impl Default for Db {
+    fn default() -> Self {
+        Db::new()
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        // 只剩下自己和后台任务持有的时候，通知后台任务退出
+        if Arc::strong_count(&self.shared) == 2 {
+            let mut state = self.shared.state.lock().unwrap();
+            state.shutdown = true;
+            drop(state);
+            self.shared.background_task.notify_one();
+        }
+    }
+}
+
+impl State {
+    // 惰性删除，访问的时候发现已经过期就直接删掉
+    fn expire_if_needed(&mut self, key: &str) {
+        let expired = matches!(
+            self.entries.get(key),
+            Some(Entry { expires_at: Some(when), .. }) if *when <= Instant::now()
+        );
+        if expired {
+            self.expire_key(key);
+        }
+    }
+
+    // 删除过期的key，惰性删除和后台清理都走这里
+    fn expire_key(&mut self, key: &str) {
+        if self.remove_entry(key).is_some() {
+            self.emit_key_event(|| KeyEvent::Expired(key.to_string()));
+        }
+        self.notify_keyspace_event(NOTIFY_EXPIRED, "expired", key);
+    }
+
+    pub(crate) fn get(&mut self, key: &str) -> Option<&Value> {
+        self.expire_if_needed(key);
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    pub(crate) fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.expire_if_needed(key);
+        self.entries.get_mut(key).map(|entry| &mut entry.value)
+    }
+
+    pub(crate) fn contains_key(&mut self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    // 读命令查找key，找不到的时候发出keymiss通知
+    pub(crate) fn lookup_read(&mut self, key: &str) -> Option<&Value> {
+        if !self.contains_key(key) {
+            self.notify_keyspace_event(NOTIFY_KEY_MISS, "keymiss", key);
+            return None;
+        }
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    // 覆盖写入，旧的过期时间会被清掉
+    pub(crate) fn set(&mut self, key: String, value: Value, expire: Option<Duration>) {
+        let name = key.clone();
+        self.insert(key, value, expire);
+        self.signal_modified_key(&name);
+    }
+
+    // 不算修改，get_or_create 创建空值的时候用，由命令写入之后自己调用signal_modified_key
+    fn insert(&mut self, key: String, value: Value, expire: Option<Duration>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut notify = false;
+        let expires_at = expire.map(|duration| {
+            let when = Instant::now() + duration;
+            // 如果新的过期时间是最早的，需要唤醒后台任务重新计算等待时间
+            notify = self
+                .next_expiration()
+                .map(|expiration| expiration > when)
+                .unwrap_or(true);
+            self.expirations.insert((when, id), key.clone());
+            when
+        });
+
+        self.signal_key_ready(&key);
+        if !self.entries.contains_key(&key) {
+            self.notify_keyspace_event(NOTIFY_NEW, "new", &key);
+        }
+        let prev = self.entries.insert(key, Entry { id, value, expires_at });
+        if let Some(Entry { id, expires_at: Some(when), .. }) = prev {
+            self.expirations.remove(&(when, id));
+        }
+
+        if notify {
+            self.background_task.notify_one();
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) -> Option<Value> {
+        let value = self.remove_entry(key)?;
+        self.emit_key_event(|| KeyEvent::Del(key.to_string()));
+        Some(value)
+    }
+
+    fn remove_entry(&mut self, key: &str) -> Option<Value> {
+        let entry = self.entries.remove(key)?;
+        if let Some(when) = entry.expires_at {
+            self.expirations.remove(&(when, entry.id));
+        }
+        self.signal_modified_key(key);
+        Some(entry.value)
+    }
+
+    // 集合类型的值被删空之后key也要删掉
+    pub(crate) fn remove_if_empty(&mut self, key: &str) {
+        if self.entries.get(key).is_some_and(|entry| entry.value.is_empty()) {
+            self.remove(key);
+            self.notify_keyspace_event(NOTIFY_GENERIC, "del", key);
+        }
+    }
+
+    pub(crate) fn get_string(&mut self, key: &str) -> crate::Result<Option<&Bytes>> {
+        self.lookup_read(key).map(Value::as_string).transpose()
+    }
+
+    // 不存在的时候创建一个空字符串，用于SETBIT这类原地修改的命令
+    pub(crate) fn get_or_create_string(&mut self, key: &str) -> crate::Result<&mut Bytes> {
+        if !self.contains_key(key) {
+            self.insert(key.to_string(), Value::String(Bytes::new()), None);
+        }
+        self.get_mut(key).unwrap().as_string_mut()
+    }
+
+    pub(crate) fn get_zset(&mut self, key: &str) -> crate::Result<Option<&ZSet>> {
+        self.lookup_read(key).map(Value::as_zset).transpose()
+    }
+
+    pub(crate) fn get_zset_mut(&mut self, key: &str) -> crate::Result<Option<&mut ZSet>> {
+        self.get_mut(key).map(Value::as_zset_mut).transpose()
+    }
+
+    // 不存在的时候创建一个空的有序集合
+    pub(crate) fn get_or_create_zset(&mut self, key: &str) -> crate::Result<&mut ZSet> {
+        if !self.contains_key(key) {
+            self.insert(key.to_string(), Value::ZSet(ZSet::new()), None);
+        }
+        self.get_mut(key).unwrap().as_zset_mut()
+    }
+
+    // 注册阻塞等待，notify会在任意一个key被写入时唤醒
+    pub(crate) fn block_on_keys(&mut self, keys: &[String], notify: &Arc<Notify>) {
+        for key in keys {
+            self.blocking_keys.entry(key.clone()).or_default().push(notify.clone());
+        }
+    }
+
+    pub(crate) fn unblock_keys(&mut self, keys: &[String], notify: &Arc<Notify>) {
+        for key in keys {
+            if let Some(waiters) = self.blocking_keys.get_mut(key) {
+                waiters.retain(|waiter| !Arc::ptr_eq(waiter, notify));
+                if waiters.is_empty() {
+                    self.blocking_keys.remove(key);
+                }
+            }
+        }
+    }
+
+    // 唤醒所有等待这个key的客户端，被唤醒的客户端会重新尝试
+    pub(crate) fn signal_key_ready(&mut self, key: &str) {
+        if let Some(waiters) = self.blocking_keys.remove(key) {
+            for waiter in waiters {
+                // notify_one 在对方还没开始等待的时候会保留许可，不会丢失唤醒
+                waiter.notify_one();
+            }
+        }
+    }
+
+    // 过期的key先删掉，这样WATCH之后才过期的key会让事务失败
+    pub(crate) fn watch_key(&mut self, key: &str, dirty: &Arc<AtomicBool>) {
+        self.expire_if_needed(key);
+        self.watched_keys.entry(key.to_string()).or_default().push(dirty.clone());
+    }
+
+    pub(crate) fn unwatch_keys(&mut self, keys: &[String], dirty: &Arc<AtomicBool>) {
+        for key in keys {
+            if let Some(clients) = self.watched_keys.get_mut(key) {
+                clients.retain(|client| !Arc::ptr_eq(client, dirty));
+                if clients.is_empty() {
+                    self.watched_keys.remove(key);
+                }
+            }
+        }
+    }
+
+    // 所有写命令修改了key之后都要调用，没有人WATCH和订阅的时候直接返回
+    pub(crate) fn signal_modified_key(&mut self, key: &str) {
+        self.dirty += 1;
+        if !self.watched_keys.is_empty() {
+            if let Some(clients) = self.watched_keys.get(key) {
+                for client in clients {
+                    client.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+        // 删除的时候也会调用，那时候key已经不在了
+        if !self.key_event_senders.is_empty() && self.entries.contains_key(key) {
+            self.emit_key_event(|| KeyEvent::Set(key.to_string()));
+        }
+    }
+
+    pub(crate) fn dirty(&self) -> u64 {
+        self.dirty
+    }
+
+    pub(crate) fn scripting(&mut self) -> &mut Scripting {
+        &mut self.scripting
+    }
+
+    pub(crate) fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub(crate) fn config(&self) -> Arc<RwLock<Config>> {
+        self.config.clone()
+    }
+
+    pub(crate) fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub(crate) fn functions(&mut self) -> &mut Functions {
+        &mut self.functions
+    }
+
+    // 没有订阅者的时候不会创建事件
+    fn emit_key_event(&mut self, event: impl FnOnce() -> KeyEvent) {
+        if self.key_event_senders.is_empty() {
+            return;
+        }
+        let event = event();
+        self.key_event_senders.retain_mut(|sender| sender.send(&event));
+    }
+
+    pub(crate) fn get_stream(&mut self, key: &str) -> crate::Result<Option<&Stream>> {
+        self.lookup_read(key).map(Value::as_stream).transpose()
+    }
+
+    pub(crate) fn get_stream_mut(&mut self, key: &str) -> crate::Result<Option<&mut Stream>> {
+        self.get_mut(key).map(Value::as_stream_mut).transpose()
+    }
+
+    pub(crate) fn get_or_create_stream(&mut self, key: &str) -> crate::Result<&mut Stream> {
+        if !self.contains_key(key) {
+            self.insert(key.to_string(), Value::Stream(Stream::new()), None);
+        }
+        self.get_mut(key).unwrap().as_stream_mut()
+    }
+
+    pub(crate) fn pub_sub(&mut self) -> &mut PubSub {
+        &mut self.pub_sub
+    }
+
+    pub(crate) fn set_notify_flags(&mut self, flags: u32) {
+        self.notify_flags = flags;
+    }
+
+    // 对应redis的notifyKeyspaceEvent，没有开启对应类型的时候直接返回
+    pub(crate) fn notify_keyspace_event(&mut self, class: u32, event: &str, key: &str) {
+        let flags = self.notify_flags;
+        if flags & class == 0 {
+            return;
+        }
+        if flags & NOTIFY_KEYSPACE != 0 {
+            let channel = Bytes::from(format!("__keyspace@0__:{}", key));
+            self.pub_sub.publish(&channel, &Bytes::from(event.to_string()));
+        }
+        if flags & NOTIFY_KEYEVENT != 0 {
+            let channel = Bytes::from(format!("__keyevent@0__:{}", event));
+            self.pub_sub.publish(&channel, &Bytes::from(key.to_string()));
+        }
+    }
+
+    fn next_expiration(&self) -> Option<Instant> {
+        self.expirations.keys().next().map(|expiration| expiration.0)
+    }
+}
+
+impl Value {
+    pub(crate) fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) => false,
+            Value::ZSet(zset) => zset.is_empty(),
+            // 和redis一样，stream删空了也保留key
+            Value::Stream(_) => false,
+            Value::Module(_) => false,
+        }
+    }
+
+    // TYPE 命令返回的类型名
+    pub(crate) fn type_name(&self) -> &str {
+        match self {
+            Value::String(_) => "string",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+            Value::Module(value) => value.type_name(),
+        }
+    }
+
+    // 粗略估算占用的内存，每个元素加上固定的结构体开销
+    pub(crate) fn mem_usage(&self) -> usize {
+        match self {
+            Value::String(value) => value.len(),
+            Value::ZSet(zset) => zset.iter().map(|(member, _)| member.len() + 64).sum(),
+            Value::Stream(stream) => stream.len() * 128 + stream.groups().len() * 256,
+            Value::Module(value) => value.mem_usage(),
+        }
+    }
+
+    pub(crate) fn as_string(&self) -> crate::Result<&Bytes> {
+        match self {
+            Value::String(value) => Ok(value),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    pub(crate) fn as_string_mut(&mut self) -> crate::Result<&mut Bytes> {
+        match self {
+            Value::String(value) => Ok(value),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    pub(crate) fn as_zset(&self) -> crate::Result<&ZSet> {
+        match self {
+            Value::ZSet(zset) => Ok(zset),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    pub(crate) fn as_zset_mut(&mut self) -> crate::Result<&mut ZSet> {
+        match self {
+            Value::ZSet(zset) => Ok(zset),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    pub(crate) fn as_stream(&self) -> crate::Result<&Stream> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+
+    pub(crate) fn as_stream_mut(&mut self) -> crate::Result<&mut Stream> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(WRONGTYPE.into()),
+        }
+    }
+}
+
+// 后台清理一轮的结果
+enum Purge {
+    // 锁被占用，比如脚本正在执行
+    Busy,
+    Shutdown,
+    // 下一个key的过期时间
+    Next(Option<Instant>),
+}
+
+// State的锁，释放的时候通知等锁的异步任务
+pub(crate) struct StateGuard<'a> {
+    // drop的时候要先释放锁再通知，所以放在Option里面
+    guard: Option<MutexGuard<'a, State>>,
+    unlocked: &'a Notify,
+}
+
+impl Deref for StateGuard<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for StateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl Drop for StateGuard<'_> {
+    fn drop(&mut self) {
+        self.guard.take();
+        self.unlocked.notify_waiters();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> StateGuard<'_> {
+        StateGuard { guard: Some(self.state.lock().unwrap()), unlocked: &self.unlocked }
+    }
+
+    fn try_lock(&self) -> Option<StateGuard<'_>> {
+        match self.state.try_lock() {
+            Ok(state) => Some(StateGuard { guard: Some(state), unlocked: &self.unlocked }),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+        }
+    }
+
+    // 清理掉所有过期的key，拿不到锁的时候直接返回，不阻塞worker线程
+    fn purge_expired_keys(&self) -> Purge {
+        let mut state = match self.try_lock() {
+            Some(state) => state,
+            None => return Purge::Busy,
+        };
+        if state.shutdown {
+            return Purge::Shutdown;
+        }
+
+        // 拿到State的可变引用，避免借用检查器报错
+        let state = &mut *state;
+        let now = Instant::now();
+        while let Some((&(when, _), key)) = state.expirations.iter().next() {
+            if when > now {
+                return Purge::Next(Some(when));
+            }
+            let key = key.clone();
+            state.expire_key(&key);
+        }
+        Purge::Next(None)
+    }
+}
+
+async fn purge_expired_tasks(shared: Arc<Shared>) {
+    loop {
+        // CLIENT PAUSE WRITE 期间key也不过期
+        if shared.clients.is_paused(true) {
+            time::sleep(Duration::from_millis(10)).await;
+            continue;
+        }
+        let unlocked = shared.unlocked.notified();
+        match shared.purge_expired_keys() {
+            Purge::Busy => unlocked.await,
+            Purge::Shutdown => break,
+            // 等到下一个key过期，或者有更早的过期时间加进来
+            Purge::Next(Some(when)) => {
+                tokio::select! {
+                    _ = time::sleep_until(when) => {}
+                    _ = shared.background_task.notified() => {}
+                }
+            }
+            Purge::Next(None) => shared.background_task.notified().await,
+        }
     }
-}
\ No newline at end of file
+    debug!("purge background task shut down")
+}
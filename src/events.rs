@@ -0,0 +1,185 @@
+use crate::util::string_match;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+// 嵌入使用的时候在进程内观察key的变化，不经过发布订阅
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyEvent {
+    // 写命令修改了key的值，包括新建，比如 SET、ZADD、ZREM、SETBIT、XDEL，不区分具体是哪个命令
+    Set(String),
+    Del(String),
+    Expired(String),
+    // 还没有实现maxmemory淘汰和RENAME命令，目前不会产生这两个事件
+    Evicted(String),
+    Renamed { from: String, to: String },
+}
+
+impl KeyEvent {
+    // 改名的时候新旧两个key有一个能匹配就会收到事件
+    fn matches(&self, pattern: &[u8]) -> bool {
+        match self {
+            KeyEvent::Set(key) | KeyEvent::Del(key) | KeyEvent::Expired(key) | KeyEvent::Evicted(key) => {
+                string_match(pattern, key.as_bytes(), false)
+            }
+            KeyEvent::Renamed { from, to } => {
+                string_match(pattern, from.as_bytes(), false) || string_match(pattern, to.as_bytes(), false)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    // 缓冲区满了，中间丢掉了这么多事件
+    Lagged(u64),
+    Closed,
+}
+
+impl std::fmt::Display for RecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecvError::Lagged(n) => write!(f, "key events lagged by {}", n),
+            RecvError::Closed => "key events closed".fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+// 每个事件带上它前面丢掉的数量，这样落后的信号会出现在正确的位置
+#[derive(Debug)]
+struct Item {
+    missed: u64,
+    event: KeyEvent,
+}
+
+// 放在State里面的发送端，在锁里面发送，不会阻塞写命令
+#[derive(Debug)]
+pub(crate) struct KeyEventSender {
+    pattern: Vec<u8>,
+    sender: mpsc::Sender<Item>,
+    // 还没有报告给接收端的丢失数量，接收端读空队列的时候也会取走
+    missed: Arc<AtomicU64>,
+}
+
+impl KeyEventSender {
+    // 返回false代表接收端已经关闭，可以删掉了
+    pub(crate) fn send(&mut self, event: &KeyEvent) -> bool {
+        // 接收端关掉之后 try_reserve 也只会返回Full，要先检查
+        if self.sender.is_closed() {
+            return false;
+        }
+        if !event.matches(&self.pattern) {
+            return true;
+        }
+        let permit = match self.sender.try_reserve() {
+            Ok(permit) => permit,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.missed.fetch_add(1, Ordering::SeqCst);
+                return true;
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => return false,
+        };
+        permit.send(Item { missed: self.missed.swap(0, Ordering::SeqCst), event: event.clone() });
+        true
+    }
+}
+
+// 接收端，由 Db::subscribe_key_events 创建
+#[derive(Debug)]
+pub struct KeyEvents {
+    receiver: mpsc::Receiver<Item>,
+    missed: Arc<AtomicU64>,
+    // 先返回Lagged，下一次再返回这个事件
+    pending: Option<KeyEvent>,
+}
+
+impl KeyEvents {
+    pub async fn recv(&mut self) -> Result<KeyEvent, RecvError> {
+        if let Some(event) = self.pending.take() {
+            return Ok(event);
+        }
+        let item = tokio::select! {
+            biased;
+            item = self.receiver.recv() => item,
+            // 队列是空的，之前丢掉的事件已经没有机会跟着下一个事件报告了
+            _ = async {} => {
+                let missed = self.missed.swap(0, Ordering::SeqCst);
+                if missed > 0 {
+                    return Err(RecvError::Lagged(missed));
+                }
+                self.receiver.recv().await
+            }
+        };
+        let item = item.ok_or(RecvError::Closed)?;
+        if item.missed > 0 {
+            self.pending = Some(item.event);
+            return Err(RecvError::Lagged(item.missed));
+        }
+        Ok(item.event)
+    }
+}
+
+// pattern是glob格式，capacity是最多缓存的事件数量
+pub(crate) fn channel(pattern: &str, capacity: usize) -> (KeyEventSender, KeyEvents) {
+    let (sender, receiver) = mpsc::channel(capacity.max(1));
+    let missed = Arc::new(AtomicU64::new(0));
+    let sender = KeyEventSender { pattern: pattern.as_bytes().to_vec(), sender, missed: missed.clone() };
+    (sender, KeyEvents { receiver, missed, pending: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(key: &str) -> KeyEvent {
+        KeyEvent::Set(key.to_string())
+    }
+
+    #[tokio::test]
+    async fn only_matching_keys_are_sent() {
+        let (mut sender, mut events) = channel("user:*", 8);
+        assert!(sender.send(&set("other")));
+        assert!(sender.send(&KeyEvent::Del("user:1".to_string())));
+        assert_eq!(events.recv().await, Ok(KeyEvent::Del("user:1".to_string())));
+        let renamed = KeyEvent::Renamed { from: "tmp".to_string(), to: "user:2".to_string() };
+        assert!(sender.send(&renamed));
+        assert_eq!(events.recv().await, Ok(renamed));
+    }
+
+    #[tokio::test]
+    async fn lag_is_reported_before_next_event() {
+        let (mut sender, mut events) = channel("*", 2);
+        for key in ["a", "b", "c", "d"] {
+            sender.send(&set(key));
+        }
+        assert_eq!(events.recv().await, Ok(set("a")));
+        assert_eq!(events.recv().await, Ok(set("b")));
+        // 队列读空之后报告丢掉的c和d
+        assert_eq!(events.recv().await, Err(RecvError::Lagged(2)));
+        sender.send(&set("e"));
+        assert_eq!(events.recv().await, Ok(set("e")));
+    }
+
+    #[tokio::test]
+    async fn lag_keeps_position_in_stream() {
+        let (mut sender, mut events) = channel("*", 1);
+        sender.send(&set("a"));
+        sender.send(&set("b"));
+        assert_eq!(events.recv().await, Ok(set("a")));
+        sender.send(&set("c"));
+        assert_eq!(events.recv().await, Err(RecvError::Lagged(1)));
+        assert_eq!(events.recv().await, Ok(set("c")));
+    }
+
+    #[tokio::test]
+    async fn closed_receiver_drops_sender() {
+        let (mut sender, events) = channel("*", 1);
+        drop(events);
+        assert!(!sender.send(&set("a")));
+        let (sender, mut events) = channel("*", 1);
+        drop(sender);
+        assert_eq!(events.recv().await, Err(RecvError::Closed));
+    }
+}
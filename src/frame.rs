@@ -6,13 +6,14 @@ use std::convert::TryInto;
 use std::num::TryFromIntError;
 use std::string::FromUtf8Error;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Frame {
     Simple(String),
     Error(String),
-    Integer(u64),
+    Integer(i64),
     Bulk(Bytes),
     Null,
+    NullArray,
     Array(Vec<Frame>),
 }
 
@@ -39,8 +40,8 @@ impl Frame {
                     skip(src, len + 2)
                 }
             }
-            b':' => { // 数字
-                get_decimal(src)?;
+            b':' => { // 数字，可能是负数
+                get_line(src)?;
                 Ok(())
             }
             b'-' => { // 错误消息
@@ -48,6 +49,10 @@ impl Frame {
                 Ok(())
             }
             b'*' => { // 数组
+                // 空数组 *-1\r\n
+                if b'-' == peek_u8(src)? {
+                    return skip(src, 4);
+                }
                 // 获取数组长度
                 let len: usize = get_decimal(src)?.try_into()?;
 
@@ -57,7 +62,7 @@ impl Frame {
                 Ok(())
             }
             actual => {
-                return Err(format!("非法的redis协议，非法字符 {}", actual).into());
+                Err(format!("非法的redis协议，非法字符 {}", actual).into())
             }
         }
     }
@@ -70,6 +75,14 @@ impl Frame {
                 Ok(Frame::Simple(str))
             }
             b'$' => { // 多行字符串
+                // 空字符串 $-1\r\n
+                if b'-' == peek_u8(src)? {
+                    let line = get_line(src)?;
+                    if line != b"-1" {
+                        return Err("protocol error; invalid frame format".into());
+                    }
+                    return Ok(Frame::Null);
+                }
                 let len = get_decimal(src)?.try_into()?;
                 let n = len + 2; // \r\n
 
@@ -82,7 +95,9 @@ impl Frame {
                 Ok(Frame::Bulk(bytes))
             }
             b':' => {
-                let d = get_decimal(src)?;
+                use atoi::atoi;
+                let line = get_line(src)?;
+                let d = atoi::<i64>(line).ok_or("protocol error; invalid frame format")?;
                 Ok(Frame::Integer(d))
             }
             b'-' => {
@@ -96,7 +111,7 @@ impl Frame {
                     if line != b"-1" {
                         return Err("protocol error; invalid frame format".into());
                     }
-                    Ok(Frame::Null)
+                    Ok(Frame::NullArray)
                 } else {
                     let len = get_decimal(src)?;
 
@@ -113,6 +128,63 @@ impl Frame {
             }
         }
     }
+
+    // 将Frame编码成redis协议格式的字节，数组可以嵌套
+    pub fn encode(&self, dst: &mut Vec<u8>) {
+        match self {
+            Frame::Simple(val) => {
+                dst.push(b'+');
+                dst.extend_from_slice(val.as_bytes());
+                dst.extend_from_slice(b"\r\n");
+            }
+            Frame::Error(val) => {
+                dst.push(b'-');
+                dst.extend_from_slice(val.as_bytes());
+                dst.extend_from_slice(b"\r\n");
+            }
+            Frame::Integer(val) => {
+                dst.push(b':');
+                dst.extend_from_slice(val.to_string().as_bytes());
+                dst.extend_from_slice(b"\r\n");
+            }
+            Frame::Bulk(val) => {
+                dst.push(b'$');
+                dst.extend_from_slice(val.len().to_string().as_bytes());
+                dst.extend_from_slice(b"\r\n");
+                dst.extend_from_slice(val);
+                dst.extend_from_slice(b"\r\n");
+            }
+            Frame::Null => {
+                dst.extend_from_slice(b"$-1\r\n");
+            }
+            Frame::NullArray => {
+                dst.extend_from_slice(b"*-1\r\n");
+            }
+            Frame::Array(val) => {
+                dst.push(b'*');
+                dst.extend_from_slice(val.len().to_string().as_bytes());
+                dst.extend_from_slice(b"\r\n");
+                for entry in val {
+                    entry.encode(dst);
+                }
+            }
+        }
+    }
+
+    // 编码之后的字节数，不用真的编码，统计输出缓冲区大小的时候用
+    pub fn encoded_len(&self) -> usize {
+        // 类型字符、长度数字和\r\n
+        fn header(len: usize) -> usize {
+            1 + len.to_string().len() + 2
+        }
+        match self {
+            Frame::Simple(val) | Frame::Error(val) => 1 + val.len() + 2,
+            Frame::Integer(val) => 1 + val.to_string().len() + 2,
+            Frame::Bulk(val) => header(val.len()) + val.len() + 2,
+            Frame::Null | Frame::NullArray => 5,
+            Frame::Array(val) => header(val.len()) + val.iter().map(Frame::encoded_len).sum::<usize>(),
+        }
+    }
 }
 
 // 这个函数返回一个[u8]的引用，需要确定其的生命周期
@@ -202,3 +274,33 @@ impl From<FromUtf8Error> for Error {
         "protocol error; invalid frame format".into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(frame: Frame) {
+        let mut buf = Vec::new();
+        frame.encode(&mut buf);
+        assert_eq!(buf.len(), frame.encoded_len());
+        Frame::check(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(Frame::parse(&mut Cursor::new(&buf[..])).unwrap(), frame);
+    }
+
+    #[test]
+    fn encode_then_parse() {
+        round_trip(Frame::Simple("OK".to_string()));
+        round_trip(Frame::Error("ERR x".to_string()));
+        round_trip(Frame::Integer(-7));
+        round_trip(Frame::Bulk(Bytes::from_static(b"a\r\nb")));
+        round_trip(Frame::Null);
+        round_trip(Frame::NullArray);
+        round_trip(Frame::Array(vec![Frame::Null, Frame::Array(vec![]), Frame::Integer(1)]));
+    }
+
+    #[test]
+    fn incomplete_frames() {
+        assert!(matches!(Frame::check(&mut Cursor::new(&b"$5\r\nab"[..])), Err(Error::Incomplete)));
+        assert!(matches!(Frame::check(&mut Cursor::new(&b"*2\r\n:1\r\n"[..])), Err(Error::Incomplete)));
+    }
+}
@@ -0,0 +1,504 @@
+use crate::db::State;
+use crate::frame::Frame;
+use crate::scripting::{create_lua, create_sequence, lua_error_message, run_script, ScriptStatus};
+use crate::util::{self, crc16, string_match};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use mlua::{Lua, MultiValue, RegistryKey, Table, Value};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::error;
+
+// FUNCTION DUMP 的格式版本
+const DUMP_VERSION: u8 = 1;
+
+// 加载一个库的时候执行库代码的时间上限，和Redis一样
+const LOAD_TIMEOUT: Duration = Duration::from_millis(500);
+
+// redis.register_function 允许的flag
+const FUNCTION_FLAGS: [&str; 5] = ["no-writes", "allow-oom", "allow-stale", "no-cluster", "allow-cross-slot-keys"];
+
+// FUNCTION RESTORE 遇到已经存在的库的处理方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestorePolicy {
+    Append,
+    Replace,
+    Flush,
+}
+
+// 函数库，和EVAL的脚本用不同的Lua虚拟机，SCRIPT FLUSH 不会影响到函数
+pub(crate) struct Functions {
+    // 执行函数期间被取出来，和EVAL一样
+    lua: Option<Lua>,
+    // 按库名排序，FUNCTION LIST 的顺序是固定的
+    libraries: BTreeMap<String, Library>,
+    functions: HashMap<String, FunctionInfo>,
+    status: Arc<ScriptStatus>,
+    // 函数库修改之后保存到文件，重启的时候再加载回来
+    persister: Option<Persister>,
+}
+
+// 后台线程写文件，不在State的锁里面做IO，积压多次修改的时候只写最新的一份
+struct Persister {
+    sender: Option<Sender<Bytes>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+struct Library {
+    code: Bytes,
+    functions: Vec<String>,
+}
+
+struct FunctionInfo {
+    library: String,
+    callback: RegistryKey,
+    description: Option<Bytes>,
+    flags: Vec<&'static str>,
+}
+
+impl fmt::Debug for Functions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Functions").field("libraries", &self.libraries.keys()).finish()
+    }
+}
+
+impl Functions {
+    pub(crate) fn new(status: Arc<ScriptStatus>) -> Functions {
+        Functions {
+            lua: Some(create_lua(&status)),
+            libraries: BTreeMap::new(),
+            functions: HashMap::new(),
+            status,
+            persister: None,
+        }
+    }
+
+    // 加载之前保存的函数库，之后的修改都会写回这个文件
+    pub(crate) fn open(&mut self, path: impl AsRef<Path>) -> crate::Result<()> {
+        let path = path.as_ref();
+        if path.exists() {
+            let payload = std::fs::read(path)?;
+            self.restore(&payload, RestorePolicy::Flush)?;
+        }
+        self.persister = Some(Persister::spawn(path.to_path_buf())?);
+        Ok(())
+    }
+
+    // 只在锁里面生成快照，写文件交给后台线程
+    pub(crate) fn persist(&self) {
+        if let Some(persister) = &self.persister {
+            persister.save(self.dump());
+        }
+    }
+
+    // 加载一个库，返回库名，replace为false的时候库已经存在会报错
+    pub(crate) fn load(&mut self, code: Bytes, replace: bool) -> crate::Result<String> {
+        let (name, body) = parse_metadata(&code)?;
+        if self.libraries.contains_key(&name) && !replace {
+            return Err(format!("ERR Library '{}' already exists", name).into());
+        }
+
+        let lua = self.lua.as_ref().ok_or("ERR scripting engine is busy")?;
+        let registered = register_functions(lua, &self.status, &name, body)?;
+        if registered.is_empty() {
+            return Err("ERR No functions registered".into());
+        }
+        for (function, _) in &registered {
+            if let Some(info) = self.functions.get(function) {
+                if info.library != name {
+                    return Err(format!("ERR Function {} already exists", function).into());
+                }
+            }
+        }
+
+        self.remove_library(&name);
+        let mut functions: Vec<String> = registered.iter().map(|(function, _)| function.clone()).collect();
+        functions.sort();
+        self.functions.extend(registered);
+        self.libraries.insert(name.clone(), Library { code, functions });
+        Ok(name)
+    }
+
+    pub(crate) fn delete(&mut self, name: &str) -> crate::Result<()> {
+        if !self.remove_library(name) {
+            return Err("ERR Library not found".into());
+        }
+        Ok(())
+    }
+
+    fn remove_library(&mut self, name: &str) -> bool {
+        match self.libraries.remove(name) {
+            Some(library) => {
+                for function in library.functions {
+                    self.functions.remove(&function);
+                }
+                if let Some(lua) = &self.lua {
+                    lua.expire_registry_values();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 换一个新的虚拟机，库里面留下的全局变量也一起清掉
+    pub(crate) fn flush(&mut self) {
+        self.libraries.clear();
+        self.functions.clear();
+        self.lua = Some(create_lua(&self.status));
+    }
+
+    // 每个库一个map，RESP2下面用交替排列的数组表示
+    pub(crate) fn list(&self, pattern: Option<&[u8]>, with_code: bool) -> Frame {
+        let mut libraries = Vec::new();
+        for (name, library) in &self.libraries {
+            if let Some(pattern) = pattern {
+                if !string_match(pattern, name.as_bytes(), true) {
+                    continue;
+                }
+            }
+            let functions = library
+                .functions
+                .iter()
+                .map(|function| {
+                    let info = &self.functions[function];
+                    Frame::Array(vec![
+                        Frame::Bulk(Bytes::from_static(b"name")),
+                        Frame::Bulk(Bytes::from(function.clone())),
+                        Frame::Bulk(Bytes::from_static(b"description")),
+                        info.description.clone().map(Frame::Bulk).unwrap_or(Frame::Null),
+                        Frame::Bulk(Bytes::from_static(b"flags")),
+                        Frame::Array(info.flags.iter().map(|flag| Frame::Bulk(Bytes::from_static(flag.as_bytes()))).collect()),
+                    ])
+                })
+                .collect();
+            let mut frames = vec![
+                Frame::Bulk(Bytes::from_static(b"library_name")),
+                Frame::Bulk(Bytes::from(name.clone())),
+                Frame::Bulk(Bytes::from_static(b"engine")),
+                Frame::Bulk(Bytes::from_static(b"LUA")),
+                Frame::Bulk(Bytes::from_static(b"functions")),
+                Frame::Array(functions),
+            ];
+            if with_code {
+                frames.push(Frame::Bulk(Bytes::from_static(b"library_code")));
+                frames.push(Frame::Bulk(library.code.clone()));
+            }
+            libraries.push(Frame::Array(frames));
+        }
+        Frame::Array(libraries)
+    }
+
+    // 版本号 + 库的数量 + 每个库的代码，最后是校验和
+    pub(crate) fn dump(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        buf.put_u8(DUMP_VERSION);
+        buf.put_u32(self.libraries.len() as u32);
+        for library in self.libraries.values() {
+            buf.put_u32(library.code.len() as u32);
+            buf.put_slice(&library.code);
+        }
+        let checksum = crc16(&buf);
+        buf.put_u16(checksum);
+        buf.freeze()
+    }
+
+    // 在新的虚拟机里面重新加载所有的库，全部成功之后才替换，失败的时候保持原样
+    pub(crate) fn restore(&mut self, payload: &[u8], policy: RestorePolicy) -> crate::Result<()> {
+        let codes = parse_dump(payload).ok_or("ERR payload version or checksum are wrong")?;
+
+        let mut restored = Functions::new(self.status.clone());
+        if policy != RestorePolicy::Flush {
+            for library in self.libraries.values() {
+                restored.load(library.code.clone(), false)?;
+            }
+        }
+        for code in codes {
+            restored.load(code, policy == RestorePolicy::Replace)?;
+        }
+
+        restored.persister = self.persister.take();
+        *self = restored;
+        Ok(())
+    }
+}
+
+impl Persister {
+    fn spawn(path: PathBuf) -> crate::Result<Persister> {
+        let (sender, receiver) = mpsc::channel::<Bytes>();
+        let worker = thread::Builder::new().name("functions-persist".to_string()).spawn(move || {
+            while let Ok(mut payload) = receiver.recv() {
+                while let Ok(next) = receiver.try_recv() {
+                    payload = next;
+                }
+                if let Err(err) = util::write_atomic(&path, &payload) {
+                    error!(cause = ?err, path = ?path, "failed to persist functions");
+                }
+            }
+        })?;
+        Ok(Persister { sender: Some(sender), worker: Some(worker) })
+    }
+
+    fn save(&self, payload: Bytes) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(payload);
+        }
+    }
+}
+
+// 退出的时候等还没写完的修改落盘
+impl Drop for Persister {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn parse_dump(payload: &[u8]) -> Option<Vec<Bytes>> {
+    if payload.len() < 7 {
+        return None;
+    }
+    let (mut body, mut checksum) = payload.split_at(payload.len() - 2);
+    if crc16(body) != checksum.get_u16() || body.get_u8() != DUMP_VERSION {
+        return None;
+    }
+    let count = body.get_u32();
+    let mut codes = Vec::new();
+    for _ in 0..count {
+        if body.remaining() < 4 {
+            return None;
+        }
+        let len = body.get_u32() as usize;
+        if body.remaining() < len {
+            return None;
+        }
+        codes.push(Bytes::copy_from_slice(&body[..len]));
+        body.advance(len);
+    }
+    if body.has_remaining() {
+        return None;
+    }
+    Some(codes)
+}
+
+// 解析第一行的 #!lua name=mylib，返回库名和去掉这一行之后的代码
+fn parse_metadata(code: &[u8]) -> crate::Result<(String, &[u8])> {
+    if !code.starts_with(b"#!") {
+        return Err("ERR Missing library metadata".into());
+    }
+    let end = code.iter().position(|&c| c == b'\n').unwrap_or(code.len());
+    let shebang = String::from_utf8_lossy(&code[2..end]);
+    let mut parts = shebang.split_whitespace();
+
+    let engine = parts.next().unwrap_or("");
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(format!("ERR Engine '{}' not found", engine).into());
+    }
+    let mut name = None;
+    for part in parts {
+        match part.strip_prefix("name=") {
+            Some(value) => name = Some(value.to_string()),
+            None => return Err(format!("ERR Invalid metadata value given: {}", part).into()),
+        }
+    }
+    let name = name.ok_or("ERR Library name was not given")?;
+    if !is_valid_name(&name) {
+        return Err("ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long".into());
+    }
+    Ok((name, &code[end..]))
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+}
+
+// 执行库的代码，收集通过redis.register_function注册的函数，期间其他连接和执行脚本的时候一样等待
+fn register_functions(lua: &Lua, status: &ScriptStatus, library: &str, body: &[u8]) -> crate::Result<Vec<(String, FunctionInfo)>> {
+    let chunk = lua
+        .load(body)
+        .set_name("@user_function")
+        .into_function()
+        .map_err(|err| format!("ERR Error compiling function: {}", lua_error_message(&err)))?;
+
+    let registered = RefCell::new(Vec::<(String, FunctionInfo)>::new());
+    let res = lua.scope(|scope| {
+        let redis: Table = lua.globals().get("redis")?;
+        redis.set("register_function", scope.create_function(|lua, args: MultiValue| {
+            let (name, info) = parse_register_args(lua, library, args)?;
+            let mut registered = registered.borrow_mut();
+            if registered.iter().any(|(function, _)| *function == name) {
+                return Err(mlua::Error::RuntimeError("Function already exists in the library".to_string()));
+            }
+            registered.push((name, info));
+            Ok(())
+        })?)?;
+        status.start_load(LOAD_TIMEOUT);
+        let res = chunk.call::<_, ()>(());
+        status.finish();
+        redis.set("register_function", Value::Nil)?;
+        res
+    });
+    res.map_err(|err| format!("ERR Error registering functions: {}", lua_error_message(&err)))?;
+    Ok(registered.into_inner())
+}
+
+// 支持 register_function(name, callback) 和 register_function{function_name=..., callback=..., flags=..., description=...}
+fn parse_register_args(lua: &Lua, library: &str, args: MultiValue) -> mlua::Result<(String, FunctionInfo)> {
+    let args = args.into_vec();
+    let (name, callback, flags, description) = match &args[..] {
+        [Value::String(name), Value::Function(callback)] => (name.to_str()?.to_string(), callback.clone(), None, None),
+        [Value::Table(table)] => {
+            let mut name = None;
+            let mut callback = None;
+            let mut flags = None;
+            let mut description = None;
+            for pair in table.clone().pairs::<String, Value>() {
+                let (key, value) = pair?;
+                match (&key[..], value) {
+                    ("function_name", Value::String(value)) => name = Some(value.to_str()?.to_string()),
+                    ("callback", Value::Function(value)) => callback = Some(value),
+                    ("flags", Value::Table(value)) => flags = Some(value),
+                    ("description", Value::String(value)) => description = Some(Bytes::copy_from_slice(value.as_bytes())),
+                    _ => return Err(mlua::Error::RuntimeError("unknown argument given to redis.register_function".to_string())),
+                }
+            }
+            let name = name.ok_or_else(|| mlua::Error::RuntimeError("redis.register_function must get a function name argument".to_string()))?;
+            let callback = callback.ok_or_else(|| mlua::Error::RuntimeError("redis.register_function must get a callback argument".to_string()))?;
+            (name, callback, flags, description)
+        }
+        _ => return Err(mlua::Error::RuntimeError("wrong arguments given to redis.register_function".to_string())),
+    };
+
+    if !is_valid_name(&name) {
+        return Err(mlua::Error::RuntimeError(
+            "Function names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string(),
+        ));
+    }
+    let mut parsed_flags = Vec::new();
+    if let Some(flags) = flags {
+        for flag in flags.sequence_values::<String>() {
+            let flag = flag?;
+            let flag = FUNCTION_FLAGS
+                .iter()
+                .find(|known| **known == flag)
+                .ok_or_else(|| mlua::Error::RuntimeError("unknown flag given".to_string()))?;
+            if !parsed_flags.contains(flag) {
+                parsed_flags.push(*flag);
+            }
+        }
+    }
+
+    let info = FunctionInfo {
+        library: library.to_string(),
+        callback: lua.create_registry_value(callback)?,
+        description,
+        flags: parsed_flags,
+    };
+    Ok((name, info))
+}
+
+// 执行FCALL，read_only是 FCALL_RO，只能调用带有no-writes的函数
+pub(crate) fn fcall(db: &mut State, name: &str, keys: Vec<Bytes>, args: Vec<Bytes>, read_only: bool) -> crate::Result<Frame> {
+    let functions = db.functions();
+    let info = functions.functions.get(name).ok_or("ERR Function not found")?;
+    let no_writes = info.flags.contains(&"no-writes");
+    if read_only && !no_writes {
+        return Err("ERR Can not execute a script with write flag using *_ro command.".into());
+    }
+    let lua = functions.lua.take().ok_or("ERR scripting engine is busy")?;
+    let status = functions.status.clone();
+
+    let result = (|| {
+        let func: mlua::Function = lua.registry_value(&db.functions().functions[name].callback)?;
+        let keys = create_sequence(&lua, &keys)?;
+        let args = create_sequence(&lua, &args)?;
+        run_script(&lua, db, &status, func, (keys, args), no_writes)
+    })();
+
+    db.functions().lua = Some(lua);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const LIBRARY: &[u8] = b"#!lua name=mylib\nredis.register_function('f', function() return 1 end)";
+
+    fn functions() -> Functions {
+        Functions::new(Arc::new(ScriptStatus::default()))
+    }
+
+    #[test]
+    fn parse_metadata_line() {
+        let (name, body) = parse_metadata(b"#!lua name=lib_1\nreturn 1").unwrap();
+        assert_eq!((name.as_str(), body), ("lib_1", &b"\nreturn 1"[..]));
+        assert!(parse_metadata(b"return 1").is_err());
+        assert!(parse_metadata(b"#!js name=x\n").is_err());
+        assert!(parse_metadata(b"#!lua\n").is_err());
+        assert!(parse_metadata(b"#!lua name=a-b\n").is_err());
+        assert!(parse_metadata(b"#!lua name=x foo=bar\n").is_err());
+    }
+
+    #[test]
+    fn dump_round_trip() {
+        let mut functions = functions();
+        functions.load(Bytes::from_static(LIBRARY), false).unwrap();
+        let dump = functions.dump();
+        assert_eq!(parse_dump(&dump).unwrap(), vec![Bytes::from_static(LIBRARY)]);
+
+        let mut corrupted = dump.to_vec();
+        corrupted[5] ^= 1;
+        assert!(parse_dump(&corrupted).is_none());
+        assert!(parse_dump(&dump[..dump.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn load_rejects_duplicates() {
+        let mut functions = functions();
+        functions.load(Bytes::from_static(LIBRARY), false).unwrap();
+        assert!(functions.load(Bytes::from_static(LIBRARY), false).is_err());
+        functions.load(Bytes::from_static(LIBRARY), true).unwrap();
+        let other = Bytes::from_static(b"#!lua name=other\nredis.register_function('f', function() return 2 end)");
+        assert_eq!(functions.load(other, false).unwrap_err().to_string(), "ERR Function f already exists");
+    }
+
+    #[test]
+    fn load_times_out() {
+        let status = Arc::new(ScriptStatus::default());
+        let mut functions = Functions::new(status.clone());
+        let start = Instant::now();
+        let err = functions.load(Bytes::from_static(b"#!lua name=slow\nwhile true do end"), false).unwrap_err();
+        assert_eq!(err.to_string(), "ERR Error registering functions: FUNCTION LOAD timeout");
+        // 截止时间精确到毫秒
+        assert!(start.elapsed() + Duration::from_millis(1) >= LOAD_TIMEOUT);
+        assert!(!status.is_running());
+        // 超时之后虚拟机还能继续用
+        functions.load(Bytes::from_static(LIBRARY), false).unwrap();
+    }
+
+    #[test]
+    fn persisted_file_is_restored() {
+        let path = std::env::temp_dir().join(format!("my-redis-functions-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut functions = functions();
+        functions.open(&path).unwrap();
+        functions.load(Bytes::from_static(LIBRARY), false).unwrap();
+        functions.persist();
+        // drop的时候等后台线程写完
+        drop(functions);
+
+        let mut reopened = self::functions();
+        reopened.open(&path).unwrap();
+        assert!(reopened.libraries.contains_key("mylib"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
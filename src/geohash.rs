@@ -0,0 +1,442 @@
+// geohash编码，移植自redis的 geohash.c 和 geohash_helper.c，保证结果和redis一致
+//
+// 纬度放在偶数位，经度放在奇数位，26步一共52位，刚好能被f64精确表示，所以直接当作有序集合的分数
+
+use crate::frame::Frame;
+use crate::util::parse_double;
+use bytes::Bytes;
+
+pub const GEO_STEP_MAX: u8 = 26;
+pub const GEO_LAT_MIN: f64 = -85.051_128_78;
+pub const GEO_LAT_MAX: f64 = 85.051_128_78;
+pub const GEO_LONG_MIN: f64 = -180.0;
+pub const GEO_LONG_MAX: f64 = 180.0;
+
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+const MERCATOR_MAX: f64 = 20_037_726.37;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoHashBits {
+    pub bits: u64,
+    pub step: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GeoHashRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GeoHashArea {
+    pub longitude: GeoHashRange,
+    pub latitude: GeoHashRange,
+}
+
+// 搜索的形状，长度都已经换算成米
+#[derive(Debug, Clone, Copy)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+// 中心格子和周围8个格子，不需要搜索的格子是None
+#[derive(Debug)]
+pub struct GeoHashRadius {
+    pub hash: GeoHashBits,
+    pub neighbors: [Option<GeoHashBits>; 8],
+}
+
+const LONG_RANGE: GeoHashRange = GeoHashRange { min: GEO_LONG_MIN, max: GEO_LONG_MAX };
+const LAT_RANGE: GeoHashRange = GeoHashRange { min: GEO_LAT_MIN, max: GEO_LAT_MAX };
+
+fn interleave64(xlo: u32, ylo: u32) -> u64 {
+    const B: [u64; 5] = [
+        0x5555_5555_5555_5555,
+        0x3333_3333_3333_3333,
+        0x0F0F_0F0F_0F0F_0F0F,
+        0x00FF_00FF_00FF_00FF,
+        0x0000_FFFF_0000_FFFF,
+    ];
+    const S: [u32; 5] = [1, 2, 4, 8, 16];
+    let mut x = xlo as u64;
+    let mut y = ylo as u64;
+    for i in (0..5).rev() {
+        x = (x | (x << S[i])) & B[i];
+        y = (y | (y << S[i])) & B[i];
+    }
+    x | (y << 1)
+}
+
+// 返回值低32位是偶数位（纬度），高32位是奇数位（经度）
+fn deinterleave64(interleaved: u64) -> u64 {
+    const B: [u64; 6] = [
+        0x5555_5555_5555_5555,
+        0x3333_3333_3333_3333,
+        0x0F0F_0F0F_0F0F_0F0F,
+        0x00FF_00FF_00FF_00FF,
+        0x0000_FFFF_0000_FFFF,
+        0x0000_0000_FFFF_FFFF,
+    ];
+    const S: [u32; 6] = [0, 1, 2, 4, 8, 16];
+    let mut x = interleaved;
+    let mut y = interleaved >> 1;
+    for i in 0..6 {
+        x = (x | (x >> S[i])) & B[i];
+        y = (y | (y >> S[i])) & B[i];
+    }
+    x | (y << 32)
+}
+
+pub fn encode(long_range: GeoHashRange, lat_range: GeoHashRange, longitude: f64, latitude: f64, step: u8) -> Option<GeoHashBits> {
+    if !(GEO_LONG_MIN..=GEO_LONG_MAX).contains(&longitude) || !(GEO_LAT_MIN..=GEO_LAT_MAX).contains(&latitude) {
+        return None;
+    }
+    if latitude < lat_range.min || latitude > lat_range.max || longitude < long_range.min || longitude > long_range.max {
+        return None;
+    }
+    let lat_offset = (latitude - lat_range.min) / (lat_range.max - lat_range.min) * (1u64 << step) as f64;
+    let long_offset = (longitude - long_range.min) / (long_range.max - long_range.min) * (1u64 << step) as f64;
+    Some(GeoHashBits {
+        bits: interleave64(lat_offset as u32, long_offset as u32),
+        step,
+    })
+}
+
+pub fn encode_wgs84(longitude: f64, latitude: f64, step: u8) -> Option<GeoHashBits> {
+    encode(LONG_RANGE, LAT_RANGE, longitude, latitude, step)
+}
+
+pub fn decode(long_range: GeoHashRange, lat_range: GeoHashRange, hash: GeoHashBits) -> GeoHashArea {
+    let sep = deinterleave64(hash.bits);
+    let ilato = (sep & 0xffff_ffff) as f64;
+    let ilono = (sep >> 32) as f64;
+    let cells = (1u64 << hash.step) as f64;
+    let lat_scale = lat_range.max - lat_range.min;
+    let long_scale = long_range.max - long_range.min;
+    GeoHashArea {
+        latitude: GeoHashRange {
+            min: lat_range.min + (ilato / cells) * lat_scale,
+            max: lat_range.min + ((ilato + 1.0) / cells) * lat_scale,
+        },
+        longitude: GeoHashRange {
+            min: long_range.min + (ilono / cells) * long_scale,
+            max: long_range.min + ((ilono + 1.0) / cells) * long_scale,
+        },
+    }
+}
+
+// 取格子的中心点作为坐标，返回 (经度, 纬度)
+pub fn decode_to_long_lat(hash: GeoHashBits) -> (f64, f64) {
+    let area = decode(LONG_RANGE, LAT_RANGE, hash);
+    let longitude = ((area.longitude.min + area.longitude.max) / 2.0).clamp(GEO_LONG_MIN, GEO_LONG_MAX);
+    let latitude = ((area.latitude.min + area.latitude.max) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX);
+    (longitude, latitude)
+}
+
+// 有序集合里面的分数就是26步的geohash
+pub fn decode_score(score: f64) -> (f64, f64) {
+    decode_to_long_lat(GeoHashBits { bits: score as u64, step: GEO_STEP_MAX })
+}
+
+// 标准的11位geohash字符串，纬度范围用的是 [-90, 90]
+pub fn to_base32(score: f64) -> String {
+    const ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+    let (longitude, latitude) = decode_score(score);
+    let long_range = GeoHashRange { min: -180.0, max: 180.0 };
+    let lat_range = GeoHashRange { min: -90.0, max: 90.0 };
+    let bits = encode(long_range, lat_range, longitude, latitude, GEO_STEP_MAX).map(|hash| hash.bits).unwrap_or(0);
+    (0..11)
+        .map(|i| {
+            // 52位只够10个字符，最后一个补0
+            let idx = if i == 10 { 0 } else { (bits >> (52 - (i + 1) * 5)) & 0x1f };
+            ALPHABET[idx as usize] as char
+        })
+        .collect()
+}
+
+// 对齐到52位，作为分数区间的端点
+pub fn align_52bits(hash: GeoHashBits) -> u64 {
+    hash.bits << (52 - hash.step as u32 * 2)
+}
+
+fn move_x(hash: &mut GeoHashBits, d: i8) {
+    let shift = 64 - hash.step as u32 * 2;
+    let mut x = hash.bits & 0xaaaa_aaaa_aaaa_aaaa;
+    let y = hash.bits & 0x5555_5555_5555_5555;
+    let zz = 0x5555_5555_5555_5555u64 >> shift;
+    if d > 0 {
+        x = x.wrapping_add(zz + 1);
+    } else {
+        x |= zz;
+        x = x.wrapping_sub(zz + 1);
+    }
+    x &= 0xaaaa_aaaa_aaaa_aaaau64 >> shift;
+    hash.bits = x | y;
+}
+
+fn move_y(hash: &mut GeoHashBits, d: i8) {
+    let shift = 64 - hash.step as u32 * 2;
+    let x = hash.bits & 0xaaaa_aaaa_aaaa_aaaa;
+    let mut y = hash.bits & 0x5555_5555_5555_5555;
+    let zz = 0xaaaa_aaaa_aaaa_aaaau64 >> shift;
+    if d > 0 {
+        y = y.wrapping_add(zz + 1);
+    } else {
+        y |= zz;
+        y = y.wrapping_sub(zz + 1);
+    }
+    y &= 0x5555_5555_5555_5555u64 >> shift;
+    hash.bits = x | y;
+}
+
+// 顺序是 north south east west north_east north_west south_east south_west，和redis搜索的顺序一致
+fn neighbors(hash: GeoHashBits) -> [GeoHashBits; 8] {
+    let moves: [(i8, i8); 8] = [(0, 1), (0, -1), (1, 0), (-1, 0), (1, 1), (-1, 1), (1, -1), (-1, -1)];
+    let mut result = [hash; 8];
+    for (neighbor, (dx, dy)) in result.iter_mut().zip(moves.iter()) {
+        if *dx != 0 {
+            move_x(neighbor, *dx);
+        }
+        if *dy != 0 {
+            move_y(neighbor, *dy);
+        }
+    }
+    result
+}
+
+fn estimate_steps_by_radius(mut range_meters: f64, latitude: f64) -> u8 {
+    if range_meters == 0.0 {
+        return GEO_STEP_MAX;
+    }
+    let mut step: i32 = 1;
+    while range_meters < MERCATOR_MAX {
+        range_meters *= 2.0;
+        step += 1;
+    }
+    // 保证大部分情况下范围都能被覆盖
+    step -= 2;
+    // 越靠近两极，经度方向的格子越窄
+    if !(-66.0..=66.0).contains(&latitude) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&latitude) {
+            step -= 1;
+        }
+    }
+    step.clamp(1, GEO_STEP_MAX as i32) as u8
+}
+
+// 返回 [min_lon, min_lat, max_lon, max_lat]
+fn bounding_box(longitude: f64, latitude: f64, shape: GeoShape) -> [f64; 4] {
+    let (width, height) = match shape {
+        GeoShape::Radius(radius) => (radius, radius),
+        GeoShape::Box { width, height } => (width / 2.0, height / 2.0),
+    };
+    let lat_delta = rad_deg(height / EARTH_RADIUS_IN_METERS);
+    let long_delta_top = rad_deg(width / EARTH_RADIUS_IN_METERS / deg_rad(latitude + lat_delta).cos());
+    let long_delta_bottom = rad_deg(width / EARTH_RADIUS_IN_METERS / deg_rad(latitude - lat_delta).cos());
+    let long_delta = if latitude < 0.0 { long_delta_bottom } else { long_delta_top };
+    [longitude - long_delta, latitude - lat_delta, longitude + long_delta, latitude + lat_delta]
+}
+
+// 算出需要搜索的9个格子
+pub fn areas_by_shape(longitude: f64, latitude: f64, shape: GeoShape) -> GeoHashRadius {
+    let [min_lon, min_lat, max_lon, max_lat] = bounding_box(longitude, latitude, shape);
+    let radius_meters = match shape {
+        GeoShape::Radius(radius) => radius,
+        GeoShape::Box { width, height } => ((width / 2.0).powi(2) + (height / 2.0).powi(2)).sqrt(),
+    };
+
+    let mut steps = estimate_steps_by_radius(radius_meters, latitude);
+    let mut hash = encode_wgs84(longitude, latitude, steps).unwrap();
+    let mut around = neighbors(hash);
+    let mut area = decode(LONG_RANGE, LAT_RANGE, hash);
+
+    // 搜索区域靠近格子边缘的时候，估算出来的步数可能不够，需要再放大一级
+    let north = decode(LONG_RANGE, LAT_RANGE, around[0]);
+    let south = decode(LONG_RANGE, LAT_RANGE, around[1]);
+    let east = decode(LONG_RANGE, LAT_RANGE, around[2]);
+    let west = decode(LONG_RANGE, LAT_RANGE, around[3]);
+    let decrease_step = north.latitude.max < max_lat
+        || south.latitude.min > min_lat
+        || east.longitude.max < max_lon
+        || west.longitude.min > min_lon;
+    if steps > 1 && decrease_step {
+        steps -= 1;
+        hash = encode_wgs84(longitude, latitude, steps).unwrap();
+        around = neighbors(hash);
+        area = decode(LONG_RANGE, LAT_RANGE, hash);
+    }
+
+    let mut result = GeoHashRadius {
+        hash,
+        neighbors: around.map(Some),
+    };
+    // 去掉完全不可能命中的格子
+    if steps >= 2 {
+        if area.latitude.min < min_lat {
+            for i in [1, 6, 7] {
+                result.neighbors[i] = None;
+            }
+        }
+        if area.latitude.max > max_lat {
+            for i in [0, 4, 5] {
+                result.neighbors[i] = None;
+            }
+        }
+        if area.longitude.min < min_lon {
+            for i in [3, 7, 5] {
+                result.neighbors[i] = None;
+            }
+        }
+        if area.longitude.max > max_lon {
+            for i in [2, 6, 4] {
+                result.neighbors[i] = None;
+            }
+        }
+    }
+    result
+}
+
+// 和redis的 deg_rad/rad_deg 保持一样的浮点运算顺序
+fn deg_rad(ang: f64) -> f64 {
+    ang * (std::f64::consts::PI / 180.0)
+}
+
+fn rad_deg(ang: f64) -> f64 {
+    ang / (std::f64::consts::PI / 180.0)
+}
+
+fn lat_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (deg_rad(lat2) - deg_rad(lat1)).abs()
+}
+
+// haversine公式，单位米
+pub fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let v = ((deg_rad(lon2) - deg_rad(lon1)) / 2.0).sin();
+    // 经度相同的时候不需要复杂的计算
+    if v == 0.0 {
+        return lat_distance(lat1, lat2);
+    }
+    let lat1r = deg_rad(lat1);
+    let lat2r = deg_rad(lat2);
+    let u = ((lat2r - lat1r) / 2.0).sin();
+    let a = u * u + lat1r.cos() * lat2r.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+// 点 (lon, lat) 在形状里面的时候返回它到中心的距离
+pub fn distance_if_in_shape(shape: GeoShape, center: (f64, f64), point: (f64, f64)) -> Option<f64> {
+    let (x1, y1) = center;
+    let (x2, y2) = point;
+    match shape {
+        GeoShape::Radius(radius) => {
+            let dist = distance(x1, y1, x2, y2);
+            if dist > radius { None } else { Some(dist) }
+        }
+        GeoShape::Box { width, height } => {
+            // 先判断计算量小的纬度方向
+            if lat_distance(y2, y1) > height / 2.0 {
+                return None;
+            }
+            if distance(x2, y2, x1, y2) > width / 2.0 {
+                return None;
+            }
+            Some(distance(x1, y1, x2, y2))
+        }
+    }
+}
+
+// 距离单位换算成米的系数
+pub fn parse_unit(unit: &[u8]) -> crate::Result<f64> {
+    match &String::from_utf8_lossy(unit).to_lowercase()[..] {
+        "m" => Ok(1.0),
+        "km" => Ok(1000.0),
+        "ft" => Ok(0.3048),
+        "mi" => Ok(1609.34),
+        _ => Err("ERR unsupported unit provided. please use M, KM, FT, MI".into()),
+    }
+}
+
+pub fn parse_long_lat(longitude: &[u8], latitude: &[u8]) -> crate::Result<(f64, f64)> {
+    let longitude = parse_double(longitude).ok_or("ERR value is not a valid float")?;
+    let latitude = parse_double(latitude).ok_or("ERR value is not a valid float")?;
+    if !(GEO_LONG_MIN..=GEO_LONG_MAX).contains(&longitude) || !(GEO_LAT_MIN..=GEO_LAT_MAX).contains(&latitude) {
+        return Err(format!("ERR invalid longitude,latitude pair {:.6},{:.6}", longitude, latitude).into());
+    }
+    Ok((longitude, latitude))
+}
+
+// 和redis一样保留4位小数
+pub fn distance_frame(meters: f64, unit: f64) -> Frame {
+    Frame::Bulk(Bytes::from(format!("{:.4}", meters / unit)))
+}
+
+// redis用 %.17Lf 输出坐标，再去掉末尾的0
+pub fn coord_frame((longitude, latitude): (f64, f64)) -> Frame {
+    let format = |v: f64| {
+        let s = format!("{:.17}", v);
+        Frame::Bulk(Bytes::from(s.trim_end_matches('0').trim_end_matches('.').to_string()))
+    };
+    Frame::Array(vec![format(longitude), format(latitude)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // redis文档里面的Palermo和Catania
+    const PALERMO: (f64, f64) = (13.361389, 38.115556);
+    const CATANIA: (f64, f64) = (15.087269, 37.502669);
+
+    #[test]
+    fn interleave_round_trip() {
+        let bits = interleave64(0x1234_5678, 0x9abc_def0);
+        assert_eq!(deinterleave64(bits), 0x9abc_def0_1234_5678);
+    }
+
+    #[test]
+    fn encode_like_redis() {
+        let hash = encode_wgs84(PALERMO.0, PALERMO.1, GEO_STEP_MAX).unwrap();
+        assert_eq!(hash.bits, 3_479_099_956_230_698);
+        assert_eq!(to_base32(hash.bits as f64), "sqc8b49rny0");
+        let (longitude, latitude) = decode_score(hash.bits as f64);
+        assert!((longitude - PALERMO.0).abs() < 1e-5 && (latitude - PALERMO.1).abs() < 1e-5);
+        assert!(encode_wgs84(0.0, 86.0, GEO_STEP_MAX).is_none());
+    }
+
+    #[test]
+    fn distances() {
+        let meters = distance(PALERMO.0, PALERMO.1, CATANIA.0, CATANIA.1);
+        assert!((meters - 166_274.15).abs() < 1.0);
+        // redis用的是存进去以后解码出来的坐标
+        let stored = |(lon, lat): (f64, f64)| decode_score(encode_wgs84(lon, lat, GEO_STEP_MAX).unwrap().bits as f64);
+        let (p, c) = (stored(PALERMO), stored(CATANIA));
+        let meters = distance(p.0, p.1, c.0, c.1);
+        assert_eq!(distance_frame(meters, parse_unit(b"KM").unwrap()), Frame::Bulk(Bytes::from("166.2742")));
+        assert!(parse_unit(b"yd").is_err());
+
+        assert!(distance_if_in_shape(GeoShape::Radius(200_000.0), PALERMO, CATANIA).is_some());
+        assert!(distance_if_in_shape(GeoShape::Radius(100_000.0), PALERMO, CATANIA).is_none());
+        // 纬度差大约68km，高度100km的框装不下
+        let wide = GeoShape::Box { width: 400_000.0, height: 100_000.0 };
+        assert!(distance_if_in_shape(wide, PALERMO, CATANIA).is_none());
+        let tall = GeoShape::Box { width: 400_000.0, height: 200_000.0 };
+        assert!(distance_if_in_shape(tall, PALERMO, CATANIA).is_some());
+    }
+
+    #[test]
+    fn search_areas_cover_center() {
+        let radius = areas_by_shape(PALERMO.0, PALERMO.1, GeoShape::Radius(200_000.0));
+        let score = encode_wgs84(CATANIA.0, CATANIA.1, GEO_STEP_MAX).unwrap().bits;
+        let covered = std::iter::once(Some(radius.hash))
+            .chain(radius.neighbors.iter().copied())
+            .flatten()
+            .any(|hash| {
+                let min = align_52bits(hash);
+                let max = align_52bits(GeoHashBits { bits: hash.bits + 1, step: hash.step });
+                (min..max).contains(&score)
+            });
+        assert!(covered);
+    }
+}
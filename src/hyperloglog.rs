@@ -0,0 +1,393 @@
+// HyperLogLog，字节格式和redis完全一致，GET出来的值可以直接给redis用
+//
+// 头部16个字节: "HYLL" + 编码(1字节) + 3字节保留 + 8字节缓存的基数(小端，最高位为1代表缓存失效)
+// dense编码: 16384个6位的寄存器，从低位开始排列
+// sparse编码: ZERO 00xxxxxx, XZERO 01xxxxxx yyyyyyyy, VAL 1vvvvvxx
+
+const HLL_P: u32 = 14;
+const HLL_Q: u32 = 64 - HLL_P;
+pub const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_P_MASK: u64 = HLL_REGISTERS as u64 - 1;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+const HLL_HDR_SIZE: usize = 16;
+pub const HLL_DENSE_SIZE: usize = HLL_HDR_SIZE + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+
+const HLL_SPARSE_VAL_MAX_VALUE: u8 = 32;
+const HLL_SPARSE_VAL_MAX_LEN: usize = 4;
+const HLL_SPARSE_ZERO_MAX_LEN: usize = 64;
+const HLL_SPARSE_XZERO_MAX_LEN: usize = 16384;
+// 对应redis的 hll-sparse-max-bytes 默认值
+const HLL_SPARSE_MAX_BYTES: usize = 3000;
+
+pub const INVALID_HLL: &str = "WRONGTYPE Key is not a valid HyperLogLog string value.";
+pub const CORRUPTED_HLL: &str = "INVALIDOBJ Corrupted HLL object detected";
+
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+    dense: bool,
+    card: [u8; 8],
+}
+
+impl HyperLogLog {
+    // 新建的都是sparse编码，缓存的基数是0
+    pub fn new() -> HyperLogLog {
+        HyperLogLog {
+            registers: vec![0; HLL_REGISTERS],
+            dense: false,
+            card: [0; 8],
+        }
+    }
+
+    // 检查头部并解出所有寄存器
+    pub fn from_bytes(src: &[u8]) -> crate::Result<HyperLogLog> {
+        if src.len() < HLL_HDR_SIZE || &src[..4] != b"HYLL" || src[4] > HLL_SPARSE {
+            return Err(INVALID_HLL.into());
+        }
+        let dense = src[4] == HLL_DENSE;
+        if dense && src.len() != HLL_DENSE_SIZE {
+            return Err(INVALID_HLL.into());
+        }
+
+        let mut card = [0; 8];
+        card.copy_from_slice(&src[8..16]);
+        let body = &src[HLL_HDR_SIZE..];
+        let registers = if dense {
+            (0..HLL_REGISTERS).map(|i| dense_get(body, i)).collect()
+        } else {
+            sparse_decode(body).ok_or(CORRUPTED_HLL)?
+        };
+        Ok(HyperLogLog { registers, dense, card })
+    }
+
+    // sparse放不下的时候自动转成dense
+    pub fn encode(&mut self) -> Vec<u8> {
+        if !self.dense {
+            match sparse_encode(&self.registers) {
+                Some(body) => return self.with_header(HLL_SPARSE, body),
+                None => self.dense = true,
+            }
+        }
+        let mut body = vec![0; HLL_DENSE_SIZE - HLL_HDR_SIZE];
+        for (i, &value) in self.registers.iter().enumerate() {
+            dense_set(&mut body, i, value);
+        }
+        self.with_header(HLL_DENSE, body)
+    }
+
+    fn with_header(&self, encoding: u8, body: Vec<u8>) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HLL_HDR_SIZE + body.len());
+        out.extend_from_slice(b"HYLL");
+        out.push(encoding);
+        out.extend_from_slice(&[0; 3]);
+        out.extend_from_slice(&self.card);
+        out.extend_from_slice(&body);
+        out
+    }
+
+    pub fn is_dense(&self) -> bool {
+        self.dense
+    }
+
+    // 返回寄存器是否有变化
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let (index, count) = pattern_len(element);
+        if self.registers[index] < count {
+            self.registers[index] = count;
+            true
+        } else {
+            false
+        }
+    }
+
+    // 每个寄存器取最大值
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (dst, src) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *dst = (*dst).max(*src);
+        }
+    }
+
+    pub fn set_dense(&mut self) {
+        self.dense = true;
+    }
+
+    pub fn cached_count(&self) -> Option<u64> {
+        if self.card[7] & (1 << 7) != 0 {
+            None
+        } else {
+            Some(u64::from_le_bytes(self.card))
+        }
+    }
+
+    pub fn set_cached_count(&mut self, count: u64) {
+        self.card = count.to_le_bytes();
+    }
+
+    pub fn invalidate_cache(&mut self) {
+        self.card[7] |= 1 << 7;
+    }
+
+    // Otmar Ertl 的改进估计算法，和redis的 hllCount 一致
+    pub fn count(&self) -> u64 {
+        let mut histogram = [0u32; 64];
+        for &value in &self.registers {
+            histogram[value as usize] += 1;
+        }
+
+        let m = HLL_REGISTERS as f64;
+        let mut z = m * tau((m - histogram[HLL_Q as usize + 1] as f64) / m);
+        for j in (1..=HLL_Q as usize).rev() {
+            z += histogram[j] as f64;
+            z *= 0.5;
+        }
+        z += m * sigma(histogram[0] as f64 / m);
+        (HLL_ALPHA_INF * m * m / z).round() as u64
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog::new()
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let prev = z;
+        z += x * y;
+        y += y;
+        if prev == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let prev = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if prev == z {
+            return z / 3.0;
+        }
+    }
+}
+
+// 返回元素对应的寄存器下标，以及从第14位开始连续0的个数加1
+fn pattern_len(element: &[u8]) -> (usize, u8) {
+    let mut hash = murmurhash64a(element, 0xadc8_3b19);
+    let index = (hash & HLL_P_MASK) as usize;
+    hash >>= HLL_P;
+    // 保证循环一定会结束
+    hash |= 1 << HLL_Q;
+    (index, hash.trailing_zeros() as u8 + 1)
+}
+
+// redis用的64位MurmurHash2，按小端读取
+fn murmurhash64a(key: &[u8], seed: u32) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed as u64 ^ (key.len() as u64).wrapping_mul(M);
+
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        let mut k = u64::from_le_bytes(word);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= (b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+fn dense_get(body: &[u8], index: usize) -> u8 {
+    let bit = index * HLL_BITS;
+    let byte = bit / 8;
+    let fb = bit % 8;
+    let b0 = body[byte] as u16;
+    // 最后一个寄存器不会跨字节
+    let b1 = body.get(byte + 1).copied().unwrap_or(0) as u16;
+    (((b0 >> fb) | (b1 << (8 - fb))) & HLL_REGISTER_MAX as u16) as u8
+}
+
+fn dense_set(body: &mut [u8], index: usize, value: u8) {
+    let bit = index * HLL_BITS;
+    let byte = bit / 8;
+    let fb = bit % 8;
+    let value = value as u16;
+    let max = HLL_REGISTER_MAX as u16;
+    body[byte] &= !((max << fb) as u8);
+    body[byte] |= (value << fb) as u8;
+    if fb + HLL_BITS > 8 {
+        body[byte + 1] &= !((max >> (8 - fb)) as u8);
+        body[byte + 1] |= (value >> (8 - fb)) as u8;
+    }
+}
+
+// 覆盖的寄存器数量不对的时候认为数据损坏
+fn sparse_decode(body: &[u8]) -> Option<Vec<u8>> {
+    let mut registers = Vec::with_capacity(HLL_REGISTERS);
+    let mut idx = 0;
+    while idx < body.len() {
+        let op = body[idx];
+        if op & 0xc0 == 0 {
+            // ZERO
+            let len = (op & 0x3f) as usize + 1;
+            registers.resize(registers.len() + len, 0);
+            idx += 1;
+        } else if op & 0xc0 == 0x40 {
+            // XZERO
+            let next = *body.get(idx + 1)?;
+            let len = ((((op & 0x3f) as usize) << 8) | next as usize) + 1;
+            registers.resize(registers.len() + len, 0);
+            idx += 2;
+        } else {
+            // VAL
+            let value = ((op >> 2) & 0x1f) + 1;
+            let len = (op & 0x03) as usize + 1;
+            registers.resize(registers.len() + len, value);
+            idx += 1;
+        }
+        if registers.len() > HLL_REGISTERS {
+            return None;
+        }
+    }
+    if registers.len() != HLL_REGISTERS {
+        return None;
+    }
+    Some(registers)
+}
+
+// 有寄存器超过sparse能表示的最大值，或者编码太长的时候返回None
+fn sparse_encode(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    let mut i = 0;
+    while i < registers.len() {
+        let value = registers[i];
+        if value > HLL_SPARSE_VAL_MAX_VALUE {
+            return None;
+        }
+        let mut run = registers[i..].iter().take_while(|v| **v == value).count();
+        i += run;
+        while run > 0 {
+            if value == 0 && run > HLL_SPARSE_ZERO_MAX_LEN {
+                let len = run.min(HLL_SPARSE_XZERO_MAX_LEN);
+                out.push(0x40 | ((len - 1) >> 8) as u8);
+                out.push(((len - 1) & 0xff) as u8);
+                run -= len;
+            } else if value == 0 {
+                out.push((run - 1) as u8);
+                run = 0;
+            } else {
+                let len = run.min(HLL_SPARSE_VAL_MAX_LEN);
+                out.push(0x80 | ((value - 1) << 2) | (len - 1) as u8);
+                run -= len;
+            }
+        }
+        // 和redis一样，长度包括头部
+        if HLL_HDR_SIZE + out.len() > HLL_SPARSE_MAX_BYTES {
+            return None;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(n: usize) -> HyperLogLog {
+        let mut hll = HyperLogLog::new();
+        for i in 0..n {
+            hll.add(format!("element:{}", i).as_bytes());
+        }
+        hll
+    }
+
+    #[test]
+    fn count_is_close() {
+        assert_eq!(HyperLogLog::new().count(), 0);
+        for &n in &[10usize, 1000, 100_000] {
+            let count = filled(n).count() as f64;
+            assert!((count - n as f64).abs() / (n as f64) < 0.02, "n={} count={}", n, count);
+        }
+    }
+
+    #[test]
+    fn sparse_then_dense() {
+        let mut small = filled(100);
+        let bytes = small.encode();
+        assert!(!small.is_dense());
+        assert!(bytes.len() < HLL_DENSE_SIZE);
+        let decoded = HyperLogLog::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.count(), small.count());
+
+        // 寄存器太多的时候sparse放不下
+        let mut large = filled(20_000);
+        let bytes = large.encode();
+        assert!(large.is_dense());
+        assert_eq!(bytes.len(), HLL_DENSE_SIZE);
+        assert_eq!(HyperLogLog::from_bytes(&bytes).unwrap().count(), large.count());
+    }
+
+    #[test]
+    fn merge_and_cache() {
+        let mut a = filled(500);
+        let mut b = HyperLogLog::new();
+        for i in 250..750 {
+            b.add(format!("element:{}", i).as_bytes());
+        }
+        a.merge(&b);
+        assert_eq!(a.count(), filled(750).count());
+
+        assert_eq!(a.cached_count(), Some(0));
+        a.invalidate_cache();
+        assert_eq!(a.cached_count(), None);
+        a.set_cached_count(42);
+        let bytes = a.encode();
+        assert_eq!(HyperLogLog::from_bytes(&bytes).unwrap().cached_count(), Some(42));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(HyperLogLog::from_bytes(b"not a hll").is_err());
+        let mut bytes = filled(10).encode();
+        bytes[4] = 7;
+        assert!(HyperLogLog::from_bytes(&bytes).is_err());
+        let mut dense = HyperLogLog::new();
+        dense.set_dense();
+        let bytes = dense.encode();
+        assert!(HyperLogLog::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
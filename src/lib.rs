@@ -12,6 +12,43 @@ pub mod cmd;
 
 pub mod parse;
 
+mod util;
+
+mod skiplist;
+
+mod zset;
+
+mod stream;
+
+mod bitmap;
+
+mod hyperloglog;
+
+mod geohash;
+
+mod pubsub;
+
+mod notify;
+
+pub mod events;
+
+pub mod config;
+
+mod scripting;
+
+mod functions;
+
+pub mod module;
+
+mod client;
+
+pub mod stats;
+
+// 外部注册命令和数据类型需要用到的类型
+pub use frame::Frame;
+
+pub use parse::{Parse, ParseError};
+
 // 默认端口
 pub const DEFAULT_PORT: &str = "6379";
 
@@ -0,0 +1,346 @@
+use crate::cmd::{command_name, error_frame, lookup_command, Command, ModuleCommand};
+use crate::db::{State, Value, WRONGTYPE};
+use crate::frame::Frame;
+use crate::parse::Parse;
+use bytes::{Bytes, BytesMut};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+// flag和key的位置内置命令也在用，定义在命令表里
+pub use crate::cmd::{
+    KeySpec, CMD_ADMIN, CMD_ALLOW_BUSY, CMD_BLOCKING, CMD_DENYOOM, CMD_FAST, CMD_LOADING, CMD_NOSCRIPT, CMD_NO_MULTI,
+    CMD_PUBSUB, CMD_READONLY, CMD_STALE, CMD_WRITE,
+};
+
+// 外部crate实现这个trait来增加命令，命令在锁住的State上执行，和内置命令一样是原子的
+pub trait CommandHandler: Send + Sync + 'static {
+    // 命令名，不区分大小写
+    fn name(&self) -> &str;
+
+    // 参数个数，包括命令名，负数表示至少这么多个
+    fn arity(&self) -> i32;
+
+    fn flags(&self) -> u32 {
+        0
+    }
+
+    fn key_spec(&self) -> KeySpec {
+        KeySpec::default()
+    }
+
+    // parse里面已经去掉了命令名，回复写到reply里面，返回的错误也会作为回复
+    fn execute(&self, parse: &mut Parse, ctx: &mut Context<'_>, reply: &mut Reply) -> crate::Result<()>;
+}
+
+// 命令执行时候的上下文，可以调用内置命令
+pub struct Context<'a> {
+    state: &'a mut State,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(state: &'a mut State) -> Context<'a> {
+        Context { state }
+    }
+
+    // 执行一个命令，和脚本里面的redis.call一样，错误作为错误回复返回
+    pub fn call(&mut self, args: &[&[u8]]) -> Frame {
+        let frame = Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg))).collect());
+        let res = self.state.registry().parse_command(frame).and_then(|cmd| {
+            if !cmd.is_script_allowed() {
+                return Err("ERR This Redis command is not allowed from module".into());
+            }
+            cmd.execute(self.state)
+        });
+        res.unwrap_or_else(error_frame)
+    }
+
+    // 修改了key之后调用，WATCH和key事件依赖这个
+    pub fn signal_modified_key(&mut self, key: &str) {
+        self.state.signal_modified_key(key);
+    }
+
+    pub fn notify_keyspace_event(&mut self, class: u32, event: &str, key: &str) {
+        self.state.notify_keyspace_event(class, event, key);
+    }
+
+    // 读取自定义类型的值，key是其他类型的时候返回WRONGTYPE
+    pub fn get_value<T: DataType>(&mut self, key: &str) -> crate::Result<Option<&T::Value>> {
+        match self.state.lookup_read(key) {
+            Some(Value::Module(value)) => value.downcast_ref::<T>().map(Some).ok_or_else(|| WRONGTYPE.into()),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    // 原地修改之后需要调用signal_modified_key
+    pub fn get_value_mut<T: DataType>(&mut self, key: &str) -> crate::Result<Option<&mut T::Value>> {
+        match self.state.get_mut(key) {
+            Some(Value::Module(value)) => value.downcast_mut::<T>().map(Some).ok_or_else(|| WRONGTYPE.into()),
+            Some(_) => Err(WRONGTYPE.into()),
+            None => Ok(None),
+        }
+    }
+
+    // 覆盖写入，类型需要先通过 Db::register_type 注册
+    pub fn set_value<T: DataType>(&mut self, key: &str, value: T::Value) -> crate::Result<()> {
+        let ty = self.state.registry().data_type(TypeId::of::<T>()).ok_or("ERR module data type is not registered")?;
+        let value = ModuleValue { ty, value: Box::new(value) };
+        self.state.set(key.to_string(), Value::Module(value), None);
+        Ok(())
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        self.state.remove(key).is_some()
+    }
+}
+
+// 命令的回复，只能回复一次，后面的会覆盖前面的
+#[derive(Debug, Default)]
+pub struct Reply {
+    frame: Option<Frame>,
+}
+
+impl Reply {
+    pub fn frame(&mut self, frame: Frame) {
+        self.frame = Some(frame);
+    }
+
+    pub fn simple(&mut self, msg: impl ToString) {
+        self.frame(Frame::Simple(msg.to_string()));
+    }
+
+    pub fn ok(&mut self) {
+        self.simple("OK");
+    }
+
+    pub fn error(&mut self, msg: impl ToString) {
+        self.frame(Frame::Error(msg.to_string()));
+    }
+
+    pub fn integer(&mut self, n: i64) {
+        self.frame(Frame::Integer(n));
+    }
+
+    pub fn bulk(&mut self, data: impl Into<Bytes>) {
+        self.frame(Frame::Bulk(data.into()));
+    }
+
+    pub fn null(&mut self) {
+        self.frame(Frame::Null);
+    }
+
+    pub fn array(&mut self, items: Vec<Frame>) {
+        self.frame(Frame::Array(items));
+    }
+
+    // 没有回复的时候返回Null
+    pub(crate) fn into_frame(self) -> Frame {
+        self.frame.unwrap_or(Frame::Null)
+    }
+}
+
+// 模块自定义的数据类型，值保存在Db里面，和内置类型一样可以过期、删除和WATCH
+pub trait DataType: Send + Sync + 'static {
+    type Value: Send + Sync + 'static;
+
+    // TYPE 命令返回的名字，和redis一样必须是9个字符
+    fn name(&self) -> &str;
+
+    // 序列化格式的版本，加载的时候会传回给rdb_load
+    fn encoding_version(&self) -> u32 {
+        0
+    }
+
+    fn rdb_save(&self, value: &Self::Value, buf: &mut BytesMut);
+
+    fn rdb_load(&self, buf: &mut Bytes, encoding_version: u32) -> crate::Result<Self::Value>;
+
+    // AOF重写的时候生成能重建这个值的命令
+    fn aof_rewrite(&self, key: &str, value: &Self::Value) -> Vec<Vec<Bytes>>;
+
+    // MEMORY USAGE 使用，不包括key本身
+    fn mem_usage(&self, _value: &Self::Value) -> usize {
+        std::mem::size_of::<Self::Value>()
+    }
+}
+
+// 持久化时候保存的内容，加载的时候按类型名找回对应的DataType
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedValue {
+    pub type_name: String,
+    pub encoding_version: u32,
+    pub payload: Bytes,
+}
+
+// 去掉了关联类型的DataType，值用Any保存
+trait ErasedType: Send + Sync {
+    fn name(&self) -> &str;
+    fn encoding_version(&self) -> u32;
+    fn rdb_save(&self, value: &dyn Any, buf: &mut BytesMut);
+    fn rdb_load(&self, buf: &mut Bytes, encoding_version: u32) -> crate::Result<Box<dyn Any + Send + Sync>>;
+    fn aof_rewrite(&self, key: &str, value: &dyn Any) -> Vec<Vec<Bytes>>;
+    fn mem_usage(&self, value: &dyn Any) -> usize;
+}
+
+struct TypeAdapter<T>(T);
+
+impl<T: DataType> TypeAdapter<T> {
+    // 值只会由同一个DataType创建，类型一定是对的
+    fn value<'a>(&self, value: &'a dyn Any) -> &'a T::Value {
+        value.downcast_ref().expect("module value type mismatch")
+    }
+}
+
+impl<T: DataType> ErasedType for TypeAdapter<T> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn encoding_version(&self) -> u32 {
+        self.0.encoding_version()
+    }
+
+    fn rdb_save(&self, value: &dyn Any, buf: &mut BytesMut) {
+        self.0.rdb_save(self.value(value), buf)
+    }
+
+    fn rdb_load(&self, buf: &mut Bytes, encoding_version: u32) -> crate::Result<Box<dyn Any + Send + Sync>> {
+        Ok(Box::new(self.0.rdb_load(buf, encoding_version)?))
+    }
+
+    fn aof_rewrite(&self, key: &str, value: &dyn Any) -> Vec<Vec<Bytes>> {
+        self.0.aof_rewrite(key, self.value(value))
+    }
+
+    fn mem_usage(&self, value: &dyn Any) -> usize {
+        self.0.mem_usage(self.value(value))
+    }
+}
+
+// Db里面保存的自定义类型的值
+pub(crate) struct ModuleValue {
+    ty: Arc<dyn ErasedType>,
+    value: Box<dyn Any + Send + Sync>,
+}
+
+impl fmt::Debug for ModuleValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModuleValue").field("type", &self.ty.name()).finish()
+    }
+}
+
+impl ModuleValue {
+    pub(crate) fn type_name(&self) -> &str {
+        self.ty.name()
+    }
+
+    pub(crate) fn mem_usage(&self) -> usize {
+        self.ty.mem_usage(self.value.as_ref())
+    }
+
+    fn downcast_ref<T: DataType>(&self) -> Option<&T::Value> {
+        self.value.downcast_ref()
+    }
+
+    fn downcast_mut<T: DataType>(&mut self) -> Option<&mut T::Value> {
+        self.value.downcast_mut()
+    }
+
+    pub(crate) fn save(&self) -> SavedValue {
+        let mut buf = BytesMut::new();
+        self.ty.rdb_save(self.value.as_ref(), &mut buf);
+        SavedValue {
+            type_name: self.ty.name().to_string(),
+            encoding_version: self.ty.encoding_version(),
+            payload: buf.freeze(),
+        }
+    }
+
+    pub(crate) fn rewrite(&self, key: &str) -> Vec<Vec<Bytes>> {
+        self.ty.aof_rewrite(key, self.value.as_ref())
+    }
+}
+
+// 注册的命令，Db和State共用一份，解析命令的时候先查这里
+#[derive(Clone, Default)]
+pub(crate) struct Registry {
+    commands: Arc<RwLock<HashMap<String, Arc<dyn CommandHandler>>>>,
+    // 按DataType的TypeId索引，加载的时候按名字查找
+    types: Arc<RwLock<HashMap<TypeId, Arc<dyn ErasedType>>>>,
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let commands = self.commands.read().unwrap();
+        f.debug_set().entries(commands.keys()).finish()
+    }
+}
+
+impl Registry {
+    pub(crate) fn register(&self, handler: impl CommandHandler) -> crate::Result<()> {
+        let name = handler.name().to_lowercase();
+        if name.is_empty() || handler.arity() == 0 {
+            return Err("invalid command name or arity".into());
+        }
+        let mut commands = self.commands.write().unwrap();
+        if lookup_command(&name).is_some() || commands.contains_key(&name) {
+            return Err(format!("command '{}' already exists", name).into());
+        }
+        commands.insert(name, Arc::new(handler));
+        Ok(())
+    }
+
+    pub(crate) fn command(&self, name: &str) -> Option<Arc<dyn CommandHandler>> {
+        self.commands.read().unwrap().get(&name.to_lowercase()).cloned()
+    }
+
+    // 按名字排好序，COMMAND 输出的时候用
+    pub(crate) fn commands(&self) -> Vec<Arc<dyn CommandHandler>> {
+        let commands = self.commands.read().unwrap();
+        let mut handlers: Vec<_> = commands.values().cloned().collect();
+        handlers.sort_by_key(|handler| handler.name().to_lowercase());
+        handlers
+    }
+
+    pub(crate) fn register_type<T: DataType>(&self, ty: T) -> crate::Result<()> {
+        let name = ty.name();
+        if name.len() != 9 || !name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_') {
+            return Err(format!("invalid data type name '{}'", name).into());
+        }
+        let mut types = self.types.write().unwrap();
+        if types.contains_key(&TypeId::of::<T>()) || types.values().any(|registered| registered.name() == name) {
+            return Err(format!("data type '{}' already exists", name).into());
+        }
+        types.insert(TypeId::of::<T>(), Arc::new(TypeAdapter(ty)));
+        Ok(())
+    }
+
+    fn data_type(&self, id: TypeId) -> Option<Arc<dyn ErasedType>> {
+        self.types.read().unwrap().get(&id).cloned()
+    }
+
+    // 按保存时候的类型名找回DataType，反序列化出值
+    pub(crate) fn load_value(&self, saved: &SavedValue) -> crate::Result<ModuleValue> {
+        let ty = self
+            .types
+            .read()
+            .unwrap()
+            .values()
+            .find(|ty| ty.name() == saved.type_name)
+            .cloned()
+            .ok_or_else(|| format!("data type '{}' is not registered", saved.type_name))?;
+        let value = ty.rdb_load(&mut saved.payload.clone(), saved.encoding_version)?;
+        Ok(ModuleValue { ty, value })
+    }
+
+    // 先查注册的命令，没有的话按内置命令解析
+    pub(crate) fn parse_command(&self, frame: Frame) -> crate::Result<Command> {
+        let handler = command_name(&frame).and_then(|name| self.commands.read().unwrap().get(&name).cloned());
+        match handler {
+            Some(handler) => Ok(Command::Module(ModuleCommand::new(handler, frame)?)),
+            None => Command::from_frame(frame),
+        }
+    }
+}
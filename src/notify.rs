@@ -0,0 +1,106 @@
+// keyspace通知的事件类型，和redis的notify-keyspace-events一一对应
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0; // K
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1; // E
+pub const NOTIFY_GENERIC: u32 = 1 << 2; // g
+pub const NOTIFY_STRING: u32 = 1 << 3; // $
+pub const NOTIFY_LIST: u32 = 1 << 4; // l
+pub const NOTIFY_SET: u32 = 1 << 5; // s
+pub const NOTIFY_HASH: u32 = 1 << 6; // h
+pub const NOTIFY_ZSET: u32 = 1 << 7; // z
+pub const NOTIFY_EXPIRED: u32 = 1 << 8; // x
+pub const NOTIFY_EVICTED: u32 = 1 << 9; // e
+pub const NOTIFY_STREAM: u32 = 1 << 10; // t
+pub const NOTIFY_KEY_MISS: u32 = 1 << 11; // m
+pub const NOTIFY_MODULE: u32 = 1 << 12; // d
+pub const NOTIFY_NEW: u32 = 1 << 13; // n
+
+// A 是 g$lshzxetd 的别名，不包括 m 和 n
+pub const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM
+    | NOTIFY_MODULE;
+
+const CLASSES: &[(char, u32)] = &[
+    ('g', NOTIFY_GENERIC),
+    ('$', NOTIFY_STRING),
+    ('l', NOTIFY_LIST),
+    ('s', NOTIFY_SET),
+    ('h', NOTIFY_HASH),
+    ('z', NOTIFY_ZSET),
+    ('x', NOTIFY_EXPIRED),
+    ('e', NOTIFY_EVICTED),
+    ('t', NOTIFY_STREAM),
+    ('m', NOTIFY_KEY_MISS),
+    ('d', NOTIFY_MODULE),
+    ('n', NOTIFY_NEW),
+];
+
+// 解析配置里面的字符串，有不认识的字符的时候返回None
+pub fn parse_flags(classes: &str) -> Option<u32> {
+    let mut flags = 0;
+    for c in classes.chars() {
+        flags |= match c {
+            'A' => NOTIFY_ALL,
+            'K' => NOTIFY_KEYSPACE,
+            'E' => NOTIFY_KEYEVENT,
+            _ => CLASSES.iter().find(|(class, _)| *class == c)?.1,
+        };
+    }
+    Some(flags)
+}
+
+// parse_flags 的反过程，CONFIG GET 的时候用
+pub fn flags_to_string(flags: u32) -> String {
+    let mut out = String::new();
+    if flags & NOTIFY_ALL == NOTIFY_ALL {
+        out.push('A');
+    } else {
+        for (c, flag) in CLASSES {
+            if flag & NOTIFY_ALL != 0 && flags & flag != 0 {
+                out.push(*c);
+            }
+        }
+    }
+    if flags & NOTIFY_KEYSPACE != 0 {
+        out.push('K');
+    }
+    if flags & NOTIFY_KEYEVENT != 0 {
+        out.push('E');
+    }
+    for (c, flag) in CLASSES {
+        if flag & NOTIFY_ALL == 0 && flags & flag != 0 {
+            out.push(*c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_classes() {
+        assert_eq!(parse_flags(""), Some(0));
+        assert_eq!(parse_flags("Kz"), Some(NOTIFY_KEYSPACE | NOTIFY_ZSET));
+        assert_eq!(parse_flags("EA"), Some(NOTIFY_KEYEVENT | NOTIFY_ALL));
+        assert_eq!(parse_flags("An").map(|flags| flags & NOTIFY_KEY_MISS), Some(0));
+        assert_eq!(parse_flags("Kq"), None);
+    }
+
+    #[test]
+    fn format_like_redis() {
+        assert_eq!(flags_to_string(0), "");
+        assert_eq!(flags_to_string(parse_flags("KEA").unwrap()), "AKE");
+        assert_eq!(flags_to_string(parse_flags("Ez$g").unwrap()), "g$zE");
+        // 不在A里面的m和n放在最后
+        assert_eq!(flags_to_string(parse_flags("nmKA").unwrap()), "AKmn");
+        assert_eq!(flags_to_string(parse_flags("Kgnlszhxetd$").unwrap()), "AKn");
+    }
+}
@@ -1,14 +1,15 @@
 use crate::frame::Frame;
 use std::vec::IntoIter;
+use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
-use bytes::{Bytes, Buf};
+use bytes::Bytes;
 
-pub(crate) struct Parse {
+pub struct Parse {
     parts: IntoIter<Frame>,
 }
 
 #[derive(Debug)]
-pub(crate) enum ParseError {
+pub enum ParseError {
     EndOfStream,
     Other(crate::Error),
 }
@@ -28,6 +29,8 @@ impl Parse {
         }
     }
 
+    // 返回的是Result，不适合实现Iterator
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Frame, ParseError> {
         self.parts.next().ok_or_else(|| ParseError::EndOfStream)
     }
@@ -65,7 +68,7 @@ impl Parse {
 
         match self.next()? {
             Frame::Simple(s) => atoi::<u64>(s.as_bytes()).ok_or_else(|| MSG.into()),
-            Frame::Integer(i) => Ok(i),
+            Frame::Integer(i) => u64::try_from(i).map_err(|_| MSG.into()),
             Frame::Bulk(b) => {
                 atoi::<u64>(&b).ok_or_else(|| MSG.into())
             }
@@ -73,6 +76,15 @@ impl Parse {
         }
     }
 
+    // 把剩下的参数全部读出来
+    pub fn rest_bytes(&mut self) -> Result<Vec<Bytes>, ParseError> {
+        let mut rest = Vec::with_capacity(self.parts.len());
+        while self.parts.len() > 0 {
+            rest.push(self.next_bytes()?);
+        }
+        Ok(rest)
+    }
+
     pub fn finish(&mut self) -> Result<(), ParseError> {
         if self.parts.next().is_none() {
             Ok(())
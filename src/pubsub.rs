@@ -0,0 +1,486 @@
+use crate::db::Db;
+use crate::frame::Frame;
+use crate::util::{string_match, key_hash_slot};
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// 频道到订阅者的映射，放在State里面，和命令一样在锁里面操作
+#[derive(Debug, Default)]
+pub struct PubSub {
+    channels: HashMap<Bytes, Topic>,
+    patterns: HashMap<Bytes, Topic>,
+    // 模式按第一个通配符之前的固定前缀分组，发布的时候只需要检查频道名的每个前缀
+    pattern_index: HashMap<Bytes, HashSet<Bytes>>,
+    // 分片频道是单独的命名空间，按hash slot分组
+    shard_channels: HashMap<Bytes, Topic>,
+    slots: HashMap<u16, HashSet<Bytes>>,
+}
+
+// 每个订阅者最多积压的字节数和消息条数，和配置无关，
+// client-output-buffer-limit 设成0或者很大的时候也不会无限增长
+pub(crate) const MAILBOX_MAX_BYTES: usize = 64 * 1024 * 1024;
+pub(crate) const MAILBOX_MAX_LEN: usize = 1024 * 1024;
+
+// 一个订阅连接还没推送的消息，发布的时候直接放进来，和redis的输出缓冲区一样不会丢，
+// 积压太多的时候由连接按 client-output-buffer-limit 断开，超过硬上限的时候连接也会被断开
+#[derive(Debug, Default)]
+pub(crate) struct Mailbox {
+    queue: Mutex<VecDeque<Frame>>,
+    backlog: Arc<Backlog>,
+    notify: Notify,
+}
+
+// 积压的情况，连接写数据卡住的时候也会实时检查
+#[derive(Debug, Default)]
+pub(crate) struct Backlog {
+    // 队列里面的消息编码之后的总字节数
+    pub(crate) bytes: AtomicUsize,
+    // 超过了硬上限，之后的消息都不再放进来，连接发现之后断开
+    pub(crate) overflowed: AtomicBool,
+}
+
+impl Mailbox {
+    fn push(&self, frame: Frame) {
+        let len = frame.encoded_len();
+        let backlog = &self.backlog;
+        let mut queue = self.queue.lock().unwrap();
+        if backlog.overflowed.load(Ordering::Relaxed) {
+            return;
+        }
+        if queue.len() >= MAILBOX_MAX_LEN || backlog.bytes.load(Ordering::Relaxed) + len > MAILBOX_MAX_BYTES {
+            // 连接反正要断开，积压的消息直接释放
+            backlog.overflowed.store(true, Ordering::Relaxed);
+            queue.clear();
+            backlog.bytes.store(0, Ordering::Relaxed);
+        } else {
+            backlog.bytes.fetch_add(len, Ordering::Relaxed);
+            queue.push_back(frame);
+        }
+        drop(queue);
+        // notify_one 会保留许可，连接还没开始等待也不会丢
+        self.notify.notify_one();
+    }
+
+    fn pop(&self) -> Option<Frame> {
+        let frame = self.queue.lock().unwrap().pop_front()?;
+        self.backlog.bytes.fetch_sub(frame.encoded_len(), Ordering::Relaxed);
+        Some(frame)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Topic {
+    subscribers: Vec<Arc<Mailbox>>,
+}
+
+impl Topic {
+    fn send(&self, frame: &Frame) -> usize {
+        for mailbox in &self.subscribers {
+            mailbox.push(frame.clone());
+        }
+        self.subscribers.len()
+    }
+}
+
+fn add_subscriber(topics: &mut HashMap<Bytes, Topic>, name: &Bytes, mailbox: &Arc<Mailbox>) {
+    topics.entry(name.clone()).or_default().subscribers.push(mailbox.clone());
+}
+
+// 返回是否已经没有订阅者，没有的时候直接删掉
+fn remove_subscriber(topics: &mut HashMap<Bytes, Topic>, name: &[u8], mailbox: &Arc<Mailbox>) -> bool {
+    if let Some(topic) = topics.get_mut(name) {
+        topic.subscribers.retain(|subscriber| !Arc::ptr_eq(subscriber, mailbox));
+        if topic.subscribers.is_empty() {
+            topics.remove(name);
+            return true;
+        }
+    }
+    false
+}
+
+// 第一个特殊字符之前的部分，所有能匹配的频道都以它开头
+fn literal_prefix(pattern: &Bytes) -> Bytes {
+    let end = pattern
+        .iter()
+        .position(|c| matches!(c, b'*' | b'?' | b'[' | b'\\'))
+        .unwrap_or(pattern.len());
+    pattern.slice(..end)
+}
+
+impl PubSub {
+    pub(crate) fn subscribe(&mut self, channel: &Bytes, mailbox: &Arc<Mailbox>) {
+        add_subscriber(&mut self.channels, channel, mailbox);
+    }
+
+    pub(crate) fn unsubscribe(&mut self, channel: &[u8], mailbox: &Arc<Mailbox>) {
+        remove_subscriber(&mut self.channels, channel, mailbox);
+    }
+
+    pub(crate) fn psubscribe(&mut self, pattern: &Bytes, mailbox: &Arc<Mailbox>) {
+        if !self.patterns.contains_key(pattern) {
+            self.pattern_index.entry(literal_prefix(pattern)).or_default().insert(pattern.clone());
+        }
+        add_subscriber(&mut self.patterns, pattern, mailbox);
+    }
+
+    pub(crate) fn punsubscribe(&mut self, pattern: &Bytes, mailbox: &Arc<Mailbox>) {
+        if remove_subscriber(&mut self.patterns, pattern, mailbox) {
+            let prefix = literal_prefix(pattern);
+            if let Some(set) = self.pattern_index.get_mut(&prefix) {
+                set.remove(pattern);
+                if set.is_empty() {
+                    self.pattern_index.remove(&prefix);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn ssubscribe(&mut self, channel: &Bytes, mailbox: &Arc<Mailbox>) {
+        if !self.shard_channels.contains_key(channel) {
+            self.slots.entry(key_hash_slot(channel)).or_default().insert(channel.clone());
+        }
+        add_subscriber(&mut self.shard_channels, channel, mailbox);
+    }
+
+    pub(crate) fn sunsubscribe(&mut self, channel: &Bytes, mailbox: &Arc<Mailbox>) {
+        if remove_subscriber(&mut self.shard_channels, channel, mailbox) {
+            let slot = key_hash_slot(channel);
+            if let Some(set) = self.slots.get_mut(&slot) {
+                set.remove(channel);
+                if set.is_empty() {
+                    self.slots.remove(&slot);
+                }
+            }
+        }
+    }
+
+    // 返回收到消息的订阅者数量，同时订阅了频道和模式的连接会算多次；
+    // 在锁里面依次放进每个订阅者的队列，所以每个订阅者看到的顺序就是发布的顺序
+    pub fn publish(&mut self, channel: &Bytes, payload: &Bytes) -> usize {
+        let mut receivers = 0;
+        if let Some(topic) = self.channels.get(channel) {
+            let frame = Frame::Array(vec![
+                Frame::Bulk(Bytes::from_static(b"message")),
+                Frame::Bulk(channel.clone()),
+                Frame::Bulk(payload.clone()),
+            ]);
+            receivers += topic.send(&frame);
+        }
+        for len in 0..=channel.len() {
+            let patterns = match self.pattern_index.get(&channel[..len]) {
+                Some(patterns) => patterns,
+                None => continue,
+            };
+            for pattern in patterns {
+                if string_match(pattern, channel, false) {
+                    let frame = Frame::Array(vec![
+                        Frame::Bulk(Bytes::from_static(b"pmessage")),
+                        Frame::Bulk(pattern.clone()),
+                        Frame::Bulk(channel.clone()),
+                        Frame::Bulk(payload.clone()),
+                    ]);
+                    receivers += self.patterns[pattern].send(&frame);
+                }
+            }
+        }
+        receivers
+    }
+
+    // 分片频道不会匹配模式订阅
+    pub fn spublish(&mut self, channel: &Bytes, payload: &Bytes) -> usize {
+        let frame = Frame::Array(vec![
+            Frame::Bulk(Bytes::from_static(b"smessage")),
+            Frame::Bulk(channel.clone()),
+            Frame::Bulk(payload.clone()),
+        ]);
+        self.shard_channels.get(channel).map_or(0, |topic| topic.send(&frame))
+    }
+
+    // 有订阅者的频道，pattern为None的时候返回全部
+    pub fn channels(&self, pattern: Option<&[u8]>) -> Vec<Bytes> {
+        self.channels
+            .keys()
+            .filter(|channel| pattern.is_none_or(|p| string_match(p, channel, false)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn numsub(&self, channel: &[u8]) -> usize {
+        self.channels.get(channel).map_or(0, |topic| topic.subscribers.len())
+    }
+
+    pub fn shard_channels(&self, pattern: Option<&[u8]>) -> Vec<Bytes> {
+        self.shard_channels
+            .keys()
+            .filter(|channel| pattern.is_none_or(|p| string_match(p, channel, false)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn shard_numsub(&self, channel: &[u8]) -> usize {
+        self.shard_channels.get(channel).map_or(0, |topic| topic.subscribers.len())
+    }
+
+    pub fn numpat(&self) -> usize {
+        self.patterns.len()
+    }
+}
+
+// 连接这一侧的订阅状态
+#[derive(Debug)]
+pub struct Subscriber {
+    channels: HashSet<Bytes>,
+    patterns: HashSet<Bytes>,
+    shard_channels: HashSet<Bytes>,
+    // 已经发布但是还没有推送给客户端的消息
+    mailbox: Arc<Mailbox>,
+}
+
+impl Subscriber {
+    pub fn new() -> Subscriber {
+        Subscriber {
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            shard_channels: HashSet::new(),
+            mailbox: Arc::new(Mailbox::default()),
+        }
+    }
+
+    // 订阅数量不为0的时候连接处于订阅模式
+    pub fn is_active(&self) -> bool {
+        self.count() + self.shard_count() > 0
+    }
+
+    // 频道和模式的订阅总数，不包括分片频道
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shard_channels.len()
+    }
+
+    // 还没有推送给客户端的消息条数和字节数
+    pub fn pending(&self) -> (usize, usize) {
+        (self.mailbox.queue.lock().unwrap().len(), self.mailbox.backlog.bytes.load(Ordering::Relaxed))
+    }
+
+    // 实时的积压情况，连接写数据卡住的时候用它检查输出缓冲区限制
+    pub(crate) fn backlog(&self) -> Arc<Backlog> {
+        self.mailbox.backlog.clone()
+    }
+
+    pub fn channels(&self) -> Vec<Bytes> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn patterns(&self) -> Vec<Bytes> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    pub fn shard_channels(&self) -> Vec<Bytes> {
+        self.shard_channels.iter().cloned().collect()
+    }
+
+    // 已经订阅过的时候什么都不做
+    pub async fn subscribe(&mut self, db: &Db, channel: Bytes) {
+        if self.channels.insert(channel.clone()) {
+            db.lock_async().await.pub_sub().subscribe(&channel, &self.mailbox);
+        }
+    }
+
+    pub async fn unsubscribe(&mut self, db: &Db, channel: &[u8]) {
+        if self.channels.remove(channel) {
+            db.lock_async().await.pub_sub().unsubscribe(channel, &self.mailbox);
+        }
+    }
+
+    pub async fn psubscribe(&mut self, db: &Db, pattern: Bytes) {
+        if self.patterns.insert(pattern.clone()) {
+            db.lock_async().await.pub_sub().psubscribe(&pattern, &self.mailbox);
+        }
+    }
+
+    pub async fn punsubscribe(&mut self, db: &Db, pattern: &Bytes) {
+        if self.patterns.remove(pattern) {
+            db.lock_async().await.pub_sub().punsubscribe(pattern, &self.mailbox);
+        }
+    }
+
+    pub async fn ssubscribe(&mut self, db: &Db, channel: Bytes) {
+        if self.shard_channels.insert(channel.clone()) {
+            db.lock_async().await.pub_sub().ssubscribe(&channel, &self.mailbox);
+        }
+    }
+
+    pub async fn sunsubscribe(&mut self, db: &Db, channel: &Bytes) {
+        if self.shard_channels.remove(channel) {
+            db.lock_async().await.pub_sub().sunsubscribe(channel, &self.mailbox);
+        }
+    }
+
+    pub async fn unsubscribe_all(&mut self, db: &Db) {
+        if self.is_active() {
+            self.detach(db.lock_async().await.pub_sub());
+        }
+    }
+
+    // 一次退订所有频道、模式和分片频道，连接断开的时候在锁里面调用
+    pub(crate) fn detach(&mut self, pub_sub: &mut PubSub) {
+        for channel in self.channels.drain() {
+            pub_sub.unsubscribe(&channel, &self.mailbox);
+        }
+        for pattern in self.patterns.drain() {
+            pub_sub.punsubscribe(&pattern, &self.mailbox);
+        }
+        for channel in self.shard_channels.drain() {
+            pub_sub.sunsubscribe(&channel, &self.mailbox);
+        }
+    }
+
+    // 等待下一条要推送给客户端的消息，没有订阅的时候会一直等待；
+    // 积压超过硬上限的时候返回None，连接应该断开
+    pub async fn recv(&mut self) -> Option<Frame> {
+        loop {
+            if self.mailbox.backlog.overflowed.load(Ordering::Relaxed) {
+                return None;
+            }
+            if let Some(frame) = self.mailbox.pop() {
+                return Some(frame);
+            }
+            self.mailbox.notify.notified().await;
+        }
+    }
+}
+
+impl Default for Subscriber {
+    fn default() -> Self {
+        Subscriber::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payloads(mailbox: &Mailbox) -> Vec<Frame> {
+        std::iter::from_fn(|| mailbox.pop()).collect()
+    }
+
+    #[test]
+    fn slow_subscriber_keeps_every_message() {
+        let mut pub_sub = PubSub::default();
+        let mailbox = Arc::new(Mailbox::default());
+        pub_sub.subscribe(&Bytes::from_static(b"ch"), &mailbox);
+        for i in 0..5000 {
+            assert_eq!(pub_sub.publish(&Bytes::from_static(b"ch"), &Bytes::from(i.to_string())), 1);
+        }
+        let frames = payloads(&mailbox);
+        assert_eq!(frames.len(), 5000);
+        assert_eq!(mailbox.backlog.bytes.load(Ordering::Relaxed), 0);
+        match &frames[4999] {
+            Frame::Array(items) => assert_eq!(items[2], Frame::Bulk(Bytes::from_static(b"4999"))),
+            frame => panic!("unexpected frame {:?}", frame),
+        }
+    }
+
+    #[test]
+    fn mailbox_overflows_at_hard_limit() {
+        let mut pub_sub = PubSub::default();
+        let mailbox = Arc::new(Mailbox::default());
+        pub_sub.subscribe(&Bytes::from_static(b"ch"), &mailbox);
+        let payload = Bytes::from(vec![b'x'; 1024 * 1024]);
+        for _ in 0..MAILBOX_MAX_BYTES / payload.len() - 1 {
+            pub_sub.publish(&Bytes::from_static(b"ch"), &payload);
+        }
+        assert!(!mailbox.backlog.overflowed.load(Ordering::Relaxed));
+        pub_sub.publish(&Bytes::from_static(b"ch"), &payload);
+        assert!(mailbox.backlog.overflowed.load(Ordering::Relaxed));
+        assert_eq!(mailbox.backlog.bytes.load(Ordering::Relaxed), 0);
+        assert!(mailbox.pop().is_none());
+    }
+
+    #[test]
+    fn pending_bytes_track_queue() {
+        let mut pub_sub = PubSub::default();
+        let mailbox = Arc::new(Mailbox::default());
+        pub_sub.subscribe(&Bytes::from_static(b"ch"), &mailbox);
+        pub_sub.publish(&Bytes::from_static(b"ch"), &Bytes::from_static(b"hello"));
+        let len = mailbox.queue.lock().unwrap()[0].encoded_len();
+        assert_eq!(mailbox.backlog.bytes.load(Ordering::Relaxed), len);
+        mailbox.pop();
+        assert_eq!(mailbox.backlog.bytes.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn channel_before_pattern_in_publish_order() {
+        let mut pub_sub = PubSub::default();
+        let mailbox = Arc::new(Mailbox::default());
+        pub_sub.subscribe(&Bytes::from_static(b"news.a"), &mailbox);
+        pub_sub.psubscribe(&Bytes::from_static(b"news.*"), &mailbox);
+        assert_eq!(pub_sub.publish(&Bytes::from_static(b"news.a"), &Bytes::from_static(b"1")), 2);
+        assert_eq!(pub_sub.publish(&Bytes::from_static(b"news.b"), &Bytes::from_static(b"2")), 1);
+        let kinds: Vec<Frame> = payloads(&mailbox)
+            .into_iter()
+            .map(|frame| match frame {
+                Frame::Array(items) => items[0].clone(),
+                frame => frame,
+            })
+            .collect();
+        let message = Frame::Bulk(Bytes::from_static(b"message"));
+        let pmessage = Frame::Bulk(Bytes::from_static(b"pmessage"));
+        assert_eq!(kinds, vec![message, pmessage.clone(), pmessage]);
+    }
+
+    #[test]
+    fn unsubscribe_removes_empty_topics() {
+        let mut pub_sub = PubSub::default();
+        let mailbox = Arc::new(Mailbox::default());
+        let pattern = Bytes::from_static(b"a*");
+        pub_sub.psubscribe(&pattern, &mailbox);
+        assert_eq!(pub_sub.numpat(), 1);
+        pub_sub.punsubscribe(&pattern, &mailbox);
+        assert_eq!(pub_sub.numpat(), 0);
+        assert!(pub_sub.pattern_index.is_empty());
+        assert_eq!(pub_sub.publish(&Bytes::from_static(b"abc"), &Bytes::from_static(b"x")), 0);
+    }
+
+    #[test]
+    fn shard_channels_are_separate() {
+        let mut pub_sub = PubSub::default();
+        let mailbox = Arc::new(Mailbox::default());
+        let channel = Bytes::from_static(b"ch");
+        pub_sub.ssubscribe(&channel, &mailbox);
+        assert_eq!(pub_sub.publish(&channel, &Bytes::from_static(b"x")), 0);
+        assert_eq!(pub_sub.spublish(&channel, &Bytes::from_static(b"x")), 1);
+        assert!(pub_sub.slots[&key_hash_slot(&channel)].contains(&channel));
+        pub_sub.sunsubscribe(&channel, &mailbox);
+        assert!(pub_sub.slots.is_empty());
+    }
+
+    #[test]
+    fn detach_leaves_everything() {
+        let mut pub_sub = PubSub::default();
+        let mut subscriber = Subscriber::new();
+        for channel in [&b"a"[..], b"b"] {
+            subscriber.channels.insert(Bytes::copy_from_slice(channel));
+            pub_sub.subscribe(&Bytes::copy_from_slice(channel), &subscriber.mailbox);
+        }
+        subscriber.patterns.insert(Bytes::from_static(b"p*"));
+        pub_sub.psubscribe(&Bytes::from_static(b"p*"), &subscriber.mailbox);
+        subscriber.detach(&mut pub_sub);
+        assert!(!subscriber.is_active());
+        assert_eq!(pub_sub.numpat(), 0);
+        assert_eq!(pub_sub.publish(&Bytes::from_static(b"a"), &Bytes::from_static(b"x")), 0);
+    }
+
+    #[test]
+    fn literal_prefix_stops_at_wildcard() {
+        assert_eq!(literal_prefix(&Bytes::from_static(b"news.*")), Bytes::from_static(b"news."));
+        assert_eq!(literal_prefix(&Bytes::from_static(b"a?c")), Bytes::from_static(b"a"));
+        assert_eq!(literal_prefix(&Bytes::from_static(b"plain")), Bytes::from_static(b"plain"));
+    }
+}
@@ -0,0 +1,356 @@
+use crate::cmd::{error_frame, Command};
+use crate::db::State;
+use crate::frame::Frame;
+use crate::util::sha1_hex;
+use bytes::Bytes;
+use mlua::{HookTriggers, IntoLuaMulti, Lua, LuaOptions, MultiValue, RegistryKey, StdLib, Table, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tracing::{debug, info, warn};
+
+// 脚本执行超过这个时间之后，其他连接的命令直接回复BUSY
+pub(crate) const BUSY_REPLY_THRESHOLD: Duration = Duration::from_secs(5);
+
+// 每执行这么多条指令检查一次有没有被SCRIPT KILL
+const HOOK_INSTRUCTIONS: u32 = 100_000;
+
+const BUSY_ERROR: &str = "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE.";
+
+// 正在执行的脚本的状态，脚本在阻塞线程里执行，期间一直持有锁，所以这些状态放在锁外面
+#[derive(Debug, Default)]
+pub(crate) struct ScriptStatus {
+    running: AtomicBool,
+    started_ms: AtomicU64,
+    // 已经执行过写命令的脚本不能被杀掉
+    wrote: AtomicBool,
+    kill: AtomicBool,
+    // FUNCTION LOAD 执行库代码的截止时间，0代表没有限制
+    load_deadline_ms: AtomicU64,
+}
+
+impl ScriptStatus {
+    fn start(&self) {
+        self.started_ms.store(crate::util::now_ms(), Ordering::SeqCst);
+        self.wrote.store(false, Ordering::SeqCst);
+        self.kill.store(false, Ordering::SeqCst);
+        self.running.store(true, Ordering::SeqCst);
+    }
+
+    // 加载函数库的时候也算是在执行脚本，超过时间之后在指令hook里面中断
+    pub(crate) fn start_load(&self, timeout: Duration) {
+        self.load_deadline_ms.store(crate::util::now_ms() + timeout.as_millis() as u64, Ordering::SeqCst);
+        self.start();
+    }
+
+    pub(crate) fn finish(&self) {
+        self.load_deadline_ms.store(0, Ordering::SeqCst);
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    fn load_timed_out(&self) -> bool {
+        let deadline = self.load_deadline_ms.load(Ordering::SeqCst);
+        deadline != 0 && crate::util::now_ms() >= deadline
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn elapsed(&self) -> Duration {
+        let started = self.started_ms.load(Ordering::SeqCst);
+        Duration::from_millis(crate::util::now_ms().saturating_sub(started))
+    }
+
+    // 正在执行的脚本还要多久开始回复BUSY，没有脚本执行的时候返回None
+    pub(crate) fn until_busy(&self) -> Option<Duration> {
+        self.is_running().then(|| BUSY_REPLY_THRESHOLD.saturating_sub(self.elapsed()))
+    }
+
+    // 脚本执行时间超过阈值之后返回BUSY
+    pub(crate) fn check_busy(&self) -> crate::Result<()> {
+        if self.is_running() && self.elapsed() >= BUSY_REPLY_THRESHOLD {
+            return Err(BUSY_ERROR.into());
+        }
+        Ok(())
+    }
+
+    // SCRIPT KILL，真正停止是在指令hook里面
+    pub(crate) fn kill(&self) -> crate::Result<Frame> {
+        if !self.is_running() {
+            return Err("NOTBUSY No scripts in execution right now.".into());
+        }
+        if self.wrote.load(Ordering::SeqCst) {
+            return Err("UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.".into());
+        }
+        self.kill.store(true, Ordering::SeqCst);
+        Ok(Frame::Simple("OK".to_string()))
+    }
+}
+
+// 脚本缓存和Lua虚拟机，放在State里面，执行脚本的时候整个State都是锁住的
+pub(crate) struct Scripting {
+    // 执行脚本期间被取出来，redis.call需要同时借用State
+    lua: Option<Lua>,
+    scripts: HashMap<String, RegistryKey>,
+    status: Arc<ScriptStatus>,
+}
+
+impl fmt::Debug for Scripting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scripting").field("scripts", &self.scripts.len()).finish()
+    }
+}
+
+impl Scripting {
+    pub(crate) fn new(status: Arc<ScriptStatus>) -> Scripting {
+        Scripting {
+            lua: Some(create_lua(&status)),
+            scripts: HashMap::new(),
+            status,
+        }
+    }
+
+    // 编译并缓存脚本，返回脚本的sha1
+    pub(crate) fn load(&mut self, body: &[u8]) -> crate::Result<String> {
+        let sha = sha1_hex(body);
+        if self.scripts.contains_key(&sha) {
+            return Ok(sha);
+        }
+        let lua = self.lua.as_ref().ok_or("ERR scripting engine is busy")?;
+        let func = lua
+            .load(body)
+            .set_name("@user_script")
+            .into_function()
+            .map_err(|err| format!("ERR Error compiling script (new function): {}", lua_error_message(&err)))?;
+        let key = lua.create_registry_value(func).map_err(|err| err.to_string())?;
+        self.scripts.insert(sha.clone(), key);
+        Ok(sha)
+    }
+
+    pub(crate) fn exists(&self, sha: &str) -> bool {
+        self.scripts.contains_key(&sha.to_lowercase())
+    }
+
+    // 丢掉所有的脚本，顺便换一个新的虚拟机，脚本留下的全局变量也一起清掉
+    pub(crate) fn flush(&mut self) {
+        self.scripts.clear();
+        self.lua = Some(create_lua(&self.status));
+    }
+}
+
+// 创建Lua虚拟机，只加载redis脚本允许使用的标准库
+pub(crate) fn create_lua(status: &Arc<ScriptStatus>) -> Lua {
+    let libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH;
+    let lua = Lua::new_with(libs, LuaOptions::default()).expect("failed to create lua state");
+
+    let status = status.clone();
+    lua.set_hook(HookTriggers::new().every_nth_instruction(HOOK_INSTRUCTIONS), move |_, _| {
+        if status.kill.load(Ordering::SeqCst) {
+            return Err(mlua::Error::RuntimeError("ERR Script killed by user with SCRIPT KILL...".to_string()));
+        }
+        if status.load_timed_out() {
+            return Err(mlua::Error::RuntimeError("FUNCTION LOAD timeout".to_string()));
+        }
+        Ok(())
+    });
+
+    register_redis_lib(&lua).expect("failed to register redis lib");
+    lua
+}
+
+// redis.call 和 redis.pcall 需要借用State，每次执行脚本的时候再设置
+fn register_redis_lib(lua: &Lua) -> mlua::Result<()> {
+    let redis = lua.create_table()?;
+    redis.set("error_reply", lua.create_function(|lua, msg: mlua::String| {
+        let reply = lua.create_table()?;
+        reply.set("err", msg)?;
+        Ok(reply)
+    })?)?;
+    redis.set("status_reply", lua.create_function(|lua, msg: mlua::String| {
+        let reply = lua.create_table()?;
+        reply.set("ok", msg)?;
+        Ok(reply)
+    })?)?;
+    redis.set("sha1hex", lua.create_function(|_, data: mlua::String| Ok(sha1_hex(data.as_bytes())))?)?;
+    redis.set("log", lua.create_function(|_, (level, msg): (i64, mlua::String)| {
+        let msg = msg.to_string_lossy();
+        match level {
+            0 => debug!(%msg, "script log"),
+            3 => warn!(%msg, "script log"),
+            _ => info!(%msg, "script log"),
+        }
+        Ok(())
+    })?)?;
+    redis.set("LOG_DEBUG", 0)?;
+    redis.set("LOG_VERBOSE", 1)?;
+    redis.set("LOG_NOTICE", 2)?;
+    redis.set("LOG_WARNING", 3)?;
+    lua.globals().set("redis", redis)
+}
+
+// 执行缓存里的脚本，调用方已经持有锁，整个脚本是原子执行的
+pub(crate) fn eval(db: &mut State, sha: &str, keys: Vec<Bytes>, args: Vec<Bytes>) -> crate::Result<Frame> {
+    let scripting = db.scripting();
+    let lua = scripting.lua.take().ok_or("ERR scripting engine is busy")?;
+    let status = scripting.status.clone();
+
+    let result = (|| {
+        let key = db.scripting().scripts.get(sha).ok_or("NOSCRIPT No matching script. Please use EVAL.")?;
+        let func: mlua::Function = lua.registry_value(key)?;
+        let globals = lua.globals();
+        globals.set("KEYS", create_sequence(&lua, &keys)?)?;
+        globals.set("ARGV", create_sequence(&lua, &args)?)?;
+        run_script(&lua, db, &status, func, (), false)
+    })();
+
+    db.scripting().lua = Some(lua);
+    result
+}
+
+pub(crate) fn create_sequence<'lua>(lua: &'lua Lua, items: &[Bytes]) -> mlua::Result<Table<'lua>> {
+    lua.create_sequence_from(items.iter().map(|item| lua.create_string(item)).collect::<mlua::Result<Vec<_>>>()?)
+}
+
+// 执行脚本，期间可以通过redis.call执行命令，read_only的脚本不能执行写命令
+pub(crate) fn run_script<'lua>(
+    lua: &'lua Lua,
+    db: &mut State,
+    status: &ScriptStatus,
+    func: mlua::Function<'lua>,
+    args: impl IntoLuaMulti<'lua>,
+    read_only: bool,
+) -> crate::Result<Frame> {
+    // 两个回调都要可变借用State，不会同时执行
+    let db = RefCell::new(db);
+    status.start();
+    let result = lua.scope(|scope| {
+        let redis: Table = lua.globals().get("redis")?;
+        redis.set("call", scope.create_function_mut(|lua, args: MultiValue| {
+            call(lua, &mut db.borrow_mut(), status, args, true, read_only)
+        })?)?;
+        redis.set("pcall", scope.create_function_mut(|lua, args: MultiValue| {
+            call(lua, &mut db.borrow_mut(), status, args, false, read_only)
+        })?)?;
+        let value = func.call::<_, Value>(args);
+        // 回调在scope结束之后就失效了，不在脚本里面的时候不能调用
+        redis.set("call", Value::Nil)?;
+        redis.set("pcall", Value::Nil)?;
+        Ok(lua_to_frame(value?))
+    });
+    status.finish();
+
+    result.map_err(|err| lua_error_message(&err).into())
+}
+
+// 在脚本里面执行redis命令，raise为true的时候错误直接抛出
+fn call<'lua>(lua: &'lua Lua, db: &mut State, status: &ScriptStatus, args: MultiValue<'lua>, raise: bool, read_only: bool) -> mlua::Result<Value<'lua>> {
+    let reply = match execute(lua, db, status, args, read_only) {
+        Ok(frame) => frame,
+        Err(err) => error_frame(err),
+    };
+    match reply {
+        Frame::Error(msg) if raise => Err(mlua::Error::RuntimeError(msg)),
+        reply => frame_to_lua(lua, reply),
+    }
+}
+
+fn execute(lua: &Lua, db: &mut State, status: &ScriptStatus, args: MultiValue, read_only: bool) -> crate::Result<Frame> {
+    if args.is_empty() {
+        return Err("ERR Please specify at least one argument for this redis lib call".into());
+    }
+    let mut frames = Vec::with_capacity(args.len());
+    for arg in args {
+        let arg = match arg {
+            Value::String(_) | Value::Integer(_) | Value::Number(_) => lua.coerce_string(arg).ok().flatten(),
+            _ => None,
+        };
+        let arg = arg.ok_or("ERR Lua redis lib command arguments must be strings or integers")?;
+        frames.push(Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes())));
+    }
+
+    let cmd = db.registry().parse_command(Frame::Array(frames))?;
+    if !cmd.is_script_allowed() {
+        return Err("ERR This Redis command is not allowed from script".into());
+    }
+    if let Command::UnKnown(_) = cmd {
+        return Err("ERR Unknown Redis command called from script".into());
+    }
+    if read_only && cmd.is_write() {
+        return Err("ERR Write commands are not allowed from read-only scripts.".into());
+    }
+
+    let dirty = db.dirty();
+    let reply = cmd.execute(db);
+    if db.dirty() != dirty {
+        status.wrote.store(true, Ordering::SeqCst);
+    }
+    reply
+}
+
+// redis的回复转成Lua的值，规则和redis一致
+fn frame_to_lua(lua: &Lua, frame: Frame) -> mlua::Result<Value<'_>> {
+    Ok(match frame {
+        Frame::Simple(msg) => {
+            let table = lua.create_table()?;
+            table.set("ok", msg)?;
+            Value::Table(table)
+        }
+        Frame::Error(msg) => {
+            let table = lua.create_table()?;
+            table.set("err", msg)?;
+            Value::Table(table)
+        }
+        Frame::Integer(n) => Value::Integer(n),
+        Frame::Bulk(data) => Value::String(lua.create_string(&data)?),
+        Frame::Null | Frame::NullArray => Value::Boolean(false),
+        Frame::Array(items) => {
+            let table = lua.create_table()?;
+            for (idx, item) in items.into_iter().enumerate() {
+                table.raw_set(idx + 1, frame_to_lua(lua, item)?)?;
+            }
+            Value::Table(table)
+        }
+    })
+}
+
+// 脚本的返回值转成回复，数组遇到nil就结束
+fn lua_to_frame(value: Value) -> Frame {
+    match value {
+        Value::Boolean(true) => Frame::Integer(1),
+        Value::Integer(n) => Frame::Integer(n),
+        Value::Number(n) => Frame::Integer(n as i64),
+        Value::String(s) => Frame::Bulk(Bytes::copy_from_slice(s.as_bytes())),
+        Value::Table(table) => {
+            if let Ok(msg) = table.raw_get::<_, mlua::String>("err") {
+                return Frame::Error(msg.to_string_lossy().into_owned());
+            }
+            if let Ok(msg) = table.raw_get::<_, mlua::String>("ok") {
+                return Frame::Simple(msg.to_string_lossy().into_owned());
+            }
+            let mut items = Vec::new();
+            for idx in 1.. {
+                match table.raw_get::<_, Value>(idx) {
+                    Ok(Value::Nil) | Err(_) => break,
+                    Ok(item) => items.push(lua_to_frame(item)),
+                }
+            }
+            Frame::Array(items)
+        }
+        _ => Frame::Null,
+    }
+}
+
+// 去掉mlua包装的回调错误，只保留redis的错误信息
+pub(crate) fn lua_error_message(err: &mlua::Error) -> String {
+    match err {
+        mlua::Error::CallbackError { cause, .. } => lua_error_message(cause),
+        // lua里面抛出的错误会带上调用栈，错误回复里面不能有换行
+        mlua::Error::RuntimeError(msg) => msg.lines().next().unwrap_or_default().to_string(),
+        mlua::Error::SyntaxError { message, .. } => message.clone(),
+        err => err.to_string(),
+    }
+}
@@ -1,23 +1,31 @@
 use tokio::net::{TcpListener, TcpStream};
 use std::future::Future;
+use std::net::SocketAddr;
 use crate::{db::Db};
 use std::sync::Arc;
-use tokio::sync::{Semaphore, broadcast, mpsc};
-use tracing::{error, info, debug};
-use std::net::SocketAddr;
-use std::io::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info, debug, warn};
 use tokio::time;
 use crate::connection::Connection;
+use crate::frame::Frame;
 use crate::shutdown::Shutdown;
-use crate::cmd::Command;
+use crate::cmd::{Command, Transaction, WatchedKeys, error_frame, command_name, command_fullname};
+use crate::client::{ClientHandle, ClientInfo};
+use crate::pubsub::Subscriber;
+use crate::config::{ClientClass, Config};
+use crate::notify;
+
+// 超过maxclients的时候回复给新连接的错误
+const MAX_CLIENTS_REACHED: &[u8] = b"-ERR max number of clients reached\r\n";
 
-const MAX_CONNECT: usize = 250;
+// 订阅模式下允许执行的命令
+const SUBSCRIBER_COMMANDS: &[&str] = &["subscribe", "unsubscribe", "psubscribe", "punsubscribe", "ssubscribe", "sunsubscribe", "ping", "quit", "reset"];
 
 #[derive(Debug)]
 struct Listener {
     db: Db,
     listener: TcpListener,
-    limit_connections: Arc<Semaphore>,
     notify_shutdown: broadcast::Sender<()>,
     shutdown_complete_rx: mpsc::Receiver<()>,
     shutdown_complete_tx: mpsc::Sender<()>,
@@ -27,33 +35,67 @@ impl Listener {
     async fn run(&mut self) -> crate::Result<()> {
         info!("初始化完，开始接收客户端请求");
         loop {
-            // 限制最大连接数 (阻塞等待)
-            self.limit_connections.acquire().await.unwrap().forget();
-            let socket = self.accept().await?;
+            let (mut socket, addr) = self.accept().await?;
+            self.db.stats_counters().connection_received();
+            // 超过最大连接数的时候回复错误之后马上关掉，让客户端尽快失败，不用一直等
+            let maxclients = self.db.config().maxclients;
+            if self.db.clients().len() as u64 >= maxclients {
+                self.db.stats_counters().connection_rejected();
+                tokio::spawn(async move {
+                    let _ = socket.write_all(MAX_CLIENTS_REACHED).await;
+                });
+                continue;
+            }
+            // 客户端连上马上就断开的时候拿不到地址，只影响这一个连接
+            let local_addr = match socket.local_addr() {
+                Ok(local_addr) => local_addr,
+                Err(err) => {
+                    debug!(cause = %err, "connection closed before registration");
+                    continue;
+                }
+            };
+            let client = self.db.clients().register(ClientInfo::new(addr, local_addr));
             let mut handler = Handler {
                 db: self.db.clone(),
+                client,
                 connection: Connection::new(socket),
-                limit_connections: self.limit_connections.clone(),
                 shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
+                subscriber: Subscriber::new(),
+                transaction: None,
+                watched: WatchedKeys::new(),
                 _shutdown_complete: self.shutdown_complete_tx.clone(),
             };
 
             // 开启一个task处理（非阻塞）
             tokio::spawn(async move {
                 if let Err(err) = handler.run().await {
-                    error!(cause = ?err, "connection error");
+                    if handler.connection.output_limit_reached() {
+                        handler.db.stats_counters().output_buffer_disconnection();
+                        let client = handler.client.info().describe();
+                        warn!(%client, "client closed for overcoming of output buffer limits");
+                    } else {
+                        error!(cause = ?err, "connection error");
+                    }
                 }
             });
         }
     }
 
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
+    async fn accept(&mut self) -> crate::Result<(TcpStream, SocketAddr)> {
         // 初始化发生错误的时候重试等待时间
         let mut backoff = 1;
 
         loop {
             match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+                Ok((socket, addr)) => {
+                    let tcp_keepalive = self.db.config().tcp_keepalive;
+                    if tcp_keepalive > 0 {
+                        if let Err(err) = set_keepalive(&socket, tcp_keepalive) {
+                            warn!(cause = %err, "failed to set tcp keepalive");
+                        }
+                    }
+                    return Ok((socket, addr));
+                }
                 Err(err) => {
                     if backoff > 64 {
                         return Err(err.into());
@@ -69,9 +111,16 @@ impl Listener {
 
 struct Handler {
     db: Db,
+    // 连接自己的状态，CLIENT 和 SELECT 会修改，也登记在Db的客户端注册表里
+    client: Arc<ClientHandle>,
     connection: Connection,
-    limit_connections: Arc<Semaphore>,
     shutdown: Shutdown,
+    // 订阅了频道之后进入订阅模式，只能执行订阅相关的命令
+    subscriber: Subscriber,
+    // MULTI 之后不为None，命令先排队，EXEC 的时候再执行
+    transaction: Option<Transaction>,
+    // WATCH 的key，EXEC 的时候检查有没有被修改过
+    watched: WatchedKeys,
     // 这个是当Handler被drop时候，会把_shutdown_complete一起drop，触发shutdown_complete_rx（这里是自动触发的，不需要手动调用）
     _shutdown_complete: mpsc::Sender<()>,
 }
@@ -81,43 +130,265 @@ impl Handler {
 
         // 只有当连接没有结束时候才循环读取
         while !self.shutdown.is_shutdown() {
+            self.refresh_client();
+            // 订阅了频道的客户端一直在等消息，不算空闲
+            let idle_timeout = self.db.config().timeout;
+            let check_idle = idle_timeout > 0 && !self.subscriber.is_active();
             // 读取Frame出来
             let maybe_frame = tokio::select! {
-                res = self.connection.read_frame() => {
-                    res?
+                res = self.connection.read_frame() => match res {
+                    Ok(frame) => frame,
+                    // 协议错误先回复错误再断开，和redis一样
+                    Err(err) if !err.is::<std::io::Error>() => {
+                        let _ = self.connection.write_frame(&error_frame(err)).await;
+                        return Ok(());
+                    }
+                    Err(err) => return Err(err),
+                },
+                // 订阅的频道有消息的时候推送给客户端
+                frame = self.subscriber.recv() => {
+                    let frame = match frame {
+                        Some(frame) => frame,
+                        // 积压超过了订阅者的硬上限
+                        None => {
+                            self.db.stats_counters().output_buffer_disconnection();
+                            let client = self.client.info().describe();
+                            warn!(%client, "client closed for overcoming of output buffer limits");
+                            return Ok(());
+                        }
+                    };
+                    self.refresh_client();
+                    self.connection.write_frame(&frame).await?;
+                    continue;
                 },
                 _ = self.shutdown.recv() => {
                     return Ok(())
                 }
+                // 被 CLIENT KILL 关掉
+                _ = self.client.killed() => {
+                    return Ok(())
+                }
+                // 阻塞命令在apply里面等待，不会走到这里
+                _ = time::sleep(time::Duration::from_secs(idle_timeout)), if check_idle => {
+                    debug!(client = %self.client.info().describe(), "closing idle client");
+                    return Ok(())
+                }
             };
             let frame = match maybe_frame {
                 Some(frame) => frame,
                 None => return Ok(()),
             };
+            if let Some(name) = command_fullname(&frame) {
+                self.client.info().touch(name);
+            }
+
 
+            if self.subscriber.is_active() {
+                let name = command_name(&frame).unwrap_or_default();
+                if !SUBSCRIBER_COMMANDS.contains(&&name[..]) {
+                    let msg = format!("ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context", name);
+                    self.connection.write_frame(&Frame::Error(msg)).await?;
+                    continue;
+                }
+            }
 
-            // 处理Frame消息
-            let cmd = Command::from_frame(frame)?;
+            // 处理Frame消息，命令格式不对的时候回复错误，连接继续可用
+            let cmd = match self.db.parse_command(frame) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    // 事务里面出现语法错误，整个事务都要放弃
+                    if let Some(transaction) = &mut self.transaction {
+                        transaction.abort();
+                    }
+                    self.connection.write_frame(&error_frame(err)).await?;
+                    continue;
+                }
+            };
+            self.db.stats_counters().command_processed();
             // 打印cmd并将错误传递到外层
             debug!(?cmd);
 
-            // 处理每个连接
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown).await?;
+            // 其他连接的脚本还在执行，先等它结束，执行太久的直接回复BUSY
+            if !cmd.is_allowed_when_busy() {
+                if let Err(err) = self.db.wait_script_idle().await {
+                    self.connection.write_frame(&error_frame(err)).await?;
+                    continue;
+                }
+            }
+
+            // 事务里面的命令先排队，未知命令会让整个事务放弃
+            if let Some(transaction) = &mut self.transaction {
+                if cmd.is_queueable() {
+                    let response = match cmd {
+                        Command::UnKnown(cmd) => {
+                            transaction.abort();
+                            cmd.execute()?
+                        }
+                        cmd => {
+                            transaction.queue(cmd);
+                            Frame::Simple("QUEUED".to_string())
+                        }
+                    };
+                    self.connection.write_frame(&response).await?;
+                    continue;
+                }
+            }
+
+            // CLIENT PAUSE 期间先等着，CLIENT 命令不受影响，运维还能 UNPAUSE
+            if !matches!(cmd, Command::Client(_)) {
+                let write = match &cmd {
+                    Command::Exec(_) => self.transaction.as_ref().is_some_and(Transaction::may_write),
+                    cmd => cmd.may_write(),
+                };
+                tokio::select! {
+                    _ = self.db.clients().wait_unpaused(write) => {}
+                    _ = self.client.killed() => return Ok(()),
+                }
+            }
+
+            // 订阅和事务相关的命令需要连接自己的状态
+            match cmd {
+                Command::Multi(_) => {
+                    let response = if self.transaction.is_some() {
+                        Frame::Error("ERR MULTI calls can not be nested".to_string())
+                    } else {
+                        self.transaction = Some(Transaction::new());
+                        Frame::Simple("OK".to_string())
+                    };
+                    self.connection.write_frame(&response).await?;
+                }
+                Command::Exec(_) => {
+                    let response = match self.transaction.take() {
+                        Some(transaction) => transaction.exec(&self.db, &mut self.watched).await?,
+                        None => Frame::Error("ERR EXEC without MULTI".to_string()),
+                    };
+                    self.connection.write_frame(&response).await?;
+                }
+                Command::Discard(_) => {
+                    let response = match self.transaction.take() {
+                        Some(_) => {
+                            self.watched.unwatch_all(&mut *self.db.lock_async().await);
+                            Frame::Simple("OK".to_string())
+                        }
+                        None => Frame::Error("ERR DISCARD without MULTI".to_string()),
+                    };
+                    self.connection.write_frame(&response).await?;
+                }
+                Command::Watch(cmd) => {
+                    let response = if self.transaction.is_some() {
+                        Frame::Error("ERR WATCH inside MULTI is not allowed".to_string())
+                    } else {
+                        cmd.execute(&mut *self.db.lock_async().await, &mut self.watched)
+                    };
+                    self.connection.write_frame(&response).await?;
+                }
+                Command::Unwatch(_) => {
+                    self.watched.unwatch_all(&mut *self.db.lock_async().await);
+                    self.connection.write_frame(&Frame::Simple("OK".to_string())).await?;
+                }
+                Command::Quit(_) => {
+                    self.connection.write_frame(&Frame::Simple("OK".to_string())).await?;
+                    return Ok(());
+                }
+                Command::Reset(_) => {
+                    self.reset().await;
+                    self.connection.write_frame(&Frame::Simple("RESET".to_string())).await?;
+                }
+                Command::Select(cmd) => {
+                    self.client.info().db = cmd.index();
+                    self.connection.write_frame(&Frame::Simple("OK".to_string())).await?;
+                }
+                Command::Client(cmd) => {
+                    self.refresh_client();
+                    let response = cmd.execute(&self.client, self.db.clients()).unwrap_or_else(error_frame);
+                    self.connection.write_frame(&response).await?;
+                }
+                Command::Subscribe(cmd) => cmd.apply(&self.db, &mut self.connection, &mut self.subscriber).await?,
+                Command::Unsubscribe(cmd) => cmd.apply(&self.db, &mut self.connection, &mut self.subscriber).await?,
+                Command::PSubscribe(cmd) => cmd.apply(&self.db, &mut self.connection, &mut self.subscriber).await?,
+                Command::PUnsubscribe(cmd) => cmd.apply(&self.db, &mut self.connection, &mut self.subscriber).await?,
+                Command::SSubscribe(cmd) => cmd.apply(&self.db, &mut self.connection, &mut self.subscriber).await?,
+                Command::SUnsubscribe(cmd) => cmd.apply(&self.db, &mut self.connection, &mut self.subscriber).await?,
+                Command::Ping(cmd) if self.subscriber.is_active() => {
+                    self.connection.write_frame(&cmd.subscribed_reply()).await?;
+                }
+                // 其他不能排队的命令在事务里面直接拒绝，整个事务都要放弃
+                _ if self.transaction.is_some() => {
+                    if let Some(transaction) = &mut self.transaction {
+                        transaction.abort();
+                    }
+                    self.connection.write_frame(&Frame::Error("ERR Command not allowed inside a transaction".to_string())).await?;
+                }
+                // 阻塞命令可能一直等下去，被 CLIENT KILL 的时候直接结束
+                cmd => {
+                    let client = self.client.clone();
+                    tokio::select! {
+                        res = cmd.apply(&self.db, &mut self.connection, &mut self.shutdown) => res?,
+                        _ = client.killed() => return Ok(()),
+                    }
+                }
+            }
         }
 
         Ok(())
     }
+
+    // 放弃事务，取消WATCH和所有订阅，回到刚连上时候的状态
+    async fn reset(&mut self) {
+        self.transaction = None;
+        if !self.watched.is_empty() {
+            self.watched.unwatch_all(&mut *self.db.lock_async().await);
+        }
+        self.subscriber.unsubscribe_all(&self.db).await;
+        self.client.info().reset();
+    }
+
+    // 把订阅、事务和缓冲区的状态同步到ClientInfo里面，CLIENT LIST 能看到
+    fn refresh_client(&mut self) {
+        let mut info = self.client.info();
+        info.sub = self.subscriber.channels().len();
+        info.psub = self.subscriber.patterns().len();
+        info.ssub = self.subscriber.shard_count();
+        info.multi = self.transaction.as_ref().map_or(-1, |transaction| transaction.queued() as i64);
+        let (qbuf, qbuf_free) = self.connection.input_buffer();
+        info.qbuf = qbuf;
+        info.qbuf_free = qbuf_free;
+        let (oll, omem) = self.subscriber.pending();
+        info.oll = oll;
+        info.omem = omem;
+        let class = if self.subscriber.is_active() { ClientClass::PubSub } else { ClientClass::Normal };
+        let limit = self.db.config().client_output_buffer_limit.get(class);
+        self.connection.set_output_limit(limit, self.subscriber.backlog());
+    }
 }
 
 
 pub async fn run(listener: TcpListener, shutdown: impl Future) -> crate::Result<()> {
+    run_with_config(listener, Config::default(), shutdown).await
+}
+
+pub async fn run_with_config(listener: TcpListener, config: Config, shutdown: impl Future) -> crate::Result<()> {
+    run_with_db(listener, Db::new(), config, shutdown).await
+}
+
+// 嵌入使用的时候传入自己的Db，可以在进程内访问数据和订阅key的变化
+pub async fn run_with_db(listener: TcpListener, db: Db, mut config: Config, shutdown: impl Future) -> crate::Result<()> {
+    let notify_flags = notify::parse_flags(&config.notify_keyspace_events).ok_or("invalid notify-keyspace-events")?;
+    // 和 CONFIG SET 之后一样保存规范化的写法
+    config.notify_keyspace_events = notify::flags_to_string(notify_flags);
+    db.lock().set_notify_flags(notify_flags);
+    if let Some(path) = &config.functions_file {
+        db.lock().functions().open(path).map_err(|err| format!("failed to load functions file: {}", err))?;
+    }
+    // 之后的修改都通过 CONFIG SET
+    *db.config_mut() = config;
+
     let (notify_shutdown, _) = broadcast::channel(1);
     let (shutdown_complete_tx, shutdown_complete_rx) = mpsc::channel(1);
 
     let mut server = Listener {
-        db: Db::new(),
+        db,
         listener,
-        limit_connections: Arc::new(Semaphore::new(MAX_CONNECT)),
         notify_shutdown,
         shutdown_complete_rx,
         shutdown_complete_tx,
@@ -152,9 +423,56 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) -> crate::Result<
     Ok(())
 }
 
+// 和redis一样，空闲interval秒之后开始探测，每interval/3秒探测一次，3次没有回应就断开
+#[cfg(unix)]
+fn set_keepalive(socket: &TcpStream, interval: u64) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = socket.as_raw_fd();
+    let setopt = |level: libc::c_int, name: libc::c_int, value: libc::c_int| {
+        // fd在socket存活期间一直有效，value的大小和类型都是setsockopt要求的
+        let res = unsafe {
+            libc::setsockopt(
+                fd,
+                level,
+                name,
+                &value as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if res == 0 { Ok(()) } else { Err(std::io::Error::last_os_error()) }
+    };
+    setopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)?;
+    #[cfg(target_os = "linux")]
+    {
+        let interval = interval.min(i32::MAX as u64) as libc::c_int;
+        setopt(libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, interval)?;
+        setopt(libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, (interval / 3).max(1))?;
+        setopt(libc::IPPROTO_TCP, libc::TCP_KEEPCNT, 3)?;
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = interval;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_keepalive(_socket: &TcpStream, _interval: u64) -> std::io::Result<()> {
+    Ok(())
+}
+
 impl Drop for Handler {
     fn drop(&mut self) {
-        // handler 触发drop代表一个连接已经结束，需要将信号量+1，这样listener那才能拿到信号量
-        self.limit_connections.add_permits(1);
+        // handler 触发drop代表一个连接已经结束，从注册表里面去掉，腾出maxclients的名额
+        self.db.clients().unregister(self.client.info().id);
+
+        // 连接断开的时候退订所有频道，取消所有WATCH，脚本正在执行的时候交给后台任务
+        if self.subscriber.is_active() || !self.watched.is_empty() {
+            let mut subscriber = std::mem::take(&mut self.subscriber);
+            let mut watched = std::mem::take(&mut self.watched);
+            self.db.lock_or_defer(move |state| {
+                subscriber.detach(state.pub_sub());
+                watched.unwatch_all(state);
+            });
+        }
     }
 }
\ No newline at end of file
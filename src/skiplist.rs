@@ -0,0 +1,559 @@
+use bytes::Bytes;
+use std::cmp::Ordering;
+
+// 和redis的zskiplist一样，最多32层，每升一层的概率是1/4
+const MAX_LEVEL: usize = 32;
+const HEAD: usize = 0;
+
+#[derive(Debug)]
+struct Level {
+    forward: Option<usize>,
+    // 到forward节点跨越了多少个节点，用来计算排名
+    span: usize,
+}
+
+#[derive(Debug)]
+struct Node {
+    member: Bytes,
+    score: f64,
+    backward: Option<usize>,
+    levels: Vec<Level>,
+}
+
+// 节点都放在nodes里面，用下标代替指针，删除的节点下标放到free里面复用
+#[derive(Debug)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    tail: Option<usize>,
+    length: usize,
+    level: usize,
+    seed: u64,
+}
+
+// 分数区间，ex代表开区间
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreRange {
+    pub min: f64,
+    pub max: f64,
+    pub minex: bool,
+    pub maxex: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum LexBound {
+    NegInf,
+    PosInf,
+    Inclusive(Bytes),
+    Exclusive(Bytes),
+}
+
+// 字典序区间，只有所有成员分数相同的时候才有意义
+#[derive(Debug, Clone)]
+pub struct LexRange {
+    pub min: LexBound,
+    pub max: LexBound,
+}
+
+impl ScoreRange {
+    pub fn value_gte_min(&self, value: f64) -> bool {
+        if self.minex { value > self.min } else { value >= self.min }
+    }
+
+    pub fn value_lte_max(&self, value: f64) -> bool {
+        if self.maxex { value < self.max } else { value <= self.max }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max || (self.min == self.max && (self.minex || self.maxex))
+    }
+}
+
+impl LexRange {
+    pub fn value_gte_min(&self, value: &[u8]) -> bool {
+        match &self.min {
+            LexBound::NegInf => true,
+            LexBound::PosInf => false,
+            LexBound::Inclusive(min) => value >= &min[..],
+            LexBound::Exclusive(min) => value > &min[..],
+        }
+    }
+
+    pub fn value_lte_max(&self, value: &[u8]) -> bool {
+        match &self.max {
+            LexBound::NegInf => false,
+            LexBound::PosInf => true,
+            LexBound::Inclusive(max) => value <= &max[..],
+            LexBound::Exclusive(max) => value < &max[..],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match (&self.min, &self.max) {
+            (LexBound::PosInf, _) | (_, LexBound::NegInf) => true,
+            (LexBound::NegInf, _) | (_, LexBound::PosInf) => false,
+            (min, max) => {
+                let (min, minex) = bound_value(min);
+                let (max, maxex) = bound_value(max);
+                match min.cmp(max) {
+                    Ordering::Greater => true,
+                    Ordering::Equal => minex || maxex,
+                    Ordering::Less => false,
+                }
+            }
+        }
+    }
+}
+
+fn bound_value(bound: &LexBound) -> (&Bytes, bool) {
+    match bound {
+        LexBound::Inclusive(v) => (v, false),
+        LexBound::Exclusive(v) => (v, true),
+        _ => unreachable!(),
+    }
+}
+
+// 先比较分数，分数相同再比较成员的字典序
+fn compare(score: f64, member: &[u8], other_score: f64, other_member: &[u8]) -> Ordering {
+    score
+        .partial_cmp(&other_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| member.cmp(other_member))
+}
+
+impl SkipList {
+    pub fn new() -> SkipList {
+        let head = Node {
+            member: Bytes::new(),
+            score: 0.0,
+            backward: None,
+            levels: (0..MAX_LEVEL).map(|_| Level { forward: None, span: 0 }).collect(),
+        };
+        SkipList {
+            nodes: vec![head],
+            free: vec![],
+            tail: None,
+            length: 0,
+            level: 1,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn member(&self, node: usize) -> &Bytes {
+        &self.nodes[node].member
+    }
+
+    pub fn score(&self, node: usize) -> f64 {
+        self.nodes[node].score
+    }
+
+    // 下一个节点（正序）
+    pub fn next(&self, node: usize) -> Option<usize> {
+        self.nodes[node].levels[0].forward
+    }
+
+    // 上一个节点（倒序）
+    pub fn prev(&self, node: usize) -> Option<usize> {
+        self.nodes[node].backward
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.nodes[HEAD].levels[0].forward
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        self.tail
+    }
+
+    fn forward(&self, node: usize, level: usize) -> Option<usize> {
+        self.nodes[node].levels[level].forward
+    }
+
+    fn random_level(&mut self) -> usize {
+        let mut level = 1;
+        loop {
+            // xorshift64
+            self.seed ^= self.seed << 13;
+            self.seed ^= self.seed >> 7;
+            self.seed ^= self.seed << 17;
+            if level < MAX_LEVEL && (self.seed & 0xffff) < 0xffff / 4 {
+                level += 1;
+            } else {
+                return level;
+            }
+        }
+    }
+
+    // 插入一个新节点，调用方需要保证成员不存在
+    pub fn insert(&mut self, score: f64, member: Bytes) -> usize {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0usize; MAX_LEVEL];
+
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+            while let Some(next) = self.forward(x, i) {
+                let n = &self.nodes[next];
+                if compare(n.score, &n.member, score, &member) == Ordering::Less {
+                    rank[i] += self.nodes[x].levels[i].span;
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+            update[i] = x;
+        }
+
+        let level = self.random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.length;
+            }
+            self.level = level;
+        }
+
+        let node = Node {
+            member,
+            score,
+            backward: None,
+            levels: (0..level).map(|_| Level { forward: None, span: 0 }).collect(),
+        };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for i in 0..level {
+            let prev = update[i];
+            self.nodes[idx].levels[i].forward = self.nodes[prev].levels[i].forward;
+            self.nodes[prev].levels[i].forward = Some(idx);
+            self.nodes[idx].levels[i].span = self.nodes[prev].levels[i].span - (rank[0] - rank[i]);
+            self.nodes[prev].levels[i].span = (rank[0] - rank[i]) + 1;
+        }
+        // 更高的层只是多跨越了一个节点
+        for (i, prev) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[*prev].levels[i].span += 1;
+        }
+
+        self.nodes[idx].backward = if update[0] == HEAD { None } else { Some(update[0]) };
+        match self.nodes[idx].levels[0].forward {
+            Some(next) => self.nodes[next].backward = Some(idx),
+            None => self.tail = Some(idx),
+        }
+        self.length += 1;
+        idx
+    }
+
+    // 删除节点，成功返回true
+    pub fn delete(&mut self, score: f64, member: &[u8]) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                let n = &self.nodes[next];
+                if compare(n.score, &n.member, score, member) == Ordering::Less {
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+            update[i] = x;
+        }
+
+        match self.forward(x, 0) {
+            Some(target) if self.nodes[target].score == score && self.nodes[target].member[..] == *member => {
+                self.delete_node(target, &update);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn delete_node(&mut self, target: usize, update: &[usize; MAX_LEVEL]) {
+        for (i, prev) in update.iter().enumerate().take(self.level) {
+            if self.nodes[*prev].levels[i].forward == Some(target) {
+                self.nodes[*prev].levels[i].span += self.nodes[target].levels[i].span;
+                self.nodes[*prev].levels[i].span -= 1;
+                self.nodes[*prev].levels[i].forward = self.nodes[target].levels[i].forward;
+            } else {
+                self.nodes[*prev].levels[i].span -= 1;
+            }
+        }
+        match self.nodes[target].levels[0].forward {
+            Some(next) => self.nodes[next].backward = self.nodes[target].backward,
+            None => self.tail = self.nodes[target].backward,
+        }
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].forward.is_none() {
+            self.level -= 1;
+        }
+        self.length -= 1;
+
+        // 释放成员的内存，下标留着复用
+        self.nodes[target].member = Bytes::new();
+        self.nodes[target].levels = vec![];
+        self.free.push(target);
+    }
+
+    // 返回成员的排名，从1开始，不存在返回0
+    pub fn rank(&self, score: f64, member: &[u8]) -> usize {
+        let mut rank = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                let n = &self.nodes[next];
+                if compare(n.score, &n.member, score, member) != Ordering::Greater {
+                    rank += self.nodes[x].levels[i].span;
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+            if x != HEAD && self.nodes[x].member[..] == *member {
+                return rank;
+            }
+        }
+        0
+    }
+
+    // 根据排名（从1开始）找到节点
+    pub fn by_rank(&self, rank: usize) -> Option<usize> {
+        if rank == 0 || rank > self.length {
+            return None;
+        }
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if traversed + self.nodes[x].levels[i].span <= rank {
+                    traversed += self.nodes[x].levels[i].span;
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+            if traversed == rank {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    // 第一个分数落在区间里的节点
+    pub fn first_in_score_range(&self, range: &ScoreRange) -> Option<usize> {
+        if range.is_empty() {
+            return None;
+        }
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if !range.value_gte_min(self.nodes[next].score) {
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+        }
+        let x = self.forward(x, 0)?;
+        if range.value_lte_max(self.nodes[x].score) {
+            Some(x)
+        } else {
+            None
+        }
+    }
+
+    // 最后一个分数落在区间里的节点
+    pub fn last_in_score_range(&self, range: &ScoreRange) -> Option<usize> {
+        if range.is_empty() {
+            return None;
+        }
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if range.value_lte_max(self.nodes[next].score) {
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+        }
+        if x != HEAD && range.value_gte_min(self.nodes[x].score) {
+            Some(x)
+        } else {
+            None
+        }
+    }
+
+    pub fn first_in_lex_range(&self, range: &LexRange) -> Option<usize> {
+        if range.is_empty() {
+            return None;
+        }
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if !range.value_gte_min(&self.nodes[next].member) {
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+        }
+        let x = self.forward(x, 0)?;
+        if range.value_lte_max(&self.nodes[x].member) {
+            Some(x)
+        } else {
+            None
+        }
+    }
+
+    pub fn last_in_lex_range(&self, range: &LexRange) -> Option<usize> {
+        if range.is_empty() {
+            return None;
+        }
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            while let Some(next) = self.forward(x, i) {
+                if range.value_lte_max(&self.nodes[next].member) {
+                    x = next;
+                } else {
+                    break;
+                }
+            }
+        }
+        if x != HEAD && range.value_gte_min(&self.nodes[x].member) {
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        SkipList::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(i: usize) -> Bytes {
+        Bytes::from(format!("m{:03}", i))
+    }
+
+    // 每一层沿着forward走，span累加起来应该等于节点在最底层的位置
+    fn check_spans(zsl: &SkipList) {
+        let mut positions = std::collections::HashMap::new();
+        let mut node = zsl.first();
+        while let Some(x) = node {
+            positions.insert(x, positions.len() + 1);
+            node = zsl.next(x);
+        }
+        assert_eq!(positions.len(), zsl.len());
+        for i in 0..zsl.level {
+            let mut total = 0;
+            let mut x = HEAD;
+            while let Some(next) = zsl.forward(x, i) {
+                total += zsl.nodes[x].levels[i].span;
+                assert_eq!(total, positions[&next], "level {}", i);
+                x = next;
+            }
+        }
+    }
+
+    fn build(n: usize) -> SkipList {
+        let mut zsl = SkipList::new();
+        // 乱序插入，分数有重复
+        for i in (0..n).map(|i| (i * 37) % n) {
+            zsl.insert((i / 3) as f64, member(i));
+        }
+        zsl
+    }
+
+    #[test]
+    fn rank_matches_order() {
+        let zsl = build(200);
+        check_spans(&zsl);
+        let mut node = zsl.first();
+        let mut rank = 0;
+        while let Some(x) = node {
+            rank += 1;
+            assert_eq!(zsl.rank(zsl.score(x), zsl.member(x)), rank);
+            assert_eq!(zsl.by_rank(rank), Some(x));
+            node = zsl.next(x);
+        }
+        assert_eq!(rank, 200);
+        assert_eq!(zsl.by_rank(0), None);
+        assert_eq!(zsl.by_rank(201), None);
+        assert_eq!(zsl.rank(1.0, b"missing"), 0);
+    }
+
+    #[test]
+    fn delete_keeps_spans() {
+        let mut zsl = build(100);
+        for i in (0..100).step_by(3) {
+            assert!(zsl.delete((i / 3) as f64, &member(i)));
+        }
+        assert!(!zsl.delete(0.0, &member(0)));
+        // 分数不对也删不掉
+        assert!(!zsl.delete(99.0, &member(1)));
+        check_spans(&zsl);
+        assert_eq!(zsl.len(), 66);
+        assert_eq!(zsl.member(zsl.first().unwrap()), &member(1));
+        assert_eq!(zsl.member(zsl.last().unwrap()), &member(98));
+        assert_eq!(zsl.prev(zsl.first().unwrap()), None);
+
+        // 删除的节点下标会被复用
+        let nodes = zsl.nodes.len();
+        zsl.insert(1000.0, Bytes::from_static(b"new"));
+        assert_eq!(zsl.nodes.len(), nodes);
+        check_spans(&zsl);
+        assert_eq!(zsl.rank(1000.0, b"new"), 67);
+    }
+
+    #[test]
+    fn score_ranges() {
+        let zsl = build(30);
+        let range = ScoreRange { min: 2.0, max: 4.0, minex: true, maxex: false };
+        assert_eq!(zsl.score(zsl.first_in_score_range(&range).unwrap()), 3.0);
+        assert_eq!(zsl.score(zsl.last_in_score_range(&range).unwrap()), 4.0);
+        let empty = ScoreRange { min: 3.0, max: 3.0, minex: true, maxex: false };
+        assert!(empty.is_empty());
+        assert_eq!(zsl.first_in_score_range(&empty), None);
+        let outside = ScoreRange { min: 100.0, max: 200.0, minex: false, maxex: false };
+        assert_eq!(zsl.first_in_score_range(&outside), None);
+        assert_eq!(zsl.last_in_score_range(&outside), None);
+    }
+
+    #[test]
+    fn lex_ranges() {
+        let mut zsl = SkipList::new();
+        for m in ["a", "b", "c", "d"] {
+            zsl.insert(0.0, Bytes::from(m));
+        }
+        let range = LexRange { min: LexBound::Exclusive(Bytes::from("a")), max: LexBound::Inclusive(Bytes::from("c")) };
+        assert_eq!(&zsl.member(zsl.first_in_lex_range(&range).unwrap())[..], b"b");
+        assert_eq!(&zsl.member(zsl.last_in_lex_range(&range).unwrap())[..], b"c");
+        let all = LexRange { min: LexBound::NegInf, max: LexBound::PosInf };
+        assert_eq!(&zsl.member(zsl.last_in_lex_range(&all).unwrap())[..], b"d");
+        let empty = LexRange { min: LexBound::Inclusive(Bytes::from("c")), max: LexBound::Exclusive(Bytes::from("c")) };
+        assert!(empty.is_empty());
+        assert!(LexRange { min: LexBound::PosInf, max: LexBound::PosInf }.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// 服务器运行的统计，CONFIG RESETSTAT 会清零
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    connections_received: AtomicU64,
+    commands_processed: AtomicU64,
+    rejected_connections: AtomicU64,
+    output_buffer_disconnections: AtomicU64,
+}
+
+// 某个时刻的统计，名字和redis INFO stats里面的一样
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StatsSnapshot {
+    pub total_connections_received: u64,
+    pub total_commands_processed: u64,
+    pub rejected_connections: u64,
+    pub client_output_buffer_limit_disconnections: u64,
+}
+
+impl Stats {
+    pub(crate) fn connection_received(&self) {
+        self.connections_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn command_processed(&self) {
+        self.commands_processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn output_buffer_disconnection(&self) {
+        self.output_buffer_disconnections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            total_connections_received: self.connections_received.load(Ordering::Relaxed),
+            total_commands_processed: self.commands_processed.load(Ordering::Relaxed),
+            rejected_connections: self.rejected_connections.load(Ordering::Relaxed),
+            client_output_buffer_limit_disconnections: self.output_buffer_disconnections.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.connections_received.store(0, Ordering::Relaxed);
+        self.commands_processed.store(0, Ordering::Relaxed);
+        self.rejected_connections.store(0, Ordering::Relaxed);
+        self.output_buffer_disconnections.store(0, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,651 @@
+use crate::frame::Frame;
+use crate::util::now_ms;
+use bytes::Bytes;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// redis的radix tree每个节点默认放100个entry，近似裁剪的时候只会整个节点删除
+pub const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+pub type StreamEntry = (StreamId, Vec<(Bytes, Bytes)>);
+
+// XADD 传入的ID
+#[derive(Debug, Clone, Copy)]
+pub enum IdSpec {
+    // *
+    Auto,
+    // ms-*
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrimOptions {
+    pub strategy: TrimStrategy,
+    // ~ 近似裁剪
+    pub approx: bool,
+    // 最多删除多少个，0代表不限制
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Vec<(Bytes, Bytes)>>,
+    last_id: StreamId,
+    // 一共添加过多少entry，用来计算消费组的lag
+    entries_added: u64,
+    // XDEL 删除过的最大ID
+    max_deleted_id: StreamId,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+// 已经投递但是还没有ACK的消息
+#[derive(Debug, Clone)]
+pub struct PendingEntry {
+    pub consumer: String,
+    // 最后一次投递的时间，毫秒
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Consumer {
+    // 最后一次尝试读取的时间
+    pub seen_time: u64,
+    // 最后一次成功读到消息的时间
+    pub active_time: Option<u64>,
+    pub pending: BTreeSet<StreamId>,
+}
+
+#[derive(Debug, Default)]
+pub struct ConsumerGroup {
+    pub last_id: StreamId,
+    // 这个组读过多少entry，None代表无法计算
+    pub entries_read: Option<u64>,
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<String, Consumer>,
+}
+
+// XCLAIM 和 XAUTOCLAIM 的选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClaimOptions {
+    // 空闲时间小于这个值的消息不会被转移
+    pub min_idle: u64,
+    pub delivery_time: u64,
+    // 指定了就直接覆盖投递次数
+    pub retry_count: Option<u64>,
+    // 不在pending列表里面也强制转移
+    pub force: bool,
+    // 只返回ID，不增加投递次数
+    pub justid: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ClaimResult {
+    Claimed,
+    Skipped,
+    // 消息已经从stream里删掉了，顺便从pending列表里面移除
+    Deleted,
+}
+
+// XREADGROUP 读历史消息的结果，消息被删除了的时候fields是None
+pub type PendingRead = (StreamId, Option<Vec<(Bytes, Bytes)>>);
+
+impl ConsumerGroup {
+    pub fn new(last_id: StreamId, entries_read: Option<u64>) -> ConsumerGroup {
+        ConsumerGroup {
+            last_id,
+            entries_read,
+            ..ConsumerGroup::default()
+        }
+    }
+
+    // 不存在的时候创建消费者，返回是否新建
+    pub fn create_consumer(&mut self, name: &str, now: u64) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+        self.consumers.insert(name.to_string(), Consumer { seen_time: now, ..Consumer::default() });
+        true
+    }
+
+    // 删除消费者，它名下的pending消息也一起删掉，返回删掉的数量
+    pub fn delete_consumer(&mut self, name: &str) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    // 把消息分配给consumer，已经在pending里面的会从原来的消费者转移过来
+    pub fn assign(&mut self, id: StreamId, consumer: &str, delivery_time: u64, delivery_count: u64) {
+        if let Some(prev) = self.pending.get(&id) {
+            if let Some(owner) = self.consumers.get_mut(&prev.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+        self.create_consumer(consumer, delivery_time);
+        self.consumers.get_mut(consumer).unwrap().pending.insert(id);
+        self.pending.insert(id, PendingEntry {
+            consumer: consumer.to_string(),
+            delivery_time,
+            delivery_count,
+        });
+    }
+
+    pub fn ack(&mut self, id: StreamId) -> bool {
+        match self.pending.remove(&id) {
+            Some(entry) => {
+                if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+                    consumer.pending.remove(&id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId { ms: u64::MAX, seq: u64::MAX };
+
+    pub fn new(ms: u64, seq: u64) -> StreamId {
+        StreamId { ms, seq }
+    }
+
+    // 解析 ms-seq，只有ms的时候seq用missing_seq补上
+    pub fn parse(src: &[u8], missing_seq: u64) -> Option<StreamId> {
+        let s = std::str::from_utf8(src).ok()?;
+        match s.split_once('-') {
+            Some((ms, seq)) => Some(StreamId::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(StreamId::new(s.parse().ok()?, missing_seq)),
+        }
+    }
+
+    pub fn incr(self) -> Option<StreamId> {
+        if self.seq < u64::MAX {
+            Some(StreamId::new(self.ms, self.seq + 1))
+        } else if self.ms < u64::MAX {
+            Some(StreamId::new(self.ms + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    pub fn decr(self) -> Option<StreamId> {
+        if self.seq > 0 {
+            Some(StreamId::new(self.ms, self.seq - 1))
+        } else if self.ms > 0 {
+            Some(StreamId::new(self.ms - 1, u64::MAX))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_frame(self) -> Frame {
+        Frame::Bulk(Bytes::from(self.to_string()))
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Stream {
+    pub fn new() -> Stream {
+        Stream::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn first_id(&self) -> StreamId {
+        self.entries.keys().next().copied().unwrap_or(StreamId::MIN)
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    pub fn first_entry(&self) -> Option<StreamEntry> {
+        self.entries.iter().next().map(|(id, fields)| (*id, fields.clone()))
+    }
+
+    pub fn last_entry(&self) -> Option<StreamEntry> {
+        self.entries.iter().next_back().map(|(id, fields)| (*id, fields.clone()))
+    }
+
+    pub fn groups(&self) -> &BTreeMap<String, ConsumerGroup> {
+        &self.groups
+    }
+
+    pub fn group(&self, name: &str) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    // 组已经存在的时候返回false
+    pub fn create_group(&mut self, name: &str, last_id: StreamId, entries_read: Option<u64>) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups.insert(name.to_string(), ConsumerGroup::new(last_id, entries_read));
+        true
+    }
+
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    // 估算从第一条entry到id一共有多少条，用来在CREATE和SETID的时候推算entries_read
+    pub fn estimate_entries_read(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        if self.is_empty() && id <= self.last_id {
+            return Some(self.entries_added);
+        }
+        if id == self.last_id {
+            return Some(self.entries_added);
+        }
+        if id > self.last_id {
+            return None;
+        }
+        // 第一条之前没有被XDEL删除过的空洞才能算准
+        let first_id = self.first_id();
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first_id {
+            let before_first = self.entries_added - self.len() as u64;
+            if id < first_id {
+                return Some(before_first);
+            }
+            if id == first_id {
+                return Some(before_first + 1);
+            }
+        }
+        None
+    }
+
+    // start之后有没有被XDEL删除的消息，有的话entries_read就不能简单累加
+    fn has_tombstones_after(&self, start: StreamId) -> bool {
+        if self.is_empty() || self.max_deleted_id == StreamId::MIN || self.max_deleted_id < self.first_id() {
+            return false;
+        }
+        self.max_deleted_id >= start
+    }
+
+    // 消费组还有多少条没有读，None代表无法计算
+    pub fn group_lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        if let Some(read) = group.entries_read {
+            if !self.has_tombstones_after(group.last_id) {
+                return Some(self.entries_added.saturating_sub(read));
+            }
+        }
+        self.estimate_entries_read(group.last_id).map(|read| self.entries_added.saturating_sub(read))
+    }
+
+    // 读取组里面还没投递过的新消息（ID为 >），并放到consumer的pending里面
+    pub fn read_group_new(&mut self, group: &str, consumer: &str, count: Option<usize>, noack: bool) -> Vec<StreamEntry> {
+        let now = now_ms();
+        let cg = self.groups.get_mut(group).unwrap();
+        cg.create_consumer(consumer, now);
+        cg.consumers.get_mut(consumer).unwrap().seen_time = now;
+
+        let start = match cg.last_id.incr() {
+            Some(start) => start,
+            None => return vec![],
+        };
+        let mut entries_read = cg.entries_read;
+        let entries: Vec<StreamEntry> = self
+            .entries
+            .range(start..)
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect();
+        if entries.is_empty() {
+            return entries;
+        }
+
+        for (id, _) in &entries {
+            entries_read = match entries_read {
+                Some(read) if !self.has_tombstones_after(*id) => Some(read + 1),
+                _ => self.estimate_entries_read(*id),
+            };
+        }
+
+        let cg = self.groups.get_mut(group).unwrap();
+        cg.entries_read = entries_read;
+        for (id, _) in &entries {
+            cg.last_id = *id;
+            if !noack {
+                cg.assign(*id, consumer, now, 1);
+            }
+        }
+        cg.consumers.get_mut(consumer).unwrap().active_time = Some(now);
+        entries
+    }
+
+    // 读取consumer自己的pending消息（历史消息），会增加投递次数
+    pub fn read_group_history(&mut self, group: &str, consumer: &str, start: StreamId, count: Option<usize>) -> Vec<PendingRead> {
+        let now = now_ms();
+        let cg = self.groups.get_mut(group).unwrap();
+        cg.create_consumer(consumer, now);
+        let owner = cg.consumers.get_mut(consumer).unwrap();
+        owner.seen_time = now;
+
+        let ids: Vec<StreamId> = owner.pending.range(start..).take(count.unwrap_or(usize::MAX)).copied().collect();
+        let mut result = Vec::with_capacity(ids.len());
+        for id in ids {
+            match self.entries.get(&id) {
+                Some(fields) => {
+                    let entry = cg.pending.get_mut(&id).unwrap();
+                    entry.delivery_time = now;
+                    entry.delivery_count += 1;
+                    result.push((id, Some(fields.clone())));
+                }
+                None => result.push((id, None)),
+            }
+        }
+        result
+    }
+
+    // 把消息转移给consumer，调用方需要保证组存在
+    pub fn claim(&mut self, group: &str, consumer: &str, id: StreamId, options: &ClaimOptions) -> ClaimResult {
+        let exists = self.entries.contains_key(&id);
+        let cg = self.groups.get_mut(group).unwrap();
+        let now = now_ms();
+        cg.create_consumer(consumer, now);
+        cg.consumers.get_mut(consumer).unwrap().seen_time = now;
+
+        let delivery_count = match cg.pending.get(&id) {
+            Some(_) if !exists => {
+                cg.ack(id);
+                return ClaimResult::Deleted;
+            }
+            Some(entry) => {
+                if now.saturating_sub(entry.delivery_time) < options.min_idle {
+                    return ClaimResult::Skipped;
+                }
+                entry.delivery_count
+            }
+            None if options.force && exists => 0,
+            None => return ClaimResult::Skipped,
+        };
+
+        let delivery_count = match options.retry_count {
+            Some(count) => count,
+            None if options.justid => delivery_count,
+            None => delivery_count + 1,
+        };
+        cg.assign(id, consumer, options.delivery_time, delivery_count);
+        cg.consumers.get_mut(consumer).unwrap().active_time = Some(now);
+        ClaimResult::Claimed
+    }
+
+    // 根据XADD的参数算出新的ID，必须比最后一个ID大
+    pub fn next_id(&self, spec: IdSpec) -> crate::Result<StreamId> {
+        const SMALLER: &str = "ERR The ID specified in XADD is equal or smaller than the target stream top item";
+        match spec {
+            IdSpec::Auto => {
+                let now = now_ms();
+                if now > self.last_id.ms {
+                    Ok(StreamId::new(now, 0))
+                } else {
+                    // 时钟回拨的时候沿用最后一个ID的时间
+                    self.last_id.incr().ok_or_else(|| SMALLER.into())
+                }
+            }
+            IdSpec::AutoSeq(ms) => {
+                if ms > self.last_id.ms {
+                    Ok(StreamId::new(ms, 0))
+                } else if ms == self.last_id.ms && self.last_id.seq < u64::MAX {
+                    Ok(StreamId::new(ms, self.last_id.seq + 1))
+                } else {
+                    Err(SMALLER.into())
+                }
+            }
+            IdSpec::Explicit(id) => {
+                if id == StreamId::MIN {
+                    Err("ERR The ID specified in XADD must be greater than 0-0".into())
+                } else if id <= self.last_id {
+                    Err(SMALLER.into())
+                } else {
+                    Ok(id)
+                }
+            }
+        }
+    }
+
+    // 调用方需要先通过next_id拿到合法的ID
+    pub fn add(&mut self, id: StreamId, fields: Vec<(Bytes, Bytes)>) {
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    pub fn delete(&mut self, id: StreamId) -> bool {
+        if self.entries.remove(&id).is_none() {
+            return false;
+        }
+        if id > self.max_deleted_id {
+            self.max_deleted_id = id;
+        }
+        true
+    }
+
+    pub fn get(&self, id: StreamId) -> Option<&Vec<(Bytes, Bytes)>> {
+        self.entries.get(&id)
+    }
+
+    // 取 [start, end] 区间，count为None代表不限制
+    pub fn range(&self, start: StreamId, end: StreamId, count: Option<usize>, rev: bool) -> Vec<StreamEntry> {
+        if start > end {
+            return vec![];
+        }
+        let iter = self.entries.range(start..=end);
+        let take = count.unwrap_or(usize::MAX);
+        if rev {
+            iter.rev().take(take).map(|(id, fields)| (*id, fields.clone())).collect()
+        } else {
+            iter.take(take).map(|(id, fields)| (*id, fields.clone())).collect()
+        }
+    }
+
+    // 裁剪掉最老的entry，返回删除的数量
+    pub fn trim(&mut self, options: &TrimOptions) -> usize {
+        let mut removable = match options.strategy {
+            TrimStrategy::MaxLen(maxlen) => self.len().saturating_sub(maxlen),
+            TrimStrategy::MinId(minid) => self.entries.range(..minid).count(),
+        };
+
+        if options.approx {
+            // 近似裁剪只删除完整的节点，默认最多删除100个节点
+            let limit = match options.limit {
+                Some(0) => usize::MAX,
+                Some(limit) => limit,
+                None => STREAM_NODE_MAX_ENTRIES * 100,
+            };
+            removable = removable.min(limit);
+            removable -= removable % STREAM_NODE_MAX_ENTRIES;
+        }
+
+        for _ in 0..removable {
+            let id = *self.entries.keys().next().unwrap();
+            self.entries.remove(&id);
+        }
+        removable
+    }
+}
+
+// entry列表转成回复，每个entry是 [id, [field, value, ...]]
+pub fn entries_frame(entries: Vec<StreamEntry>) -> Frame {
+    Frame::Array(entries.into_iter().map(entry_frame).collect())
+}
+
+pub fn entry_frame((id, fields): StreamEntry) -> Frame {
+    let mut kv = Vec::with_capacity(fields.len() * 2);
+    for (field, value) in fields {
+        kv.push(Frame::Bulk(field));
+        kv.push(Frame::Bulk(value));
+    }
+    Frame::Array(vec![id.to_frame(), Frame::Array(kv)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(ms: u64, seq: u64) -> StreamId {
+        StreamId::new(ms, seq)
+    }
+
+    fn sample(n: u64) -> Stream {
+        let mut stream = Stream::new();
+        for i in 1..=n {
+            stream.add(id(i, 0), vec![(Bytes::from_static(b"f"), Bytes::from(i.to_string()))]);
+        }
+        stream
+    }
+
+    #[test]
+    fn parse_and_step_ids() {
+        assert_eq!(StreamId::parse(b"5-3", 0), Some(id(5, 3)));
+        assert_eq!(StreamId::parse(b"5", u64::MAX), Some(id(5, u64::MAX)));
+        assert_eq!(StreamId::parse(b"5-x", 0), None);
+        assert_eq!(StreamId::parse(b"-1", 0), None);
+        assert_eq!(id(1, u64::MAX).incr(), Some(id(2, 0)));
+        assert_eq!(StreamId::MAX.incr(), None);
+        assert_eq!(id(2, 0).decr(), Some(id(1, u64::MAX)));
+        assert_eq!(StreamId::MIN.decr(), None);
+        assert_eq!(id(7, 1).to_string(), "7-1");
+    }
+
+    #[test]
+    fn next_id_must_grow() {
+        let stream = sample(3);
+        assert_eq!(stream.next_id(IdSpec::AutoSeq(3)).unwrap(), id(3, 1));
+        assert_eq!(stream.next_id(IdSpec::AutoSeq(4)).unwrap(), id(4, 0));
+        assert!(stream.next_id(IdSpec::AutoSeq(2)).is_err());
+        assert!(stream.next_id(IdSpec::Explicit(id(3, 0))).is_err());
+        assert!(Stream::new().next_id(IdSpec::Explicit(StreamId::MIN)).is_err());
+        assert!(stream.next_id(IdSpec::Auto).unwrap() > id(3, 0));
+    }
+
+    #[test]
+    fn range_and_delete() {
+        let mut stream = sample(5);
+        let ids = |entries: Vec<StreamEntry>| entries.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>();
+        assert_eq!(ids(stream.range(id(2, 0), id(4, 0), None, false)), vec![2, 3, 4]);
+        assert_eq!(ids(stream.range(StreamId::MIN, StreamId::MAX, Some(2), true)), vec![5, 4]);
+        assert!(stream.range(id(4, 0), id(2, 0), None, false).is_empty());
+        assert!(stream.delete(id(3, 0)));
+        assert!(!stream.delete(id(3, 0)));
+        assert_eq!(stream.max_deleted_id(), id(3, 0));
+        assert_eq!(stream.len(), 4);
+        assert_eq!(stream.entries_added(), 5);
+    }
+
+    #[test]
+    fn trim_exact_and_approx() {
+        let mut stream = sample(10);
+        let exact = TrimOptions { strategy: TrimStrategy::MaxLen(4), approx: false, limit: None };
+        assert_eq!(stream.trim(&exact), 6);
+        assert_eq!(stream.first_id(), id(7, 0));
+        let minid = TrimOptions { strategy: TrimStrategy::MinId(id(9, 0)), approx: false, limit: None };
+        assert_eq!(stream.trim(&minid), 2);
+
+        // 近似裁剪只删整个节点
+        let mut stream = sample(250);
+        let approx = TrimOptions { strategy: TrimStrategy::MaxLen(10), approx: true, limit: None };
+        assert_eq!(stream.trim(&approx), 200);
+        let limited = TrimOptions { strategy: TrimStrategy::MaxLen(0), approx: true, limit: Some(99) };
+        assert_eq!(stream.trim(&limited), 0);
+    }
+
+    #[test]
+    fn group_reads_and_acks() {
+        let mut stream = sample(3);
+        assert!(stream.create_group("g", StreamId::MIN, Some(0)));
+        assert!(!stream.create_group("g", StreamId::MIN, Some(0)));
+        assert_eq!(stream.group_lag(stream.group("g").unwrap()), Some(3));
+
+        let entries = stream.read_group_new("g", "alice", Some(2), false);
+        assert_eq!(entries.len(), 2);
+        let group = stream.group("g").unwrap();
+        assert_eq!(group.last_id, id(2, 0));
+        assert_eq!(group.entries_read, Some(2));
+        assert_eq!(group.pending.len(), 2);
+        assert_eq!(stream.group_lag(group), Some(1));
+
+        // 历史消息会增加投递次数，被删掉的消息返回None
+        stream.delete(id(2, 0));
+        let history = stream.read_group_history("g", "alice", StreamId::MIN, None);
+        assert_eq!(history.len(), 2);
+        assert!(history[1].1.is_none());
+        assert_eq!(stream.group("g").unwrap().pending[&id(1, 0)].delivery_count, 2);
+
+        let group = stream.group_mut("g").unwrap();
+        assert!(group.ack(id(1, 0)));
+        assert!(!group.ack(id(1, 0)));
+        assert_eq!(group.consumers["alice"].pending.len(), 1);
+        assert_eq!(group.delete_consumer("alice"), Some(1));
+        assert!(group.pending.is_empty());
+    }
+
+    #[test]
+    fn claim_moves_pending() {
+        let mut stream = sample(2);
+        stream.create_group("g", StreamId::MIN, Some(0));
+        stream.read_group_new("g", "alice", None, false);
+        let options = ClaimOptions { delivery_time: now_ms(), ..ClaimOptions::default() };
+        assert_eq!(stream.claim("g", "bob", id(1, 0), &options), ClaimResult::Claimed);
+        let group = stream.group("g").unwrap();
+        assert_eq!(group.pending[&id(1, 0)].consumer, "bob");
+        assert_eq!(group.pending[&id(1, 0)].delivery_count, 2);
+        assert!(!group.consumers["alice"].pending.contains(&id(1, 0)));
+
+        // 空闲时间不够的不转移
+        let idle = ClaimOptions { min_idle: 60_000, ..options };
+        assert_eq!(stream.claim("g", "bob", id(2, 0), &idle), ClaimResult::Skipped);
+        // 已经删除的消息从pending里面去掉
+        stream.delete(id(2, 0));
+        assert_eq!(stream.claim("g", "bob", id(2, 0), &options), ClaimResult::Deleted);
+        assert!(!stream.group("g").unwrap().pending.contains_key(&id(2, 0)));
+        // 不在pending里面的需要FORCE
+        assert_eq!(stream.claim("g", "bob", id(9, 0), &options), ClaimResult::Skipped);
+    }
+}
@@ -0,0 +1,447 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 解析redis格式的浮点数，支持 inf +inf -inf，不接受 nan
+pub fn parse_double(src: &[u8]) -> Option<f64> {
+    let s = std::str::from_utf8(src).ok()?;
+    // 和strtod一样，不允许前后有空白
+    if s.is_empty() || s.trim() != s {
+        return None;
+    }
+    match s.parse::<f64>() {
+        Ok(v) if v.is_nan() => None,
+        Ok(v) => Some(v),
+        Err(_) => None,
+    }
+}
+
+// 解析redis格式的整数，超出i64范围返回None
+pub fn parse_int(src: &[u8]) -> Option<i64> {
+    let s = std::str::from_utf8(src).ok()?;
+    // "+1" 和 "01" 这种redis都是不接受的
+    if s.starts_with('+') || (s.len() > 1 && s.starts_with('0')) || s.starts_with("-0") {
+        return None;
+    }
+    s.parse::<i64>().ok()
+}
+
+// 将浮点数格式化成redis的格式，取能还原的最短位数，排版规则和 %.17g 一致
+pub fn format_double(v: f64) -> String {
+    if v.is_infinite() {
+        return if v > 0.0 { "inf".to_string() } else { "-inf".to_string() };
+    }
+    if v == 0.0 {
+        return if v.is_sign_negative() { "-0".to_string() } else { "0".to_string() };
+    }
+
+    // {:e} 输出的是能还原的最短表示，比如 1.5e0 1e20
+    let sci = format!("{:e}", v);
+    let (mantissa, exp) = sci.split_at(sci.find('e').unwrap());
+    let exp: i32 = exp[1..].parse().unwrap();
+    let (neg, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => (true, m),
+        None => (false, mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+
+    let mut out = String::new();
+    if neg {
+        out.push('-');
+    }
+    if !(-4..17).contains(&exp) {
+        // 科学计数法，指数至少两位
+        out.push_str(&digits[..1]);
+        if digits.len() > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if exp < 0 { '-' } else { '+' });
+        out.push_str(&format!("{:02}", exp.abs()));
+    } else if exp < 0 {
+        out.push_str("0.");
+        for _ in 0..(-exp - 1) {
+            out.push('0');
+        }
+        out.push_str(&digits);
+    } else {
+        let int_len = exp as usize + 1;
+        if digits.len() <= int_len {
+            out.push_str(&digits);
+            for _ in digits.len()..int_len {
+                out.push('0');
+            }
+        } else {
+            out.push_str(&digits[..int_len]);
+            out.push('.');
+            out.push_str(&digits[int_len..]);
+        }
+    }
+    out
+}
+
+// 解析配置里面的内存大小，比如 32mb，k/m/g 是1000进制，kb/mb/gb 是1024进制，和redis的memtoull一样
+pub fn parse_memory(src: &str) -> Option<u64> {
+    let src = src.to_lowercase();
+    let digits = src.find(|c: char| !c.is_ascii_digit()).unwrap_or(src.len());
+    let unit = match &src[digits..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    src[..digits].parse::<u64>().ok()?.checked_mul(unit)
+}
+
+// 当前的unix时间戳，单位毫秒
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// 先写到同一个目录下的临时文件再改名，写到一半失败或者进程退出不会破坏原来的文件
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)
+}
+
+// 按sdssplitargs的规则把一行切成参数：双引号里面支持 \n \r \t \b \a 和 \xHH 转义，单引号里面只能转义单引号，
+// 引号结束之后必须是空白或者行尾，引号不匹配的时候返回None
+pub fn split_args(line: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Some(args);
+        }
+        let mut arg = Vec::new();
+        let (mut in_double, mut in_single) = (false, false);
+        loop {
+            let c = line.get(i).copied();
+            if in_double {
+                match c? {
+                    b'\\' if i + 3 < line.len() && line[i + 1] == b'x' && hex_pair(line[i + 2], line[i + 3]).is_some() => {
+                        arg.push(hex_pair(line[i + 2], line[i + 3]).unwrap());
+                        i += 3;
+                    }
+                    b'\\' if i + 1 < line.len() => {
+                        i += 1;
+                        arg.push(match line[i] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            c => c,
+                        });
+                    }
+                    b'"' => {
+                        if line.get(i + 1).is_some_and(|c| !c.is_ascii_whitespace()) {
+                            return None;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    c => arg.push(c),
+                }
+            } else if in_single {
+                match c? {
+                    b'\\' if line.get(i + 1) == Some(&b'\'') => {
+                        arg.push(b'\'');
+                        i += 1;
+                    }
+                    b'\'' => {
+                        if line.get(i + 1).is_some_and(|c| !c.is_ascii_whitespace()) {
+                            return None;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    c => arg.push(c),
+                }
+            } else {
+                match c {
+                    None => break,
+                    Some(c) if c.is_ascii_whitespace() => break,
+                    Some(b'"') => in_double = true,
+                    Some(b'\'') => in_single = true,
+                    Some(c) => arg.push(c),
+                }
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+fn hex_pair(high: u8, low: u8) -> Option<u8> {
+    let digit = |c: u8| (c as char).to_digit(16);
+    Some((digit(high)? * 16 + digit(low)?) as u8)
+}
+
+// redis的glob匹配，支持 * ? [abc] [^a-z] 和 \ 转义，规则和stringmatchlen一致
+pub fn string_match(pattern: &[u8], string: &[u8], nocase: bool) -> bool {
+    let eq = |a: u8, b: u8| if nocase { a.eq_ignore_ascii_case(&b) } else { a == b };
+    let (mut p, mut s) = (0, 0);
+    while p < pattern.len() && s < string.len() {
+        match pattern[p] {
+            b'*' => {
+                while p + 1 < pattern.len() && pattern[p + 1] == b'*' {
+                    p += 1;
+                }
+                if p + 1 == pattern.len() {
+                    return true;
+                }
+                return (s..string.len()).any(|i| string_match(&pattern[p + 1..], &string[i..], nocase));
+            }
+            b'?' => s += 1,
+            b'[' => {
+                p += 1;
+                let not = p < pattern.len() && pattern[p] == b'^';
+                if not {
+                    p += 1;
+                }
+                let mut matched = false;
+                loop {
+                    if p >= pattern.len() {
+                        // 没有闭合的 [ 当作到结尾为止
+                        p -= 1;
+                        break;
+                    } else if pattern[p] == b'\\' && p + 1 < pattern.len() {
+                        p += 1;
+                        matched |= pattern[p] == string[s];
+                    } else if pattern[p] == b']' {
+                        break;
+                    } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' {
+                        let (mut start, mut end, mut c) = (pattern[p], pattern[p + 2], string[s]);
+                        if start > end {
+                            std::mem::swap(&mut start, &mut end);
+                        }
+                        if nocase {
+                            start = start.to_ascii_lowercase();
+                            end = end.to_ascii_lowercase();
+                            c = c.to_ascii_lowercase();
+                        }
+                        p += 2;
+                        matched |= c >= start && c <= end;
+                    } else {
+                        matched |= eq(pattern[p], string[s]);
+                    }
+                    p += 1;
+                }
+                if matched == not {
+                    return false;
+                }
+                s += 1;
+            }
+            c => {
+                let c = if c == b'\\' && p + 1 < pattern.len() {
+                    p += 1;
+                    pattern[p]
+                } else {
+                    c
+                };
+                if !eq(c, string[s]) {
+                    return false;
+                }
+                s += 1;
+            }
+        }
+        p += 1;
+        if s == string.len() {
+            while p < pattern.len() && pattern[p] == b'*' {
+                p += 1;
+            }
+        }
+    }
+    p == pattern.len() && s == string.len()
+}
+
+// 集群使用的CRC16(XMODEM)
+pub fn crc16(buf: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in buf {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// key对应的hash slot，有 {tag} 的时候只计算花括号里面的部分
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    if let Some(start) = key.iter().position(|&c| c == b'{') {
+        if let Some(len) = key[start + 1..].iter().position(|&c| c == b'}') {
+            if len > 0 {
+                return crc16(&key[start + 1..start + 1 + len]) & 0x3fff;
+            }
+        }
+    }
+    crc16(key) & 0x3fff
+}
+
+// 脚本缓存用的SHA1，返回40位小写十六进制
+pub fn sha1_hex(data: &[u8]) -> String {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // 补齐到64字节的整数倍，最后8个字节是原始长度(bit)
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([chunk[i * 4], chunk[i * 4 + 1], chunk[i * 4 + 2], chunk[i * 4 + 3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+
+    h.iter().map(|x| format!("{:08x}", x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(line: &str) -> Vec<String> {
+        split_args(line.as_bytes()).unwrap().into_iter().map(|arg| String::from_utf8(arg).unwrap()).collect()
+    }
+
+    #[test]
+    fn parse_numbers() {
+        assert_eq!(parse_double(b"1.5"), Some(1.5));
+        assert_eq!(parse_double(b"-inf"), Some(f64::NEG_INFINITY));
+        assert_eq!(parse_double(b"+inf"), Some(f64::INFINITY));
+        assert_eq!(parse_double(b"nan"), None);
+        assert_eq!(parse_double(b" 1"), None);
+        assert_eq!(parse_double(b""), None);
+        assert_eq!(parse_int(b"-42"), Some(-42));
+        assert_eq!(parse_int(b"9223372036854775807"), Some(i64::MAX));
+        assert_eq!(parse_int(b"9223372036854775808"), None);
+        assert_eq!(parse_int(b"+1"), None);
+        assert_eq!(parse_int(b"01"), None);
+    }
+
+    #[test]
+    fn format_double_like_redis() {
+        assert_eq!(format_double(1.0), "1");
+        assert_eq!(format_double(1.5), "1.5");
+        assert_eq!(format_double(-0.0), "-0");
+        assert_eq!(format_double(f64::INFINITY), "inf");
+        assert_eq!(format_double(f64::NEG_INFINITY), "-inf");
+        assert_eq!(format_double(0.1), "0.1");
+        assert_eq!(format_double(1e20), "1e+20");
+        // 和Redis 7.2一样输出能还原的最短表示
+        assert_eq!(format_double(1.5e-7), "1.5e-07");
+        assert_eq!(format_double(123456789012345680.0), "1.2345678901234568e+17");
+        assert_eq!(format_double(0.0001), "0.0001");
+    }
+
+    #[test]
+    fn write_atomic_replaces_file() {
+        let dir = std::env::temp_dir().join(format!("my-redis-util-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.conf");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        // 临时文件在原文件名后面加后缀，不会和同名不同扩展名的文件冲突
+        assert!(!dir.join("data.conf.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(string_match(b"*", b"anything", false));
+        assert!(string_match(b"news.*", b"news.tech", false));
+        assert!(!string_match(b"news.*", b"new", false));
+        assert!(string_match(b"h?llo", b"hallo", false));
+        assert!(string_match(b"h[ae]llo", b"hello", false));
+        assert!(!string_match(b"h[^e]llo", b"hello", false));
+        assert!(string_match(b"h[a-c]llo", b"hbllo", false));
+        assert!(string_match(b"h[c-a]llo", b"hbllo", false));
+        assert!(string_match(b"a\\*b", b"a*b", false));
+        assert!(!string_match(b"a\\*b", b"axb", false));
+        assert!(string_match(b"*a*b*", b"xxaxxbxx", false));
+        assert!(string_match(b"HELLO", b"hello", true));
+        assert!(!string_match(b"HELLO", b"hello", false));
+    }
+
+    #[test]
+    fn sha1_like_script_load() {
+        assert_eq!(sha1_hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(sha1_hex(b"return 1"), "e0e1f9fabfc9d4800c877a703b823ac0578ff8db");
+        // 超过一个64字节分组
+        assert_eq!(sha1_hex(&[b'a'; 100]), "7f9000257a4918d7072655ea468540cdcbd42e0c");
+    }
+
+    #[test]
+    fn cluster_key_slots() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_hash_slot(b"foo"), 12182);
+        assert_eq!(key_hash_slot(b"bar"), 5061);
+        // 只计算第一个花括号里面的部分，空的 {} 不算
+        assert_eq!(key_hash_slot(b"{user1000}.following"), key_hash_slot(b"user1000"));
+        assert_eq!(key_hash_slot(b"foo{bar}{zap}"), key_hash_slot(b"bar"));
+        assert_eq!(key_hash_slot(b"{}foo"), crc16(b"{}foo") & 0x3fff);
+        assert_eq!(key_hash_slot(b"foo{"), crc16(b"foo{") & 0x3fff);
+    }
+
+    #[test]
+    fn split_args_quotes_and_escapes() {
+        assert_eq!(split("SET \"a b\" \"c d\""), vec!["SET", "a b", "c d"]);
+        assert_eq!(split("set 'it\\'s' \"\\x41\\x4a\\n\""), vec!["set", "it's", "AJ\n"]);
+        assert_eq!(split("  ping  \r\n"), vec!["ping"]);
+        // 引号可以出现在参数中间
+        assert_eq!(split("a\"b c\" d"), vec!["ab c", "d"]);
+        assert_eq!(split_args(b"x \"\\xZZ\"").unwrap(), vec![b"x".to_vec(), b"xZZ".to_vec()]);
+        assert_eq!(split_args(b"").unwrap(), Vec::<Vec<u8>>::new());
+    }
+
+    #[test]
+    fn split_args_rejects_unbalanced_quotes() {
+        assert!(split_args(b"set \"a").is_none());
+        assert!(split_args(b"set 'a").is_none());
+        assert!(split_args(b"set \"a\"b").is_none());
+        assert!(split_args(b"set 'a'b").is_none());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::skiplist::{SkipList, ScoreRange, LexRange, LexBound};
+use crate::util::{parse_double, format_double};
+use crate::frame::Frame;
+use bytes::Bytes;
+use std::collections::HashMap;
+
+// 有序集合，dict负责 O(1) 查分数，skiplist负责排名和范围查询
+#[derive(Debug, Default)]
+pub struct ZSet {
+    dict: HashMap<Bytes, f64>,
+    zsl: SkipList,
+}
+
+// ZADD 的选项
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AddFlags {
+    pub nx: bool,
+    pub xx: bool,
+    pub gt: bool,
+    pub lt: bool,
+    pub incr: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AddResult {
+    Added(f64),
+    Updated(f64),
+    // 分数没有变化，也算处理过
+    Unchanged(f64),
+    // 被 NX/XX/GT/LT 拦下来了
+    Skipped,
+    Nan,
+}
+
+impl ZSet {
+    pub fn new() -> ZSet {
+        ZSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.zsl.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zsl.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.dict.get(member).copied()
+    }
+
+    pub fn add(&mut self, member: Bytes, score: f64, flags: AddFlags) -> AddResult {
+        match self.dict.get(&member).copied() {
+            Some(cur) => {
+                if flags.nx {
+                    return AddResult::Skipped;
+                }
+                let score = if flags.incr { cur + score } else { score };
+                if score.is_nan() {
+                    return AddResult::Nan;
+                }
+                if (flags.lt && score >= cur) || (flags.gt && score <= cur) {
+                    return AddResult::Skipped;
+                }
+                if score == cur {
+                    return AddResult::Unchanged(score);
+                }
+                self.zsl.delete(cur, &member);
+                self.zsl.insert(score, member.clone());
+                self.dict.insert(member, score);
+                AddResult::Updated(score)
+            }
+            None => {
+                if flags.xx {
+                    return AddResult::Skipped;
+                }
+                self.zsl.insert(score, member.clone());
+                self.dict.insert(member, score);
+                AddResult::Added(score)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.dict.remove(member) {
+            Some(score) => {
+                self.zsl.delete(score, member);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 排名从0开始，rev代表从大到小
+    pub fn rank(&self, member: &[u8], rev: bool) -> Option<usize> {
+        let score = self.score(member)?;
+        let rank = self.zsl.rank(score, member);
+        if rev {
+            Some(self.len() - rank)
+        } else {
+            Some(rank - 1)
+        }
+    }
+
+    // 按排名取 [start, end]，调用方需要保证下标合法
+    pub fn range_by_rank(&self, start: usize, end: usize, rev: bool) -> Vec<(Bytes, f64)> {
+        let mut result = Vec::with_capacity(end - start + 1);
+        let mut node = if rev {
+            self.zsl.by_rank(self.len() - start)
+        } else {
+            self.zsl.by_rank(start + 1)
+        };
+        for _ in start..=end {
+            let x = match node {
+                Some(x) => x,
+                None => break,
+            };
+            result.push((self.zsl.member(x).clone(), self.zsl.score(x)));
+            node = if rev { self.zsl.prev(x) } else { self.zsl.next(x) };
+        }
+        result
+    }
+
+    // count为None代表不限制数量
+    pub fn range_by_score(&self, range: &ScoreRange, rev: bool, offset: usize, count: Option<usize>) -> Vec<(Bytes, f64)> {
+        let node = if rev {
+            self.zsl.last_in_score_range(range)
+        } else {
+            self.zsl.first_in_score_range(range)
+        };
+        self.collect_range(node, rev, offset, count, |zsl, x| {
+            if rev {
+                range.value_gte_min(zsl.score(x))
+            } else {
+                range.value_lte_max(zsl.score(x))
+            }
+        })
+    }
+
+    pub fn range_by_lex(&self, range: &LexRange, rev: bool, offset: usize, count: Option<usize>) -> Vec<(Bytes, f64)> {
+        let node = if rev {
+            self.zsl.last_in_lex_range(range)
+        } else {
+            self.zsl.first_in_lex_range(range)
+        };
+        self.collect_range(node, rev, offset, count, |zsl, x| {
+            if rev {
+                range.value_gte_min(zsl.member(x))
+            } else {
+                range.value_lte_max(zsl.member(x))
+            }
+        })
+    }
+
+    fn collect_range<F>(&self, mut node: Option<usize>, rev: bool, offset: usize, count: Option<usize>, in_range: F) -> Vec<(Bytes, f64)>
+        where F: Fn(&SkipList, usize) -> bool {
+        let step = |x: usize| if rev { self.zsl.prev(x) } else { self.zsl.next(x) };
+
+        // 跳过offset个
+        for _ in 0..offset {
+            node = match node {
+                Some(x) => step(x),
+                None => break,
+            };
+        }
+
+        let mut result = vec![];
+        while let Some(x) = node {
+            if count.is_some_and(|c| result.len() >= c) || !in_range(&self.zsl, x) {
+                break;
+            }
+            result.push((self.zsl.member(x).clone(), self.zsl.score(x)));
+            node = step(x);
+        }
+        result
+    }
+
+    // 利用排名相减，O(log n)
+    pub fn count_in_score_range(&self, range: &ScoreRange) -> usize {
+        let first = match self.zsl.first_in_score_range(range) {
+            Some(x) => x,
+            None => return 0,
+        };
+        let last = self.zsl.last_in_score_range(range).unwrap();
+        let first_rank = self.zsl.rank(self.zsl.score(first), self.zsl.member(first));
+        let last_rank = self.zsl.rank(self.zsl.score(last), self.zsl.member(last));
+        last_rank - first_rank + 1
+    }
+
+    // 弹出count个分数最小（max为true时最大）的成员
+    pub fn pop(&mut self, count: usize, max: bool) -> Vec<(Bytes, f64)> {
+        let mut result = vec![];
+        while result.len() < count {
+            let x = match if max { self.zsl.last() } else { self.zsl.first() } {
+                Some(x) => x,
+                None => break,
+            };
+            let member = self.zsl.member(x).clone();
+            let score = self.zsl.score(x);
+            self.remove(&member);
+            result.push((member, score));
+        }
+        result
+    }
+
+    // 按分数从小到大遍历
+    pub fn iter(&self) -> impl Iterator<Item=(&Bytes, f64)> + '_ {
+        let mut node = self.zsl.first();
+        std::iter::from_fn(move || {
+            let x = node?;
+            node = self.zsl.next(x);
+            Some((self.zsl.member(x), self.zsl.score(x)))
+        })
+    }
+}
+
+// 解析分数区间的一端，"(" 开头代表开区间
+fn parse_score_bound(src: &[u8]) -> Option<(f64, bool)> {
+    match src.first() {
+        Some(b'(') => parse_double(&src[1..]).map(|v| (v, true)),
+        _ => parse_double(src).map(|v| (v, false)),
+    }
+}
+
+pub fn parse_score_range(min: &[u8], max: &[u8]) -> crate::Result<ScoreRange> {
+    match (parse_score_bound(min), parse_score_bound(max)) {
+        (Some((min, minex)), Some((max, maxex))) => Ok(ScoreRange { min, max, minex, maxex }),
+        _ => Err("ERR min or max is not a float".into()),
+    }
+}
+
+fn parse_lex_bound(src: &Bytes) -> Option<LexBound> {
+    match src.first() {
+        Some(b'+') if src.len() == 1 => Some(LexBound::PosInf),
+        Some(b'-') if src.len() == 1 => Some(LexBound::NegInf),
+        Some(b'(') => Some(LexBound::Exclusive(src.slice(1..))),
+        Some(b'[') => Some(LexBound::Inclusive(src.slice(1..))),
+        _ => None,
+    }
+}
+
+pub fn parse_lex_range(min: &Bytes, max: &Bytes) -> crate::Result<LexRange> {
+    match (parse_lex_bound(min), parse_lex_bound(max)) {
+        (Some(min), Some(max)) => Ok(LexRange { min, max }),
+        _ => Err("ERR min or max not valid string range item".into()),
+    }
+}
+
+// 成员列表转成回复，带分数的时候成员和分数交替出现
+pub fn members_frame(items: Vec<(Bytes, f64)>, with_scores: bool) -> Frame {
+    let mut frames = Vec::with_capacity(if with_scores { items.len() * 2 } else { items.len() });
+    for (member, score) in items {
+        frames.push(Frame::Bulk(member));
+        if with_scores {
+            frames.push(Frame::Bulk(Bytes::from(format_double(score))));
+        }
+    }
+    Frame::Array(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zset(items: &[(&'static str, f64)]) -> ZSet {
+        let mut zset = ZSet::new();
+        for (member, score) in items {
+            zset.add(Bytes::from_static(member.as_bytes()), *score, AddFlags::default());
+        }
+        zset
+    }
+
+    fn members(items: Vec<(Bytes, f64)>) -> Vec<String> {
+        items.into_iter().map(|(member, _)| String::from_utf8(member.to_vec()).unwrap()).collect()
+    }
+
+    #[test]
+    fn add_flags() {
+        let mut z = zset(&[("a", 1.0)]);
+        let member = || Bytes::from_static(b"a");
+        assert_eq!(z.add(member(), 5.0, AddFlags { nx: true, ..Default::default() }), AddResult::Skipped);
+        assert_eq!(z.add(member(), 0.5, AddFlags { gt: true, ..Default::default() }), AddResult::Skipped);
+        assert_eq!(z.add(member(), 2.0, AddFlags { gt: true, ..Default::default() }), AddResult::Updated(2.0));
+        assert_eq!(z.add(member(), 2.0, AddFlags::default()), AddResult::Unchanged(2.0));
+        assert_eq!(z.add(member(), 3.0, AddFlags { incr: true, ..Default::default() }), AddResult::Updated(5.0));
+        assert_eq!(z.add(Bytes::from_static(b"b"), 1.0, AddFlags { xx: true, ..Default::default() }), AddResult::Skipped);
+        assert_eq!(z.add(Bytes::from_static(b"b"), 1.0, AddFlags::default()), AddResult::Added(1.0));
+        // inf + -inf 是NaN
+        z.add(Bytes::from_static(b"c"), f64::INFINITY, AddFlags::default());
+        assert_eq!(z.add(Bytes::from_static(b"c"), f64::NEG_INFINITY, AddFlags { incr: true, ..Default::default() }), AddResult::Nan);
+        assert_eq!(z.len(), 3);
+        assert_eq!(z.rank(b"a", false), Some(1));
+        assert_eq!(z.rank(b"a", true), Some(1));
+    }
+
+    #[test]
+    fn ranges() {
+        let z = zset(&[("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0)]);
+        assert_eq!(members(z.range_by_rank(1, 2, false)), vec!["b", "c"]);
+        assert_eq!(members(z.range_by_rank(0, 1, true)), vec!["d", "c"]);
+        let range = parse_score_range(b"(1", b"+inf").unwrap();
+        assert_eq!(members(z.range_by_score(&range, false, 1, Some(1))), vec!["c"]);
+        assert_eq!(members(z.range_by_score(&range, true, 0, None)), vec!["d", "c", "b"]);
+        assert_eq!(z.count_in_score_range(&range), 3);
+        assert!(parse_score_range(b"x", b"1").is_err());
+
+        let lex = zset(&[("a", 0.0), ("b", 0.0), ("c", 0.0)]);
+        let range = parse_lex_range(&Bytes::from_static(b"[b"), &Bytes::from_static(b"+")).unwrap();
+        assert_eq!(members(lex.range_by_lex(&range, false, 0, None)), vec!["b", "c"]);
+        assert!(parse_lex_range(&Bytes::from_static(b"b"), &Bytes::from_static(b"+")).is_err());
+    }
+
+    #[test]
+    fn pop_and_remove() {
+        let mut z = zset(&[("a", 1.0), ("b", 2.0), ("c", 3.0)]);
+        assert_eq!(z.pop(1, true), vec![(Bytes::from_static(b"c"), 3.0)]);
+        assert_eq!(members(z.pop(5, false)), vec!["a", "b"]);
+        assert!(z.is_empty());
+        assert!(!z.remove(b"a"));
+        assert_eq!(z.score(b"a"), None);
+    }
+
+    #[test]
+    fn members_with_scores() {
+        let frame = members_frame(vec![(Bytes::from_static(b"a"), 1.5)], true);
+        assert_eq!(frame, Frame::Array(vec![Frame::Bulk(Bytes::from_static(b"a")), Frame::Bulk(Bytes::from_static(b"1.5"))]));
+    }
+}
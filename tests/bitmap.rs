@@ -0,0 +1,51 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, ok, start_server, Client};
+
+#[tokio::test]
+async fn bit_commands() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["SETBIT", "b", "7", "1"]).await, Frame::Integer(0));
+    assert_eq!(client.call(&["SETBIT", "b", "7", "0"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["SETBIT", "b", "1", "1"]).await, Frame::Integer(0));
+    assert_eq!(client.call(&["GET", "b"]).await, bulk("@"));
+    assert_eq!(client.call(&["GETBIT", "b", "1"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["GETBIT", "b", "100"]).await, Frame::Integer(0));
+    assert_eq!(
+        client.call(&["SETBIT", "b", "1", "2"]).await,
+        Frame::Error("ERR bit is not an integer or out of range".to_string())
+    );
+
+    assert_eq!(client.call(&["SET", "s", "foobar"]).await, ok());
+    assert_eq!(client.call(&["BITCOUNT", "s"]).await, Frame::Integer(26));
+    assert_eq!(client.call(&["BITCOUNT", "s", "1", "1"]).await, Frame::Integer(6));
+    assert_eq!(client.call(&["BITCOUNT", "s", "5", "30", "BIT"]).await, Frame::Integer(17));
+    assert_eq!(client.call(&["BITPOS", "s", "0"]).await, Frame::Integer(0));
+    assert_eq!(client.call(&["BITPOS", "s", "1", "2"]).await, Frame::Integer(17));
+    assert_eq!(client.call(&["BITPOS", "missing", "0"]).await, Frame::Integer(0));
+    assert_eq!(client.call(&["BITPOS", "missing", "1"]).await, Frame::Integer(-1));
+
+    client.call(&["SET", "x", "\u{0f}\u{0f}"]).await;
+    client.call(&["SET", "y", "\u{33}"]).await;
+    assert_eq!(client.call(&["BITOP", "AND", "dst", "x", "y"]).await, Frame::Integer(2));
+    assert_eq!(client.call(&["GET", "dst"]).await, bulk("\u{03}\u{00}"));
+    assert_eq!(client.call(&["BITOP", "NOT", "dst", "x", "y"]).await, Frame::Error("ERR BITOP NOT must be called with a single source key.".to_string()));
+}
+
+#[tokio::test]
+async fn bitfield() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(
+        client.call(&["BITFIELD", "f", "SET", "u8", "0", "200", "GET", "u8", "0", "INCRBY", "u8", "0", "100"]).await,
+        Frame::Array(vec![Frame::Integer(0), Frame::Integer(200), Frame::Integer(44)])
+    );
+    assert_eq!(
+        client.call(&["BITFIELD", "f", "OVERFLOW", "SAT", "INCRBY", "i8", "0", "200", "OVERFLOW", "FAIL", "INCRBY", "u8", "#0", "250"]).await,
+        Frame::Array(vec![Frame::Integer(127), Frame::Null])
+    );
+    assert_eq!(client.call(&["BITFIELD_RO", "f", "GET", "u4", "0"]).await, Frame::Array(vec![Frame::Integer(7)]));
+    assert!(matches!(client.call(&["BITFIELD_RO", "f", "SET", "u4", "0", "1"]).await, Frame::Error(_)));
+}
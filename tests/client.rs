@@ -0,0 +1,231 @@
+mod support;
+
+use my_redis::config::Config;
+use my_redis::db::Db;
+use my_redis::frame::Frame;
+use support::{bulk, config, ok, start_server, start_server_with, Client};
+
+fn text(frame: Frame) -> String {
+    match frame {
+        Frame::Bulk(data) => String::from_utf8(data.to_vec()).unwrap(),
+        frame => panic!("unexpected {:?}", frame),
+    }
+}
+
+// CLIENT INFO 里面某个字段的值
+fn field(line: &str, name: &str) -> String {
+    let prefix = format!("{}=", name);
+    line.split(' ').find_map(|pair| pair.strip_prefix(&prefix)).unwrap_or_default().trim_end().to_string()
+}
+
+#[tokio::test]
+async fn connection_commands() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["PING"]).await, Frame::Simple("PONG".to_string()));
+    assert_eq!(client.call(&["PING", "hi"]).await, bulk("hi"));
+    assert_eq!(client.call(&["ECHO", "hello"]).await, bulk("hello"));
+    // 只有一个数据库
+    assert_eq!(client.call(&["SELECT", "0"]).await, ok());
+    assert_eq!(client.call(&["SELECT", "1"]).await, Frame::Error("ERR DB index is out of range".to_string()));
+
+    assert_eq!(client.call(&["CLIENT", "GETNAME"]).await, Frame::Null);
+    assert_eq!(client.call(&["CLIENT", "SETNAME", "worker-1"]).await, ok());
+    assert_eq!(client.call(&["CLIENT", "GETNAME"]).await, bulk("worker-1"));
+    assert_eq!(
+        client.call(&["CLIENT", "SETNAME", "has space"]).await,
+        Frame::Error("ERR Client names cannot contain spaces, newlines or special characters.".to_string())
+    );
+
+    let id = match client.call(&["CLIENT", "ID"]).await {
+        Frame::Integer(id) => id,
+        frame => panic!("unexpected {:?}", frame),
+    };
+    let info = text(client.call(&["CLIENT", "INFO"]).await);
+    assert!(info.ends_with('\n'));
+    assert_eq!(field(&info, "id"), id.to_string());
+    assert_eq!(field(&info, "name"), "worker-1");
+    assert_eq!(field(&info, "db"), "0");
+    assert_eq!(field(&info, "cmd"), "client|info");
+
+    // 和redis一样，RESET 清掉事务和订阅，但是保留名字
+    client.call(&["MULTI"]).await;
+    assert_eq!(client.call(&["RESET"]).await, Frame::Simple("RESET".to_string()));
+    assert_eq!(client.call(&["CLIENT", "GETNAME"]).await, bulk("worker-1"));
+    assert_eq!(client.call(&["SUBSCRIBE", "c"]).await, Frame::Array(vec![bulk("subscribe"), bulk("c"), Frame::Integer(1)]));
+    assert_eq!(client.call(&["RESET"]).await, Frame::Simple("RESET".to_string()));
+    let info = text(client.call(&["CLIENT", "INFO"]).await);
+    assert_eq!(field(&info, "multi"), "-1");
+    assert_eq!(field(&info, "sub"), "0");
+
+    assert_eq!(client.call(&["QUIT"]).await, ok());
+    assert!(client.is_closed().await);
+}
+
+async fn client_id(client: &mut Client) -> String {
+    match client.call(&["CLIENT", "ID"]).await {
+        Frame::Integer(id) => id.to_string(),
+        frame => panic!("unexpected {:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn list_and_kill() {
+    let addr = start_server().await;
+    let mut admin = Client::connect(addr).await;
+    let mut worker = Client::connect(addr).await;
+    let mut listener = Client::connect(addr).await;
+    worker.call(&["CLIENT", "SETNAME", "worker"]).await;
+    listener.call(&["SUBSCRIBE", "c"]).await;
+    let worker_id = client_id(&mut worker).await;
+
+    let list = text(admin.call(&["CLIENT", "LIST"]).await);
+    assert_eq!(list.lines().count(), 3);
+    let line = list.lines().find(|line| field(line, "id") == worker_id).unwrap();
+    assert_eq!(field(line, "name"), "worker");
+    assert_eq!(field(line, "flags"), "N");
+
+    let pubsub = text(admin.call(&["CLIENT", "LIST", "TYPE", "pubsub"]).await);
+    assert_eq!(pubsub.lines().count(), 1);
+    assert_eq!(field(&pubsub, "flags"), "P");
+    assert_eq!(field(&pubsub, "sub"), "1");
+    let only = text(admin.call(&["CLIENT", "LIST", "ID", &worker_id]).await);
+    assert_eq!(field(&only, "name"), "worker");
+
+    // 新格式回复关掉的连接数，默认不关掉自己
+    assert_eq!(admin.call(&["CLIENT", "KILL", "TYPE", "normal"]).await, Frame::Integer(1));
+    assert!(worker.is_closed().await);
+    let addr_of_listener = field(&pubsub, "addr");
+    assert_eq!(admin.call(&["CLIENT", "KILL", &addr_of_listener]).await, ok());
+    assert!(listener.is_closed().await);
+    assert_eq!(admin.call(&["CLIENT", "KILL", "127.0.0.1:1"]).await, Frame::Error("ERR No such client".to_string()));
+    assert_eq!(admin.call(&["CLIENT", "KILL", "ID", "0"]).await, Frame::Error("ERR client-id should be greater than 0".to_string()));
+    assert_eq!(text(admin.call(&["CLIENT", "LIST"]).await).lines().count(), 1);
+}
+
+#[tokio::test]
+async fn pause_and_unpause() {
+    let addr = start_server().await;
+    let mut admin = Client::connect(addr).await;
+    let mut writer = Client::connect(addr).await;
+
+    // WRITE 模式只挡住写命令
+    assert_eq!(admin.call(&["CLIENT", "PAUSE", "10000", "WRITE"]).await, ok());
+    assert_eq!(writer.call(&["GET", "k"]).await, Frame::Null);
+    writer.send(&["SET", "k", "v"]).await;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert_eq!(admin.call(&["GET", "k"]).await, Frame::Null);
+    assert_eq!(admin.call(&["CLIENT", "UNPAUSE"]).await, ok());
+    assert_eq!(writer.read().await, ok());
+
+    // 超时之后自动恢复
+    assert_eq!(admin.call(&["CLIENT", "PAUSE", "50"]).await, ok());
+    let start = std::time::Instant::now();
+    assert_eq!(writer.call(&["GET", "k"]).await, bulk("v"));
+    assert!(start.elapsed() >= std::time::Duration::from_millis(40));
+    assert_eq!(
+        admin.call(&["CLIENT", "PAUSE", "10", "READ"]).await,
+        Frame::Error("ERR CLIENT PAUSE mode must be WRITE or ALL".to_string())
+    );
+}
+
+#[tokio::test]
+async fn output_buffer_limit() {
+    let mut config = config();
+    config.client_output_buffer_limit.update("normal 256kb 0 0").unwrap();
+    let addr = start_server_with(Db::new(), config).await;
+    let mut client = Client::connect(addr).await;
+    let value = "x".repeat(300 * 1024);
+    assert_eq!(client.call(&["SET", "big", &value]).await, ok());
+    assert_eq!(client.call(&["GET", "missing"]).await, Frame::Null);
+
+    // 一个回复就超过了硬限制，直接断开
+    client.send(&["GET", "big"]).await;
+    assert!(client.is_closed().await);
+}
+
+async fn publish_until_dropped(admin: &mut Client, max: usize) -> i64 {
+    let payload = "x".repeat(64 * 1024);
+    let mut receivers = 1;
+    for _ in 0..max {
+        match admin.call(&["PUBLISH", "c", &payload]).await {
+            Frame::Integer(n) => receivers = n,
+            frame => panic!("unexpected {:?}", frame),
+        }
+        if receivers == 0 {
+            break;
+        }
+    }
+    receivers
+}
+
+#[tokio::test]
+async fn slow_subscriber_is_disconnected() {
+    let addr = start_server().await;
+    let mut admin = Client::connect(addr).await;
+    let mut slow = Client::connect(addr).await;
+    assert_eq!(admin.call(&["CONFIG", "SET", "client-output-buffer-limit", "pubsub 256kb 0 0"]).await, ok());
+    slow.call(&["SUBSCRIBE", "c"]).await;
+
+    // 订阅者一直不读，积压超过硬限制之后被断开
+    assert_eq!(publish_until_dropped(&mut admin, 1000).await, 0);
+    assert_eq!(text(admin.call(&["CLIENT", "LIST", "TYPE", "pubsub"]).await), "");
+}
+
+#[tokio::test]
+async fn unlimited_subscriber_hits_mailbox_cap() {
+    let addr = start_server().await;
+    let mut admin = Client::connect(addr).await;
+    let mut slow = Client::connect(addr).await;
+    assert_eq!(admin.call(&["CONFIG", "SET", "client-output-buffer-limit", "pubsub 0 0 0"]).await, ok());
+    slow.call(&["SUBSCRIBE", "c"]).await;
+
+    // 没有配置限制的时候也不会无限积压
+    assert_eq!(publish_until_dropped(&mut admin, 4000).await, 0);
+    assert_eq!(text(admin.call(&["CLIENT", "LIST", "TYPE", "pubsub"]).await), "");
+}
+
+#[tokio::test]
+async fn idle_clients_time_out() {
+    let config = Config { timeout: 1, ..config() };
+    let addr = start_server_with(Db::new(), config).await;
+    let mut idle = Client::connect(addr).await;
+    let mut subscriber = Client::connect(addr).await;
+    subscriber.call(&["SUBSCRIBE", "c"]).await;
+
+    let start = std::time::Instant::now();
+    assert!(idle.is_closed().await);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(900));
+    // 订阅模式的连接不会因为空闲被关掉
+    let mut publisher = Client::connect(addr).await;
+    assert_eq!(publisher.call(&["PUBLISH", "c", "still here"]).await, Frame::Integer(1));
+    assert_eq!(
+        subscriber.read().await,
+        Frame::Array(vec![bulk("message"), bulk("c"), bulk("still here")])
+    );
+}
+
+#[tokio::test]
+async fn maxclients() {
+    let addr = start_server().await;
+    let mut admin = Client::connect(addr).await;
+    assert_eq!(admin.call(&["CONFIG", "SET", "maxclients", "2"]).await, ok());
+    let mut second = Client::connect(addr).await;
+    assert_eq!(second.call(&["PING"]).await, Frame::Simple("PONG".to_string()));
+
+    let mut rejected = Client::connect(addr).await;
+    assert_eq!(rejected.read().await, Frame::Error("ERR max number of clients reached".to_string()));
+    assert!(rejected.is_closed().await);
+
+    // 断开一个之后就有名额了
+    drop(second);
+    for _ in 0..100 {
+        let mut client = Client::connect(addr).await;
+        client.send(&["PING"]).await;
+        if client.read().await == Frame::Simple("PONG".to_string()) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("slot was not released");
+}
@@ -0,0 +1,135 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, start_server, Client};
+
+fn simples(items: &[&str]) -> Frame {
+    Frame::Array(items.iter().map(|item| Frame::Simple(item.to_string())).collect())
+}
+
+#[tokio::test]
+async fn count_list_and_info() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let count = match client.call(&["COMMAND", "COUNT"]).await {
+        Frame::Integer(count) => count,
+        frame => panic!("unexpected {:?}", frame),
+    };
+    match client.call(&["COMMAND", "LIST"]).await {
+        Frame::Array(names) => {
+            assert_eq!(names.len() as i64, count);
+            assert!(names.contains(&bulk("zadd")));
+        }
+        frame => panic!("unexpected {:?}", frame),
+    }
+
+    let info = match client.call(&["COMMAND", "INFO", "GET", "nosuchcommand"]).await {
+        Frame::Array(items) => items,
+        frame => panic!("unexpected {:?}", frame),
+    };
+    assert_eq!(info[1], Frame::Null);
+    match &info[0] {
+        Frame::Array(fields) => {
+            assert_eq!(fields[0], bulk("get"));
+            assert_eq!(fields[1], Frame::Integer(2));
+            assert_eq!(fields[2], simples(&["readonly", "fast"]));
+            assert_eq!(&fields[3..6], &[Frame::Integer(1), Frame::Integer(1), Frame::Integer(1)]);
+            assert_eq!(fields[6], simples(&["@read", "@string", "@fast"]));
+        }
+        frame => panic!("unexpected {:?}", frame),
+    }
+
+    assert_eq!(
+        client.call(&["COMMAND", "DOCS", "get", "nosuchcommand"]).await,
+        Frame::Array(vec![
+            bulk("get"),
+            Frame::Array(vec![
+                bulk("summary"),
+                bulk("Returns the string value of a key."),
+                bulk("since"),
+                bulk("1.0.0"),
+                bulk("group"),
+                bulk("string"),
+            ]),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn getkeys_and_arity() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(
+        client.call(&["COMMAND", "GETKEYS", "ZUNIONSTORE", "dst", "2", "a", "b", "WEIGHTS", "1", "2"]).await,
+        Frame::Array(vec![bulk("dst"), bulk("a"), bulk("b")])
+    );
+    assert_eq!(
+        client.call(&["COMMAND", "GETKEYS", "EVAL", "return 1", "1", "k"]).await,
+        Frame::Array(vec![bulk("k")])
+    );
+    assert_eq!(
+        client.call(&["COMMAND", "GETKEYS", "PING"]).await,
+        Frame::Error("ERR The command has no key arguments".to_string())
+    );
+    assert_eq!(
+        client.call(&["COMMAND", "GETKEYS", "GET"]).await,
+        Frame::Error("ERR Invalid number of arguments specified for command".to_string())
+    );
+    assert_eq!(
+        client.call(&["COMMAND", "GETKEYS", "NOSUCH", "x"]).await,
+        Frame::Error("ERR Invalid command specified".to_string())
+    );
+    assert_eq!(
+        client.call(&["COMMAND", "NOPE"]).await,
+        Frame::Error("ERR unknown subcommand 'NOPE'. Try COMMAND HELP.".to_string())
+    );
+
+    // 命令表里的参数个数对内置命令同样生效
+    assert_eq!(
+        client.call(&["ZSCORE", "z"]).await,
+        Frame::Error("ERR wrong number of arguments for 'zscore' command".to_string())
+    );
+    assert_eq!(
+        client.call(&["XLEN", "s", "extra"]).await,
+        Frame::Error("ERR wrong number of arguments for 'xlen' command".to_string())
+    );
+    // 子命令按子命令表检查，PING 这样参数个数有上限的命令多出来的参数也一样
+    assert_eq!(
+        client.call(&["CONFIG", "REWRITE", "extra"]).await,
+        Frame::Error("ERR wrong number of arguments for 'config|rewrite' command".to_string())
+    );
+    assert_eq!(
+        client.call(&["XGROUP", "CREATE", "s"]).await,
+        Frame::Error("ERR wrong number of arguments for 'xgroup|create' command".to_string())
+    );
+    assert_eq!(
+        client.call(&["PING", "a", "b"]).await,
+        Frame::Error("ERR wrong number of arguments for 'ping' command".to_string())
+    );
+}
+
+#[tokio::test]
+async fn subcommand_info() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let info = match client.call(&["COMMAND", "INFO", "config|get", "config"]).await {
+        Frame::Array(items) => items,
+        frame => panic!("unexpected {:?}", frame),
+    };
+    let get = match &info[0] {
+        Frame::Array(fields) => {
+            assert_eq!(fields[0], bulk("config|get"));
+            assert_eq!(fields[1], Frame::Integer(-3));
+            assert_eq!(fields[2], simples(&["admin", "noscript", "loading", "stale"]));
+            info[0].clone()
+        }
+        frame => panic!("unexpected {:?}", frame),
+    };
+    match &info[1] {
+        Frame::Array(fields) => match &fields[9] {
+            Frame::Array(subcommands) => assert!(subcommands.contains(&get)),
+            frame => panic!("unexpected {:?}", frame),
+        },
+        frame => panic!("unexpected {:?}", frame),
+    }
+}
@@ -0,0 +1,80 @@
+mod support;
+
+use my_redis::config::Config;
+use my_redis::db::Db;
+use my_redis::frame::Frame;
+use std::fs;
+use support::{bulk, ok, start_server, start_server_with, Client};
+
+#[tokio::test]
+async fn get_and_set() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "maxclients", "*clients", "timeout"]).await,
+        Frame::Array(vec![bulk("maxclients"), bulk("10000"), bulk("timeout"), bulk("0")])
+    );
+    assert_eq!(client.call(&["CONFIG", "GET", "nosuchoption"]).await, Frame::Array(vec![]));
+    assert_eq!(client.call(&["CONFIG", "SET", "maxclients", "50", "timeout", "30"]).await, ok());
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "maxclients", "timeout"]).await,
+        Frame::Array(vec![bulk("maxclients"), bulk("50"), bulk("timeout"), bulk("30")])
+    );
+
+    // 有一个参数不对的时候整个命令都不生效
+    assert!(matches!(
+        client.call(&["CONFIG", "SET", "timeout", "5", "maxclients", "abc"]).await,
+        Frame::Error(err) if err.starts_with("ERR CONFIG SET failed (possibly related to argument 'maxclients')")
+    ));
+    assert_eq!(client.call(&["CONFIG", "GET", "timeout"]).await, Frame::Array(vec![bulk("timeout"), bulk("30")]));
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "nosuchoption", "1"]).await,
+        Frame::Error("ERR Unknown option or number of arguments for CONFIG SET - 'nosuchoption'".to_string())
+    );
+    assert_eq!(
+        client.call(&["CONFIG", "SET", "timeout"]).await,
+        Frame::Error("ERR wrong number of arguments for 'config|set' command".to_string())
+    );
+    assert!(matches!(client.call(&["CONFIG", "REWRITE"]).await, Frame::Error(_)));
+}
+
+#[tokio::test]
+async fn rewrite_and_resetstat() {
+    let path = std::env::temp_dir().join(format!("my-redis-config-test-{}.conf", std::process::id()));
+    fs::write(&path, "# my settings\nmaxclients 100\nnotify-keyspace-events zK\n").unwrap();
+    let config = Config::from_file(path.to_str().unwrap()).unwrap();
+    let db = Db::new();
+    let addr = start_server_with(db.clone(), config).await;
+    let mut client = Client::connect(addr).await;
+
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "maxclients", "notify-keyspace-events"]).await,
+        Frame::Array(vec![bulk("maxclients"), bulk("100"), bulk("notify-keyspace-events"), bulk("zK")])
+    );
+    assert_eq!(client.call(&["CONFIG", "SET", "maxclients", "200", "timeout", "7"]).await, ok());
+    assert_eq!(client.call(&["CONFIG", "REWRITE"]).await, ok());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# my settings\nmaxclients 200\nnotify-keyspace-events zK\n# Generated by CONFIG REWRITE\ntimeout 7\n"
+    );
+    fs::remove_file(&path).unwrap();
+
+    assert!(db.stats().total_commands_processed > 0);
+    assert_eq!(client.call(&["CONFIG", "RESETSTAT"]).await, ok());
+    assert_eq!(db.stats().total_commands_processed, 0);
+}
+
+#[tokio::test]
+async fn rewrite_inside_multi() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["SET", "k", "v"]).await, Frame::Simple("QUEUED".to_string()));
+    assert_eq!(
+        client.call(&["CONFIG", "REWRITE"]).await,
+        Frame::Error("ERR Command not allowed inside a transaction".to_string())
+    );
+    assert!(matches!(client.call(&["EXEC"]).await, Frame::Error(err) if err.starts_with("EXECABORT")));
+    assert_eq!(client.call(&["GET", "k"]).await, Frame::Null);
+}
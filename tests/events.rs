@@ -0,0 +1,44 @@
+mod support;
+
+use my_redis::db::Db;
+use my_redis::events::{KeyEvent, RecvError};
+use my_redis::frame::Frame;
+use support::{config, ok, start_server_with, Client};
+
+#[tokio::test]
+async fn set_del_and_expired() {
+    let db = Db::new();
+    let mut events = db.subscribe_key_events("user:*", 16);
+    let addr = start_server_with(db.clone(), config()).await;
+    let mut client = Client::connect(addr).await;
+
+    assert_eq!(client.call(&["SET", "user:1", "a"]).await, ok());
+    assert_eq!(client.call(&["SET", "other", "b"]).await, ok());
+    assert_eq!(client.call(&["ZADD", "user:2", "1", "m"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["DEL", "user:1", "other"]).await, Frame::Integer(2));
+    assert_eq!(client.call(&["SET", "user:3", "c", "PX", "20"]).await, ok());
+
+    assert_eq!(events.recv().await, Ok(KeyEvent::Set("user:1".to_string())));
+    assert_eq!(events.recv().await, Ok(KeyEvent::Set("user:2".to_string())));
+    assert_eq!(events.recv().await, Ok(KeyEvent::Del("user:1".to_string())));
+    assert_eq!(events.recv().await, Ok(KeyEvent::Set("user:3".to_string())));
+    // 后台任务清理过期的key
+    assert_eq!(events.recv().await, Ok(KeyEvent::Expired("user:3".to_string())));
+}
+
+#[tokio::test]
+async fn slow_consumer_sees_lag() {
+    let db = Db::new();
+    let mut events = db.subscribe_key_events("*", 2);
+    let addr = start_server_with(db.clone(), config()).await;
+    let mut client = Client::connect(addr).await;
+    for key in ["a", "b", "c", "d"] {
+        assert_eq!(client.call(&["SET", key, "v"]).await, ok());
+    }
+
+    assert_eq!(events.recv().await, Ok(KeyEvent::Set("a".to_string())));
+    assert_eq!(events.recv().await, Ok(KeyEvent::Set("b".to_string())));
+    assert_eq!(events.recv().await, Err(RecvError::Lagged(2)));
+    assert_eq!(client.call(&["SET", "e", "v"]).await, ok());
+    assert_eq!(events.recv().await, Ok(KeyEvent::Set("e".to_string())));
+}
@@ -0,0 +1,62 @@
+mod support;
+
+use my_redis::config::Config;
+use my_redis::db::Db;
+use my_redis::frame::Frame;
+use support::{bulk, config, start_server, start_server_with, Client};
+use tokio::time::{sleep, Duration};
+
+#[tokio::test]
+async fn load_and_call() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let code = "#!lua name=lib\nredis.register_function('echo', function(keys, args) return args[1] end)";
+    assert_eq!(client.call(&["FUNCTION", "LOAD", code]).await, bulk("lib"));
+    assert_eq!(client.call(&["FCALL", "echo", "0", "hi"]).await, bulk("hi"));
+    match client.call(&["FUNCTION", "LOAD", code]).await {
+        Frame::Error(err) => assert_eq!(err, "ERR Library 'lib' already exists"),
+        frame => panic!("unexpected reply {:?}", frame),
+    }
+}
+
+// 库代码执行超过500毫秒之后中断，期间其他连接的命令等待加载结束
+#[tokio::test]
+async fn load_timeout_keeps_server_responsive() {
+    let addr = start_server().await;
+    let mut loader = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+
+    loader.send(&["FUNCTION", "LOAD", "#!lua name=slow\nwhile true do end"]).await;
+    assert_eq!(other.call(&["PING"]).await, Frame::Simple("PONG".to_string()));
+    match loader.read().await {
+        Frame::Error(err) => assert_eq!(err, "ERR Error registering functions: FUNCTION LOAD timeout"),
+        frame => panic!("unexpected reply {:?}", frame),
+    }
+    assert_eq!(other.call(&["FUNCTION", "LIST"]).await, Frame::Array(vec![]));
+}
+
+// 默认配置会把函数库保存到文件，用同一个文件启动的新服务可以直接调用
+#[tokio::test]
+async fn libraries_survive_restart() {
+    assert_eq!(Config::default().functions_file.as_deref(), Some("functions.dump"));
+    let path = std::env::temp_dir().join(format!("my-redis-functions-{}.dump", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let config = Config { functions_file: Some(path.to_string_lossy().to_string()), ..config() };
+
+    let addr = start_server_with(Db::new(), config.clone()).await;
+    let mut client = Client::connect(addr).await;
+    let code = "#!lua name=persisted\nredis.register_function('hello', function() return 'hi' end)";
+    assert_eq!(client.call(&["FUNCTION", "LOAD", code]).await, bulk("persisted"));
+    // 文件在后台线程里写
+    for _ in 0..100 {
+        if std::fs::metadata(&path).is_ok() {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    let addr = start_server_with(Db::new(), config).await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["FCALL", "hello", "0"]).await, bulk("hi"));
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,67 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, start_server, Client};
+
+async fn sicily(client: &mut Client) {
+    let added = client
+        .call(&["GEOADD", "Sicily", "13.361389", "38.115556", "Palermo", "15.087269", "37.502669", "Catania"])
+        .await;
+    assert_eq!(added, Frame::Integer(2));
+}
+
+#[tokio::test]
+async fn add_pos_hash_and_dist() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    sicily(&mut client).await;
+    assert_eq!(client.call(&["GEOADD", "Sicily", "NX", "0", "0", "Palermo"]).await, Frame::Integer(0));
+    assert_eq!(
+        client.call(&["GEOADD", "Sicily", "0", "86", "x"]).await,
+        Frame::Error("ERR invalid longitude,latitude pair 0.000000,86.000000".to_string())
+    );
+    assert_eq!(client.call(&["ZSCORE", "Sicily", "Palermo"]).await, bulk("3479099956230698"));
+
+    assert_eq!(client.call(&["GEODIST", "Sicily", "Palermo", "Catania"]).await, bulk("166274.1516"));
+    assert_eq!(client.call(&["GEODIST", "Sicily", "Palermo", "Catania", "km"]).await, bulk("166.2742"));
+    assert_eq!(client.call(&["GEODIST", "Sicily", "Palermo", "Nowhere"]).await, Frame::Null);
+    assert_eq!(
+        client.call(&["GEOHASH", "Sicily", "Palermo", "Catania", "Nowhere"]).await,
+        Frame::Array(vec![bulk("sqc8b49rny0"), bulk("sqdtr74hyu0"), Frame::Null])
+    );
+    assert_eq!(
+        client.call(&["GEOPOS", "Sicily", "Palermo", "Nowhere"]).await,
+        Frame::Array(vec![
+            Frame::Array(vec![bulk("13.36138933897018433"), bulk("38.11555639549629859")]),
+            Frame::NullArray,
+        ])
+    );
+}
+
+#[tokio::test]
+async fn search_and_store() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    sicily(&mut client).await;
+    client.call(&["GEOADD", "Sicily", "12.758489", "38.788135", "edge1", "17.241510", "38.788135", "edge2"]).await;
+
+    assert_eq!(
+        client.call(&["GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "200", "km", "ASC"]).await,
+        Frame::Array(vec![bulk("Catania"), bulk("Palermo")])
+    );
+    assert_eq!(
+        client
+            .call(&["GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "400", "400", "km", "ASC", "COUNT", "1", "WITHDIST"])
+            .await,
+        Frame::Array(vec![Frame::Array(vec![bulk("Catania"), bulk("56.4413")])])
+    );
+    assert_eq!(
+        client.call(&["GEOSEARCH", "Sicily", "FROMMEMBER", "Palermo", "BYRADIUS", "10", "km"]).await,
+        Frame::Array(vec![bulk("Palermo")])
+    );
+    assert_eq!(
+        client.call(&["GEOSEARCHSTORE", "near", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "200", "km"]).await,
+        Frame::Integer(2)
+    );
+    assert_eq!(client.call(&["ZRANGE", "near", "0", "-1"]).await, Frame::Array(vec![bulk("Palermo"), bulk("Catania")]));
+}
@@ -0,0 +1,38 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{ok, start_server, Client};
+
+#[tokio::test]
+async fn add_count_and_merge() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["PFADD", "h1", "a", "b", "c"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["PFADD", "h1", "a"]).await, Frame::Integer(0));
+    assert_eq!(client.call(&["PFADD", "h2"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["PFADD", "h2", "c", "d"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["PFCOUNT", "h1"]).await, Frame::Integer(3));
+    assert_eq!(client.call(&["PFCOUNT", "h1", "h2", "missing"]).await, Frame::Integer(4));
+    assert_eq!(client.call(&["PFMERGE", "dst", "h1", "h2"]).await, ok());
+    assert_eq!(client.call(&["PFCOUNT", "dst"]).await, Frame::Integer(4));
+
+    let mut args = vec!["PFADD".to_string(), "big".to_string()];
+    args.extend((0..5000).map(|i| i.to_string()));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    assert_eq!(client.call(&args).await, Frame::Integer(1));
+    match client.call(&["PFCOUNT", "big"]).await {
+        Frame::Integer(n) => assert!((4900..5100).contains(&n), "count {}", n),
+        frame => panic!("unexpected {:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn rejects_other_strings() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    client.call(&["SET", "s", "hello"]).await;
+    let err = Frame::Error("WRONGTYPE Key is not a valid HyperLogLog string value.".to_string());
+    assert_eq!(client.call(&["PFADD", "s", "a"]).await, err);
+    assert_eq!(client.call(&["PFCOUNT", "s"]).await, err);
+    assert_eq!(client.call(&["PFMERGE", "dst", "s"]).await, err);
+}
@@ -0,0 +1,239 @@
+mod support;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use my_redis::db::Db;
+use my_redis::module::{CommandHandler, Context, DataType, Reply, SavedValue, CMD_WRITE};
+use my_redis::{Frame, Parse};
+use support::{bulk, config, ok, start_server_with, Client};
+
+// 把值转成大写再用内置的SET保存
+struct UpperSet;
+
+impl CommandHandler for UpperSet {
+    fn name(&self) -> &str {
+        "UPPER.SET"
+    }
+
+    fn arity(&self) -> i32 {
+        3
+    }
+
+    fn flags(&self) -> u32 {
+        CMD_WRITE
+    }
+
+    fn execute(&self, parse: &mut Parse, ctx: &mut Context<'_>, reply: &mut Reply) -> my_redis::Result<()> {
+        let key = parse.next_string()?;
+        let value = parse.next_string()?.to_uppercase();
+        reply.frame(ctx.call(&[b"SET", key.as_bytes(), value.as_bytes()]));
+        Ok(())
+    }
+}
+
+// 参数个数可变，返回所有key的长度之和
+struct TotalLen;
+
+impl CommandHandler for TotalLen {
+    fn name(&self) -> &str {
+        "total.len"
+    }
+
+    fn arity(&self) -> i32 {
+        -2
+    }
+
+    fn execute(&self, parse: &mut Parse, ctx: &mut Context<'_>, reply: &mut Reply) -> my_redis::Result<()> {
+        let mut total = 0;
+        for key in parse.rest_bytes()? {
+            match ctx.call(&[b"GET", &key]) {
+                Frame::Bulk(value) => total += value.len() as i64,
+                Frame::Null => {}
+                _ => return Err("WRONGTYPE Operation against a key holding the wrong kind of value".into()),
+            }
+        }
+        reply.integer(total);
+        Ok(())
+    }
+}
+
+fn db() -> Db {
+    let db = Db::new();
+    db.register_command(UpperSet).unwrap();
+    db.register_command(TotalLen).unwrap();
+    db
+}
+
+// Db::new 会启动后台任务，需要在runtime里面
+#[tokio::test]
+async fn register_rejects_duplicates() {
+    let db = db();
+    assert!(db.register_command(UpperSet).is_err());
+
+    struct Get;
+    impl CommandHandler for Get {
+        fn name(&self) -> &str {
+            "get"
+        }
+
+        fn arity(&self) -> i32 {
+            2
+        }
+
+        fn execute(&self, _parse: &mut Parse, _ctx: &mut Context<'_>, _reply: &mut Reply) -> my_redis::Result<()> {
+            Ok(())
+        }
+    }
+    assert!(db.register_command(Get).is_err());
+}
+
+#[tokio::test]
+async fn module_commands() {
+    let addr = start_server_with(db(), config()).await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["upper.set", "k", "hello"]).await, ok());
+    assert_eq!(client.call(&["GET", "k"]).await, bulk("HELLO"));
+    assert_eq!(
+        client.call(&["UPPER.SET", "k"]).await,
+        Frame::Error("ERR wrong number of arguments for 'upper.set' command".to_string())
+    );
+    client.call(&["SET", "other", "abc"]).await;
+    assert_eq!(client.call(&["TOTAL.LEN", "k", "other", "missing"]).await, Frame::Integer(8));
+    client.call(&["ZADD", "z", "1", "a"]).await;
+    assert!(matches!(client.call(&["TOTAL.LEN", "z"]).await, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+
+    // 模块命令和内置命令一样可以放进事务，也会触发WATCH
+    let mut other = Client::connect(addr).await;
+    assert_eq!(client.call(&["WATCH", "k"]).await, ok());
+    assert_eq!(other.call(&["UPPER.SET", "k", "changed"]).await, ok());
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["UPPER.SET", "k", "mine"]).await, Frame::Simple("QUEUED".to_string()));
+    assert_eq!(client.call(&["EXEC"]).await, Frame::NullArray);
+    assert_eq!(client.call(&["GET", "k"]).await, bulk("CHANGED"));
+}
+
+// 自定义类型，值是一个计数器
+struct Counter;
+
+impl DataType for Counter {
+    type Value = u64;
+
+    fn name(&self) -> &str {
+        "mycounter"
+    }
+
+    fn encoding_version(&self) -> u32 {
+        1
+    }
+
+    fn rdb_save(&self, value: &u64, buf: &mut BytesMut) {
+        buf.put_u64(*value);
+    }
+
+    fn rdb_load(&self, buf: &mut Bytes, encoding_version: u32) -> my_redis::Result<u64> {
+        if encoding_version != 1 || buf.len() != 8 {
+            return Err("bad counter payload".into());
+        }
+        Ok(buf.get_u64())
+    }
+
+    fn aof_rewrite(&self, key: &str, value: &u64) -> Vec<Vec<Bytes>> {
+        vec![vec![Bytes::from("COUNTER.INCRBY"), Bytes::from(key.to_string()), Bytes::from(value.to_string())]]
+    }
+}
+
+struct CounterIncrBy;
+
+impl CommandHandler for CounterIncrBy {
+    fn name(&self) -> &str {
+        "counter.incrby"
+    }
+
+    fn arity(&self) -> i32 {
+        3
+    }
+
+    fn flags(&self) -> u32 {
+        CMD_WRITE
+    }
+
+    fn execute(&self, parse: &mut Parse, ctx: &mut Context<'_>, reply: &mut Reply) -> my_redis::Result<()> {
+        let key = parse.next_string()?;
+        let by = parse.next_int()?;
+        let value = match ctx.get_value_mut::<Counter>(&key)? {
+            Some(value) => {
+                *value += by;
+                *value
+            }
+            None => {
+                ctx.set_value::<Counter>(&key, by)?;
+                by
+            }
+        };
+        ctx.signal_modified_key(&key);
+        reply.integer(value as i64);
+        Ok(())
+    }
+}
+
+fn counter_db() -> Db {
+    let db = Db::new();
+    db.register_type(Counter).unwrap();
+    db.register_command(CounterIncrBy).unwrap();
+    db
+}
+
+#[tokio::test]
+async fn data_type_registration() {
+    let db = counter_db();
+    assert!(db.register_type(Counter).is_err());
+
+    struct BadName;
+    impl DataType for BadName {
+        type Value = ();
+
+        fn name(&self) -> &str {
+            "short"
+        }
+
+        fn rdb_save(&self, _value: &(), _buf: &mut BytesMut) {}
+
+        fn rdb_load(&self, _buf: &mut Bytes, _encoding_version: u32) -> my_redis::Result<()> {
+            Ok(())
+        }
+
+        fn aof_rewrite(&self, _key: &str, _value: &()) -> Vec<Vec<Bytes>> {
+            vec![]
+        }
+    }
+    assert!(db.register_type(BadName).is_err());
+}
+
+#[tokio::test]
+async fn custom_values() {
+    let db = counter_db();
+    let addr = start_server_with(db.clone(), config()).await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["COUNTER.INCRBY", "c", "5"]).await, Frame::Integer(5));
+    assert_eq!(client.call(&["COUNTER.INCRBY", "c", "2"]).await, Frame::Integer(7));
+    assert_eq!(client.call(&["TYPE", "c"]).await, Frame::Simple("mycounter".to_string()));
+    assert!(matches!(client.call(&["GET", "c"]).await, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+    client.call(&["SET", "s", "v"]).await;
+    assert!(matches!(client.call(&["COUNTER.INCRBY", "s", "1"]).await, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+
+    // 持久化的钩子
+    let saved = db.save_module_value("c").unwrap().unwrap();
+    assert_eq!(saved.type_name, "mycounter");
+    assert_eq!(saved.encoding_version, 1);
+    assert_eq!(&saved.payload[..], &7u64.to_be_bytes());
+    assert_eq!(
+        db.rewrite_module_value("c").unwrap().unwrap(),
+        vec![vec![Bytes::from("COUNTER.INCRBY"), Bytes::from("c"), Bytes::from("7")]]
+    );
+    assert!(db.save_module_value("s").is_err());
+    assert_eq!(db.save_module_value("missing").unwrap(), None);
+
+    db.load_module_value("restored", &saved).unwrap();
+    assert_eq!(client.call(&["COUNTER.INCRBY", "restored", "1"]).await, Frame::Integer(8));
+    let unknown = SavedValue { type_name: "othertype".to_string(), ..saved };
+    assert!(db.load_module_value("x", &unknown).is_err());
+}
@@ -0,0 +1,52 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, ok, start_server, Client};
+
+fn pmessage(pattern: &str, channel: &str, payload: &str) -> Frame {
+    Frame::Array(vec![bulk("pmessage"), bulk(pattern), bulk(channel), bulk(payload)])
+}
+
+#[tokio::test]
+async fn keyspace_and_keyevent() {
+    let addr = start_server().await;
+    let mut subscriber = Client::connect(addr).await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["CONFIG", "SET", "notify-keyspace-events", "KEz$"]).await, ok());
+    assert_eq!(
+        client.call(&["CONFIG", "GET", "notify-keyspace-events"]).await,
+        Frame::Array(vec![bulk("notify-keyspace-events"), bulk("$zKE")])
+    );
+    assert!(matches!(
+        client.call(&["CONFIG", "SET", "notify-keyspace-events", "Kq"]).await,
+        Frame::Error(_)
+    ));
+
+    assert_eq!(
+        subscriber.call(&["PSUBSCRIBE", "__key*__:*"]).await,
+        Frame::Array(vec![bulk("psubscribe"), bulk("__key*__:*"), Frame::Integer(1)])
+    );
+    client.call(&["SET", "k", "v"]).await;
+    assert_eq!(subscriber.read().await, pmessage("__key*__:*", "__keyspace@0__:k", "set"));
+    assert_eq!(subscriber.read().await, pmessage("__key*__:*", "__keyevent@0__:set", "k"));
+
+    // 没有开启的类型不发通知
+    client.call(&["XADD", "s", "*", "f", "v"]).await;
+    client.call(&["ZADD", "z", "1", "a"]).await;
+    assert_eq!(subscriber.read().await, pmessage("__key*__:*", "__keyspace@0__:z", "zadd"));
+    assert_eq!(subscriber.read().await, pmessage("__key*__:*", "__keyevent@0__:zadd", "z"));
+}
+
+#[tokio::test]
+async fn expired_events() {
+    let addr = start_server().await;
+    let mut subscriber = Client::connect(addr).await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["CONFIG", "SET", "notify-keyspace-events", "Ex"]).await, ok());
+    subscriber.call(&["SUBSCRIBE", "__keyevent@0__:expired"]).await;
+    client.call(&["SET", "k", "v", "PX", "20"]).await;
+    assert_eq!(
+        subscriber.read().await,
+        Frame::Array(vec![bulk("message"), bulk("__keyevent@0__:expired"), bulk("k")])
+    );
+}
@@ -0,0 +1,144 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, start_server, Client};
+
+fn reply(kind: &str, name: &str, count: i64) -> Frame {
+    Frame::Array(vec![bulk(kind), bulk(name), Frame::Integer(count)])
+}
+
+fn message(channel: &str, payload: &str) -> Frame {
+    Frame::Array(vec![bulk("message"), bulk(channel), bulk(payload)])
+}
+
+// 不带参数退订的时候顺序不固定，按名字排序以后返回
+async fn unsubscribed(client: &mut Client, kind: &str, count: i64, remaining: i64) -> Vec<Frame> {
+    let mut names = vec![];
+    for left in (remaining..remaining + count).rev() {
+        match client.read().await {
+            Frame::Array(items) => {
+                assert_eq!(items[0], bulk(kind));
+                assert_eq!(items[2], Frame::Integer(left));
+                names.push(items[1].clone());
+            }
+            frame => panic!("unexpected {:?}", frame),
+        }
+    }
+    names.sort_by_key(|frame| format!("{:?}", frame));
+    names
+}
+
+#[tokio::test]
+async fn subscribe_and_publish() {
+    let addr = start_server().await;
+    let mut subscriber = Client::connect(addr).await;
+    let mut publisher = Client::connect(addr).await;
+
+    subscriber.send(&["SUBSCRIBE", "a", "b"]).await;
+    assert_eq!(subscriber.read().await, reply("subscribe", "a", 1));
+    assert_eq!(subscriber.read().await, reply("subscribe", "b", 2));
+
+    assert_eq!(publisher.call(&["PUBLISH", "a", "hello"]).await, Frame::Integer(1));
+    assert_eq!(publisher.call(&["PUBLISH", "c", "nobody"]).await, Frame::Integer(0));
+    assert_eq!(subscriber.read().await, message("a", "hello"));
+
+    // 订阅模式下只能执行订阅相关的命令
+    assert_eq!(
+        subscriber.call(&["GET", "k"]).await,
+        Frame::Error("ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context".to_string())
+    );
+    assert_eq!(subscriber.call(&["PING"]).await, Frame::Array(vec![bulk("pong"), bulk("")]));
+
+    subscriber.send(&["UNSUBSCRIBE"]).await;
+    assert_eq!(unsubscribed(&mut subscriber, "unsubscribe", 2, 0).await, vec![bulk("a"), bulk("b")]);
+    assert_eq!(publisher.call(&["PUBLISH", "a", "gone"]).await, Frame::Integer(0));
+    assert_eq!(subscriber.call(&["PING"]).await, Frame::Simple("PONG".to_string()));
+}
+
+#[tokio::test]
+async fn disconnect_removes_subscriptions() {
+    let addr = start_server().await;
+    let mut publisher = Client::connect(addr).await;
+    {
+        let mut subscriber = Client::connect(addr).await;
+        assert_eq!(subscriber.call(&["SUBSCRIBE", "a"]).await, reply("subscribe", "a", 1));
+        assert_eq!(publisher.call(&["PUBLISH", "a", "x"]).await, Frame::Integer(1));
+    }
+    for _ in 0..100 {
+        if publisher.call(&["PUBLISH", "a", "x"]).await == Frame::Integer(0) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("subscription was not removed");
+}
+
+#[tokio::test]
+async fn patterns_and_introspection() {
+    let addr = start_server().await;
+    let mut subscriber = Client::connect(addr).await;
+    let mut publisher = Client::connect(addr).await;
+
+    subscriber.send(&["PSUBSCRIBE", "news.*", "h?llo"]).await;
+    assert_eq!(subscriber.read().await, reply("psubscribe", "news.*", 1));
+    assert_eq!(subscriber.read().await, reply("psubscribe", "h?llo", 2));
+    assert_eq!(subscriber.call(&["SUBSCRIBE", "news.tech"]).await, reply("subscribe", "news.tech", 3));
+
+    // 频道和模式都匹配的时候收到两条
+    assert_eq!(publisher.call(&["PUBLISH", "news.tech", "x"]).await, Frame::Integer(2));
+    assert_eq!(subscriber.read().await, message("news.tech", "x"));
+    assert_eq!(
+        subscriber.read().await,
+        Frame::Array(vec![bulk("pmessage"), bulk("news.*"), bulk("news.tech"), bulk("x")])
+    );
+    assert_eq!(publisher.call(&["PUBLISH", "hallo", "y"]).await, Frame::Integer(1));
+    assert_eq!(
+        subscriber.read().await,
+        Frame::Array(vec![bulk("pmessage"), bulk("h?llo"), bulk("hallo"), bulk("y")])
+    );
+
+    assert_eq!(publisher.call(&["PUBSUB", "CHANNELS"]).await, Frame::Array(vec![bulk("news.tech")]));
+    assert_eq!(publisher.call(&["PUBSUB", "CHANNELS", "sport.*"]).await, Frame::Array(vec![]));
+    assert_eq!(
+        publisher.call(&["PUBSUB", "NUMSUB", "news.tech", "other"]).await,
+        Frame::Array(vec![bulk("news.tech"), Frame::Integer(1), bulk("other"), Frame::Integer(0)])
+    );
+    assert_eq!(publisher.call(&["PUBSUB", "NUMPAT"]).await, Frame::Integer(2));
+
+    subscriber.send(&["PUNSUBSCRIBE"]).await;
+    assert_eq!(unsubscribed(&mut subscriber, "punsubscribe", 2, 1).await, vec![bulk("h?llo"), bulk("news.*")]);
+    assert_eq!(publisher.call(&["PUBSUB", "NUMPAT"]).await, Frame::Integer(0));
+}
+
+#[tokio::test]
+async fn shard_channels() {
+    let addr = start_server().await;
+    let mut subscriber = Client::connect(addr).await;
+    let mut publisher = Client::connect(addr).await;
+
+    subscriber.send(&["SSUBSCRIBE", "{user}.a", "{user}.b"]).await;
+    assert_eq!(subscriber.read().await, reply("ssubscribe", "{user}.a", 1));
+    assert_eq!(subscriber.read().await, reply("ssubscribe", "{user}.b", 2));
+    // 普通频道和分片频道互不影响
+    assert_eq!(subscriber.call(&["SUBSCRIBE", "{user}.a"]).await, reply("subscribe", "{user}.a", 1));
+
+    assert_eq!(publisher.call(&["SPUBLISH", "{user}.a", "x"]).await, Frame::Integer(1));
+    assert_eq!(subscriber.read().await, Frame::Array(vec![bulk("smessage"), bulk("{user}.a"), bulk("x")]));
+    assert_eq!(publisher.call(&["PUBLISH", "{user}.a", "y"]).await, Frame::Integer(1));
+    assert_eq!(subscriber.read().await, message("{user}.a", "y"));
+
+    assert_eq!(publisher.call(&["PUBSUB", "SHARDCHANNELS", "*.b"]).await, Frame::Array(vec![bulk("{user}.b")]));
+    assert_eq!(
+        publisher.call(&["PUBSUB", "SHARDNUMSUB", "{user}.a", "other"]).await,
+        Frame::Array(vec![bulk("{user}.a"), Frame::Integer(1), bulk("other"), Frame::Integer(0)])
+    );
+    assert_eq!(publisher.call(&["PUBSUB", "CHANNELS"]).await, Frame::Array(vec![bulk("{user}.a")]));
+
+    subscriber.send(&["SUNSUBSCRIBE"]).await;
+    assert_eq!(
+        unsubscribed(&mut subscriber, "sunsubscribe", 2, 0).await,
+        vec![bulk("{user}.a"), bulk("{user}.b")]
+    );
+    assert_eq!(publisher.call(&["SPUBLISH", "{user}.a", "z"]).await, Frame::Integer(0));
+    assert_eq!(publisher.call(&["PUBLISH", "{user}.a", "z"]).await, Frame::Integer(1));
+}
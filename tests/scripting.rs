@@ -0,0 +1,78 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, ok, start_server, Client};
+use tokio::time::{sleep, Duration};
+
+// #[tokio::test] 默认是current_thread的runtime，脚本不能占住唯一的线程
+#[tokio::test]
+async fn eval_on_current_thread_runtime() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["EVAL", "return redis.call('SET', KEYS[1], ARGV[1])", "1", "k", "v"]).await, ok());
+    assert_eq!(client.call(&["GET", "k"]).await, bulk("v"));
+    assert_eq!(client.call(&["EVAL", "return tonumber(ARGV[1]) + 1", "0", "41"]).await, Frame::Integer(42));
+}
+
+#[tokio::test]
+async fn script_cache() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let sha = "e0e1f9fabfc9d4800c877a703b823ac0578ff8db";
+    assert_eq!(client.call(&["SCRIPT", "LOAD", "return 1"]).await, bulk(sha));
+    assert_eq!(client.call(&["EVALSHA", sha, "0"]).await, Frame::Integer(1));
+    assert_eq!(
+        client.call(&["SCRIPT", "EXISTS", sha, "0000000000000000000000000000000000000000"]).await,
+        Frame::Array(vec![Frame::Integer(1), Frame::Integer(0)])
+    );
+    assert_eq!(client.call(&["SCRIPT", "FLUSH"]).await, ok());
+    assert!(matches!(client.call(&["EVALSHA", sha, "0"]).await, Frame::Error(err) if err.starts_with("NOSCRIPT")));
+}
+
+#[tokio::test]
+async fn script_kill_stops_running_script() {
+    let addr = start_server().await;
+    let mut runner = Client::connect(addr).await;
+    let mut killer = Client::connect(addr).await;
+
+    runner.send(&["EVAL", "while true do end", "0"]).await;
+    // 等脚本开始执行
+    loop {
+        sleep(Duration::from_millis(20)).await;
+        match killer.call(&["SCRIPT", "KILL"]).await {
+            Frame::Simple(reply) => {
+                assert_eq!(reply, "OK");
+                break;
+            }
+            Frame::Error(err) => assert!(err.starts_with("NOTBUSY"), "{}", err),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+    }
+    match runner.read().await {
+        Frame::Error(err) => assert!(err.contains("Script killed by user"), "{}", err),
+        frame => panic!("unexpected reply {:?}", frame),
+    }
+    // 锁已经释放，其他连接可以继续执行命令
+    assert_eq!(killer.call(&["SET", "a", "1"]).await, ok());
+    assert_eq!(runner.call(&["GET", "a"]).await, bulk("1"));
+}
+
+#[tokio::test]
+async fn exec_with_script_and_watch() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+
+    assert_eq!(client.call(&["WATCH", "w"]).await, ok());
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["EVAL", "return redis.call('ZINCRBY', KEYS[1], 1, 'm')", "1", "n"]).await, Frame::Simple("QUEUED".to_string()));
+    assert_eq!(client.call(&["EXEC"]).await, Frame::Array(vec![bulk("1")]));
+
+    // WATCH 的key被其他连接修改之后事务不执行
+    assert_eq!(client.call(&["WATCH", "w"]).await, ok());
+    assert_eq!(other.call(&["SET", "w", "x"]).await, ok());
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    client.call(&["EVAL", "return redis.call('ZINCRBY', KEYS[1], 1, 'm')", "1", "n"]).await;
+    assert_eq!(client.call(&["EXEC"]).await, Frame::NullArray);
+    assert_eq!(client.call(&["ZSCORE", "n", "m"]).await, bulk("1"));
+}
@@ -0,0 +1,60 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, start_server, Client};
+
+fn entry(id: &str, field: &str, value: &str) -> Frame {
+    Frame::Array(vec![bulk(id), Frame::Array(vec![bulk(field), bulk(value)])])
+}
+
+#[tokio::test]
+async fn add_range_and_trim() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["XADD", "s", "1-1", "f", "a"]).await, bulk("1-1"));
+    assert_eq!(client.call(&["XADD", "s", "1-*", "f", "b"]).await, bulk("1-2"));
+    assert_eq!(client.call(&["XADD", "s", "2", "f", "c"]).await, bulk("2-0"));
+    assert_eq!(
+        client.call(&["XADD", "s", "1-5", "f", "d"]).await,
+        Frame::Error("ERR The ID specified in XADD is equal or smaller than the target stream top item".to_string())
+    );
+    assert_eq!(client.call(&["XADD", "nostream", "NOMKSTREAM", "*", "f", "a"]).await, Frame::Null);
+    assert_eq!(client.call(&["XLEN", "s"]).await, Frame::Integer(3));
+
+    assert_eq!(
+        client.call(&["XRANGE", "s", "-", "+", "COUNT", "2"]).await,
+        Frame::Array(vec![entry("1-1", "f", "a"), entry("1-2", "f", "b")])
+    );
+    assert_eq!(client.call(&["XRANGE", "s", "(1-1", "1"]).await, Frame::Array(vec![entry("1-2", "f", "b")]));
+    assert_eq!(
+        client.call(&["XREVRANGE", "s", "+", "1-2"]).await,
+        Frame::Array(vec![entry("2-0", "f", "c"), entry("1-2", "f", "b")])
+    );
+
+    assert_eq!(client.call(&["XDEL", "s", "1-2", "9-9"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["XTRIM", "s", "MAXLEN", "1"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["XRANGE", "s", "-", "+"]).await, Frame::Array(vec![entry("2-0", "f", "c")]));
+    assert_eq!(client.call(&["XADD", "s", "MINID", "3", "3-0", "f", "d"]).await, bulk("3-0"));
+    assert_eq!(client.call(&["XLEN", "s"]).await, Frame::Integer(1));
+}
+
+#[tokio::test]
+async fn xread_blocks_until_add() {
+    let addr = start_server().await;
+    let mut blocked = Client::connect(addr).await;
+    let mut writer = Client::connect(addr).await;
+    writer.call(&["XADD", "s", "1-0", "f", "a"]).await;
+    assert_eq!(
+        blocked.call(&["XREAD", "STREAMS", "s", "0"]).await,
+        Frame::Array(vec![Frame::Array(vec![bulk("s"), Frame::Array(vec![entry("1-0", "f", "a")])])])
+    );
+    assert_eq!(blocked.call(&["XREAD", "BLOCK", "50", "STREAMS", "s", "$"]).await, Frame::NullArray);
+
+    blocked.send(&["XREAD", "BLOCK", "0", "STREAMS", "s", "$"]).await;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    writer.call(&["XADD", "s", "2-0", "f", "b"]).await;
+    assert_eq!(
+        blocked.read().await,
+        Frame::Array(vec![Frame::Array(vec![bulk("s"), Frame::Array(vec![entry("2-0", "f", "b")])])])
+    );
+}
@@ -0,0 +1,127 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, ok, start_server, Client};
+
+fn entry(id: &str, value: &str) -> Frame {
+    Frame::Array(vec![bulk(id), Frame::Array(vec![bulk("f"), bulk(value)])])
+}
+
+fn read_reply(key: &str, entries: Vec<Frame>) -> Frame {
+    Frame::Array(vec![Frame::Array(vec![bulk(key), Frame::Array(entries)])])
+}
+
+#[tokio::test]
+async fn read_ack_and_pending() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(
+        client.call(&["XGROUP", "CREATE", "s", "g", "$"]).await,
+        Frame::Error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.".to_string())
+    );
+    assert_eq!(client.call(&["XGROUP", "CREATE", "s", "g", "$", "MKSTREAM"]).await, ok());
+    assert_eq!(client.call(&["XGROUP", "CREATE", "s", "g", "$"]).await, Frame::Error("BUSYGROUP Consumer Group name already exists".to_string()));
+    client.call(&["XADD", "s", "1-0", "f", "a"]).await;
+    client.call(&["XADD", "s", "2-0", "f", "b"]).await;
+
+    assert_eq!(
+        client.call(&["XREADGROUP", "GROUP", "g", "alice", "COUNT", "1", "STREAMS", "s", ">"]).await,
+        read_reply("s", vec![entry("1-0", "a")])
+    );
+    assert_eq!(
+        client.call(&["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", ">"]).await,
+        read_reply("s", vec![entry("2-0", "b")])
+    );
+    // 读自己的历史消息
+    assert_eq!(
+        client.call(&["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", "0"]).await,
+        read_reply("s", vec![entry("1-0", "a")])
+    );
+    assert_eq!(
+        client.call(&["XPENDING", "s", "g"]).await,
+        Frame::Array(vec![
+            Frame::Integer(2),
+            bulk("1-0"),
+            bulk("2-0"),
+            Frame::Array(vec![
+                Frame::Array(vec![bulk("alice"), bulk("1")]),
+                Frame::Array(vec![bulk("bob"), bulk("1")]),
+            ]),
+        ])
+    );
+    assert_eq!(client.call(&["XACK", "s", "g", "1-0", "1-0", "9-0"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["XPENDING", "s", "g", "-", "+", "10", "alice"]).await, Frame::Array(vec![]));
+
+    let info = client.call(&["XINFO", "GROUPS", "s"]).await;
+    assert_eq!(
+        info,
+        Frame::Array(vec![Frame::Array(vec![
+            bulk("name"),
+            bulk("g"),
+            bulk("consumers"),
+            Frame::Integer(2),
+            bulk("pending"),
+            Frame::Integer(1),
+            bulk("last-delivered-id"),
+            bulk("2-0"),
+            bulk("entries-read"),
+            Frame::Integer(2),
+            bulk("lag"),
+            Frame::Integer(0),
+        ])])
+    );
+    assert_eq!(client.call(&["XGROUP", "DELCONSUMER", "s", "g", "bob"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["XGROUP", "DESTROY", "s", "g"]).await, Frame::Integer(1));
+    assert!(matches!(
+        client.call(&["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"]).await,
+        Frame::Error(err) if err.starts_with("NOGROUP")
+    ));
+}
+
+#[tokio::test]
+async fn claim_and_autoclaim() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    client.call(&["XGROUP", "CREATE", "s", "g", "0", "MKSTREAM"]).await;
+    client.call(&["XADD", "s", "1-0", "f", "a"]).await;
+    client.call(&["XADD", "s", "2-0", "f", "b"]).await;
+    client.call(&["XADD", "s", "3-0", "f", "c"]).await;
+    client.call(&["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"]).await;
+
+    assert_eq!(client.call(&["XCLAIM", "s", "g", "bob", "60000", "1-0"]).await, Frame::Array(vec![]));
+    assert_eq!(client.call(&["XCLAIM", "s", "g", "bob", "0", "1-0"]).await, Frame::Array(vec![entry("1-0", "a")]));
+    assert_eq!(client.call(&["XCLAIM", "s", "g", "bob", "0", "1-0", "JUSTID"]).await, Frame::Array(vec![bulk("1-0")]));
+
+    // 被删除的消息在XAUTOCLAIM里面单独返回
+    client.call(&["XDEL", "s", "3-0"]).await;
+    assert_eq!(
+        client.call(&["XAUTOCLAIM", "s", "g", "carol", "0", "2-0"]).await,
+        Frame::Array(vec![bulk("0-0"), Frame::Array(vec![entry("2-0", "b")]), Frame::Array(vec![bulk("3-0")])])
+    );
+    assert_eq!(
+        client.call(&["XAUTOCLAIM", "s", "g", "carol", "0", "0", "COUNT", "1", "JUSTID"]).await,
+        Frame::Array(vec![bulk("2-0"), Frame::Array(vec![bulk("1-0")]), Frame::Array(vec![])])
+    );
+}
+
+#[tokio::test]
+async fn xreadgroup_blocks_until_add() {
+    let addr = start_server().await;
+    let mut blocked = Client::connect(addr).await;
+    let mut writer = Client::connect(addr).await;
+    writer.call(&["XGROUP", "CREATE", "s", "g", "$", "MKSTREAM"]).await;
+
+    blocked.send(&["XREADGROUP", "GROUP", "g", "alice", "BLOCK", "0", "STREAMS", "s", ">"]).await;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    writer.call(&["XADD", "s", "1-0", "f", "a"]).await;
+    assert_eq!(blocked.read().await, read_reply("s", vec![entry("1-0", "a")]));
+    assert_eq!(
+        writer.call(&["XPENDING", "s", "g"]).await,
+        Frame::Array(vec![
+            Frame::Integer(1),
+            bulk("1-0"),
+            bulk("1-0"),
+            Frame::Array(vec![Frame::Array(vec![bulk("alice"), bulk("1")])]),
+        ])
+    );
+}
@@ -0,0 +1,70 @@
+use bytes::Bytes;
+use my_redis::config::Config;
+use my_redis::connection::Connection;
+use my_redis::db::Db;
+use my_redis::frame::Frame;
+use my_redis::server;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+
+// 测试用的配置，函数库不落盘，多个测试之间不会互相影响
+#[allow(dead_code)]
+pub fn config() -> Config {
+    Config { functions_file: None, ..Config::default() }
+}
+
+// 在随机端口上启动一个服务，测试结束的时候随runtime一起退出
+#[allow(dead_code)]
+pub async fn start_server() -> SocketAddr {
+    start_server_with(Db::new(), config()).await
+}
+
+// 用外面创建的Db和配置启动，测试里面可以直接检查Db的状态
+#[allow(dead_code)]
+pub async fn start_server_with(db: Db, config: Config) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { server::run_with_db(listener, db, config, std::future::pending::<()>()).await });
+    addr
+}
+
+pub struct Client {
+    connection: Connection,
+}
+
+impl Client {
+    pub async fn connect(addr: SocketAddr) -> Client {
+        let socket = TcpStream::connect(addr).await.unwrap();
+        Client { connection: Connection::new(socket) }
+    }
+
+    pub async fn send(&mut self, args: &[&str]) {
+        let frame = Frame::Array(args.iter().map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg.as_bytes()))).collect());
+        self.connection.write_frame(&frame).await.unwrap();
+    }
+
+    pub async fn read(&mut self) -> Frame {
+        self.connection.read_frame().await.unwrap().expect("connection closed")
+    }
+
+    pub async fn call(&mut self, args: &[&str]) -> Frame {
+        self.send(args).await;
+        self.read().await
+    }
+
+    // 服务端关闭了连接
+    #[allow(dead_code)]
+    pub async fn is_closed(&mut self) -> bool {
+        matches!(self.connection.read_frame().await, Ok(None) | Err(_))
+    }
+}
+
+#[allow(dead_code)]
+pub fn bulk(value: &str) -> Frame {
+    Frame::Bulk(Bytes::copy_from_slice(value.as_bytes()))
+}
+
+#[allow(dead_code)]
+pub fn ok() -> Frame {
+    Frame::Simple("OK".to_string())
+}
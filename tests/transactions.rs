@@ -0,0 +1,116 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, ok, start_server, Client};
+
+fn queued() -> Frame {
+    Frame::Simple("QUEUED".to_string())
+}
+
+#[tokio::test]
+async fn multi_exec_and_discard() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["EXEC"]).await, Frame::Error("ERR EXEC without MULTI".to_string()));
+    assert_eq!(client.call(&["DISCARD"]).await, Frame::Error("ERR DISCARD without MULTI".to_string()));
+
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["MULTI"]).await, Frame::Error("ERR MULTI calls can not be nested".to_string()));
+    assert_eq!(client.call(&["SET", "k", "v"]).await, queued());
+    assert_eq!(client.call(&["ZADD", "k", "1", "a"]).await, queued());
+    assert_eq!(client.call(&["GET", "k"]).await, queued());
+    // 执行时的错误不影响其他命令
+    assert_eq!(
+        client.call(&["EXEC"]).await,
+        Frame::Array(vec![
+            ok(),
+            Frame::Error("WRONGTYPE Operation against a key holding the wrong kind of value".to_string()),
+            bulk("v"),
+        ])
+    );
+
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["SET", "k", "other"]).await, queued());
+    assert_eq!(client.call(&["DISCARD"]).await, ok());
+    assert_eq!(client.call(&["GET", "k"]).await, bulk("v"));
+}
+
+#[tokio::test]
+async fn queue_errors_abort_exec() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["SET", "k", "v"]).await, queued());
+    assert_eq!(client.call(&["GET"]).await, Frame::Error("ERR wrong number of arguments for 'get' command".to_string()));
+    assert_eq!(
+        client.call(&["EXEC"]).await,
+        Frame::Error("EXECABORT Transaction discarded because of previous errors.".to_string())
+    );
+    assert_eq!(client.call(&["GET", "k"]).await, Frame::Null);
+
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert!(matches!(client.call(&["NOSUCHCOMMAND"]).await, Frame::Error(_)));
+    assert!(matches!(client.call(&["EXEC"]).await, Frame::Error(err) if err.starts_with("EXECABORT")));
+}
+
+#[tokio::test]
+async fn other_clients_wait_for_exec() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["SET", "k", "1"]).await, queued());
+    // 排队的命令在EXEC之前对其他连接不可见
+    assert_eq!(other.call(&["GET", "k"]).await, Frame::Null);
+    assert_eq!(client.call(&["EXEC"]).await, Frame::Array(vec![ok()]));
+    assert_eq!(other.call(&["GET", "k"]).await, bulk("1"));
+}
+
+#[tokio::test]
+async fn watch_aborts_on_change() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+
+    assert_eq!(client.call(&["WATCH", "k", "z"]).await, ok());
+    assert_eq!(other.call(&["ZADD", "z", "1", "a"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["WATCH", "x"]).await, Frame::Error("ERR WATCH inside MULTI is not allowed".to_string()));
+    assert_eq!(client.call(&["SET", "k", "1"]).await, queued());
+    assert_eq!(client.call(&["EXEC"]).await, Frame::NullArray);
+    assert_eq!(client.call(&["GET", "k"]).await, Frame::Null);
+
+    // EXEC之后WATCH就失效了
+    assert_eq!(other.call(&["ZADD", "z", "2", "b"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["SET", "k", "1"]).await, queued());
+    assert_eq!(client.call(&["EXEC"]).await, Frame::Array(vec![ok()]));
+}
+
+#[tokio::test]
+async fn unwatch_and_untouched_keys() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+
+    assert_eq!(client.call(&["WATCH", "k"]).await, ok());
+    assert_eq!(other.call(&["SET", "unrelated", "1"]).await, ok());
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["GET", "k"]).await, queued());
+    assert_eq!(client.call(&["EXEC"]).await, Frame::Array(vec![Frame::Null]));
+
+    assert_eq!(client.call(&["WATCH", "k"]).await, ok());
+    assert_eq!(client.call(&["UNWATCH"]).await, ok());
+    assert_eq!(other.call(&["SET", "k", "1"]).await, ok());
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["GET", "k"]).await, queued());
+    assert_eq!(client.call(&["EXEC"]).await, Frame::Array(vec![bulk("1")]));
+
+    // 过期也算修改
+    assert_eq!(other.call(&["SET", "t", "1", "PX", "20"]).await, ok());
+    assert_eq!(client.call(&["WATCH", "t"]).await, ok());
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert_eq!(client.call(&["MULTI"]).await, ok());
+    assert_eq!(client.call(&["GET", "t"]).await, queued());
+    assert_eq!(client.call(&["EXEC"]).await, Frame::NullArray);
+}
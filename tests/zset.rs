@@ -0,0 +1,73 @@
+mod support;
+
+use my_redis::frame::Frame;
+use support::{bulk, start_server, Client};
+
+fn bulks(items: &[&str]) -> Frame {
+    Frame::Array(items.iter().map(|item| bulk(item)).collect())
+}
+
+#[tokio::test]
+async fn basic_commands() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["ZADD", "z", "1", "a", "2", "b", "3", "c"]).await, Frame::Integer(3));
+    assert_eq!(client.call(&["ZADD", "z", "CH", "5", "a", "4", "d"]).await, Frame::Integer(2));
+    assert_eq!(client.call(&["ZRANGE", "z", "0", "-1"]).await, bulks(&["b", "c", "d", "a"]));
+    assert_eq!(client.call(&["ZRANGE", "z", "0", "1", "WITHSCORES"]).await, bulks(&["b", "2", "c", "3"]));
+    assert_eq!(client.call(&["ZRANGE", "z", "(2", "4", "BYSCORE"]).await, bulks(&["c", "d"]));
+    assert_eq!(client.call(&["ZRANK", "z", "a"]).await, Frame::Integer(3));
+    assert_eq!(client.call(&["ZRANK", "z", "missing"]).await, Frame::Null);
+    assert_eq!(client.call(&["ZSCORE", "z", "a"]).await, bulk("5"));
+    assert_eq!(client.call(&["ZINCRBY", "z", "0.5", "b"]).await, bulk("2.5"));
+    assert_eq!(client.call(&["ZCOUNT", "z", "-inf", "(4"]).await, Frame::Integer(2));
+    assert_eq!(client.call(&["ZREM", "z", "a", "x"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["ZPOPMIN", "z"]).await, bulks(&["b", "2.5"]));
+    assert_eq!(client.call(&["ZPOPMAX", "z", "5"]).await, bulks(&["d", "4", "c", "3"]));
+    assert_eq!(client.call(&["ZRANGE", "z", "0", "-1"]).await, Frame::Array(vec![]));
+}
+
+#[tokio::test]
+async fn errors() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.call(&["ZADD", "z", "x", "a"]).await, Frame::Error("ERR value is not a valid float".to_string()));
+    assert_eq!(
+        client.call(&["ZADD", "z", "NX", "XX", "1", "a"]).await,
+        Frame::Error("ERR XX and NX options at the same time are not compatible".to_string())
+    );
+    assert_eq!(client.call(&["ZRANGE", "z", "0"]).await, Frame::Error("ERR wrong number of arguments for 'zrange' command".to_string()));
+}
+
+// 阻塞在空key上，其他连接写入之后被唤醒
+#[tokio::test]
+async fn bzpop_wakes_up() {
+    let addr = start_server().await;
+    let mut blocked = Client::connect(addr).await;
+    let mut writer = Client::connect(addr).await;
+    assert_eq!(blocked.call(&["BZPOPMIN", "z", "0.05"]).await, Frame::NullArray);
+
+    blocked.send(&["BZPOPMAX", "z1", "z2", "0"]).await;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert_eq!(writer.call(&["ZADD", "z2", "1", "a", "2", "b"]).await, Frame::Integer(2));
+    assert_eq!(blocked.read().await, bulks(&["z2", "b", "2"]));
+}
+
+#[tokio::test]
+async fn store_commands() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr).await;
+    client.call(&["ZADD", "a", "1", "x", "2", "y"]).await;
+    client.call(&["ZADD", "b", "10", "y", "20", "z"]).await;
+    assert_eq!(client.call(&["ZUNIONSTORE", "u", "2", "a", "b", "WEIGHTS", "1", "2"]).await, Frame::Integer(3));
+    assert_eq!(client.call(&["ZRANGE", "u", "0", "-1", "WITHSCORES"]).await, bulks(&["x", "1", "y", "22", "z", "40"]));
+    assert_eq!(client.call(&["ZINTERSTORE", "i", "2", "a", "b", "AGGREGATE", "MAX"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["ZRANGE", "i", "0", "-1", "WITHSCORES"]).await, bulks(&["y", "10"]));
+    assert_eq!(client.call(&["ZDIFFSTORE", "d", "2", "a", "b"]).await, Frame::Integer(1));
+    assert_eq!(client.call(&["ZRANGE", "d", "0", "-1"]).await, bulks(&["x"]));
+    assert_eq!(client.call(&["ZRANGESTORE", "r", "u", "1", "2"]).await, Frame::Integer(2));
+    assert_eq!(client.call(&["ZRANGE", "r", "0", "-1"]).await, bulks(&["y", "z"]));
+    // 结果为空的时候目标key被删掉
+    assert_eq!(client.call(&["ZINTERSTORE", "r", "2", "a", "missing"]).await, Frame::Integer(0));
+    assert_eq!(client.call(&["ZRANGE", "r", "0", "-1"]).await, Frame::Array(vec![]));
+}